@@ -0,0 +1,191 @@
+//! Module dealing with the visualization of accelerations, published as
+//! geometry_msgs::AccelStamped, e.g. the acceleration commanded by a controller.
+//!
+//! The linear acceleration is drawn as an arrow from the origin of the anchor frame, its
+//! length being proportional to the acceleration. The angular acceleration around the z
+//! axis is drawn as an arc around the origin, its sweep being proportional to the
+//! acceleration, counter-clockwise for a positive one.
+
+use crate::config::AccelListenerConfig;
+use crate::rate::RateMonitor;
+use crate::transformation::ros_transform_to_isometry;
+use nalgebra::geometry::{Isometry3, Point3};
+use std::f64::consts::PI;
+use std::sync::{Arc, RwLock};
+use tui::style::Color;
+use tui::widgets::canvas::Line;
+
+/// Accelerations below this value are considered null and are not drawn.
+const MIN_ACCEL: f64 = 1.0e-6;
+/// Angle between the points of the arcs, in radians.
+const ARC_STEP: f64 = PI / 16.0;
+
+fn get_line(p1: &Point3<f64>, p2: &Point3<f64>, color: Color) -> Line {
+    Line {
+        x1: p1.x,
+        y1: p1.y,
+        x2: p2.x,
+        y2: p2.y,
+        color,
+    }
+}
+
+/// Returns the arrow of a linear acceleration, expressed in the anchor frame.
+///
+/// # Arguments
+/// - `pose`: pose of the anchor frame in the static frame.
+/// - `x`, `y`: the acceleration, already scaled to meters.
+fn get_arrow_lines(pose: &Isometry3<f64>, x: f64, y: f64, color: Color) -> Vec<Line> {
+    let length = x.hypot(y);
+    if length < MIN_ACCEL {
+        return Vec::new();
+    }
+    let (ux, uy) = (x / length, y / length);
+    let head = length.min(0.3) / 2.0;
+    let origin = pose.transform_point(&Point3::origin());
+    let tip = pose.transform_point(&Point3::new(x, y, 0.0));
+    // a closed head, unlike the arrows of the poses
+    let left = pose.transform_point(&Point3::new(
+        x - head * ux - head / 2.0 * uy,
+        y - head * uy + head / 2.0 * ux,
+        0.0,
+    ));
+    let right = pose.transform_point(&Point3::new(
+        x - head * ux + head / 2.0 * uy,
+        y - head * uy - head / 2.0 * ux,
+        0.0,
+    ));
+    vec![
+        get_line(&origin, &tip, color),
+        get_line(&tip, &left, color),
+        get_line(&tip, &right, color),
+        get_line(&left, &right, color),
+    ]
+}
+
+/// Returns the arc of an angular acceleration, around the origin of the anchor frame.
+///
+/// # Arguments
+/// - `pose`: pose of the anchor frame in the static frame.
+/// - `sweep`: the acceleration, already scaled to radians. Clamped to a full turn.
+/// - `radius`: radius of the arc, in meters.
+fn get_arc_lines(pose: &Isometry3<f64>, sweep: f64, radius: f64, color: Color) -> Vec<Line> {
+    if sweep.abs() < MIN_ACCEL {
+        return Vec::new();
+    }
+    let sweep = sweep.clamp(-2.0 * PI, 2.0 * PI);
+    let steps = (sweep.abs() / ARC_STEP).ceil().max(1.0) as usize;
+    let points: Vec<Point3<f64>> = (0..=steps)
+        .map(|i| {
+            let angle = sweep * i as f64 / steps as f64;
+            pose.transform_point(&Point3::new(
+                radius * angle.cos(),
+                radius * angle.sin(),
+                0.0,
+            ))
+        })
+        .collect();
+    let mut lines: Vec<Line> = points
+        .windows(2)
+        .map(|w| get_line(&w[0], &w[1], color))
+        .collect();
+    // a tick at the end of the arc, pointing backward, shows the direction
+    let end = sweep.signum() * (sweep.abs() - ARC_STEP).max(0.0);
+    let tick = pose.transform_point(&Point3::new(
+        radius * 0.8 * end.cos(),
+        radius * 0.8 * end.sin(),
+        0.0,
+    ));
+    lines.push(get_line(&points[steps], &tick, color));
+    lines
+}
+
+pub struct AccelListener {
+    pub config: AccelListenerConfig,
+    /// Last acceleration, with the frame it is expressed in.
+    accel: Arc<RwLock<Option<(String, rosrust_msg::geometry_msgs::Accel)>>>,
+    pub rate: RateMonitor,
+    tf_listener: Arc<rustros_tf::TfListener>,
+    static_frame: String,
+    _subscriber: rosrust::Subscriber,
+}
+
+impl AccelListener {
+    pub fn new(
+        config: AccelListenerConfig,
+        tf_listener: Arc<rustros_tf::TfListener>,
+        static_frame: String,
+    ) -> AccelListener {
+        let accel = Arc::new(RwLock::new(None));
+        let cb_accel = accel.clone();
+        let rate = RateMonitor::new();
+        let cb_rate = rate.clone();
+        let anchor_frame = config.frame.clone();
+        let sub = rosrust::subscribe(
+            &config.topic,
+            2,
+            move |msg: rosrust_msg::geometry_msgs::AccelStamped| {
+                cb_rate.record();
+                cb_rate.record_stamp(msg.header.stamp);
+                let frame = anchor_frame
+                    .clone()
+                    .unwrap_or_else(|| msg.header.frame_id.clone());
+                *cb_accel.write().unwrap() = Some((frame, msg.accel));
+            },
+        )
+        .unwrap();
+
+        AccelListener {
+            config,
+            accel,
+            rate,
+            tf_listener,
+            static_frame,
+            _subscriber: sub,
+        }
+    }
+
+    /// Forgets the received acceleration.
+    pub fn clear(&self) {
+        *self.accel.write().unwrap() = None;
+    }
+
+    /// Returns the lines of the last acceleration, at the current pose of the anchor frame.
+    /// Nothing is drawn if the acceleration is null or if the anchor frame is unknown.
+    pub fn get_lines(&self) -> Vec<Line> {
+        let (frame, accel) = match self.accel.read().unwrap().clone() {
+            Some(accel) => accel,
+            None => return Vec::new(),
+        };
+        let pose = if frame.is_empty() || frame == self.static_frame {
+            Isometry3::identity()
+        } else {
+            match self.tf_listener.lookup_transform(
+                &self.static_frame,
+                &frame,
+                rosrust::Time::new(),
+            ) {
+                Ok(tf) => ros_transform_to_isometry(&tf.transform),
+                Err(_e) => return Vec::new(),
+            }
+        };
+        let color = Color::Rgb(
+            self.config.color.r,
+            self.config.color.g,
+            self.config.color.b,
+        );
+        let mut lines = get_arrow_lines(
+            &pose,
+            accel.linear.x * self.config.linear_scale,
+            accel.linear.y * self.config.linear_scale,
+            color,
+        );
+        lines.extend(get_arc_lines(
+            &pose,
+            accel.angular.z * self.config.angular_scale,
+            self.config.arc_radius,
+            color,
+        ));
+        lines
+    }
+}
@@ -1,24 +1,36 @@
 use crate::app_modes;
-use crate::config::TermvizConfig;
-use crate::footprint::get_footprint;
+use crate::app_modes::viewport::{UseViewport, Viewport};
+use crate::command_palette::{CommandPalette, PaletteEvent};
+use crate::config::{export_config, get_duration, GifRecordingConfig, TermvizConfig};
+use crate::footprint::ParameterPolygon;
 use crate::listeners::Listeners;
+use crate::liveness::PublisherMonitor;
+use crate::logging::Notifications;
+use crate::recorder::GifRecorder;
+use crate::ros_master;
+use crate::time_watchdog::TimeWatchdog;
+use crate::timer::Timer;
+use crate::units::Units;
 use crossterm::{
-    event::EnableMouseCapture,
+    event::{EnableMouseCapture, KeyCode},
     execute,
     terminal::{enable_raw_mode, size, EnterAlternateScreen},
 };
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::convert::TryFrom;
 use std::io;
+use std::path::Path;
 use std::rc::Rc;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tui::backend::Backend;
 use tui::backend::CrosstermBackend;
-use tui::layout::{Alignment, Constraint, Direction, Layout};
+use tui::buffer::Buffer;
+use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
 use tui::style::{Color, Modifier, Style};
 use tui::text::{Span, Spans};
-use tui::widgets::{Block, Borders, Paragraph, Row, Table, Wrap};
+use tui::widgets::{Block, Borders, Clear, Paragraph, Row, Table, Wrap};
 use tui::{Frame, Terminal};
 
 pub struct App<B: Backend> {
@@ -26,49 +38,201 @@ pub struct App<B: Backend> {
     show_help: bool,
     keymap: HashMap<String, String>,
     app_modes: Vec<Box<dyn app_modes::BaseMode<B>>>,
+    suppress_idle_redraw: bool,
+    last_draw: Instant,
+    viewport: Rc<RefCell<Viewport>>,
+    /// Fields shown in the status bar, in order.
+    status_bar: Vec<String>,
+    /// Instants of the draws of the last second, to compute the framerate.
+    draw_times: VecDeque<Instant>,
+    gif_recording: GifRecordingConfig,
+    recorder: Option<GifRecorder>,
+    time_watchdog: TimeWatchdog,
+    timer: Timer,
+    /// Master to which termviz has to switch, set once it was found reachable.
+    master_switch: Option<String>,
+    palette: Option<CommandPalette>,
+    notifications: Notifications,
+    /// For how long a logged message is shown.
+    notification_duration: Duration,
+    /// The loaded configuration, to which the live settings are applied when exported.
+    config: TermvizConfig,
 }
 
+/// File to which the configuration is exported, in the current directory.
+const EXPORTED_CONFIG_PATH: &str = "termviz_export.yml";
+
+/// Maximum time between two draws when the idle redraws are suppressed.
+const MAX_REDRAW_PERIOD: Duration = Duration::from_secs(1);
+
 impl<B: Backend> App<B> {
-    pub fn new(tf_listener: Arc<rustros_tf::TfListener>, config: TermvizConfig) -> App<B> {
+    pub fn new(
+        tf_listener: Arc<rustros_tf::TfListener>,
+        config: TermvizConfig,
+        notifications: Notifications,
+    ) -> App<B> {
         let config_copy = config.clone();
+        let suppress_idle_redraw = config.suppress_idle_redraw;
         let listeners = Listeners::new(
             tf_listener.clone(),
             config.fixed_frame.clone(),
             config.laser_topics,
             config.marker_topics,
             config.marker_array_topics,
+            config.interactive_marker_topics,
             config.map_topics,
+            config.image_overlays,
+            config.heatmap_topics,
+            config.gradient_topics,
             config.pose_stamped_topics,
             config.pose_array_topics,
             config.pointcloud2_topics,
             config.path_topics,
+            config.polygon_topics,
+            config.navsat_topics,
+            config.accel_topics,
+            config.correspondence_overlays,
+            config.detection_topics,
+            config.segment_topics,
+            config.tf_comparison,
+            config.robot_model,
+            config.thumbnail,
+            config.target_framerate,
         );
-        let viewport = Rc::new(RefCell::new(app_modes::viewport::Viewport::new(
+        let rates = listeners.get_rates();
+        let viewport = Rc::new(RefCell::new(Viewport::new(
             &config.fixed_frame,
             &config.robot_frame,
-            tf_listener,
+            tf_listener.clone(),
             &config.visible_area,
-            &get_footprint(),
+            ParameterPolygon::new(
+                &config.footprint_param,
+                &config.footprint_param_prefix,
+                config.footprint_poll_interval,
+            ),
+            config.heading_wedge,
+            config.footprint_buffer,
             config.axis_length,
             config.zoom_factor,
+            config.show_scale_bar,
+            config.show_tf_frames,
+            config.snap_lines,
+            config.background_color.clone(),
             listeners,
             size().unwrap(),
+            config.live_parameters_interval,
+            if config.auto_fit {
+                Some(config.auto_fit_padding)
+            } else {
+                None
+            },
+            Units::new(&config.units),
+            config.home_view,
+            config.minimap,
+            config.map_hud,
+            config.pose_readout,
+            config.exclusive_maps,
+            &config.point_blending,
+            config.snapshot_opacity,
+            config.age_ramp,
+            config.cursor,
+            config.time_sync,
         )));
         let send_pose = Box::new(app_modes::send_pose::SendPose::new(
             &config.send_pose_topics,
             viewport.clone(),
         ));
         let teleop = Box::new(app_modes::teleoperate::Teleoperate::new(
-            viewport,
+            viewport.clone(),
             config.teleop,
         ));
-        let topic_manager = Box::new(app_modes::topic_managment::TopicManager::new(config_copy));
+        let topic_manager = Box::new(app_modes::topic_managment::TopicManager::new(
+            config_copy.clone(),
+        ));
         let image_view = Box::new(app_modes::image_view::ImageView::new(config.image_topics));
+        let diagnostics = Box::new(app_modes::diagnostics::Diagnostics::new(
+            rates,
+            PublisherMonitor::new(config.publisher_check_interval),
+        ));
+        let waypoints = Box::new(app_modes::waypoints::Waypoints::new(
+            config.waypoints,
+            viewport.clone(),
+        ));
+        let polygon_editor = Box::new(app_modes::polygon_editor::PolygonEditor::new(
+            config.polygon_editor,
+            viewport.clone(),
+        ));
+        let tf_echo = Box::new(app_modes::tf_echo::TfEcho::new(
+            config.tf_echo,
+            viewport.clone(),
+        ));
+        let inspect_cell = Box::new(app_modes::inspect_cell::InspectCell::new(viewport.clone()));
+        let mut app_modes: Vec<Box<dyn app_modes::BaseMode<B>>> = vec![
+            send_pose,
+            teleop,
+            image_view,
+            topic_manager,
+            diagnostics,
+            waypoints,
+            polygon_editor,
+            tf_echo,
+            inspect_cell,
+        ];
+        if let Some(pose_diff_config) = config.pose_diff {
+            app_modes.push(Box::new(app_modes::pose_diff::PoseDiff::new(
+                pose_diff_config,
+                tf_listener,
+                viewport.clone(),
+            )));
+        }
+        if let Some(twist_estimate_config) = config.twist_estimate {
+            app_modes.push(Box::new(app_modes::twist_estimate::TwistEstimate::new(
+                twist_estimate_config,
+                viewport.clone(),
+            )));
+        }
+        if let Some(clear_cost_config) = config.clear_cost {
+            app_modes.push(Box::new(app_modes::clear_cost::ClearCost::new(
+                clear_cost_config,
+                viewport.clone(),
+            )));
+        }
+        if let Some(diagnostic_status_config) = config.diagnostic_status {
+            app_modes.push(Box::new(
+                app_modes::diagnostic_status::DiagnosticStatus::new(diagnostic_status_config),
+            ));
+        }
+        if let Some(tracking_error_config) = config.tracking_error {
+            app_modes.push(Box::new(app_modes::tracking_error::TrackingError::new(
+                tracking_error_config,
+                viewport.clone(),
+            )));
+        }
+        if let Some(alignment_config) = config.alignment {
+            app_modes.push(Box::new(app_modes::alignment::Alignment::new(
+                alignment_config,
+                viewport.clone(),
+            )));
+        }
         App {
             mode: 1,
             show_help: false,
             keymap: config.key_mapping,
-            app_modes: vec![send_pose, teleop, image_view, topic_manager],
+            app_modes,
+            suppress_idle_redraw,
+            last_draw: Instant::now(),
+            viewport,
+            status_bar: config.status_bar,
+            draw_times: VecDeque::new(),
+            gif_recording: config.gif_recording,
+            recorder: None,
+            time_watchdog: TimeWatchdog::new(config.time_jump_threshold),
+            timer: Timer::new(config.timer),
+            master_switch: None,
+            palette: None,
+            notifications,
+            notification_duration: get_duration(config.logging.notification_duration),
+            config: config_copy,
         }
     }
 
@@ -82,18 +246,333 @@ impl<B: Backend> App<B> {
     }
 
     pub fn run(&mut self) {
+        if self.time_watchdog.check() {
+            self.viewport.borrow().listeners.clear_transformed_data();
+        }
         self.app_modes[self.mode - 1].run();
     }
 
+    /// Returns true if the frame must be drawn.
+    ///
+    /// If idle redraws are suppressed, the frame is only drawn after an input, a change of
+    /// the content of the current mode, during a recording, or at least once per
+    /// MAX_REDRAW_PERIOD.
+    ///
+    /// # Arguments
+    /// - `event_received`: true if an input or a resize of the terminal happened.
+    pub fn needs_redraw(&mut self, event_received: bool) -> bool {
+        let content_changed = self.app_modes[self.mode - 1].content_changed();
+        if !self.suppress_idle_redraw
+            || event_received
+            || content_changed
+            || self.recorder.is_some()
+            || self.timer.is_running()
+            || self.last_draw.elapsed() >= MAX_REDRAW_PERIOD
+        {
+            self.last_draw = Instant::now();
+            self.draw_times.push_back(self.last_draw);
+            while self.last_draw - self.draw_times[0] > Duration::from_secs(1) {
+                self.draw_times.pop_front();
+            }
+            return true;
+        }
+        false
+    }
+
     pub fn draw(&self, f: &mut Frame<B>) {
         if self.show_help {
             self.show_help(f);
         } else {
             self.app_modes[self.mode - 1].draw(f);
         }
+        self.draw_status_bar(f);
+        self.draw_notifications(f);
+        self.draw_timer(f);
+        if let Some(palette) = &self.palette {
+            palette.draw(f, &self.keymap);
+        }
+    }
+
+    /// Draws the configured fields of the status bar on the last line of the terminal.
+    fn draw_status_bar(&self, f: &mut Frame<B>) {
+        if self.status_bar.is_empty()
+            && self.recorder.is_none()
+            && !self.time_watchdog.show_notice()
+        {
+            return;
+        }
+        let viewport = self.viewport.borrow();
+        let mut fields: Vec<String> = self
+            .status_bar
+            .iter()
+            .filter_map(|field| match field.as_str() {
+                "mode" => Some(format!(
+                    "{}: {}",
+                    self.mode,
+                    self.app_modes[self.mode - 1].get_name()
+                )),
+                "static_frame" => Some(format!("frame: {}", viewport.static_frame)),
+                "follow_frame" => Some(format!("follow: {}", viewport.follow_frame)),
+                "footprint" => Some(format!("footprint: {}", viewport.footprint.param)),
+                "map_style" => {
+                    let styles: Vec<String> = viewport
+                        .listeners
+                        .maps
+                        .iter()
+                        .map(|map| map.get_style())
+                        .collect();
+                    // hidden without maps
+                    (!styles.is_empty()).then(|| format!("map: {}", styles.join(", ")))
+                }
+                "active_map" => viewport
+                    .get_active_map()
+                    .map(|map| format!("active map: {}", map.config.topic)),
+                "time_sync" => viewport.get_time_sync_note(),
+                "markers" => {
+                    let notes = viewport.listeners.markers.get_sampling_notes();
+                    // hidden while no marker array is subsampled
+                    (!notes.is_empty()).then(|| format!("markers: {}", notes.join(", ")))
+                }
+                "zoom" => {
+                    let x_bounds = viewport.x_bounds();
+                    Some(format!(
+                        "width: {}",
+                        viewport.units.format_length(x_bounds[1] - x_bounds[0])
+                    ))
+                }
+                "fps" => Some(format!("fps: {}", self.draw_times.len())),
+                _ => None,
+            })
+            .collect();
+        if let Some(recorder) = &self.recorder {
+            fields.push(recorder.get_progress());
+        }
+        if self.time_watchdog.show_notice() {
+            fields.push("TIME RESET".to_string());
+        }
+
+        let mut area = f.size();
+        if area.height == 0 {
+            return;
+        }
+        area.y += area.height - 1;
+        area.height = 1;
+        let status_bar = Paragraph::new(Spans::from(Span::raw(fields.join(" | "))))
+            .style(Style::default().fg(Color::Black).bg(Color::Gray));
+        f.render_widget(status_bar, area);
+    }
+
+    /// Draws the timer in its corner, the bottom corners being above the status bar.
+    fn draw_timer(&self, f: &mut Frame<B>) {
+        let (text, highlighted) = match self.timer.get_text() {
+            Some(timer) => timer,
+            None => return,
+        };
+        let area = f.size();
+        let width = (text.chars().count() as u16 + 2).min(area.width);
+        if area.height < 2 {
+            return;
+        }
+        let x = match self.timer.config.corner.as_str() {
+            "top_left" | "bottom_left" => area.x,
+            _ => area.x + area.width - width,
+        };
+        let y = match self.timer.config.corner.as_str() {
+            "bottom_left" | "bottom_right" => area.y + area.height - 2,
+            _ => area.y,
+        };
+        let style = if highlighted {
+            Style::default()
+                .fg(Color::White)
+                .bg(Color::Red)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Black).bg(Color::Yellow)
+        };
+        let timer_area = Rect::new(x, y, width, 1);
+        f.render_widget(Clear, timer_area);
+        f.render_widget(
+            Paragraph::new(Span::styled(format!(" {} ", text), style)),
+            timer_area,
+        );
+    }
+
+    /// Draws the most recent logged messages above the status bar, one per line.
+    fn draw_notifications(&self, f: &mut Frame<B>) {
+        let notifications = self.notifications.get(self.notification_duration);
+        let mut area = f.size();
+        if notifications.is_empty() || area.height <= notifications.len() as u16 {
+            return;
+        }
+        area.y += area.height - 1 - notifications.len() as u16;
+        area.height = notifications.len() as u16;
+        let lines: Vec<Spans> = notifications
+            .into_iter()
+            .map(|notification| {
+                let color = match notification.level {
+                    log::Level::Error => Color::Red,
+                    log::Level::Warn => Color::Yellow,
+                    _ => Color::White,
+                };
+                Spans::from(Span::styled(
+                    format!("{}: {}", notification.level, notification.message),
+                    Style::default().fg(color),
+                ))
+            })
+            .collect();
+        f.render_widget(Clear, area);
+        f.render_widget(Paragraph::new(lines), area);
+    }
+
+    /// Adds the rendered frame to the recording, if any, and saves the recording once
+    /// it is complete.
+    pub fn record(&mut self, buffer: &Buffer) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record(buffer);
+            if recorder.is_done() {
+                self.recorder.take().unwrap().save();
+            }
+        }
+    }
+
+    /// Starts a recording, or stops and saves the current one.
+    fn toggle_recording(&mut self) {
+        match self.recorder.take() {
+            Some(recorder) => recorder.save(),
+            None => self.recorder = Some(GifRecorder::new(self.gif_recording.clone())),
+        }
+    }
+
+    /// Exports the configuration with the live settings, e.g. the colors and the view
+    /// changed at runtime, so that it can be loaded at the next start.
+    fn export_config(&self) {
+        let mut config = self.config.clone();
+        self.viewport.borrow().apply_to_config(&mut config);
+        match export_config(&config, Path::new(EXPORTED_CONFIG_PATH)) {
+            Ok(()) => log::info!("Configuration exported to {}", EXPORTED_CONFIG_PATH),
+            Err(e) => log::error!("Could not export the configuration: {}", e),
+        }
+    }
+
+    /// Writes the TF tree known so far to tf_tree_path, as a Graphviz DOT graph.
+    fn export_tf_tree(&self) {
+        let viewport = self.viewport.borrow();
+        let dot = viewport.listeners.frames.get_dot(&viewport.tf_listener);
+        match std::fs::write(&self.config.tf_tree_path, dot) {
+            Ok(()) => log::info!("TF tree exported to {}", self.config.tf_tree_path),
+            Err(e) => log::error!("Could not export the TF tree: {}", e),
+        }
+    }
+
+    /// Prepares the switch to the next configured ROS master, if it is reachable, otherwise
+    /// keeps the current connection.
+    fn switch_ros_master(&mut self) {
+        let uri = match ros_master::get_next_uri(&self.config.ros_masters) {
+            Some(uri) => uri.clone(),
+            None => {
+                log::warn!("No ros_masters are configured to switch to");
+                return;
+            }
+        };
+        if ros_master::is_same_uri(&uri, &ros_master::get_current_uri()) {
+            log::info!("Already connected to {}", uri);
+            return;
+        }
+        log::info!("Connecting to {}...", uri);
+        match ros_master::probe(&uri) {
+            Ok(()) => self.master_switch = Some(uri),
+            Err(e) => log::error!("Keeping the current ROS master: {}", e),
+        }
+    }
+
+    /// Returns the master to switch to, which requires to restart termviz, if any.
+    pub fn take_master_switch(&mut self) -> Option<String> {
+        self.master_switch.take()
+    }
+
+    pub fn is_palette_open(&self) -> bool {
+        self.palette.is_some()
+    }
+
+    /// Opens the command palette, with the actions available in the current mode.
+    fn open_palette(&mut self) {
+        let keymap: Vec<String> = self.app_modes[self.mode - 1]
+            .get_keymap()
+            .into_iter()
+            .map(|[action, _]| action)
+            .collect();
+        let global_actions = [
+            app_modes::input::SHOW_HELP,
+            app_modes::input::RECORD_GIF,
+            app_modes::input::TOGGLE_TIMER,
+            app_modes::input::RESET_TIMER,
+            app_modes::input::SWITCH_ROS_MASTER,
+            app_modes::input::EXPORT_CONFIG,
+            app_modes::input::EXPORT_TF_TREE,
+        ];
+        let actions = app_modes::input::MODES
+            .iter()
+            .take(self.app_modes.len())
+            .chain(app_modes::input::ACTIONS.iter().filter(|action| {
+                keymap.iter().any(|a| a == *action) || global_actions.contains(action)
+            }))
+            .map(|action| action.to_string())
+            .collect();
+        self.palette = Some(CommandPalette::new(actions));
+    }
+
+    /// Handles a key while the command palette is open.
+    pub fn handle_palette_key(&mut self, key: KeyCode) {
+        let event = match &mut self.palette {
+            Some(palette) => palette.handle_key(key),
+            None => return,
+        };
+        match event {
+            PaletteEvent::None => (),
+            PaletteEvent::Close => self.palette = None,
+            PaletteEvent::Execute(action) => {
+                self.palette = None;
+                self.handle_input(&action);
+            }
+        }
     }
 
     pub fn handle_input(&mut self, input: &String) {
+        // the modifier applies to the cursor steps of the unprefixed input
+        let unmodified = self.viewport.borrow_mut().set_cursor_modifier(input);
+        if let Some(unmodified) = unmodified {
+            self.handle_input(&unmodified);
+            self.viewport.borrow_mut().reset_cursor_modifier();
+            return;
+        }
+        if input == app_modes::input::OPEN_PALETTE {
+            self.open_palette();
+            return;
+        }
+        if input == app_modes::input::EXPORT_CONFIG {
+            self.export_config();
+            return;
+        }
+        if input == app_modes::input::EXPORT_TF_TREE {
+            self.export_tf_tree();
+            return;
+        }
+        if input == app_modes::input::RECORD_GIF {
+            self.toggle_recording();
+            return;
+        }
+        if input == app_modes::input::SWITCH_ROS_MASTER {
+            self.switch_ros_master();
+            return;
+        }
+        if input == app_modes::input::TOGGLE_TIMER {
+            self.timer.toggle();
+            return;
+        }
+        if input == app_modes::input::RESET_TIMER {
+            self.timer.reset();
+            return;
+        }
         if input == app_modes::input::SHOW_HELP {
             if !self.show_help {
                 self.show_help = true;
@@ -117,6 +596,12 @@ impl<B: Backend> App<B> {
                 app_modes::input::MODE_7 => new_mode = 7,
                 app_modes::input::MODE_8 => new_mode = 8,
                 app_modes::input::MODE_9 => new_mode = 9,
+                app_modes::input::MODE_10 => new_mode = 10,
+                app_modes::input::MODE_11 => new_mode = 11,
+                app_modes::input::MODE_12 => new_mode = 12,
+                app_modes::input::MODE_13 => new_mode = 13,
+                app_modes::input::MODE_14 => new_mode = 14,
+                app_modes::input::MODE_15 => new_mode = 15,
                 _ => {}
             },
         }
@@ -156,6 +641,39 @@ impl<B: Backend> App<B> {
                 app_modes::input::SHOW_HELP.to_string(),
                 "Opens/closes this page.".to_string(),
             ],
+            [
+                app_modes::input::RECORD_GIF.to_string(),
+                format!(
+                    "Records the screen to {} (press again to stop early).",
+                    self.gif_recording.path
+                ),
+            ],
+            [
+                app_modes::input::TOGGLE_TIMER.to_string(),
+                "Starts/stops the timer.".to_string(),
+            ],
+            [
+                app_modes::input::RESET_TIMER.to_string(),
+                "Stops and hides the timer.".to_string(),
+            ],
+            [
+                app_modes::input::SWITCH_ROS_MASTER.to_string(),
+                "Restarts connected to the next of the ros_masters, if reachable.".to_string(),
+            ],
+            [
+                app_modes::input::EXPORT_TF_TREE.to_string(),
+                format!(
+                    "Saves the TF tree as a Graphviz DOT graph to {}.",
+                    self.config.tf_tree_path
+                ),
+            ],
+            [
+                app_modes::input::EXPORT_CONFIG.to_string(),
+                format!(
+                    "Saves the configuration with the current colors and view to {}.",
+                    EXPORTED_CONFIG_PATH
+                ),
+            ],
             ["Ctrl+c".to_string(), "Quits the application.".to_string()],
         ]);
         for e in &mut key_bindings_raw {
@@ -0,0 +1,210 @@
+//! Alignment mode compares a ground-truth frame with an estimate frame, e.g. a motion
+//! capture frame with the localization of the robot.
+//!
+//! Both frames are looked up from TF in the static frame at each update, and the error is
+//! the transform of the estimate in the ground-truth frame.
+
+use crate::app_modes::viewport::{UseViewport, Viewport};
+use crate::app_modes::{AppMode, BaseMode};
+use crate::config::{AlignmentConfig, MinimapConfig, ThumbnailConfig};
+use crate::minimap::MinimapData;
+use crate::transformation::ros_transform_to_isometry;
+use image::RgbaImage;
+use nalgebra::geometry::Isometry3;
+use std::cell::RefCell;
+use std::rc::Rc;
+use tui::backend::Backend;
+use tui::style::{Color, Style};
+use tui::text::Span;
+use tui::widgets::canvas::{Context, Line};
+
+/// Represents the alignment mode.
+pub struct Alignment {
+    viewport: Rc<RefCell<Viewport>>,
+    config: AlignmentConfig,
+    /// Lines of the panel at the last call of content_changed.
+    last_panel: Vec<String>,
+}
+
+impl Alignment {
+    pub fn new(config: AlignmentConfig, viewport: Rc<RefCell<Viewport>>) -> Alignment {
+        Alignment {
+            viewport,
+            config,
+            last_panel: Vec::new(),
+        }
+    }
+
+    /// Returns the transform of the frame in the static frame, None if it is unavailable.
+    fn lookup(&self, frame: &str) -> Option<rosrust_msg::geometry_msgs::Transform> {
+        let viewport = self.viewport.borrow();
+        viewport
+            .tf_listener
+            .lookup_transform(&viewport.static_frame, frame, rosrust::Time::new())
+            .ok()
+            .map(|transform| transform.transform)
+    }
+
+    /// Returns the pose of the estimate in the ground-truth frame, None if one of the frames
+    /// is unavailable.
+    fn get_error(&self) -> Option<Isometry3<f64>> {
+        let ground_truth =
+            ros_transform_to_isometry(&self.lookup(&self.config.ground_truth_frame)?);
+        let estimate = ros_transform_to_isometry(&self.lookup(&self.config.estimate_frame)?);
+        Some(ground_truth.inverse() * estimate)
+    }
+
+    /// Returns the lines of the panel: the translational and rotational errors, then the
+    /// error along the axes of the ground-truth frame.
+    fn get_panel_lines(&self) -> Vec<String> {
+        let error = match self.get_error() {
+            Some(error) => error,
+            None => {
+                return vec![
+                    "Translation error: N/A".to_string(),
+                    "Rotation error: N/A".to_string(),
+                ]
+            }
+        };
+        let units = &self.viewport.borrow().units;
+        let translation = error.translation.vector;
+        let (_, _, yaw) = error.rotation.euler_angles();
+        vec![
+            format!(
+                "Translation error: {}",
+                units.format_length(translation.norm())
+            ),
+            format!(
+                "Rotation error: {:.1}°",
+                error.rotation.angle().to_degrees()
+            ),
+            format!(
+                "In {}: x {}, y {}, yaw {:.1}°",
+                self.config.ground_truth_frame,
+                units.format_length(translation.x),
+                units.format_length(translation.y),
+                yaw.to_degrees()
+            ),
+        ]
+    }
+}
+
+impl<B: Backend> BaseMode<B> for Alignment {}
+
+impl AppMode for Alignment {
+    fn run(&mut self) {
+        self.viewport.borrow_mut().run();
+    }
+
+    fn reset(&mut self) {}
+
+    fn handle_input(&mut self, input: &String) {
+        self.viewport.borrow_mut().handle_input(input);
+    }
+
+    fn content_changed(&mut self) -> bool {
+        // the frames are not part of the content of the viewport
+        let panel = self.get_panel_lines();
+        let panel_changed = panel != self.last_panel;
+        self.last_panel = panel;
+        self.viewport.borrow_mut().content_changed() || panel_changed
+    }
+
+    fn get_name(&self) -> String {
+        "Alignment".to_string()
+    }
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            format!(
+                "This mode compares the estimate frame {} with the ground-truth frame {}.",
+                self.config.estimate_frame, self.config.ground_truth_frame
+            ),
+            "Both frames are drawn, joined by a line, and the panel shows the error of the estimate in the ground-truth frame."
+                .to_string(),
+            "The error is N/A while one of the frames is not available from TF.".to_string(),
+        ]
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        self.viewport.borrow().get_keymap()
+    }
+}
+
+impl UseViewport for Alignment {
+    fn draw_in_viewport(&self, ctx: &mut Context) {
+        let viewport = self.viewport.borrow();
+        viewport.draw_in_viewport(ctx);
+        let frames = [
+            (
+                &self.config.ground_truth_frame,
+                &self.config.ground_truth_color,
+            ),
+            (&self.config.estimate_frame, &self.config.estimate_color),
+        ];
+        let mut origins = Vec::new();
+        for (frame, color) in frames {
+            let transform = match self.lookup(frame) {
+                Some(transform) => transform,
+                None => continue,
+            };
+            for line in Viewport::get_frame_lines(&transform, viewport.axis_length) {
+                viewport.draw_line(ctx, &line);
+            }
+            ctx.print(
+                transform.translation.x,
+                transform.translation.y,
+                Span::styled(
+                    frame.clone(),
+                    Style::default().fg(Color::Rgb(color.r, color.g, color.b)),
+                ),
+            );
+            origins.push((transform.translation.x, transform.translation.y));
+        }
+        if let [ground_truth, estimate] = origins[..] {
+            viewport.draw_line(
+                ctx,
+                &Line {
+                    x1: ground_truth.0,
+                    y1: ground_truth.1,
+                    x2: estimate.0,
+                    y2: estimate.1,
+                    color: Color::Yellow,
+                },
+            );
+        }
+    }
+
+    fn x_bounds(&self) -> [f64; 2] {
+        self.viewport.borrow().x_bounds()
+    }
+
+    fn y_bounds(&self) -> [f64; 2] {
+        self.viewport.borrow().y_bounds()
+    }
+
+    fn get_thumbnail(&self) -> Option<(RgbaImage, ThumbnailConfig)> {
+        self.viewport.borrow().get_thumbnail()
+    }
+
+    fn get_minimap(&self) -> Option<(MinimapData, MinimapConfig)> {
+        self.viewport.borrow().get_minimap()
+    }
+
+    fn get_panel(&self) -> Option<(String, Vec<String>)> {
+        Some((
+            format!(
+                "{} → {}",
+                self.config.ground_truth_frame, self.config.estimate_frame
+            ),
+            self.get_panel_lines(),
+        ))
+    }
+
+    fn info(&self) -> String {
+        format!(
+            "Ground truth: {}, Estimate: {}",
+            self.config.ground_truth_frame, self.config.estimate_frame
+        )
+    }
+}
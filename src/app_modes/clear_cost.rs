@@ -0,0 +1,316 @@
+//! Clear cost mode clears the obstacles of a costmap in a region under a cursor, by
+//! calling a clearing service and/or publishing the region on a clearing topic.
+
+use crate::app_modes::viewport::{UseViewport, Viewport};
+use crate::app_modes::{input, AppMode, BaseMode};
+use crate::config::{ClearCostConfig, MinimapConfig, ThumbnailConfig};
+use crate::minimap::MinimapData;
+use image::RgbaImage;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tui::backend::Backend;
+use tui::style::Color;
+use tui::widgets::canvas::{Context, Line};
+
+/// Represents the clear cost mode.
+pub struct ClearCost {
+    viewport: Rc<RefCell<Viewport>>,
+    service: Option<String>,
+    topic: Option<String>,
+    publisher: Option<rosrust::Publisher<rosrust_msg::geometry_msgs::PolygonStamped>>,
+    size: f64,
+    /// Position of the cursor in the static frame, at the center of the region.
+    cursor: (f64, f64),
+    increment: f64,
+    /// If true, the next Confirm clears the region.
+    armed: bool,
+    /// Outcome of the last clearing, set by the thread calling the service.
+    status: Arc<Mutex<String>>,
+    /// Status at the last call of content_changed.
+    last_status: String,
+}
+
+impl ClearCost {
+    pub fn new(config: ClearCostConfig, viewport: Rc<RefCell<Viewport>>) -> ClearCost {
+        if config.service.is_none() && config.topic.is_none() {
+            log::warn!(
+                "Neither a service nor a topic is set for clear_cost, nothing will be cleared."
+            );
+        }
+        let increment = viewport.borrow().cursor.step;
+        let mut clear_cost = ClearCost {
+            viewport,
+            publisher: config
+                .topic
+                .as_ref()
+                .map(|topic| rosrust::publish(topic, 1).unwrap()),
+            service: config.service,
+            topic: config.topic,
+            size: config.size,
+            cursor: (0.0, 0.0),
+            increment,
+            armed: false,
+            status: Arc::new(Mutex::new(String::new())),
+            last_status: String::new(),
+        };
+        clear_cost.reset_cursor();
+        clear_cost
+    }
+
+    /// Moves the cursor to the robot, if its transform is available.
+    fn reset_cursor(&mut self) {
+        let viewport = self.viewport.borrow();
+        if let Ok(tf) = viewport.tf_listener.lookup_transform(
+            &viewport.static_frame,
+            &viewport.robot_frame,
+            rosrust::Time::new(),
+        ) {
+            self.cursor = (tf.transform.translation.x, tf.transform.translation.y);
+        }
+    }
+
+    /// Returns the corners of the square region centered on the cursor, in the static frame.
+    fn get_region(&self) -> [(f64, f64); 4] {
+        let half_size = self.size / 2.0;
+        let (x, y) = self.cursor;
+        [
+            (x - half_size, y - half_size),
+            (x + half_size, y - half_size),
+            (x + half_size, y + half_size),
+            (x - half_size, y + half_size),
+        ]
+    }
+
+    /// Publishes the region on the topic and calls the service, if set.
+    ///
+    /// The service is called in a thread, so that a slow service doesn't block the
+    /// interface, and its outcome is shown in the panel.
+    fn clear(&mut self) {
+        if let Some(publisher) = &self.publisher {
+            let mut msg = rosrust_msg::geometry_msgs::PolygonStamped::default();
+            msg.header.frame_id = self.viewport.borrow().static_frame.clone();
+            msg.header.stamp = rosrust::now();
+            msg.polygon.points = self
+                .get_region()
+                .iter()
+                .map(|(x, y)| rosrust_msg::geometry_msgs::Point32 {
+                    x: *x as f32,
+                    y: *y as f32,
+                    z: 0.0,
+                })
+                .collect();
+            if let Err(e) = publisher.send(msg) {
+                log::error!("Could not publish the region to clear: {}", e);
+            }
+        }
+        let service = match &self.service {
+            Some(service) => service.clone(),
+            None => {
+                *self.status.lock().unwrap() = "Region published".to_string();
+                return;
+            }
+        };
+        *self.status.lock().unwrap() = format!("Calling {}...", service);
+        let status = self.status.clone();
+        thread::spawn(move || {
+            let result = rosrust::client::<rosrust_msg::std_srvs::Empty>(&service)
+                .map_err(|e| e.to_string())
+                .and_then(|client| {
+                    client
+                        .req(&rosrust_msg::std_srvs::EmptyReq {})
+                        .map_err(|e| e.to_string())?
+                })
+                .map(|_| ());
+            *status.lock().unwrap() = match result {
+                Ok(()) => format!("Cleared with {}", service),
+                Err(e) => {
+                    log::error!("Could not call {}: {}", service, e);
+                    format!("Call to {} failed: {}", service, e)
+                }
+            };
+        });
+    }
+}
+
+impl<B: Backend> BaseMode<B> for ClearCost {}
+
+impl AppMode for ClearCost {
+    fn run(&mut self) {
+        self.viewport.borrow_mut().run();
+    }
+
+    fn reset(&mut self) {
+        self.armed = false;
+    }
+
+    fn handle_input(&mut self, input: &String) {
+        self.viewport.borrow_mut().handle_input(input);
+        let step = self.viewport.borrow().cursor_step(self.increment);
+        // any other input cancels a pending confirmation
+        let was_armed = std::mem::replace(&mut self.armed, false);
+        match input.as_str() {
+            input::UP => self.cursor.1 += step,
+            input::DOWN => self.cursor.1 -= step,
+            input::RIGHT => self.cursor.0 += step,
+            input::LEFT => self.cursor.0 -= step,
+            input::INCREMENT_STEP => self.increment *= 2.0,
+            input::DECREMENT_STEP => self.increment /= 2.0,
+            input::CANCEL if !was_armed => self.reset_cursor(),
+            input::CONFIRM => {
+                if was_armed {
+                    self.clear();
+                } else {
+                    self.armed = true;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn content_changed(&mut self) -> bool {
+        // the outcome of a service call is not part of the content of the viewport
+        let status = self.status.lock().unwrap().clone();
+        let status_changed = status != self.last_status;
+        self.last_status = status;
+        self.viewport.borrow_mut().content_changed() || status_changed
+    }
+
+    fn get_name(&self) -> String {
+        "Clear Cost".to_string()
+    }
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            "This mode clears the obstacles of a costmap in the region under the cursor."
+                .to_string(),
+            "The region is outlined in yellow, and in red once Confirm was pressed: pressing Confirm again clears it."
+                .to_string(),
+        ]
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        let mut keymap = vec![
+            [
+                input::UP.to_string(),
+                "Shifts the cursor positively along the y axis of the static frame.".to_string(),
+            ],
+            [
+                input::DOWN.to_string(),
+                "Shifts the cursor negatively along the y axis of the static frame.".to_string(),
+            ],
+            [
+                input::RIGHT.to_string(),
+                "Shifts the cursor positively along the x axis of the static frame.".to_string(),
+            ],
+            [
+                input::LEFT.to_string(),
+                "Shifts the cursor negatively along the x axis of the static frame.".to_string(),
+            ],
+            [
+                input::CONFIRM.to_string(),
+                "Asks to clear the region, then clears it when pressed again.".to_string(),
+            ],
+            [
+                input::CANCEL.to_string(),
+                "Cancels the clearing, or moves the cursor back to the robot.".to_string(),
+            ],
+            [
+                input::INCREMENT_STEP.to_string(),
+                "Doubles the step size for moving the cursor.".to_string(),
+            ],
+            [
+                input::DECREMENT_STEP.to_string(),
+                "Halves the step size for moving the cursor.".to_string(),
+            ],
+        ];
+        keymap.extend(self.viewport.borrow().get_keymap());
+        keymap
+    }
+}
+
+impl UseViewport for ClearCost {
+    fn draw_in_viewport(&self, ctx: &mut Context) {
+        let viewport = self.viewport.borrow();
+        viewport.draw_in_viewport(ctx);
+        let color = if self.armed {
+            Color::Red
+        } else {
+            Color::Yellow
+        };
+        let region = self.get_region();
+        for i in 0..region.len() {
+            let (start, end) = (region[i], region[(i + 1) % region.len()]);
+            viewport.draw_line(
+                ctx,
+                &Line {
+                    x1: start.0,
+                    y1: start.1,
+                    x2: end.0,
+                    y2: end.1,
+                    color,
+                },
+            );
+        }
+    }
+
+    fn x_bounds(&self) -> [f64; 2] {
+        let viewport = self.viewport.borrow();
+        let scale_factor = viewport.terminal_size.0 as f64 / viewport.terminal_size.1 as f64 * 0.5;
+        [
+            self.cursor.0 + viewport.initial_bounds[0] / viewport.zoom * scale_factor,
+            self.cursor.0 + viewport.initial_bounds[1] / viewport.zoom * scale_factor,
+        ]
+    }
+
+    fn y_bounds(&self) -> [f64; 2] {
+        let viewport = self.viewport.borrow();
+        [
+            self.cursor.1 + viewport.initial_bounds[2] / viewport.zoom,
+            self.cursor.1 + viewport.initial_bounds[3] / viewport.zoom,
+        ]
+    }
+
+    fn get_thumbnail(&self) -> Option<(RgbaImage, ThumbnailConfig)> {
+        self.viewport.borrow().get_thumbnail()
+    }
+
+    fn get_minimap(&self) -> Option<(MinimapData, MinimapConfig)> {
+        self.viewport.borrow().get_minimap()
+    }
+
+    fn get_panel(&self) -> Option<(String, Vec<String>)> {
+        let mut lines = Vec::new();
+        if self.armed {
+            lines.push("Press Confirm again to clear, any other key cancels".to_string());
+        }
+        let status = self.status.lock().unwrap();
+        if !status.is_empty() {
+            lines.push(status.clone());
+        }
+        if lines.is_empty() {
+            return None;
+        }
+        Some((
+            format!(
+                "Region of {} m at {}",
+                self.size,
+                self.viewport
+                    .borrow()
+                    .format_position(self.cursor.0, self.cursor.1)
+            ),
+            lines,
+        ))
+    }
+
+    fn info(&self) -> String {
+        let targets: Vec<String> = self
+            .service
+            .iter()
+            .map(|service| format!("Service: {}", service))
+            .chain(self.topic.iter().map(|topic| format!("Topic: /{}", topic)))
+            .collect();
+        format!("{}, Cursor step: {:.3}", targets.join(", "), self.increment)
+    }
+}
@@ -0,0 +1,191 @@
+//! Diagnostic status mode shows the statuses received on a diagnostic_msgs::DiagnosticArray
+//! topic, e.g. the aggregated diagnostics of a robot, as a lightweight dashboard.
+
+use crate::app_modes::{input, AppMode, BaseMode, Drawable};
+use crate::config::DiagnosticStatusConfig;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tui::backend::Backend;
+use tui::layout::{Alignment, Constraint, Layout};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Paragraph, Wrap};
+use tui::Frame;
+
+/// Last status received for each name, with the instant it was received.
+type Statuses = BTreeMap<String, (Instant, rosrust_msg::diagnostic_msgs::DiagnosticStatus)>;
+
+/// Returns the label and the color of a diagnostic level.
+fn get_level_style(level: i32) -> (&'static str, Color) {
+    match level {
+        0 => ("OK", Color::Green),
+        1 => ("WARN", Color::Yellow),
+        2 => ("ERROR", Color::Red),
+        _ => ("STALE", Color::Gray),
+    }
+}
+
+/// Represents the diagnostic status mode.
+pub struct DiagnosticStatus {
+    topic: String,
+    /// Time after which a status that was not refreshed is stale, None to never be.
+    timeout: Option<Duration>,
+    /// If true, the stale statuses are hidden instead of dimmed.
+    drop_stale: bool,
+    statuses: Arc<RwLock<Statuses>>,
+    /// First line shown, scrolled with Up and Down.
+    scroll: u16,
+    _subscriber: rosrust::Subscriber,
+}
+
+impl DiagnosticStatus {
+    pub fn new(config: DiagnosticStatusConfig) -> DiagnosticStatus {
+        let statuses = Arc::new(RwLock::new(BTreeMap::new()));
+        let cb_statuses = statuses.clone();
+        let sub = rosrust::subscribe(
+            &config.topic,
+            10,
+            move |msg: rosrust_msg::diagnostic_msgs::DiagnosticArray| {
+                let now = Instant::now();
+                // an array can update only some of the statuses, e.g. those of one node
+                let mut statuses = cb_statuses.write().unwrap();
+                for status in msg.status {
+                    statuses.insert(status.name.clone(), (now, status));
+                }
+            },
+        )
+        .unwrap();
+
+        DiagnosticStatus {
+            topic: config.topic,
+            timeout: if config.timeout > 0.0 {
+                Some(Duration::from_secs_f64(config.timeout))
+            } else {
+                None
+            },
+            drop_stale: config.drop_stale,
+            statuses,
+            scroll: 0,
+            _subscriber: sub,
+        }
+    }
+
+    /// Returns the lines of the statuses: the level, the name and the message of each
+    /// status, followed by its key-value pairs.
+    fn get_lines(&self) -> Vec<Spans<'static>> {
+        let mut lines = Vec::new();
+        for (name, (time, status)) in self.statuses.read().unwrap().iter() {
+            let stale = self.timeout.is_some_and(|timeout| time.elapsed() > timeout);
+            if stale && self.drop_stale {
+                continue;
+            }
+            let (label, color) = get_level_style(i32::from(status.level));
+            let (level_style, text_style) = if stale {
+                (
+                    Style::default().fg(Color::DarkGray),
+                    Style::default().fg(Color::DarkGray),
+                )
+            } else {
+                (
+                    Style::default().fg(color).add_modifier(Modifier::BOLD),
+                    Style::default().fg(Color::White),
+                )
+            };
+            let mut spans = vec![
+                Span::styled(format!("{:<7}", format!("[{}]", label)), level_style),
+                Span::styled(name.clone(), text_style.add_modifier(Modifier::BOLD)),
+            ];
+            if !status.message.is_empty() {
+                spans.push(Span::styled(format!(": {}", status.message), text_style));
+            }
+            if stale {
+                spans.push(Span::styled(" (stale)", text_style));
+            }
+            lines.push(Spans::from(spans));
+            for value in &status.values {
+                lines.push(Spans::from(Span::styled(
+                    format!("        {}: {}", value.key, value.value),
+                    text_style,
+                )));
+            }
+        }
+        lines
+    }
+}
+
+impl AppMode for DiagnosticStatus {
+    fn run(&mut self) {}
+    fn reset(&mut self) {}
+    fn content_changed(&mut self) -> bool {
+        true
+    }
+
+    fn handle_input(&mut self, input: &String) {
+        match input.as_str() {
+            input::UP => self.scroll = self.scroll.saturating_sub(1),
+            input::DOWN => {
+                let last_line = self.get_lines().len().saturating_sub(1) as u16;
+                self.scroll = (self.scroll + 1).min(last_line);
+            }
+            _ => (),
+        }
+    }
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            format!(
+                "This mode shows the statuses received on /{}, with their level, message and values.",
+                self.topic.trim_start_matches('/')
+            ),
+            "The statuses that are not refreshed in time are dimmed as stale, or hidden.".to_string(),
+        ]
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        vec![
+            [input::UP.to_string(), "Scrolls up.".to_string()],
+            [input::DOWN.to_string(), "Scrolls down.".to_string()],
+        ]
+    }
+
+    fn get_name(&self) -> String {
+        "Diagnostic Status".to_string()
+    }
+}
+
+impl<B: Backend> Drawable<B> for DiagnosticStatus {
+    fn draw(&self, f: &mut Frame<B>) {
+        let chunks = Layout::default()
+            .constraints([Constraint::Length(1), Constraint::Min(0)].as_ref())
+            .split(f.size());
+
+        let lines = self.get_lines();
+        let header = Paragraph::new(Spans::from(vec![
+            Span::styled(
+                self.get_name(),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(if self.statuses.read().unwrap().is_empty() {
+                format!(
+                    " - No status received on /{}",
+                    self.topic.trim_start_matches('/')
+                )
+            } else {
+                format!(" - /{}", self.topic.trim_start_matches('/'))
+            }),
+        ]))
+        .block(Block::default().borders(Borders::NONE))
+        .style(Style::default().fg(Color::White))
+        .alignment(Alignment::Left);
+        f.render_widget(header, chunks[0]);
+
+        let statuses = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::NONE))
+            .scroll((self.scroll, 0))
+            .wrap(Wrap { trim: false });
+        f.render_widget(statuses, chunks[1]);
+    }
+}
+
+impl<B: Backend> BaseMode<B> for DiagnosticStatus {}
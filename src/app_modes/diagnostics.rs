@@ -0,0 +1,119 @@
+//! Diagnostics mode shows the health of the subscribed topics.
+
+use crate::app_modes::{AppMode, BaseMode, Drawable};
+use crate::liveness::PublisherMonitor;
+use crate::rate::{RateMonitor, HISTORY_LENGTH};
+use tui::backend::Backend;
+use tui::layout::{Alignment, Constraint, Direction, Layout};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Paragraph, Sparkline, Wrap};
+use tui::Frame;
+
+pub struct Diagnostics {
+    rates: Vec<(String, RateMonitor)>,
+    publishers: PublisherMonitor,
+}
+
+impl Diagnostics {
+    pub fn new(rates: Vec<(String, RateMonitor)>, publishers: PublisherMonitor) -> Diagnostics {
+        Diagnostics { rates, publishers }
+    }
+}
+
+impl AppMode for Diagnostics {
+    fn run(&mut self) {}
+    fn reset(&mut self) {}
+    fn content_changed(&mut self) -> bool {
+        true
+    }
+    fn handle_input(&mut self, _input: &String) {}
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            "This mode shows the rate at which the messages of each topic are received."
+                .to_string(),
+            format!(
+                "Each bar is the number of messages received in one second, over the last {} seconds.",
+                HISTORY_LENGTH
+            ),
+            "Each topic is marked active if it has publishers according to the ROS master, so a node that is down can be told from a node that doesn't publish."
+                .to_string(),
+        ]
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        vec![]
+    }
+
+    fn get_name(&self) -> String {
+        "Diagnostics".to_string()
+    }
+}
+
+impl<B: Backend> Drawable<B> for Diagnostics {
+    fn draw(&self, f: &mut Frame<B>) {
+        let mut constraints = vec![Constraint::Length(1)];
+        constraints.extend(self.rates.iter().map(|_| Constraint::Length(2)));
+        constraints.push(Constraint::Min(0));
+        let chunks = Layout::default().constraints(constraints).split(f.size());
+
+        let header = Paragraph::new(Spans::from(vec![
+            Span::styled(
+                self.get_name(),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(if self.rates.is_empty() {
+                " - No topic configured!"
+            } else {
+                " - Messages per second"
+            }),
+        ]))
+        .block(Block::default().borders(Borders::NONE))
+        .style(Style::default().fg(Color::White))
+        .alignment(Alignment::Left)
+        .wrap(Wrap { trim: false });
+        f.render_widget(header, chunks[0]);
+
+        for (i, (topic, rate)) in self.rates.iter().enumerate() {
+            let counts = rate.get_counts();
+            let row = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Length(40), Constraint::Min(0)].as_ref())
+                .split(chunks[i + 1]);
+            let mut spans = vec![
+                Span::raw(format!("/{} ", topic.trim_start_matches('/'))),
+                Span::styled(
+                    format!("{} Hz", counts.last().unwrap_or(&0)),
+                    Style::default().fg(Color::Yellow),
+                ),
+            ];
+            if !rate.is_subscribed() {
+                spans.push(Span::styled(
+                    " (not subscribed)",
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            match self.publishers.has_publishers(topic) {
+                Some(true) => {
+                    spans.push(Span::styled(" active", Style::default().fg(Color::Green)))
+                }
+                Some(false) => spans.push(Span::styled(
+                    " no publishers",
+                    Style::default().fg(Color::Red),
+                )),
+                None => (),
+            }
+            let label = Paragraph::new(Spans::from(spans))
+                .block(Block::default().borders(Borders::NONE))
+                .style(Style::default().fg(Color::White));
+            f.render_widget(label, row[0]);
+            let sparkline = Sparkline::default()
+                .data(&counts)
+                .style(Style::default().fg(Color::Green));
+            f.render_widget(sparkline, row[1]);
+        }
+    }
+}
+
+impl<B: Backend> BaseMode<B> for Diagnostics {}
@@ -45,6 +45,10 @@ impl AppMode for ImageView {
         }
     }
 
+    fn content_changed(&mut self) -> bool {
+        true
+    }
+
     fn handle_input(&mut self, input: &String) {
         if self.images.len() > 0 {
             match input.as_str() {
@@ -0,0 +1,230 @@
+//! Inspect cell mode shows the values of the map cells under a cursor, e.g. to read a
+//! costmap.
+
+use crate::app_modes::viewport::{UseViewport, Viewport};
+use crate::app_modes::{input, AppMode, BaseMode};
+use crate::config::{MinimapConfig, ThumbnailConfig};
+use crate::minimap::MinimapData;
+use image::RgbaImage;
+use std::cell::RefCell;
+use std::rc::Rc;
+use tui::backend::Backend;
+use tui::style::Color;
+use tui::widgets::canvas::{Context, Line};
+
+/// Represents the inspect cell mode.
+pub struct InspectCell {
+    viewport: Rc<RefCell<Viewport>>,
+    /// Position of the cursor in the static frame.
+    cursor: (f64, f64),
+    increment: f64,
+}
+
+impl InspectCell {
+    pub fn new(viewport: Rc<RefCell<Viewport>>) -> InspectCell {
+        let increment = viewport.borrow().cursor.step;
+        let mut inspect_cell = InspectCell {
+            viewport,
+            cursor: (0.0, 0.0),
+            increment,
+        };
+        inspect_cell.reset_cursor();
+        inspect_cell
+    }
+
+    /// Moves the cursor to the robot, if its transform is available.
+    fn reset_cursor(&mut self) {
+        let viewport = self.viewport.borrow();
+        if let Ok(tf) = viewport.tf_listener.lookup_transform(
+            &viewport.static_frame,
+            &viewport.robot_frame,
+            rosrust::Time::new(),
+        ) {
+            self.cursor = (tf.transform.translation.x, tf.transform.translation.y);
+        }
+    }
+
+    /// Returns the lines of the panel: the value of the cell under the cursor in each map
+    /// containing it.
+    fn get_panel_lines(&self) -> Vec<String> {
+        let viewport = self.viewport.borrow();
+        let lines: Vec<String> = viewport
+            .listeners
+            .maps
+            .iter()
+            .filter_map(|map| {
+                let cell = map.cell_at_point(self.cursor.0, self.cursor.1)?;
+                let value = if cell.value == map.config.unknown_value {
+                    "unknown".to_string()
+                } else {
+                    cell.value.to_string()
+                };
+                Some(format!(
+                    "{}: {} at ({}, {})",
+                    map.config.topic, value, cell.column, cell.line
+                ))
+            })
+            .collect();
+        if lines.is_empty() {
+            return vec!["No map under the cursor".to_string()];
+        }
+        lines
+    }
+}
+
+impl<B: Backend> BaseMode<B> for InspectCell {}
+
+impl AppMode for InspectCell {
+    fn run(&mut self) {
+        self.viewport.borrow_mut().run();
+    }
+
+    fn reset(&mut self) {}
+
+    fn handle_input(&mut self, input: &String) {
+        self.viewport.borrow_mut().handle_input(input);
+        let step = self.viewport.borrow().cursor_step(self.increment);
+        match input.as_str() {
+            input::UP => self.cursor.1 += step,
+            input::DOWN => self.cursor.1 -= step,
+            input::RIGHT => self.cursor.0 += step,
+            input::LEFT => self.cursor.0 -= step,
+            input::INCREMENT_STEP => self.increment *= 2.0,
+            input::DECREMENT_STEP => self.increment /= 2.0,
+            input::CANCEL => self.reset_cursor(),
+            _ => (),
+        }
+    }
+
+    fn content_changed(&mut self) -> bool {
+        self.viewport.borrow_mut().content_changed()
+    }
+
+    fn get_name(&self) -> String {
+        "Inspect Cell".to_string()
+    }
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            "This mode shows the value of the cell under the cursor in each map.".to_string(),
+            "The outline of the inspected cell is drawn in yellow, nothing is drawn outside of the maps."
+                .to_string(),
+        ]
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        let mut keymap = vec![
+            [
+                input::UP.to_string(),
+                "Shifts the cursor positively along the y axis of the static frame.".to_string(),
+            ],
+            [
+                input::DOWN.to_string(),
+                "Shifts the cursor negatively along the y axis of the static frame.".to_string(),
+            ],
+            [
+                input::RIGHT.to_string(),
+                "Shifts the cursor positively along the x axis of the static frame.".to_string(),
+            ],
+            [
+                input::LEFT.to_string(),
+                "Shifts the cursor negatively along the x axis of the static frame.".to_string(),
+            ],
+            [
+                input::CANCEL.to_string(),
+                "Moves the cursor back to the robot.".to_string(),
+            ],
+            [
+                input::INCREMENT_STEP.to_string(),
+                "Doubles the step size for moving the cursor.".to_string(),
+            ],
+            [
+                input::DECREMENT_STEP.to_string(),
+                "Halves the step size for moving the cursor.".to_string(),
+            ],
+        ];
+        keymap.extend(self.viewport.borrow().get_keymap());
+        keymap
+    }
+}
+
+impl UseViewport for InspectCell {
+    fn draw_in_viewport(&self, ctx: &mut Context) {
+        let viewport = self.viewport.borrow();
+        viewport.draw_in_viewport(ctx);
+        for map in &viewport.listeners.maps {
+            let corners = match map.cell_at_point(self.cursor.0, self.cursor.1) {
+                Some(cell) => cell.corners,
+                None => continue,
+            };
+            for i in 0..corners.len() {
+                let (start, end) = (corners[i], corners[(i + 1) % corners.len()]);
+                viewport.draw_line(
+                    ctx,
+                    &Line {
+                        x1: start.0,
+                        y1: start.1,
+                        x2: end.0,
+                        y2: end.1,
+                        color: Color::Yellow,
+                    },
+                );
+            }
+        }
+        // a small cross at the cursor
+        let size = viewport.axis_length / 4.0;
+        for (dx, dy) in [(size, 0.0), (0.0, size)] {
+            viewport.draw_line(
+                ctx,
+                &Line {
+                    x1: self.cursor.0 - dx,
+                    y1: self.cursor.1 - dy,
+                    x2: self.cursor.0 + dx,
+                    y2: self.cursor.1 + dy,
+                    color: Color::Gray,
+                },
+            );
+        }
+    }
+
+    fn x_bounds(&self) -> [f64; 2] {
+        let viewport = self.viewport.borrow();
+        let scale_factor = viewport.terminal_size.0 as f64 / viewport.terminal_size.1 as f64 * 0.5;
+        [
+            self.cursor.0 + viewport.initial_bounds[0] / viewport.zoom * scale_factor,
+            self.cursor.0 + viewport.initial_bounds[1] / viewport.zoom * scale_factor,
+        ]
+    }
+
+    fn y_bounds(&self) -> [f64; 2] {
+        let viewport = self.viewport.borrow();
+        [
+            self.cursor.1 + viewport.initial_bounds[2] / viewport.zoom,
+            self.cursor.1 + viewport.initial_bounds[3] / viewport.zoom,
+        ]
+    }
+
+    fn get_thumbnail(&self) -> Option<(RgbaImage, ThumbnailConfig)> {
+        self.viewport.borrow().get_thumbnail()
+    }
+
+    fn get_minimap(&self) -> Option<(MinimapData, MinimapConfig)> {
+        self.viewport.borrow().get_minimap()
+    }
+
+    fn get_panel(&self) -> Option<(String, Vec<String>)> {
+        Some((
+            format!(
+                "Cell at {}",
+                self.viewport
+                    .borrow()
+                    .format_position(self.cursor.0, self.cursor.1)
+            ),
+            self.get_panel_lines(),
+        ))
+    }
+
+    fn info(&self) -> String {
+        format!("Cursor step: {:.3}", self.increment)
+    }
+}
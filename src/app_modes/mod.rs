@@ -1,10 +1,21 @@
 //! A module that contains all the builing blocks to create app modes, as well as the app modes themselves.
 
+pub mod alignment;
+pub mod clear_cost;
+pub mod diagnostic_status;
+pub mod diagnostics;
 pub mod image_view;
+pub mod inspect_cell;
+pub mod polygon_editor;
+pub mod pose_diff;
 pub mod send_pose;
 pub mod teleoperate;
+pub mod tf_echo;
 pub mod topic_managment;
+pub mod tracking_error;
+pub mod twist_estimate;
 pub mod viewport;
+pub mod waypoints;
 
 use tui::backend::Backend;
 use tui::Frame;
@@ -21,6 +32,12 @@ pub mod input {
     pub const MODE_7: &str = "Switch to mode 7";
     pub const MODE_8: &str = "Switch to mode 8";
     pub const MODE_9: &str = "Switch to mode 9";
+    pub const MODE_10: &str = "Switch to mode 10";
+    pub const MODE_11: &str = "Switch to mode 11";
+    pub const MODE_12: &str = "Switch to mode 12";
+    pub const MODE_13: &str = "Switch to mode 13";
+    pub const MODE_14: &str = "Switch to mode 14";
+    pub const MODE_15: &str = "Switch to mode 15";
     pub const LEFT: &str = "Left";
     pub const RIGHT: &str = "Right";
     pub const UP: &str = "Up";
@@ -36,7 +53,91 @@ pub mod input {
     pub const NEXT: &str = "Next";
     pub const PREVIOUS: &str = "Previous";
     pub const SHOW_HELP: &str = "Show help";
+    pub const CYCLE_FOLLOW_FRAME: &str = "Cycle followed frame";
+    pub const EXPORT_MAP: &str = "Export map";
+    pub const TOGGLE_SCALE_BAR: &str = "Toggle scale bar";
+    pub const TOGGLE_MAP_HUD: &str = "Toggle map HUD";
+    pub const TOGGLE_POSE_READOUT: &str = "Toggle pose readout";
+    pub const TOGGLE_RELATIVE_COORDINATES: &str = "Toggle relative coordinates";
+    pub const TOGGLE_TIME_SYNC: &str = "Toggle time sync";
+    pub const CYCLE_ACTIVE_MAP: &str = "Cycle active map";
+    pub const TOGGLE_POSE_DENSITY: &str = "Toggle pose density";
+    pub const TOGGLE_LAZY_LAYERS: &str = "Toggle lazy layers";
+    pub const CYCLE_MAP_STYLE: &str = "Cycle map style";
+    pub const TOGGLE_MINIMAP: &str = "Toggle minimap";
+    pub const NEXT_FOOTPRINT: &str = "Next footprint parameter";
+    pub const RECORD_GIF: &str = "Record GIF";
+    pub const TOGGLE_TIMER: &str = "Start/stop timer";
+    pub const RESET_TIMER: &str = "Reset timer";
+    pub const SWITCH_ROS_MASTER: &str = "Switch ROS master";
+    pub const CANCEL_GOAL: &str = "Cancel goal";
+    pub const ADD_WAYPOINT: &str = "Add waypoint";
+    pub const UNDO: &str = "Undo";
+    pub const STORE_VIEW: &str = "Store view";
+    pub const TOGGLE_SNAPSHOT: &str = "Toggle snapshot";
+    /// Prefix of the inputs of the view slots, followed by the number of the slot.
+    pub const VIEW_SLOT: &str = "View slot ";
+    /// Prefix of the cursor movements with a fine step, e.g. with Shift.
+    pub const FINE: &str = "Fine ";
+    /// Prefix of the cursor movements with a coarse step, e.g. with Alt.
+    pub const COARSE: &str = "Coarse ";
+    pub const OPEN_PALETTE: &str = "Command palette";
+    pub const EXPORT_CONFIG: &str = "Export config";
+    pub const EXPORT_TF_TREE: &str = "Export TF tree";
     pub const UNMAPPED: &str = "Any other";
+
+    /// The actions switching to each mode, in order.
+    pub const MODES: [&str; 15] = [
+        MODE_1, MODE_2, MODE_3, MODE_4, MODE_5, MODE_6, MODE_7, MODE_8, MODE_9, MODE_10, MODE_11,
+        MODE_12, MODE_13, MODE_14, MODE_15,
+    ];
+
+    /// The actions moving the cursor of the interactive modes, which accept the FINE and
+    /// COARSE prefixes.
+    pub const CURSOR_MOVES: [&str; 6] = [UP, DOWN, LEFT, RIGHT, ROTATE_LEFT, ROTATE_RIGHT];
+
+    /// All the other actions that can be triggered by name, e.g. from the command palette.
+    pub const ACTIONS: &[&str] = &[
+        LEFT,
+        RIGHT,
+        UP,
+        DOWN,
+        ROTATE_LEFT,
+        ROTATE_RIGHT,
+        CONFIRM,
+        CANCEL,
+        ZOOM_IN,
+        ZOOM_OUT,
+        INCREMENT_STEP,
+        DECREMENT_STEP,
+        NEXT,
+        PREVIOUS,
+        SHOW_HELP,
+        CYCLE_FOLLOW_FRAME,
+        EXPORT_MAP,
+        TOGGLE_SCALE_BAR,
+        TOGGLE_MAP_HUD,
+        TOGGLE_POSE_READOUT,
+        TOGGLE_RELATIVE_COORDINATES,
+        TOGGLE_TIME_SYNC,
+        CYCLE_ACTIVE_MAP,
+        TOGGLE_POSE_DENSITY,
+        TOGGLE_LAZY_LAYERS,
+        CYCLE_MAP_STYLE,
+        TOGGLE_MINIMAP,
+        NEXT_FOOTPRINT,
+        RECORD_GIF,
+        TOGGLE_TIMER,
+        RESET_TIMER,
+        SWITCH_ROS_MASTER,
+        CANCEL_GOAL,
+        ADD_WAYPOINT,
+        UNDO,
+        STORE_VIEW,
+        TOGGLE_SNAPSHOT,
+        EXPORT_CONFIG,
+        EXPORT_TF_TREE,
+    ];
 }
 
 /// Represents all the basic methods that an app mode must implement.
@@ -47,6 +148,10 @@ pub trait AppMode {
     /// Runs when the mode is reset (e.g., if switching from another mode).
     fn reset(&mut self);
 
+    /// Returns true if what the mode draws may have changed since the last call, e.g.,
+    /// because new messages were received. Used to skip drawing identical frames.
+    fn content_changed(&mut self) -> bool;
+
     /// Handles the received input.
     ///
     /// # Arguments
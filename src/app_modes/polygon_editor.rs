@@ -0,0 +1,326 @@
+//! Polygon mode allows to draw a polygon, e.g. a keepout zone, and to send it.
+
+use crate::app_modes::viewport::{UseViewport, Viewport};
+use crate::app_modes::{input, AppMode, BaseMode};
+use crate::config::{MinimapConfig, PolygonEditorConfig, PolygonListenerConfig, ThumbnailConfig};
+use crate::minimap::MinimapData;
+use crate::polygon::PolygonData;
+use crate::transformation;
+use image::RgbaImage;
+use nalgebra::{Isometry2, Vector2};
+use std::cell::RefCell;
+use std::rc::Rc;
+use tui::backend::Backend;
+use tui::style::Color;
+use tui::widgets::canvas::{Context, Line};
+
+/// Represents the polygon mode.
+pub struct PolygonEditor {
+    viewport: Rc<RefCell<Viewport>>,
+    topic: String,
+    publisher: rosrust::Publisher<rosrust_msg::geometry_msgs::PolygonStamped>,
+    /// Draws the preview like the polygon topics.
+    preview: PolygonData,
+    increment: f64,
+    robot_pose: Isometry2<f64>,
+    cursor: Isometry2<f64>,
+    cursor_active: bool,
+    /// The placed vertices, in the static frame.
+    vertices: Vec<(f64, f64)>,
+}
+
+impl PolygonEditor {
+    pub fn new(config: PolygonEditorConfig, viewport: Rc<RefCell<Viewport>>) -> PolygonEditor {
+        let base_link_pose = viewport
+            .borrow()
+            .tf_listener
+            .lookup_transform(
+                &viewport.borrow().static_frame,
+                &viewport.borrow().robot_frame,
+                rosrust::Time::new(),
+            )
+            .unwrap()
+            .transform;
+        let robot_pose = transformation::ros_to_iso2d(&base_link_pose);
+        let preview = PolygonData::new(PolygonListenerConfig {
+            topic: config.topic.clone(),
+            color: config.color,
+            z_style: "project".to_string(),
+            fill: "none".to_string(),
+            hatch_spacing: 0.2,
+            hatch_angle: 45.0,
+            outline_on_top: true,
+            vertices: None,
+            frame_filter: None,
+            override_frame_id: None,
+            lookup_tolerance: 0.0,
+            opacity: 1.0,
+            center_on_first: false,
+        });
+
+        let increment = viewport.borrow().cursor.step;
+        PolygonEditor {
+            viewport,
+            publisher: rosrust::publish(&config.topic, 1).unwrap(),
+            topic: config.topic,
+            preview,
+            increment,
+            robot_pose,
+            cursor: robot_pose,
+            cursor_active: false,
+            vertices: Vec::new(),
+        }
+    }
+
+    fn move_cursor(&mut self, x: f64, y: f64, yaw: f64) {
+        let new_yaw = self.cursor.rotation.angle() + yaw;
+        let new_x = x * new_yaw.cos() - y * new_yaw.sin() + self.cursor.translation.x;
+        let new_y = x * new_yaw.sin() + y * new_yaw.cos() + self.cursor.translation.y;
+        self.cursor = Isometry2::new(Vector2::new(new_x, new_y), new_yaw);
+        self.cursor_active = true;
+    }
+
+    /// Returns the polygon made of the given vertices.
+    fn get_polygon(vertices: &[(f64, f64)]) -> rosrust_msg::geometry_msgs::Polygon {
+        rosrust_msg::geometry_msgs::Polygon {
+            points: vertices
+                .iter()
+                .map(|(x, y)| rosrust_msg::geometry_msgs::Point32 {
+                    x: *x as f32,
+                    y: *y as f32,
+                    z: 0.0,
+                })
+                .collect(),
+        }
+    }
+
+    /// Updates the preview after a change of the vertices.
+    fn update_preview(&mut self) {
+        if self.vertices.len() < 2 {
+            self.preview.lines_in_static_frame.clear();
+            return;
+        }
+        // the vertices are already in the static frame
+        self.preview.update(
+            &PolygonEditor::get_polygon(&self.vertices),
+            &transformation::iso2d_to_ros(&Isometry2::identity()),
+        );
+    }
+
+    fn send_polygon(&mut self) {
+        if self.vertices.is_empty() {
+            return;
+        }
+        let mut msg = rosrust_msg::geometry_msgs::PolygonStamped {
+            polygon: PolygonEditor::get_polygon(&self.vertices),
+            ..Default::default()
+        };
+        msg.header.frame_id = self.viewport.borrow().static_frame.to_string();
+        msg.header.stamp = rosrust::now();
+        self.publisher.send(msg).unwrap();
+        self.vertices.clear();
+        self.cursor_active = false;
+    }
+}
+
+impl<B: Backend> BaseMode<B> for PolygonEditor {}
+
+impl AppMode for PolygonEditor {
+    fn run(&mut self) {
+        self.viewport.borrow_mut().run();
+        let base_link_pose = self
+            .viewport
+            .borrow()
+            .tf_listener
+            .lookup_transform(
+                &self.viewport.borrow().static_frame,
+                &self.viewport.borrow().robot_frame,
+                rosrust::Time::new(),
+            )
+            .unwrap()
+            .transform;
+        self.robot_pose = transformation::ros_to_iso2d(&base_link_pose);
+        if !self.cursor_active {
+            self.cursor = self.robot_pose;
+        }
+    }
+    fn reset(&mut self) {
+        self.cursor_active = false;
+        self.run(); // Update the robot pose
+    }
+    fn handle_input(&mut self, input: &String) {
+        self.viewport.borrow_mut().handle_input(input);
+        let step = self.viewport.borrow().cursor_step(self.increment);
+        let angle_step = self.viewport.borrow().rotation_step(self.increment);
+        match input.as_str() {
+            input::UP => self.move_cursor(step, 0.0, 0.0),
+            input::DOWN => self.move_cursor(-step, 0.0, 0.0),
+            input::LEFT => self.move_cursor(0.0, step, 0.0),
+            input::RIGHT => self.move_cursor(0.0, -step, 0.0),
+            input::ROTATE_LEFT => self.move_cursor(0.0, 0.0, angle_step),
+            input::ROTATE_RIGHT => self.move_cursor(0.0, 0.0, -angle_step),
+            input::INCREMENT_STEP => self.increment += 0.1,
+            input::DECREMENT_STEP => self.increment -= 0.1,
+            input::ADD_WAYPOINT => {
+                self.vertices
+                    .push((self.cursor.translation.x, self.cursor.translation.y));
+                self.cursor_active = true;
+            }
+            input::UNDO => {
+                self.vertices.pop();
+            }
+            input::CANCEL => {
+                self.vertices.clear();
+                self.reset();
+            }
+            input::CONFIRM => self.send_polygon(),
+            _ => return,
+        }
+        self.update_preview();
+    }
+
+    fn content_changed(&mut self) -> bool {
+        self.viewport.borrow_mut().content_changed()
+    }
+
+    fn get_name(&self) -> String {
+        "Polygon".to_string()
+    }
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            "This mode allows to draw a polygon, e.g. a keepout zone, and to publish it."
+                .to_string(),
+            "The vertices are placed one after the other at the position of the cursor. The polygon is drawn closed, and the gray edges show it with a vertex added at the cursor."
+                .to_string(),
+            "The polygon is sent in the static frame. The top bar shows the topic and the number of placed vertices."
+                .to_string(),
+        ]
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        let mut keymap = vec![
+            [
+                input::UP.to_string(),
+                "Shifts the cursor positively along the x axis.".to_string(),
+            ],
+            [
+                input::DOWN.to_string(),
+                "Shifts the cursor negatively along the x axis.".to_string(),
+            ],
+            [
+                input::RIGHT.to_string(),
+                "Shifts the cursor positively along the y axis.".to_string(),
+            ],
+            [
+                input::LEFT.to_string(),
+                "Shifts the cursor negatively along the y axis.".to_string(),
+            ],
+            [
+                input::ROTATE_LEFT.to_string(),
+                "Rotates the directions in which the cursor moves counter-clockwise.".to_string(),
+            ],
+            [
+                input::ROTATE_RIGHT.to_string(),
+                "Rotates the directions in which the cursor moves clockwise.".to_string(),
+            ],
+            [
+                input::ADD_WAYPOINT.to_string(),
+                "Adds a vertex at the cursor.".to_string(),
+            ],
+            [
+                input::UNDO.to_string(),
+                "Removes the last vertex.".to_string(),
+            ],
+            [
+                input::CANCEL.to_string(),
+                "Removes all the vertices and resets the cursor.".to_string(),
+            ],
+            [input::CONFIRM.to_string(), "Sends the polygon.".to_string()],
+            [
+                input::INCREMENT_STEP.to_string(),
+                "Increases the step size for moving the cursor.".to_string(),
+            ],
+            [
+                input::DECREMENT_STEP.to_string(),
+                "Decreases the step size for moving the cursor.".to_string(),
+            ],
+        ];
+        keymap.extend(self.viewport.borrow().get_keymap());
+        keymap
+    }
+}
+
+impl UseViewport for PolygonEditor {
+    fn draw_in_viewport(&self, ctx: &mut Context) {
+        self.viewport.borrow().draw_in_viewport(ctx);
+        for line in &self.preview.lines_in_static_frame {
+            self.viewport.borrow().draw_line(ctx, line);
+        }
+        // the edges that would be added with the next vertex
+        let cursor = (self.cursor.translation.x, self.cursor.translation.y);
+        let ends = match (self.vertices.first(), self.vertices.last()) {
+            (Some(first), Some(last)) if self.vertices.len() > 1 => vec![*first, *last],
+            (Some(first), _) => vec![*first],
+            _ => Vec::new(),
+        };
+        for end in ends {
+            self.viewport.borrow().draw_line(
+                ctx,
+                &Line {
+                    x1: end.0,
+                    y1: end.1,
+                    x2: cursor.0,
+                    y2: cursor.1,
+                    color: Color::Gray,
+                },
+            );
+        }
+        if self.cursor_active {
+            let cursor_ros = transformation::iso2d_to_ros(&self.cursor);
+            let axis_length = self.viewport.borrow().axis_length;
+            for mut line in Viewport::get_frame_lines(&cursor_ros, axis_length) {
+                line.color = Color::Gray;
+                self.viewport.borrow().draw_line(ctx, &line);
+            }
+        }
+    }
+    fn x_bounds(&self) -> [f64; 2] {
+        let scale_factor = self.viewport.borrow().terminal_size.0 as f64
+            / self.viewport.borrow().terminal_size.1 as f64
+            * 0.5;
+        [
+            self.cursor.translation.x
+                + self.viewport.borrow().initial_bounds[0] / self.viewport.borrow().zoom
+                    * scale_factor,
+            self.cursor.translation.x
+                + self.viewport.borrow().initial_bounds[1] / self.viewport.borrow().zoom
+                    * scale_factor,
+        ]
+    }
+    fn y_bounds(&self) -> [f64; 2] {
+        [
+            self.cursor.translation.y
+                + self.viewport.borrow().initial_bounds[2] / self.viewport.borrow().zoom,
+            self.cursor.translation.y
+                + self.viewport.borrow().initial_bounds[3] / self.viewport.borrow().zoom,
+        ]
+    }
+
+    fn get_thumbnail(&self) -> Option<(RgbaImage, ThumbnailConfig)> {
+        self.viewport.borrow().get_thumbnail()
+    }
+
+    fn get_minimap(&self) -> Option<(MinimapData, MinimapConfig)> {
+        self.viewport.borrow().get_minimap()
+    }
+
+    fn info(&self) -> String {
+        format!(
+            "Topic: /{}, Vertices: {}, Cursor step: {:.2}",
+            &self.topic,
+            self.vertices.len(),
+            &self.increment
+        )
+    }
+}
@@ -0,0 +1,165 @@
+//! Pose difference mode compares two sources of the same pose, e.g. a localization
+//! estimate and the ground truth.
+
+use crate::app_modes::viewport::{UseViewport, Viewport};
+use crate::app_modes::{AppMode, BaseMode};
+use crate::config::{MinimapConfig, PoseDiffConfig, ThumbnailConfig};
+use crate::minimap::MinimapData;
+use crate::pose::PoseStampedListener;
+use image::RgbaImage;
+use nalgebra::geometry::Isometry3;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use tui::backend::Backend;
+use tui::style::{Color, Style};
+use tui::text::Span;
+use tui::widgets::canvas::{Context, Line};
+
+/// Represents the pose difference mode.
+pub struct PoseDiff {
+    viewport: Rc<RefCell<Viewport>>,
+    first: PoseStampedListener,
+    second: PoseStampedListener,
+    /// Number of poses received at the last call of content_changed.
+    last_message_count: u64,
+}
+
+impl PoseDiff {
+    pub fn new(
+        config: PoseDiffConfig,
+        tf_listener: Arc<rustros_tf::TfListener>,
+        viewport: Rc<RefCell<Viewport>>,
+    ) -> PoseDiff {
+        let static_frame = viewport.borrow().static_frame.clone();
+        PoseDiff {
+            first: PoseStampedListener::new(
+                config.first,
+                tf_listener.clone(),
+                static_frame.clone(),
+            ),
+            second: PoseStampedListener::new(config.second, tf_listener, static_frame),
+            viewport,
+            last_message_count: 0,
+        }
+    }
+
+    /// Returns the translational and rotational (in degrees) difference from the first
+    /// to the second pose, None if one of them is missing.
+    fn get_difference(&self) -> Option<(f64, f64)> {
+        let first: Isometry3<f64> = self.first.get_pose()?;
+        let second: Isometry3<f64> = self.second.get_pose()?;
+        let translation = (second.translation.vector - first.translation.vector).norm();
+        let rotation = first.rotation.angle_to(&second.rotation).to_degrees();
+        Some((translation, rotation))
+    }
+
+    fn get_difference_text(&self) -> String {
+        match self.get_difference() {
+            Some((translation, rotation)) => format!(
+                "Δ translation: {}, Δ rotation: {:.1}°",
+                self.viewport.borrow().units.format_length(translation),
+                rotation
+            ),
+            None => "Δ: N/A".to_string(),
+        }
+    }
+}
+
+impl<B: Backend> BaseMode<B> for PoseDiff {}
+
+impl AppMode for PoseDiff {
+    fn run(&mut self) {
+        self.viewport.borrow_mut().run();
+    }
+
+    fn reset(&mut self) {}
+
+    fn handle_input(&mut self, input: &String) {
+        self.viewport.borrow_mut().handle_input(input);
+    }
+
+    fn content_changed(&mut self) -> bool {
+        // the poses are not part of the content of the viewport
+        let message_count = self.first.rate.get_total() + self.second.rate.get_total();
+        let poses_changed = message_count != self.last_message_count;
+        self.last_message_count = message_count;
+        self.viewport.borrow_mut().content_changed() || poses_changed
+    }
+
+    fn get_name(&self) -> String {
+        "Pose Difference".to_string()
+    }
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            "This mode compares the poses received on two topics.".to_string(),
+            format!(
+                "Both poses are drawn, joined by a line, and the top bar shows the difference from {} to {}.",
+                self.first.config.topic, self.second.config.topic
+            ),
+            "The difference is N/A while one of the poses is missing.".to_string(),
+        ]
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        self.viewport.borrow().get_keymap()
+    }
+}
+
+impl UseViewport for PoseDiff {
+    fn draw_in_viewport(&self, ctx: &mut Context) {
+        self.viewport.borrow().draw_in_viewport(ctx);
+        for listener in [&self.first, &self.second] {
+            for (age, line) in listener.get_history_lines() {
+                self.viewport.borrow().draw_aged_line(ctx, &line, age, 1.0);
+            }
+            for line in listener.get_lines() {
+                self.viewport.borrow().draw_line(ctx, &line);
+            }
+            if let Some((x, y, label)) = listener.get_label() {
+                let color = &listener.config.color;
+                ctx.print(
+                    x,
+                    y,
+                    Span::styled(
+                        label,
+                        Style::default().fg(Color::Rgb(color.r, color.g, color.b)),
+                    ),
+                );
+            }
+        }
+        if let (Some(first), Some(second)) = (self.first.get_pose(), self.second.get_pose()) {
+            self.viewport.borrow().draw_line(
+                ctx,
+                &Line {
+                    x1: first.translation.x,
+                    y1: first.translation.y,
+                    x2: second.translation.x,
+                    y2: second.translation.y,
+                    color: Color::Yellow,
+                },
+            );
+        }
+    }
+
+    fn x_bounds(&self) -> [f64; 2] {
+        self.viewport.borrow().x_bounds()
+    }
+
+    fn y_bounds(&self) -> [f64; 2] {
+        self.viewport.borrow().y_bounds()
+    }
+
+    fn get_thumbnail(&self) -> Option<(RgbaImage, ThumbnailConfig)> {
+        self.viewport.borrow().get_thumbnail()
+    }
+
+    fn get_minimap(&self) -> Option<(MinimapData, MinimapConfig)> {
+        self.viewport.borrow().get_minimap()
+    }
+
+    fn info(&self) -> String {
+        self.get_difference_text()
+    }
+}
@@ -2,10 +2,12 @@
 
 use crate::app_modes::viewport::{UseViewport, Viewport};
 use crate::app_modes::{input, AppMode, BaseMode};
-use crate::config::SendPoseConfig;
+use crate::config::{GoalToleranceConfig, MinimapConfig, SendPoseConfig, ThumbnailConfig};
 use crate::footprint::get_current_footprint;
+use crate::minimap::MinimapData;
 use crate::transformation;
 use approx::AbsDiffEq;
+use image::RgbaImage;
 use nalgebra::{Isometry2, Vector2};
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -13,6 +15,61 @@ use tui::backend::Backend;
 use tui::style::Color;
 use tui::widgets::canvas::{Context, Line};
 
+/// Number of segments of the polyline approximating the tolerance circle.
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// Returns the lines of the tolerances around a goal: a circle of the xy tolerance, and a
+/// wedge of the yaw tolerance on each side of the heading, closed by an arc.
+///
+/// # Arguments
+/// - `goal`: the goal in the static frame.
+/// - `tolerance`: the tolerances of the topic.
+/// - `length`: radius of the wedge, used if the xy tolerance is 0.
+fn get_tolerance_lines(
+    goal: &Isometry2<f64>,
+    tolerance: &GoalToleranceConfig,
+    length: f64,
+    color: Color,
+) -> Vec<Line> {
+    let (x, y) = (goal.translation.x, goal.translation.y);
+    let point = |radius: f64, angle: f64| (x + radius * angle.cos(), y + radius * angle.sin());
+    let line = |(x1, y1): (f64, f64), (x2, y2): (f64, f64)| Line {
+        x1,
+        y1,
+        x2,
+        y2,
+        color,
+    };
+    let mut lines = Vec::new();
+    let step = 2.0 * std::f64::consts::PI / CIRCLE_SEGMENTS as f64;
+    if tolerance.xy > 0.0 {
+        for i in 0..CIRCLE_SEGMENTS {
+            lines.push(line(
+                point(tolerance.xy, i as f64 * step),
+                point(tolerance.xy, (i + 1) as f64 * step),
+            ));
+        }
+    }
+    if tolerance.yaw > 0.0 {
+        let radius = if tolerance.xy > 0.0 {
+            tolerance.xy
+        } else {
+            length
+        };
+        let heading = goal.rotation.angle();
+        let half_angle = tolerance.yaw.to_radians().min(std::f64::consts::PI);
+        let (start, end) = (heading - half_angle, heading + half_angle);
+        lines.push(line((x, y), point(radius, start)));
+        lines.push(line((x, y), point(radius, end)));
+        let segments = ((end - start) / step).ceil().max(1.0) as usize;
+        for i in 0..segments {
+            let angle = |i: usize| start + (end - start) * i as f64 / segments as f64;
+            lines.push(line(point(radius, angle(i)), point(radius, angle(i + 1))));
+        }
+    }
+    lines
+}
+
 trait BasePosePubWrapper {
     fn get_topic(&self) -> &String;
     fn send(&self, msg: rosrust_msg::geometry_msgs::Pose, frame_id: String);
@@ -116,6 +173,21 @@ pub struct SendPose {
     new_pose: Isometry2<f64>,
     current_topic: usize,
     publishers: Vec<Box<dyn BasePosePubWrapper>>,
+    /// Snap radius of each publisher, 0 if snapping is disabled.
+    snap_radii: Vec<f64>,
+    /// Goal tolerances of each publisher, if any.
+    tolerances: Vec<Option<GoalToleranceConfig>>,
+    /// Publisher cancelling the goals of each publisher, if any.
+    cancel_publishers: Vec<
+        Option<(
+            String,
+            rosrust::Publisher<rosrust_msg::actionlib_msgs::GoalID>,
+        )>,
+    >,
+    /// If true, the next Confirm cancels the goals instead of sending the pose.
+    cancel_armed: bool,
+    /// Outcome of the last cancellation, until the next input.
+    cancel_status: String,
     ghost_active: bool,
 }
 
@@ -134,6 +206,9 @@ impl SendPose {
         let robot_pose = transformation::ros_to_iso2d(&base_link_pose);
 
         let mut publishers = Vec::<Box<dyn BasePosePubWrapper>>::new();
+        let mut snap_radii = Vec::<f64>::new();
+        let mut tolerances = Vec::new();
+        let mut cancel_publishers = Vec::new();
 
         for topic in topics {
             match topic.msg_type.as_str() {
@@ -144,15 +219,29 @@ impl SendPose {
                 "PoseWithCovarianceStamped" => {
                     publishers.push(Box::new(PoseCovPubWrapper::new(&topic.topic)))
                 }
-                _ => {}
+                _ => continue,
             }
+            snap_radii.push(topic.snap_radius);
+            tolerances.push(topic.tolerance.clone());
+            cancel_publishers.push(topic.cancel_topic.as_ref().map(|cancel_topic| {
+                (
+                    cancel_topic.clone(),
+                    rosrust::publish(cancel_topic, 1).unwrap(),
+                )
+            }));
         }
 
+        let increment = viewport.borrow().cursor.step;
         SendPose {
             viewport: viewport,
             current_topic: 0,
             publishers: publishers,
-            increment: 0.1,
+            snap_radii,
+            tolerances,
+            cancel_publishers,
+            cancel_armed: false,
+            cancel_status: String::new(),
+            increment,
             robot_pose: robot_pose.clone(),
             new_pose: robot_pose,
             ghost_active: false,
@@ -167,9 +256,36 @@ impl SendPose {
         self.ghost_active = true;
     }
 
+    /// Returns the desired pose moved to the nearest free cell, if snapping is enabled for
+    /// the current topic and the desired pose is not in a free cell of the first map
+    /// containing it.
+    fn get_snapped_pose(&self) -> Option<Isometry2<f64>> {
+        let radius = *self.snap_radii.get(self.current_topic)?;
+        if radius <= 0.0 {
+            return None;
+        }
+        let (x, y) = (self.new_pose.translation.x, self.new_pose.translation.y);
+        let viewport = self.viewport.borrow();
+        let map = viewport
+            .listeners
+            .maps
+            .iter()
+            .find(|map| map.value_at(x, y).is_some())?;
+        let value = map.value_at(x, y)?;
+        if map.is_free(value) {
+            return None;
+        }
+        let (snapped_x, snapped_y) = map.nearest_free_cell(x, y, radius)?;
+        Some(Isometry2::new(
+            Vector2::new(snapped_x, snapped_y),
+            self.new_pose.rotation.angle(),
+        ))
+    }
+
     fn send_new_pose(&mut self) {
         if self.new_pose.abs_diff_ne(&self.robot_pose, 0.01) {
-            let pose = transformation::iso2d_to_ros(&self.new_pose);
+            let new_pose = self.get_snapped_pose().unwrap_or(self.new_pose);
+            let pose = transformation::iso2d_to_ros(&new_pose);
             let frame_id = self.viewport.borrow().static_frame.to_string();
             let mut msg = rosrust_msg::geometry_msgs::Pose::default();
             msg.orientation.x = pose.rotation.x;
@@ -183,12 +299,37 @@ impl SendPose {
             self.ghost_active = false;
         }
     }
+
+    /// Asks to cancel the goals of the current topic, if it has a cancel topic.
+    fn arm_cancel(&mut self) {
+        match self.cancel_publishers.get(self.current_topic) {
+            Some(Some(_)) => self.cancel_armed = true,
+            _ => {
+                self.cancel_status = format!(
+                    "No cancel_topic for /{}",
+                    self.publishers[self.current_topic].get_topic()
+                )
+            }
+        }
+    }
+
+    /// Cancels all the goals of the current topic, by publishing an empty goal ID.
+    fn cancel_goals(&mut self) {
+        if let Some(Some((topic, publisher))) = self.cancel_publishers.get(self.current_topic) {
+            self.cancel_status =
+                match publisher.send(rosrust_msg::actionlib_msgs::GoalID::default()) {
+                    Ok(()) => format!("Cancelled all the goals on /{}", topic),
+                    Err(e) => format!("Could not cancel the goals on /{}: {}", topic, e),
+                };
+        }
+    }
 }
 
 impl<B: Backend> BaseMode<B> for SendPose {}
 
 impl AppMode for SendPose {
     fn run(&mut self) {
+        self.viewport.borrow_mut().run();
         let base_link_pose = self
             .viewport
             .borrow()
@@ -207,17 +348,23 @@ impl AppMode for SendPose {
     }
     fn reset(&mut self) {
         self.ghost_active = false;
+        self.cancel_armed = false;
         self.run(); // Update the robot pose
     }
     fn handle_input(&mut self, input: &String) {
         self.viewport.borrow_mut().handle_input(input);
+        // any other input aborts a pending cancellation
+        let cancel_armed = std::mem::replace(&mut self.cancel_armed, false);
+        self.cancel_status.clear();
+        let step = self.viewport.borrow().cursor_step(self.increment);
+        let angle_step = self.viewport.borrow().rotation_step(self.increment);
         match input.as_str() {
-            input::UP => self.move_new_pose(self.increment, 0.0, 0.0),
-            input::DOWN => self.move_new_pose(-self.increment, 0.0, 0.0),
-            input::LEFT => self.move_new_pose(0.0, self.increment, 0.0),
-            input::RIGHT => self.move_new_pose(0.0, -self.increment, 0.0),
-            input::ROTATE_LEFT => self.move_new_pose(0.0, 0.0, self.increment),
-            input::ROTATE_RIGHT => self.move_new_pose(0.0, 0.0, -self.increment),
+            input::UP => self.move_new_pose(step, 0.0, 0.0),
+            input::DOWN => self.move_new_pose(-step, 0.0, 0.0),
+            input::LEFT => self.move_new_pose(0.0, step, 0.0),
+            input::RIGHT => self.move_new_pose(0.0, -step, 0.0),
+            input::ROTATE_LEFT => self.move_new_pose(0.0, 0.0, angle_step),
+            input::ROTATE_RIGHT => self.move_new_pose(0.0, 0.0, -angle_step),
             input::INCREMENT_STEP => self.increment += 0.1,
             input::DECREMENT_STEP => self.increment -= 0.1,
             input::NEXT => self.current_topic = (self.current_topic + 1) % self.publishers.len(),
@@ -228,12 +375,19 @@ impl AppMode for SendPose {
                     self.publishers.len() - 1
                 };
             }
+            input::CANCEL_GOAL => self.arm_cancel(),
+            input::CANCEL if cancel_armed => (),
             input::CANCEL => self.reset(),
+            input::CONFIRM if cancel_armed => self.cancel_goals(),
             input::CONFIRM => self.send_new_pose(),
             _ => (),
         }
     }
 
+    fn content_changed(&mut self) -> bool {
+        self.viewport.borrow_mut().content_changed()
+    }
+
     fn get_name(&self) -> String {
         "Send Pose".to_string()
     }
@@ -244,6 +398,12 @@ impl AppMode for SendPose {
             "The top bar shows the current selected topic to which the pose is sent.".to_string(),
             "The viewport is centered on the preview outline of where the pose is on the map."
                 .to_string(),
+            "If snapping is enabled for the topic, the green outline shows the pose moved to the nearest free cell, which is the one that will be sent."
+                .to_string(),
+            "If the topic has goal tolerances, they are drawn in cyan around the pose that will be sent."
+                .to_string(),
+            "If the topic has a cancel topic, its goals can be cancelled, after a confirmation."
+                .to_string(),
         ]
     }
 
@@ -297,6 +457,10 @@ impl AppMode for SendPose {
                 input::PREVIOUS.to_string(),
                 "Switches to the previous topic to which the poses are sent.".to_string(),
             ],
+            [
+                input::CANCEL_GOAL.to_string(),
+                "Asks to cancel all the goals of the topic, then Confirm cancels them.".to_string(),
+            ],
         ];
         keymap.extend(self.viewport.borrow().get_keymap());
         keymap
@@ -308,22 +472,62 @@ impl UseViewport for SendPose {
         self.viewport.borrow().draw_in_viewport(ctx);
         if self.new_pose.abs_diff_ne(&self.robot_pose, 0.01) {
             let pose_estimate_ros = transformation::iso2d_to_ros(&self.new_pose);
-            for elem in
-                &get_current_footprint(&pose_estimate_ros, &self.viewport.borrow().footprint)
-            {
-                ctx.draw(&Line {
-                    x1: elem.0,
-                    y1: elem.1,
-                    x2: elem.2,
-                    y2: elem.3,
-                    color: Color::Gray,
-                });
+            for elem in &get_current_footprint(
+                &pose_estimate_ros,
+                &self.viewport.borrow().footprint.polygon,
+            ) {
+                self.viewport.borrow().draw_line(
+                    ctx,
+                    &Line {
+                        x1: elem.0,
+                        y1: elem.1,
+                        x2: elem.2,
+                        y2: elem.3,
+                        color: Color::Gray,
+                    },
+                );
             }
             for mut line in
                 Viewport::get_frame_lines(&pose_estimate_ros, self.viewport.borrow().axis_length)
             {
                 line.color = Color::Gray;
-                ctx.draw(&line);
+                self.viewport.borrow().draw_line(ctx, &line);
+            }
+            // the pose which will actually be sent, if it is snapped to a free cell
+            if let Some(snapped_pose) = self.get_snapped_pose() {
+                let snapped_pose_ros = transformation::iso2d_to_ros(&snapped_pose);
+                for elem in &get_current_footprint(
+                    &snapped_pose_ros,
+                    &self.viewport.borrow().footprint.polygon,
+                ) {
+                    self.viewport.borrow().draw_line(
+                        ctx,
+                        &Line {
+                            x1: elem.0,
+                            y1: elem.1,
+                            x2: elem.2,
+                            y2: elem.3,
+                            color: Color::LightGreen,
+                        },
+                    );
+                }
+                self.viewport.borrow().draw_line(
+                    ctx,
+                    &Line {
+                        x1: self.new_pose.translation.x,
+                        y1: self.new_pose.translation.y,
+                        x2: snapped_pose.translation.x,
+                        y2: snapped_pose.translation.y,
+                        color: Color::LightGreen,
+                    },
+                );
+            }
+            if let Some(Some(tolerance)) = self.tolerances.get(self.current_topic) {
+                let goal = self.get_snapped_pose().unwrap_or(self.new_pose);
+                let axis_length = self.viewport.borrow().axis_length;
+                for line in get_tolerance_lines(&goal, tolerance, axis_length, Color::Cyan) {
+                    self.viewport.borrow().draw_line(ctx, &line);
+                }
             }
         }
     }
@@ -349,6 +553,25 @@ impl UseViewport for SendPose {
         ]
     }
 
+    fn get_thumbnail(&self) -> Option<(RgbaImage, ThumbnailConfig)> {
+        self.viewport.borrow().get_thumbnail()
+    }
+
+    fn get_minimap(&self) -> Option<(MinimapData, MinimapConfig)> {
+        self.viewport.borrow().get_minimap()
+    }
+
+    fn get_panel(&self) -> Option<(String, Vec<String>)> {
+        let line = if self.cancel_armed {
+            "Press Confirm to cancel all the goals, any other key aborts".to_string()
+        } else if !self.cancel_status.is_empty() {
+            self.cancel_status.clone()
+        } else {
+            return None;
+        };
+        Some(("Cancel goal".to_string(), vec![line]))
+    }
+
     fn info(&self) -> String {
         format!(
             "Topic: /{}, Cursor step: {:.2}",
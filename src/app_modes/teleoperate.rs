@@ -1,6 +1,8 @@
 use crate::app_modes::viewport::{UseViewport, Viewport};
 use crate::app_modes::{input, AppMode, BaseMode};
-use crate::config::TeleopConfig;
+use crate::config::{MinimapConfig, TeleopConfig, ThumbnailConfig};
+use crate::minimap::MinimapData;
+use image::RgbaImage;
 use rosrust;
 use rosrust_msg;
 use std::cell::RefCell;
@@ -77,6 +79,7 @@ impl AppMode for Teleoperate {
     }
 
     fn run(&mut self) {
+        self.viewport.borrow_mut().run();
         // If the velocity is reset to 0 only publish it once
         // this prevents the robot from being blocked if the
         // app mode is not closed
@@ -106,6 +109,10 @@ impl AppMode for Teleoperate {
         self.run(); // Send 0 velocities just in case
     }
 
+    fn content_changed(&mut self) -> bool {
+        self.viewport.borrow_mut().content_changed()
+    }
+
     fn get_name(&self) -> String {
         "Teleoperate".to_string()
     }
@@ -172,7 +179,19 @@ impl UseViewport for Teleoperate {
         self.viewport.borrow().y_bounds()
     }
 
+    fn get_thumbnail(&self) -> Option<(RgbaImage, ThumbnailConfig)> {
+        self.viewport.borrow().get_thumbnail()
+    }
+
+    fn get_minimap(&self) -> Option<(MinimapData, MinimapConfig)> {
+        self.viewport.borrow().get_minimap()
+    }
+
     fn info(&self) -> String {
-        format!("Velocity step: {:.2}", &self.increment)
+        format!(
+            "Following: {}, Velocity step: {:.2}",
+            &self.viewport.borrow().follow_frame,
+            &self.increment
+        )
     }
 }
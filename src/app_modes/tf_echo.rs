@@ -0,0 +1,225 @@
+//! TF echo mode shows the live transform between two frames, like `tf_echo`.
+
+use crate::app_modes::viewport::{UseViewport, Viewport};
+use crate::app_modes::{input, AppMode, BaseMode};
+use crate::config::{MinimapConfig, TfEchoConfig, ThumbnailConfig};
+use crate::minimap::MinimapData;
+use crate::transformation::ros_transform_to_isometry;
+use image::RgbaImage;
+use std::cell::RefCell;
+use std::rc::Rc;
+use tui::backend::Backend;
+use tui::style::{Color, Style};
+use tui::text::Span;
+use tui::widgets::canvas::Context;
+
+/// Represents the TF echo mode.
+pub struct TfEcho {
+    viewport: Rc<RefCell<Viewport>>,
+    /// Frame in which the transform is expressed.
+    source: String,
+    /// Frame whose pose in the source frame is shown.
+    target: String,
+    show_axes: bool,
+    /// Text of the panel at the last call of content_changed.
+    last_panel: Vec<String>,
+}
+
+impl TfEcho {
+    pub fn new(config: TfEchoConfig, viewport: Rc<RefCell<Viewport>>) -> TfEcho {
+        let source = config
+            .source
+            .unwrap_or_else(|| viewport.borrow().static_frame.clone());
+        let target = config
+            .target
+            .unwrap_or_else(|| viewport.borrow().robot_frame.clone());
+        TfEcho {
+            viewport,
+            source,
+            target,
+            show_axes: config.show_axes,
+            last_panel: Vec::new(),
+        }
+    }
+
+    /// Returns the frame after (or before, with a negative step) the given one among the
+    /// frames known to TF, sorted alphabetically.
+    fn cycle_frame(&self, current: &str, step: isize) -> String {
+        let frames = self.viewport.borrow().listeners.frames.get_frame_names();
+        if frames.is_empty() {
+            return current.to_string();
+        }
+        let count = frames.len() as isize;
+        let next = match frames.iter().position(|frame| frame == current) {
+            Some(i) => (i as isize + step).rem_euclid(count),
+            None => 0,
+        };
+        frames[next as usize].clone()
+    }
+
+    /// Returns the lines of the panel: the translation and the rotation of the target
+    /// frame in the source frame, or why they are not available.
+    fn get_panel_lines(&self) -> Vec<String> {
+        let viewport = self.viewport.borrow();
+        let transform = match viewport.tf_listener.lookup_transform(
+            &self.source,
+            &self.target,
+            rosrust::Time::new(),
+        ) {
+            Ok(transform) => transform.transform,
+            Err(e) => {
+                return vec![
+                    format!("No transform from {} to {}:", self.source, self.target),
+                    format!("{:?}", e),
+                ]
+            }
+        };
+        let isometry = ros_transform_to_isometry(&transform);
+        let translation = isometry.translation;
+        let (roll, pitch, yaw) = isometry.rotation.euler_angles();
+        let quaternion = isometry.rotation.quaternion();
+        vec![
+            format!(
+                "Translation: x {}, y {}, z {}",
+                viewport.units.format_length(translation.x),
+                viewport.units.format_length(translation.y),
+                viewport.units.format_length(translation.z)
+            ),
+            format!(
+                "Rotation: yaw {:.1}°, pitch {:.1}°, roll {:.1}°",
+                yaw.to_degrees(),
+                pitch.to_degrees(),
+                roll.to_degrees()
+            ),
+            format!(
+                "Quaternion: x {:.4}, y {:.4}, z {:.4}, w {:.4}",
+                quaternion.i, quaternion.j, quaternion.k, quaternion.w
+            ),
+        ]
+    }
+}
+
+impl<B: Backend> BaseMode<B> for TfEcho {}
+
+impl AppMode for TfEcho {
+    fn run(&mut self) {
+        self.viewport.borrow_mut().run();
+    }
+
+    fn reset(&mut self) {}
+
+    fn handle_input(&mut self, input: &String) {
+        self.viewport.borrow_mut().handle_input(input);
+        match input.as_str() {
+            input::UP => self.source = self.cycle_frame(&self.source, 1),
+            input::DOWN => self.source = self.cycle_frame(&self.source, -1),
+            input::RIGHT => self.target = self.cycle_frame(&self.target, 1),
+            input::LEFT => self.target = self.cycle_frame(&self.target, -1),
+            input::CONFIRM => std::mem::swap(&mut self.source, &mut self.target),
+            _ => (),
+        }
+    }
+
+    fn content_changed(&mut self) -> bool {
+        // the transform is not part of the content of the viewport
+        let panel = self.get_panel_lines();
+        let panel_changed = panel != self.last_panel;
+        self.last_panel = panel;
+        self.viewport.borrow_mut().content_changed() || panel_changed
+    }
+
+    fn get_name(&self) -> String {
+        "TF Echo".to_string()
+    }
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            "This mode shows the live transform between two frames known to TF.".to_string(),
+            "The panel shows the translation and the rotation of the target frame in the source frame, like tf_echo."
+                .to_string(),
+            "Both frames are selected among the frames received on the TF topics.".to_string(),
+        ]
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        let mut keymap = vec![
+            [
+                input::UP.to_string(),
+                "Selects the next source frame.".to_string(),
+            ],
+            [
+                input::DOWN.to_string(),
+                "Selects the previous source frame.".to_string(),
+            ],
+            [
+                input::RIGHT.to_string(),
+                "Selects the next target frame.".to_string(),
+            ],
+            [
+                input::LEFT.to_string(),
+                "Selects the previous target frame.".to_string(),
+            ],
+            [
+                input::CONFIRM.to_string(),
+                "Swaps the source and the target frames.".to_string(),
+            ],
+        ];
+        keymap.extend(self.viewport.borrow().get_keymap());
+        keymap
+    }
+}
+
+impl UseViewport for TfEcho {
+    fn draw_in_viewport(&self, ctx: &mut Context) {
+        let viewport = self.viewport.borrow();
+        viewport.draw_in_viewport(ctx);
+        if !self.show_axes {
+            return;
+        }
+        for frame in [&self.source, &self.target] {
+            let transform = match viewport.tf_listener.lookup_transform(
+                &viewport.static_frame,
+                frame,
+                rosrust::Time::new(),
+            ) {
+                Ok(transform) => transform.transform,
+                Err(_e) => continue,
+            };
+            for line in Viewport::get_frame_lines(&transform, viewport.axis_length) {
+                viewport.draw_line(ctx, &line);
+            }
+            ctx.print(
+                transform.translation.x,
+                transform.translation.y,
+                Span::styled(frame.clone(), Style::default().fg(Color::Yellow)),
+            );
+        }
+    }
+
+    fn x_bounds(&self) -> [f64; 2] {
+        self.viewport.borrow().x_bounds()
+    }
+
+    fn y_bounds(&self) -> [f64; 2] {
+        self.viewport.borrow().y_bounds()
+    }
+
+    fn get_thumbnail(&self) -> Option<(RgbaImage, ThumbnailConfig)> {
+        self.viewport.borrow().get_thumbnail()
+    }
+
+    fn get_minimap(&self) -> Option<(MinimapData, MinimapConfig)> {
+        self.viewport.borrow().get_minimap()
+    }
+
+    fn get_panel(&self) -> Option<(String, Vec<String>)> {
+        Some((
+            format!("{} → {}", self.source, self.target),
+            self.get_panel_lines(),
+        ))
+    }
+
+    fn info(&self) -> String {
+        format!("Source: {}, Target: {}", self.source, self.target)
+    }
+}
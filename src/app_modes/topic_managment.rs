@@ -1,8 +1,12 @@
 use crate::app_modes::{input, AppMode, BaseMode, Drawable};
 use crate::config::Color as ConfigColor;
 use crate::config::TermvizConfig;
-use crate::config::{ImageListenerConfig, ListenerConfig, ListenerConfigColor, PoseListenerConfig};
+use crate::config::{
+    ImageListenerConfig, LaserListenerConfig, ListenerConfig, MarkerArrayListenerConfig,
+    PolygonListenerConfig, PoseListenerConfig,
+};
 use rand::Rng;
+use std::collections::HashMap;
 use tui::backend::Backend;
 use tui::layout::{Alignment, Constraint, Direction, Layout};
 use tui::style::{Color, Modifier, Style};
@@ -132,6 +136,11 @@ impl TopicManager {
             .iter()
             .map(|i| [i.topic.clone(), "nav_msgs/Path".to_string()])
             .collect();
+        let active_polygon_topics: Vec<[String; 2]> = config
+            .polygon_topics
+            .iter()
+            .map(|i| [i.topic.clone(), "geometry_msgs/PolygonStamped".to_string()])
+            .collect();
         let active_image_topics: Vec<[String; 2]> = config
             .path_topics
             .iter()
@@ -144,6 +153,7 @@ impl TopicManager {
             active_marker_array_topics,
             active_marker_topics,
             active_path_topics,
+            active_polygon_topics,
             active_pose_array_topics,
             active_pose_stamped_topics,
         ]
@@ -151,6 +161,7 @@ impl TopicManager {
 
         // We could get this from config, but would need some breaking changes in config
         let supported_topic_types = vec![
+            "geometry_msgs/PolygonStamped".to_string(),
             "geometry_msgs/PoseArray".to_string(),
             "geometry_msgs/PoseStamped".to_string(),
             "nav_msgs/Path".to_string(),
@@ -209,6 +220,7 @@ impl TopicManager {
         config.pose_stamped_topics.clear();
         config.pose_array_topics.clear();
         config.path_topics.clear();
+        config.polygon_topics.clear();
 
         // Fill the respective topics
         // The current implementation hardcodes where the topics must go
@@ -216,21 +228,44 @@ impl TopicManager {
         let mut rng = rand::thread_rng();
         for topic in self.selected_topics.items.iter() {
             match topic[1].clone().as_ref() {
-                "sensor_msgs/LaserScan" => config.laser_topics.push(ListenerConfigColor {
+                "sensor_msgs/LaserScan" => config.laser_topics.push(LaserListenerConfig {
                     topic: topic[0].clone(),
                     color: ConfigColor {
                         r: rng.gen_range(0..255),
                         g: rng.gen_range(0..255),
                         b: rng.gen_range(0..255),
                     },
+                    frame_filter: None,
+                    override_frame_id: None,
+                    lookup_tolerance: 0.0,
+                    normalize_angles: false,
+                    point_style: "dot".to_string(),
+                    point_size: 1.0,
+                    size_mode: "screen".to_string(),
+                    opacity: 1.0,
+                    hull_only: false,
+                    angle_window: None,
+                    priority: 0,
+                    connect_gap: None,
+                    sources: Vec::new(),
+                    fan: None,
+                    min_range: None,
+                    center_on_first: false,
                 }),
                 "visualization_msgs/MarkerArray" => {
-                    config.marker_array_topics.push(ListenerConfig {
+                    config.marker_array_topics.push(MarkerArrayListenerConfig {
                         topic: topic[0].clone(),
+                        frame_filter: None,
+                        lookup_tolerance: 0.0,
+                        color_by_namespace: false,
+                        namespace_colors: HashMap::new(),
+                        max_markers: None,
                     })
                 }
                 "visualization_msgs/Marker" => config.marker_topics.push(ListenerConfig {
                     topic: topic[0].clone(),
+                    frame_filter: None,
+                    lookup_tolerance: 0.0,
                 }),
                 "geometry_msgs/PoseStamped" => {
                     config.pose_stamped_topics.push(PoseListenerConfig {
@@ -242,6 +277,24 @@ impl TopicManager {
                         },
                         length: 0.2,
                         style: "axis".to_string(),
+                        frame_filter: None,
+                        override_frame_id: None,
+                        ghost_lag: None,
+                        cost: None,
+                        edges: None,
+                        velocities: None,
+                        spline_samples: None,
+                        breadcrumbs: None,
+                        timeout: None,
+                        stale_color: None,
+                        smoothing: 1.0,
+                        dash_length: 0.0,
+                        thickness: 1,
+                        opacity: 1.0,
+                        center_on_first: false,
+                        density: None,
+                        tube: None,
+                        band: None,
                     })
                 }
                 "geometry_msgs/PoseArray" => config.pose_array_topics.push(PoseListenerConfig {
@@ -253,6 +306,24 @@ impl TopicManager {
                     },
                     length: 0.2,
                     style: "axis".to_string(),
+                    frame_filter: None,
+                    override_frame_id: None,
+                    ghost_lag: None,
+                    cost: None,
+                    edges: None,
+                    velocities: None,
+                    spline_samples: None,
+                    breadcrumbs: None,
+                    timeout: None,
+                    stale_color: None,
+                    smoothing: 1.0,
+                    dash_length: 0.0,
+                    thickness: 1,
+                    opacity: 1.0,
+                    center_on_first: false,
+                    density: None,
+                    tube: None,
+                    band: None,
                 }),
                 "nav_msgs/Path" => config.path_topics.push(PoseListenerConfig {
                     topic: topic[0].clone(),
@@ -263,7 +334,46 @@ impl TopicManager {
                     },
                     length: 0.2,
                     style: "axis".to_string(),
+                    frame_filter: None,
+                    override_frame_id: None,
+                    ghost_lag: None,
+                    cost: None,
+                    edges: None,
+                    velocities: None,
+                    spline_samples: None,
+                    breadcrumbs: None,
+                    timeout: None,
+                    stale_color: None,
+                    smoothing: 1.0,
+                    dash_length: 0.0,
+                    thickness: 1,
+                    opacity: 1.0,
+                    center_on_first: false,
+                    density: None,
+                    tube: None,
+                    band: None,
                 }),
+                "geometry_msgs/PolygonStamped" => {
+                    config.polygon_topics.push(PolygonListenerConfig {
+                        topic: topic[0].clone(),
+                        color: ConfigColor {
+                            r: rng.gen_range(0..255),
+                            g: rng.gen_range(0..255),
+                            b: rng.gen_range(0..255),
+                        },
+                        z_style: "project".to_string(),
+                        fill: "none".to_string(),
+                        hatch_spacing: 0.2,
+                        hatch_angle: 45.0,
+                        outline_on_top: true,
+                        vertices: None,
+                        frame_filter: None,
+                        override_frame_id: None,
+                        lookup_tolerance: 0.0,
+                        opacity: 1.0,
+                        center_on_first: false,
+                    })
+                }
                 "sensor_msg/Image" => config.image_topics.push(ImageListenerConfig {
                     topic: topic[0].clone(),
                     rotation: 0,
@@ -284,6 +394,9 @@ impl<B: Backend> BaseMode<B> for TopicManager {}
 impl AppMode for TopicManager {
     fn run(&mut self) {}
     fn reset(&mut self) {}
+    fn content_changed(&mut self) -> bool {
+        true
+    }
     fn get_description(&self) -> Vec<String> {
         vec!["Topic manager can enable and disable displayed topics".to_string()]
     }
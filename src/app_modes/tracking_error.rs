@@ -0,0 +1,374 @@
+//! Tracking error mode compares the trail of the robot with a reference trajectory, e.g.
+//! to tune a path tracking controller.
+//!
+//! The reference is a nav_msgs::Path, transformed to the static frame when received, or
+//! a file of points in the static frame. The error of a pose of the trail is its distance
+//! to the closest point of the reference polyline, not to its closest vertex, so that the
+//! reference and the trail can be sampled with different densities.
+
+use crate::app_modes::viewport::{UseViewport, Viewport};
+use crate::app_modes::{input, AppMode, BaseMode};
+use crate::config::Color as RgbColor;
+use crate::config::{MinimapConfig, ThumbnailConfig, TrackingErrorConfig};
+use crate::minimap::MinimapData;
+use crate::transformation::{lookup_transform_with_tolerance, ros_transform_to_isometry};
+use image::RgbaImage;
+use nalgebra::geometry::Point3;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, RwLock};
+use tui::backend::Backend;
+use tui::style::Color;
+use tui::widgets::canvas::{Context, Line};
+
+/// Points of the reference in the static frame, with the number of references received.
+type Reference = (u64, Vec<(f64, f64)>);
+
+/// Reads the points of a reference file, one "x y" or "x,y" per line. The empty lines and
+/// the lines starting with # are skipped, as well as the invalid ones with a warning.
+fn read_reference_file(path: &str) -> Vec<(f64, f64)> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            log::error!("Could not read the reference trajectory {}: {}", path, e);
+            return Vec::new();
+        }
+    };
+    let mut points = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let values: Vec<f64> = line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|value| !value.is_empty())
+            .filter_map(|value| value.parse().ok())
+            .collect();
+        match values.as_slice() {
+            [x, y] => points.push((*x, *y)),
+            _ => log::warn!("Skipping line {} of {}, it is not 'x y'", i + 1, path),
+        }
+    }
+    points
+}
+
+/// Returns the closest point of the polyline to the point, with its distance. None if the
+/// polyline is empty.
+fn get_projection(point: (f64, f64), polyline: &[(f64, f64)]) -> Option<((f64, f64), f64)> {
+    let (x, y) = point;
+    let mut closest: Option<((f64, f64), f64)> = polyline
+        .first()
+        .map(|start| (*start, (start.0 - x).hypot(start.1 - y)));
+    for segment in polyline.windows(2) {
+        let ((x1, y1), (x2, y2)) = (segment[0], segment[1]);
+        let (dx, dy) = (x2 - x1, y2 - y1);
+        let squared_length = dx * dx + dy * dy;
+        let t = if squared_length > 0.0 {
+            (((x - x1) * dx + (y - y1) * dy) / squared_length).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let projection = (x1 + t * dx, y1 + t * dy);
+        let distance = (projection.0 - x).hypot(projection.1 - y);
+        if closest.is_none_or(|(_, closest)| distance < closest) {
+            closest = Some((projection, distance));
+        }
+    }
+    closest
+}
+
+/// A pose of the trail, with its closest point on the reference and its distance to it.
+struct TrailPoint {
+    position: (f64, f64),
+    projection: Option<((f64, f64), f64)>,
+    /// If true, the error line of the point is drawn.
+    marked: bool,
+}
+
+/// Represents the tracking error mode.
+pub struct TrackingError {
+    viewport: Rc<RefCell<Viewport>>,
+    config: TrackingErrorConfig,
+    reference: Arc<RwLock<Reference>>,
+    /// Number of references received at the last update of the errors.
+    reference_count: u64,
+    trail: Vec<TrailPoint>,
+    /// Distance travelled since the last marked point of the trail.
+    distance_since_mark: f64,
+    _subscriber: Option<rosrust::Subscriber>,
+}
+
+impl TrackingError {
+    pub fn new(config: TrackingErrorConfig, viewport: Rc<RefCell<Viewport>>) -> TrackingError {
+        let points = config
+            .file
+            .as_ref()
+            .map(|path| read_reference_file(path))
+            .unwrap_or_default();
+        let reference = Arc::new(RwLock::new((1, points)));
+        let subscriber = config.topic.as_ref().map(|topic| {
+            let cb_reference = reference.clone();
+            let tf_listener = viewport.borrow().tf_listener.clone();
+            let static_frame = viewport.borrow().static_frame.clone();
+            let cb_topic = topic.clone();
+            rosrust::subscribe(topic, 1, move |path: rosrust_msg::nav_msgs::Path| {
+                // the reference is fixed in the static frame from its reception
+                let transform = match lookup_transform_with_tolerance(
+                    &tf_listener,
+                    &static_frame,
+                    &path.header.frame_id,
+                    path.header.stamp,
+                    0.0,
+                ) {
+                    Ok(transform) => transform.transform,
+                    Err(_e) => {
+                        log::warn!(
+                            "Ignoring the reference received on {}, no transform from {}",
+                            cb_topic,
+                            path.header.frame_id
+                        );
+                        return;
+                    }
+                };
+                let isometry = ros_transform_to_isometry(&transform);
+                let points = path
+                    .poses
+                    .iter()
+                    .map(|pose| {
+                        let position = &pose.pose.position;
+                        let point = isometry
+                            .transform_point(&Point3::new(position.x, position.y, position.z));
+                        (point.x, point.y)
+                    })
+                    .collect();
+                let mut reference = cb_reference.write().unwrap();
+                *reference = (reference.0 + 1, points);
+            })
+            .unwrap()
+        });
+        if config.topic.is_none() && config.file.is_none() {
+            log::warn!(
+                "Neither a topic nor a file is set for tracking_error, there is no reference."
+            );
+        }
+        TrackingError {
+            viewport,
+            config,
+            reference,
+            reference_count: 0,
+            trail: Vec::new(),
+            distance_since_mark: 0.0,
+            _subscriber: subscriber,
+        }
+    }
+
+    /// Returns the position of the robot in the static frame, None without its transform.
+    fn get_robot_position(&self) -> Option<(f64, f64)> {
+        let viewport = self.viewport.borrow();
+        let tf = viewport
+            .tf_listener
+            .lookup_transform(
+                &viewport.static_frame,
+                &viewport.robot_frame,
+                rosrust::Time::new(),
+            )
+            .ok()?;
+        Some((tf.transform.translation.x, tf.transform.translation.y))
+    }
+
+    /// Adds the position of the robot to the trail if it moved by trail_spacing, and updates
+    /// the errors of the whole trail if a new reference was received.
+    fn update(&mut self) {
+        let reference = self.reference.clone();
+        let reference = reference.read().unwrap();
+        if reference.0 != self.reference_count {
+            self.reference_count = reference.0;
+            for point in &mut self.trail {
+                point.projection = get_projection(point.position, &reference.1);
+            }
+        }
+        let position = match self.get_robot_position() {
+            Some(position) => position,
+            None => return,
+        };
+        let step = self
+            .trail
+            .last()
+            .map(|last| (position.0 - last.position.0).hypot(position.1 - last.position.1));
+        if step.is_some_and(|step| step < self.config.trail_spacing) {
+            return;
+        }
+        self.distance_since_mark += step.unwrap_or(0.0);
+        let marked = step.is_none() || self.distance_since_mark >= self.config.error_interval;
+        if marked {
+            self.distance_since_mark = 0.0;
+        }
+        self.trail.push(TrailPoint {
+            position,
+            projection: get_projection(position, &reference.1),
+            marked,
+        });
+        if self.trail.len() > self.config.max_trail {
+            self.trail.remove(0);
+        }
+    }
+
+    /// Returns the RMS and the maximum of the errors of the trail, with the number of poses
+    /// they are computed on. None while no pose has an error.
+    fn get_statistics(&self) -> Option<(f64, f64, usize)> {
+        let errors: Vec<f64> = self
+            .trail
+            .iter()
+            .filter_map(|point| point.projection.map(|(_, distance)| distance))
+            .collect();
+        if errors.is_empty() {
+            return None;
+        }
+        let squared_sum: f64 = errors.iter().map(|error| error * error).sum();
+        let max = errors.iter().cloned().fold(0.0, f64::max);
+        Some((
+            (squared_sum / errors.len() as f64).sqrt(),
+            max,
+            errors.len(),
+        ))
+    }
+}
+
+impl<B: Backend> BaseMode<B> for TrackingError {}
+
+impl AppMode for TrackingError {
+    fn run(&mut self) {
+        self.viewport.borrow_mut().run();
+        self.update();
+    }
+
+    fn reset(&mut self) {}
+
+    fn handle_input(&mut self, input: &String) {
+        self.viewport.borrow_mut().handle_input(input);
+        if input == input::CANCEL {
+            self.trail.clear();
+            self.distance_since_mark = 0.0;
+        }
+    }
+
+    fn content_changed(&mut self) -> bool {
+        // the trail grows with the robot, which moves the view only when followed
+        true
+    }
+
+    fn get_name(&self) -> String {
+        "Tracking Error".to_string()
+    }
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            "This mode compares the trail of the robot with a reference trajectory.".to_string(),
+            "The error of a pose is its distance to the closest point of the reference, drawn at intervals and summarized as an RMS in the panel."
+                .to_string(),
+        ]
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        let mut keymap = vec![[
+            input::CANCEL.to_string(),
+            "Clears the trail and its errors.".to_string(),
+        ]];
+        keymap.extend(self.viewport.borrow().get_keymap());
+        keymap
+    }
+}
+
+impl UseViewport for TrackingError {
+    fn draw_in_viewport(&self, ctx: &mut Context) {
+        let viewport = self.viewport.borrow();
+        viewport.draw_in_viewport(ctx);
+        let rgb = |color: &RgbColor| Color::Rgb(color.r, color.g, color.b);
+        let line = |start: (f64, f64), end: (f64, f64), color: Color| Line {
+            x1: start.0,
+            y1: start.1,
+            x2: end.0,
+            y2: end.1,
+            color,
+        };
+        for segment in self.reference.read().unwrap().1.windows(2) {
+            viewport.draw_line(
+                ctx,
+                &line(segment[0], segment[1], rgb(&self.config.reference_color)),
+            );
+        }
+        for point in self.trail.iter().filter(|point| point.marked) {
+            if let Some((projection, _)) = point.projection {
+                viewport.draw_line(
+                    ctx,
+                    &line(point.position, projection, rgb(&self.config.error_color)),
+                );
+            }
+        }
+        for segment in self.trail.windows(2) {
+            viewport.draw_line(
+                ctx,
+                &line(
+                    segment[0].position,
+                    segment[1].position,
+                    rgb(&self.config.trail_color),
+                ),
+            );
+        }
+    }
+
+    fn x_bounds(&self) -> [f64; 2] {
+        self.viewport.borrow().x_bounds()
+    }
+
+    fn y_bounds(&self) -> [f64; 2] {
+        self.viewport.borrow().y_bounds()
+    }
+
+    fn get_thumbnail(&self) -> Option<(RgbaImage, ThumbnailConfig)> {
+        self.viewport.borrow().get_thumbnail()
+    }
+
+    fn get_minimap(&self) -> Option<(MinimapData, MinimapConfig)> {
+        self.viewport.borrow().get_minimap()
+    }
+
+    fn get_panel(&self) -> Option<(String, Vec<String>)> {
+        let units = &self.viewport.borrow().units;
+        let lines = if self.reference.read().unwrap().1.is_empty() {
+            vec!["No reference received".to_string()]
+        } else {
+            match self.get_statistics() {
+                Some((rms, max, count)) => vec![
+                    format!("RMS: {} over {} poses", units.format_length(rms), count),
+                    format!("Max: {}", units.format_length(max)),
+                    format!(
+                        "Current: {}",
+                        self.trail
+                            .last()
+                            .and_then(|point| point.projection)
+                            .map_or("N/A".to_string(), |(_, distance)| units
+                                .format_length(distance))
+                    ),
+                ],
+                None => vec!["Waiting for the robot to move".to_string()],
+            }
+        };
+        Some(("Tracking error".to_string(), lines))
+    }
+
+    fn info(&self) -> String {
+        let reference = match (&self.config.topic, &self.config.file) {
+            (Some(topic), _) => format!("/{}", topic.trim_start_matches('/')),
+            (None, Some(file)) => file.clone(),
+            (None, None) => "none".to_string(),
+        };
+        format!(
+            "Reference: {}, Trail: {}/{} poses",
+            reference,
+            self.trail.len(),
+            self.config.max_trail
+        )
+    }
+}
@@ -0,0 +1,362 @@
+//! Twist estimate mode computes the velocity connecting two placed poses over a time
+//! interval, and publishes it, e.g. to inject test commands when calibrating.
+
+use crate::app_modes::viewport::{UseViewport, Viewport};
+use crate::app_modes::{input, AppMode, BaseMode};
+use crate::config::{MinimapConfig, ThumbnailConfig, TwistEstimateConfig};
+use crate::minimap::MinimapData;
+use crate::transformation;
+use image::RgbaImage;
+use nalgebra::{Isometry2, Vector2};
+use std::cell::RefCell;
+use std::rc::Rc;
+use tui::backend::Backend;
+use tui::style::Color;
+use tui::widgets::canvas::{Context, Line};
+
+/// Returns the constant twist (vx, vy, wz), in the frame of the start pose, that moves the
+/// start pose to the end pose in `dt` seconds, i.e. the logarithm of their relative
+/// transform in SE(2) divided by `dt`.
+///
+/// A forward motion of 1 m without rotation in 2 s gives (0.5, 0, 0). With a rotation,
+/// the start pose moves along an arc, so its linear velocity is not the straight
+/// displacement divided by `dt`.
+fn get_twist(start: &Isometry2<f64>, end: &Isometry2<f64>, dt: f64) -> (f64, f64, f64) {
+    let relative = start.inverse() * end;
+    let (dx, dy) = (relative.translation.x, relative.translation.y);
+    let angle = relative.rotation.angle();
+    if angle.abs() < 1e-9 {
+        return (dx / dt, dy / dt, 0.0);
+    }
+    // inverse of the matrix V mapping the velocity to the displacement
+    let (sin, one_minus_cos) = (angle.sin(), 1.0 - angle.cos());
+    let factor = angle / (2.0 * one_minus_cos);
+    let vx = factor * (sin * dx + one_minus_cos * dy);
+    let vy = factor * (-one_minus_cos * dx + sin * dy);
+    (vx / dt, vy / dt, angle / dt)
+}
+
+/// Represents the twist estimate mode.
+pub struct TwistEstimate {
+    viewport: Rc<RefCell<Viewport>>,
+    topic: String,
+    publisher: rosrust::Publisher<rosrust_msg::geometry_msgs::Twist>,
+    dt: f64,
+    increment: f64,
+    robot_pose: Isometry2<f64>,
+    cursor: Isometry2<f64>,
+    cursor_active: bool,
+    /// The placed poses, at most two: the pose at time 0 and the pose at time dt.
+    poses: Vec<Isometry2<f64>>,
+}
+
+impl TwistEstimate {
+    pub fn new(config: TwistEstimateConfig, viewport: Rc<RefCell<Viewport>>) -> TwistEstimate {
+        let base_link_pose = viewport
+            .borrow()
+            .tf_listener
+            .lookup_transform(
+                &viewport.borrow().static_frame,
+                &viewport.borrow().robot_frame,
+                rosrust::Time::new(),
+            )
+            .unwrap()
+            .transform;
+        let robot_pose = transformation::ros_to_iso2d(&base_link_pose);
+
+        let increment = viewport.borrow().cursor.step;
+        TwistEstimate {
+            viewport,
+            publisher: rosrust::publish(&config.topic, 1).unwrap(),
+            topic: config.topic,
+            dt: config.dt,
+            increment,
+            robot_pose,
+            cursor: robot_pose,
+            cursor_active: false,
+            poses: Vec::new(),
+        }
+    }
+
+    fn move_cursor(&mut self, x: f64, y: f64, yaw: f64) {
+        let new_yaw = self.cursor.rotation.angle() + yaw;
+        let new_x = x * new_yaw.cos() - y * new_yaw.sin() + self.cursor.translation.x;
+        let new_y = x * new_yaw.sin() + y * new_yaw.cos() + self.cursor.translation.y;
+        self.cursor = Isometry2::new(Vector2::new(new_x, new_y), new_yaw);
+        self.cursor_active = true;
+    }
+
+    /// Returns the twist connecting the two placed poses, None until both are placed or
+    /// if dt is not positive.
+    fn get_twist(&self) -> Option<(f64, f64, f64)> {
+        match self.poses.as_slice() {
+            [start, end] if self.dt > 0.0 => Some(get_twist(start, end, self.dt)),
+            _ => None,
+        }
+    }
+
+    fn send_twist(&mut self) {
+        let (vx, vy, wz) = match self.get_twist() {
+            Some(twist) => twist,
+            None => return,
+        };
+        let mut msg = rosrust_msg::geometry_msgs::Twist::default();
+        msg.linear.x = vx;
+        msg.linear.y = vy;
+        msg.angular.z = wz;
+        self.publisher.send(msg).unwrap();
+        self.poses.clear();
+        self.cursor_active = false;
+    }
+}
+
+impl<B: Backend> BaseMode<B> for TwistEstimate {}
+
+impl AppMode for TwistEstimate {
+    fn run(&mut self) {
+        self.viewport.borrow_mut().run();
+        let base_link_pose = self
+            .viewport
+            .borrow()
+            .tf_listener
+            .lookup_transform(
+                &self.viewport.borrow().static_frame,
+                &self.viewport.borrow().robot_frame,
+                rosrust::Time::new(),
+            )
+            .unwrap()
+            .transform;
+        self.robot_pose = transformation::ros_to_iso2d(&base_link_pose);
+        if !self.cursor_active {
+            self.cursor = self.robot_pose;
+        }
+    }
+    fn reset(&mut self) {
+        self.cursor_active = false;
+        self.run(); // Update the robot pose
+    }
+    fn handle_input(&mut self, input: &String) {
+        self.viewport.borrow_mut().handle_input(input);
+        let step = self.viewport.borrow().cursor_step(self.increment);
+        let angle_step = self.viewport.borrow().rotation_step(self.increment);
+        match input.as_str() {
+            input::UP => self.move_cursor(step, 0.0, 0.0),
+            input::DOWN => self.move_cursor(-step, 0.0, 0.0),
+            input::LEFT => self.move_cursor(0.0, step, 0.0),
+            input::RIGHT => self.move_cursor(0.0, -step, 0.0),
+            input::ROTATE_LEFT => self.move_cursor(0.0, 0.0, angle_step),
+            input::ROTATE_RIGHT => self.move_cursor(0.0, 0.0, -angle_step),
+            input::INCREMENT_STEP => self.increment += 0.1,
+            input::DECREMENT_STEP => self.increment -= 0.1,
+            input::ADD_WAYPOINT => {
+                if self.poses.len() < 2 {
+                    self.poses.push(self.cursor);
+                }
+                self.cursor_active = true;
+            }
+            input::UNDO => {
+                self.poses.pop();
+            }
+            input::CANCEL => {
+                self.poses.clear();
+                self.reset();
+            }
+            input::CONFIRM => self.send_twist(),
+            _ => (),
+        }
+    }
+
+    fn content_changed(&mut self) -> bool {
+        self.viewport.borrow_mut().content_changed()
+    }
+
+    fn get_name(&self) -> String {
+        "Twist Estimate".to_string()
+    }
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            "This mode publishes the velocity that moves a start pose to an end pose, e.g. to inject test commands."
+                .to_string(),
+            format!(
+                "The two poses are placed at the cursor, the end pose being reached {} s after the start pose.",
+                self.dt
+            ),
+            "The panel shows the twist, in the frame of the start pose, before it is sent."
+                .to_string(),
+        ]
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        let mut keymap = vec![
+            [
+                input::UP.to_string(),
+                "Shifts the cursor positively along the x axis.".to_string(),
+            ],
+            [
+                input::DOWN.to_string(),
+                "Shifts the cursor negatively along the x axis.".to_string(),
+            ],
+            [
+                input::RIGHT.to_string(),
+                "Shifts the cursor positively along the y axis.".to_string(),
+            ],
+            [
+                input::LEFT.to_string(),
+                "Shifts the cursor negatively along the y axis.".to_string(),
+            ],
+            [
+                input::ROTATE_LEFT.to_string(),
+                "Rotates the cursor counter-clockwise.".to_string(),
+            ],
+            [
+                input::ROTATE_RIGHT.to_string(),
+                "Rotates the cursor clockwise.".to_string(),
+            ],
+            [
+                input::ADD_WAYPOINT.to_string(),
+                "Places the start pose, then the end pose, at the cursor.".to_string(),
+            ],
+            [
+                input::UNDO.to_string(),
+                "Removes the last placed pose.".to_string(),
+            ],
+            [
+                input::CANCEL.to_string(),
+                "Removes the placed poses and resets the cursor.".to_string(),
+            ],
+            [input::CONFIRM.to_string(), "Sends the twist.".to_string()],
+            [
+                input::INCREMENT_STEP.to_string(),
+                "Increases the step size for moving the cursor.".to_string(),
+            ],
+            [
+                input::DECREMENT_STEP.to_string(),
+                "Decreases the step size for moving the cursor.".to_string(),
+            ],
+        ];
+        keymap.extend(self.viewport.borrow().get_keymap());
+        keymap
+    }
+}
+
+impl UseViewport for TwistEstimate {
+    fn draw_in_viewport(&self, ctx: &mut Context) {
+        self.viewport.borrow().draw_in_viewport(ctx);
+        let axis_length = self.viewport.borrow().axis_length;
+        for pose in &self.poses {
+            for line in Viewport::get_frame_lines(&transformation::iso2d_to_ros(pose), axis_length)
+            {
+                self.viewport.borrow().draw_line(ctx, &line);
+            }
+        }
+        if let [start, end] = self.poses.as_slice() {
+            self.viewport.borrow().draw_line(
+                ctx,
+                &Line {
+                    x1: start.translation.x,
+                    y1: start.translation.y,
+                    x2: end.translation.x,
+                    y2: end.translation.y,
+                    color: Color::LightYellow,
+                },
+            );
+        }
+        if self.cursor_active {
+            let cursor_ros = transformation::iso2d_to_ros(&self.cursor);
+            for mut line in Viewport::get_frame_lines(&cursor_ros, axis_length) {
+                line.color = Color::Gray;
+                self.viewport.borrow().draw_line(ctx, &line);
+            }
+        }
+    }
+    fn x_bounds(&self) -> [f64; 2] {
+        let scale_factor = self.viewport.borrow().terminal_size.0 as f64
+            / self.viewport.borrow().terminal_size.1 as f64
+            * 0.5;
+        [
+            self.cursor.translation.x
+                + self.viewport.borrow().initial_bounds[0] / self.viewport.borrow().zoom
+                    * scale_factor,
+            self.cursor.translation.x
+                + self.viewport.borrow().initial_bounds[1] / self.viewport.borrow().zoom
+                    * scale_factor,
+        ]
+    }
+    fn y_bounds(&self) -> [f64; 2] {
+        [
+            self.cursor.translation.y
+                + self.viewport.borrow().initial_bounds[2] / self.viewport.borrow().zoom,
+            self.cursor.translation.y
+                + self.viewport.borrow().initial_bounds[3] / self.viewport.borrow().zoom,
+        ]
+    }
+
+    fn get_thumbnail(&self) -> Option<(RgbaImage, ThumbnailConfig)> {
+        self.viewport.borrow().get_thumbnail()
+    }
+
+    fn get_minimap(&self) -> Option<(MinimapData, MinimapConfig)> {
+        self.viewport.borrow().get_minimap()
+    }
+
+    fn get_panel(&self) -> Option<(String, Vec<String>)> {
+        let lines = match self.get_twist() {
+            Some((vx, vy, wz)) => vec![
+                format!("Linear: x {:.3} m/s, y {:.3} m/s", vx, vy),
+                format!("Angular: z {:.3} rad/s", wz),
+            ],
+            None if self.dt <= 0.0 => vec![format!("Invalid dt: {} s", self.dt)],
+            None => vec![format!(
+                "Place the {} pose",
+                ["start", "end"][self.poses.len()]
+            )],
+        };
+        Some((format!("Twist over {} s", self.dt), lines))
+    }
+
+    fn info(&self) -> String {
+        format!(
+            "Topic: /{}, Poses: {}/2, Cursor step: {:.2}",
+            &self.topic,
+            self.poses.len(),
+            &self.increment
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_2;
+
+    fn assert_twist(twist: (f64, f64, f64), expected: (f64, f64, f64)) {
+        assert!(
+            (twist.0 - expected.0).abs() < 1e-9
+                && (twist.1 - expected.1).abs() < 1e-9
+                && (twist.2 - expected.2).abs() < 1e-9,
+            "{:?} is not {:?}",
+            twist,
+            expected
+        );
+    }
+
+    #[test]
+    fn forward_motion() {
+        let start = Isometry2::new(Vector2::new(0.0, 0.0), 0.0);
+        let end = Isometry2::new(Vector2::new(1.0, 0.0), 0.0);
+        assert_twist(get_twist(&start, &end, 2.0), (0.5, 0.0, 0.0));
+        // the twist is expressed in the frame of the start pose
+        let start = Isometry2::new(Vector2::new(2.0, 3.0), FRAC_PI_2);
+        let end = Isometry2::new(Vector2::new(2.0, 4.0), FRAC_PI_2);
+        assert_twist(get_twist(&start, &end, 2.0), (0.5, 0.0, 0.0));
+    }
+
+    #[test]
+    fn quarter_circle() {
+        // a quarter of the circle of radius 1 on the left, in 1 s
+        let start = Isometry2::new(Vector2::new(0.0, 0.0), 0.0);
+        let end = Isometry2::new(Vector2::new(1.0, 1.0), FRAC_PI_2);
+        assert_twist(get_twist(&start, &end, 1.0), (FRAC_PI_2, 0.0, FRAC_PI_2));
+    }
+}
@@ -2,17 +2,127 @@
 //! A mode can borrow the viewport to draw whatever is needed.
 
 use crate::app_modes::{input, AppMode, Drawable};
-use crate::footprint::get_current_footprint;
-use crate::listeners::Listeners;
+use crate::config::Color as ConfigColor;
+use crate::config::{
+    AgeRampConfig, CursorConfig, FootprintBufferConfig, HeadingWedgeConfig, HomeViewConfig,
+    MapHudConfig, MinimapConfig, PoseReadoutConfig, TermvizConfig, ThumbnailConfig, TimeSyncConfig,
+};
+use crate::footprint::{
+    get_current_footprint, get_heading_wedge, get_inflated_polygon, ParameterPolygon,
+};
+use crate::laser::LaserListener;
+use crate::listeners::{get_points_bounds, is_finite_line, Listeners};
+use crate::live_params::{DisplayParameters, LiveParameters};
+use crate::map::{ColoredPoints, MapListener};
+use crate::minimap::{get_minimap, MinimapData};
+use crate::opacity::{dim_color, get_age_color};
+use crate::point_style::{
+    blend_coincident_points, get_glyph_width, get_hull_lines, get_point_lines, is_dot,
+};
+use crate::rate::RateMonitor;
 use crate::transformation;
+use crate::units::Units;
+use image::RgbaImage;
+use nalgebra::Point2;
+use std::cell::RefCell;
+use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tui::backend::Backend;
-use tui::layout::{Constraint, Layout};
+use tui::layout::{Constraint, Layout, Rect};
 use tui::style::{Color, Modifier, Style};
 use tui::text::{Span, Spans};
-use tui::widgets::canvas::{Canvas, Context, Line, Points};
-use tui::widgets::{Block, Borders};
+use tui::widgets::canvas::{Canvas, Context, Line, Painter, Points, Rectangle, Shape};
+use tui::widgets::{Block, Borders, Clear, Paragraph};
 use tui::Frame;
+use tui_image::{ColorMode, Image};
+
+/// Splits a line into dashes of the given length, separated by gaps of the same length.
+fn dash_line(line: &Line, dash_length: f64) -> Vec<Line> {
+    let length = (line.x2 - line.x1).hypot(line.y2 - line.y1);
+    if dash_length.is_nan() || dash_length <= 0.0 || length <= dash_length {
+        return vec![line.clone()];
+    }
+    let count = (length / dash_length).ceil() as usize;
+    (0..count)
+        .step_by(2)
+        .map(|i| {
+            let t1 = i as f64 / count as f64;
+            let t2 = ((i + 1) as f64 / count as f64).min(1.0);
+            Line {
+                x1: line.x1 + t1 * (line.x2 - line.x1),
+                y1: line.y1 + t1 * (line.y2 - line.y1),
+                x2: line.x1 + t2 * (line.x2 - line.x1),
+                y2: line.y1 + t2 * (line.y2 - line.y1),
+                color: line.color,
+            }
+        })
+        .collect()
+}
+
+/// Returns the line and parallel copies of it, for a line of the given thickness.
+///
+/// # Arguments
+/// - `thickness`: total number of lines.
+/// - `spacing`: distance between two copies, e.g. the size of a dot.
+fn thicken_line(line: &Line, thickness: u8, spacing: f64) -> Vec<Line> {
+    let length = (line.x2 - line.x1).hypot(line.y2 - line.y1);
+    if thickness <= 1 || length == 0.0 {
+        return vec![line.clone()];
+    }
+    // unit normal of the line
+    let (nx, ny) = (-(line.y2 - line.y1) / length, (line.x2 - line.x1) / length);
+    (0..thickness)
+        .map(|i| {
+            let offset = (i as f64 - (thickness - 1) as f64 / 2.0) * spacing;
+            Line {
+                x1: line.x1 + offset * nx,
+                y1: line.y1 + offset * ny,
+                x2: line.x2 + offset * nx,
+                y2: line.y2 + offset * ny,
+                color: line.color,
+            }
+        })
+        .collect()
+}
+
+/// A line whose endpoints are moved to the same dot of their terminal cell.
+///
+/// Lines whose endpoints are in the same cells are then drawn with the same dots, which
+/// avoids the flickering and doubled dots of sub-cell positions, at the cost of
+/// accuracy. The snapping is done on the dots of the canvas, i.e., after the mapping of
+/// the world to the screen.
+struct CellSnappedLine<'a>(&'a Line);
+
+impl Shape for CellSnappedLine<'_> {
+    fn draw(&self, painter: &mut Painter) {
+        let line = self.0;
+        let (start, end) = match (
+            painter.get_point(line.x1, line.y1),
+            painter.get_point(line.x2, line.y2),
+        ) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return,
+        };
+        // a braille character has 2x4 dots, the second row is the closest to the center
+        let snap = |(x, y): (usize, usize)| ((x / 2 * 2) as f64, (y / 4 * 4 + 1) as f64);
+        let (x1, y1) = snap(start);
+        let (x2, y2) = snap(end);
+        let steps = (x2 - x1).abs().max((y2 - y1).abs()) as usize;
+        for i in 0..=steps {
+            let t = if steps == 0 {
+                0.0
+            } else {
+                i as f64 / steps as f64
+            };
+            painter.paint(
+                (x1 + t * (x2 - x1)).round() as usize,
+                (y1 + t * (y2 - y1)).round() as usize,
+                line.color,
+            );
+        }
+    }
+}
 
 /// Represents modes that use the viewport.
 pub trait UseViewport: AppMode {
@@ -32,6 +142,18 @@ pub trait UseViewport: AppMode {
 
     /// Returns additional information that will be displayed on the top bar of the viewport.
     fn info(&self) -> String;
+
+    /// Returns the image of the thumbnail panel and the configuration of the panel, if any.
+    fn get_thumbnail(&self) -> Option<(RgbaImage, ThumbnailConfig)>;
+
+    /// Returns the minimap and the size of its panel, if it is shown.
+    fn get_minimap(&self) -> Option<(MinimapData, MinimapConfig)>;
+
+    /// Returns the title and the lines of a text panel drawn in the top left corner of the
+    /// viewport, if any.
+    fn get_panel(&self) -> Option<(String, Vec<String>)> {
+        None
+    }
 }
 
 impl<B: Backend, T: UseViewport> Drawable<B> for T {
@@ -59,45 +181,277 @@ impl<B: Backend, T: UseViewport> Drawable<B> for T {
                 self.draw_in_viewport(ctx);
             });
         f.render_widget(canvas, chunks[0]);
+
+        if let Some((img, config)) = self.get_thumbnail() {
+            let area = chunks[0];
+            let width = (config.width + 2).min(area.width);
+            let height = (config.height + 2).min(area.height);
+            let panel_area = Rect::new(area.x + area.width - width, area.y, width, height);
+            let color_mode = if config.grayscale {
+                ColorMode::Luma
+            } else {
+                ColorMode::Rgb
+            };
+            let panel = Image::with_img(img)
+                .color_mode(color_mode)
+                .block(Block::default().title(config.topic).borders(Borders::ALL));
+            f.render_widget(Clear, panel_area);
+            f.render_widget(panel, panel_area);
+        }
+
+        if let Some((minimap, config)) = self.get_minimap() {
+            let area = chunks[0];
+            // above the status bar
+            let width = (config.width + 2).min(area.width);
+            let height = (config.height + 2).min(area.height.saturating_sub(1));
+            let panel_area = Rect::new(
+                area.x + area.width - width,
+                area.y + area.height.saturating_sub(height + 1),
+                width,
+                height,
+            );
+            let (x_bounds, y_bounds) = (self.x_bounds(), self.y_bounds());
+            let panel = Canvas::default()
+                .block(Block::default().title("Minimap").borders(Borders::ALL))
+                .x_bounds(minimap.x_bounds)
+                .y_bounds(minimap.y_bounds)
+                .paint(|ctx| {
+                    ctx.draw(&Points {
+                        coords: &minimap.points,
+                        color: Color::Gray,
+                    });
+                    ctx.draw(&Rectangle {
+                        x: x_bounds[0],
+                        y: y_bounds[0],
+                        width: x_bounds[1] - x_bounds[0],
+                        height: y_bounds[1] - y_bounds[0],
+                        color: Color::Yellow,
+                    });
+                });
+            f.render_widget(Clear, panel_area);
+            f.render_widget(panel, panel_area);
+        }
+
+        if let Some((title, lines)) = self.get_panel() {
+            let area = chunks[0];
+            let text_width = lines
+                .iter()
+                .chain([&title])
+                .map(|line| line.chars().count())
+                .max()
+                .unwrap_or(0) as u16;
+            // below the top bar
+            let panel_area = Rect::new(
+                area.x,
+                area.y + 1,
+                (text_width + 2).min(area.width),
+                (lines.len() as u16 + 2).min(area.height.saturating_sub(1)),
+            );
+            let panel = Paragraph::new(
+                lines
+                    .into_iter()
+                    .map(|line| Spans::from(Span::raw(line)))
+                    .collect::<Vec<Spans>>(),
+            )
+            .block(Block::default().title(title).borders(Borders::ALL));
+            f.render_widget(Clear, panel_area);
+            f.render_widget(panel, panel_area);
+        }
     }
 }
 
+/// What determines the visible area, stored in the view slots.
+#[derive(Clone)]
+struct View {
+    follow_frame: String,
+    offset: [f64; 2],
+    initial_bounds: Vec<f64>,
+    zoom: f64,
+}
+
+/// Number of view slots, 0 being the home view.
+const VIEW_SLOTS: usize = 10;
+
+/// Returns the common time of the layers from their latest stamps: the oldest one, at which
+/// all of them have data. None without stamps.
+fn get_common_stamp(stamps: impl Iterator<Item = rosrust::Time>) -> Option<rosrust::Time> {
+    stamps.min_by_key(|stamp| stamp.nanos())
+}
+
+/// Returns true if the latest stamp of a layer is within `tolerance` seconds of the common
+/// time, so that it is not dimmed by the time sync.
+fn is_at_common_time(stamp: rosrust::Time, time: rosrust::Time, tolerance: f64) -> bool {
+    (stamp.seconds() - time.seconds()).abs() <= tolerance
+}
+
+/// What the layers drew when TOGGLE_SNAPSHOT was pressed, in the static frame, drawn
+/// faded under the live data until toggled again.
+struct Snapshot {
+    time: Instant,
+    lines: Vec<Line>,
+    points: ColoredPoints,
+}
+
 pub struct Viewport {
     pub static_frame: String,
     pub robot_frame: String,
+    /// Frame on which the view is centered.
+    pub follow_frame: String,
     pub tf_listener: Arc<rustros_tf::TfListener>,
     pub initial_bounds: Vec<f64>,
-    pub footprint: Vec<(f64, f64)>,
+    pub footprint: ParameterPolygon,
+    /// Wedge drawn along the heading of the robot, with the footprint.
+    heading_wedge: Option<HeadingWedgeConfig>,
+    /// Inflated outline drawn around the footprint.
+    footprint_buffer: Option<FootprintBufferConfig>,
     pub axis_length: f64,
     pub zoom: f64,
     pub zoom_factor: f64,
+    pub show_scale_bar: bool,
+    /// If true, the axes of all the frames known to TF are drawn.
+    pub show_tf_frames: bool,
+    /// If true, the endpoints of the lines are snapped to the terminal cells.
+    pub snap_lines: bool,
+    /// Color toward which the layers are faded, according to their opacity.
+    pub background_color: ConfigColor,
     pub terminal_size: (u16, u16),
+    /// Units of the lengths shown to the user.
+    pub units: Units,
     pub listeners: Listeners, // TODO split properly config and listeners
+    live_parameters: LiveParameters,
+    /// Values of the live parameters at the last update, to apply only the changes.
+    applied_parameters: DisplayParameters,
+    /// What determines the drawn content (bounds, robot pose, number of messages received)
+    /// at the last call of content_changed.
+    last_content_state: Vec<f64>,
+    /// Offset of the center of the view from the followed frame, in the static frame.
+    view_offset: [f64; 2],
+    /// Padding of the fit of the view to the data, None once done or if disabled.
+    pending_auto_fit: Option<f64>,
+    /// Indices in Listeners::get_layer_points of the layers on which the view was centered.
+    centered_layers: HashSet<usize>,
+    /// False once all the layers with center_on_first received data.
+    pending_centering: bool,
+    creation_time: Instant,
+    view_slots: Vec<Option<View>>,
+    /// If true, the next view slot input stores the current view instead of recalling it.
+    storing_view: bool,
+    minimap_config: MinimapConfig,
+    pub show_minimap: bool,
+    map_hud: MapHudConfig,
+    /// If true, the north arrow is drawn with the scale bar.
+    pub show_map_hud: bool,
+    pose_readout: PoseReadoutConfig,
+    pub show_pose_readout: bool,
+    /// Index of the only map drawn, None to draw all the maps.
+    pub active_map: Option<usize>,
+    /// If true, the colors of the laser points in the same terminal cell are averaged.
+    blend_points: bool,
+    /// Last minimap computed, with the number of map messages it was computed from.
+    minimap_cache: RefCell<Option<(u64, Option<MinimapData>)>>,
+    snapshot: Option<Snapshot>,
+    /// Opacity of the snapshot, drawn under the live data.
+    snapshot_opacity: f64,
+    /// Fading of the past elements (breadcrumbs, ghost poses, snapshot) with their age.
+    age_ramp: AgeRampConfig,
+    pub cursor: CursorConfig,
+    /// Factor of the cursor steps for the input being handled, from its modifier.
+    cursor_factor: f64,
+    time_sync: TimeSyncConfig,
+    /// If true, the robot and the frames are drawn at the common time of the layers.
+    pub synchronized: bool,
+    /// Common time of the layers for the current frame, None if not synchronized.
+    sync_time: Option<rosrust::Time>,
 }
 
+/// Maximum time to wait for data on all the layers before fitting the view.
+const AUTO_FIT_TIMEOUT: Duration = Duration::from_secs(3);
+
 impl Viewport {
     pub fn new(
         static_frame: &String,
         robot_frame: &String,
         tf_listener: Arc<rustros_tf::TfListener>,
         initial_bounds: &Vec<f64>,
-        footprint: &Vec<(f64, f64)>,
+        footprint: ParameterPolygon,
+        heading_wedge: Option<HeadingWedgeConfig>,
+        footprint_buffer: Option<FootprintBufferConfig>,
         axis_length: f64,
         zoom_factor: f64,
+        show_scale_bar: bool,
+        show_tf_frames: bool,
+        snap_lines: bool,
+        background_color: ConfigColor,
         listeners: Listeners,
         terminal_size: (u16, u16),
+        live_parameters_interval: f64,
+        auto_fit_padding: Option<f64>,
+        units: Units,
+        home_view: Option<HomeViewConfig>,
+        minimap_config: MinimapConfig,
+        map_hud: MapHudConfig,
+        pose_readout: PoseReadoutConfig,
+        exclusive_maps: bool,
+        point_blending: &str,
+        snapshot_opacity: f64,
+        age_ramp: AgeRampConfig,
+        cursor: CursorConfig,
+        time_sync: TimeSyncConfig,
     ) -> Viewport {
+        let mut view_slots = vec![None; VIEW_SLOTS];
+        view_slots[0] = home_view.map(|home| View {
+            follow_frame: home.follow_frame,
+            offset: home.offset,
+            initial_bounds: initial_bounds.clone(),
+            zoom: home.zoom,
+        });
+        let has_maps = !listeners.maps.is_empty();
         Viewport {
             static_frame: static_frame.clone(),
             robot_frame: robot_frame.clone(),
+            follow_frame: robot_frame.clone(),
             tf_listener: tf_listener.clone(),
             initial_bounds: initial_bounds.clone(),
             zoom: 1.0,
             zoom_factor: zoom_factor,
-            footprint: footprint.clone(),
+            show_scale_bar,
+            show_tf_frames,
+            snap_lines,
+            background_color,
+            footprint,
+            heading_wedge,
+            footprint_buffer,
             axis_length: axis_length,
             listeners: listeners,
             terminal_size: terminal_size,
+            units,
+            live_parameters: LiveParameters::new(live_parameters_interval),
+            applied_parameters: DisplayParameters::default(),
+            last_content_state: Vec::new(),
+            view_offset: [0.0, 0.0],
+            pending_auto_fit: auto_fit_padding,
+            centered_layers: HashSet::new(),
+            pending_centering: true,
+            creation_time: Instant::now(),
+            view_slots,
+            storing_view: false,
+            show_minimap: minimap_config.show,
+            minimap_config,
+            minimap_cache: RefCell::new(None),
+            show_map_hud: map_hud.show,
+            map_hud,
+            show_pose_readout: pose_readout.show,
+            pose_readout,
+            active_map: (exclusive_maps && has_maps).then_some(0),
+            blend_points: point_blending == "average",
+            snapshot: None,
+            snapshot_opacity,
+            age_ramp,
+            cursor,
+            cursor_factor: 1.0,
+            synchronized: time_sync.enabled,
+            time_sync,
+            sync_time: None,
         }
     }
     pub fn get_frame_lines(
@@ -123,15 +477,845 @@ impl Viewport {
         });
         result
     }
+
+    /// Centers the view on the data of the layers with center_on_first when they first
+    /// receive some, once per layer. Layers receiving data at the same time are applied
+    /// in the order of the configuration, the last one winning.
+    fn center_on_first_data(&mut self) {
+        if !self.pending_centering {
+            return;
+        }
+        let mut pending = false;
+        for (i, (center_on_first, points)) in self
+            .listeners
+            .get_layer_points(true)
+            .into_iter()
+            .enumerate()
+        {
+            if !center_on_first || self.centered_layers.contains(&i) {
+                continue;
+            }
+            match get_points_bounds(&points) {
+                Some([min_x, max_x, min_y, max_y]) => {
+                    self.follow_frame = self.static_frame.clone();
+                    self.view_offset = [(min_x + max_x) / 2.0, (min_y + max_y) / 2.0];
+                    self.centered_layers.insert(i);
+                }
+                None => pending = true,
+            }
+        }
+        self.pending_centering = pending;
+    }
+
+    /// Fits the view to the data once all the layers received data, or after
+    /// AUTO_FIT_TIMEOUT. The view is then centered on the data in the static frame.
+    fn auto_fit(&mut self) {
+        let padding = match self.pending_auto_fit {
+            Some(padding) => padding,
+            None => return,
+        };
+        let all_received = self
+            .listeners
+            .get_rates()
+            .iter()
+            .all(|(_, rate)| rate.get_total() > 0);
+        if !all_received && self.creation_time.elapsed() < AUTO_FIT_TIMEOUT {
+            return;
+        }
+        self.pending_auto_fit = None;
+        // without data, the configured visible area is kept
+        let [min_x, max_x, min_y, max_y] = match self.listeners.get_bounds() {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        // the width of the view is the height times scale_factor (see x_bounds)
+        let scale_factor = self.terminal_size.0 as f64 / self.terminal_size.1 as f64 * 0.5;
+        let half_size =
+            ((max_x - min_x) / 2.0 / scale_factor).max((max_y - min_y) / 2.0) * (1.0 + padding);
+        if half_size.is_nan() || half_size <= 0.0 {
+            return;
+        }
+        self.follow_frame = self.static_frame.clone();
+        self.view_offset = [(min_x + max_x) / 2.0, (min_y + max_y) / 2.0];
+        self.initial_bounds = vec![-half_size, half_size, -half_size, half_size];
+        self.zoom = 1.0;
+    }
+
+    /// Sets the factor of the cursor steps from the prefix of an input, FINE or COARSE, and
+    /// returns the input without it. None if the input has no such prefix.
+    pub fn set_cursor_modifier(&mut self, input: &str) -> Option<String> {
+        if let Some(stripped) = input.strip_prefix(input::FINE) {
+            self.cursor_factor = self.cursor.fine_factor;
+            Some(stripped.to_string())
+        } else if let Some(stripped) = input.strip_prefix(input::COARSE) {
+            self.cursor_factor = self.cursor.coarse_factor;
+            Some(stripped.to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Restores the normal cursor steps after a modified input.
+    pub fn reset_cursor_modifier(&mut self) {
+        self.cursor_factor = 1.0;
+    }
+
+    /// Returns the distance in meters of a cursor step, scaled by the modifier of the input
+    /// and, with the screen step_mode, by the width of a terminal cell at the current zoom.
+    pub fn cursor_step(&self, step: f64) -> f64 {
+        let unit = if self.cursor.step_mode == "screen" {
+            let x_bounds = self.x_bounds();
+            (x_bounds[1] - x_bounds[0]) / self.terminal_size.0 as f64
+        } else {
+            1.0
+        };
+        step * unit * self.cursor_factor
+    }
+
+    /// Returns the angle in radians of a cursor rotation step, scaled by the modifier of
+    /// the input.
+    pub fn rotation_step(&self, step: f64) -> f64 {
+        step * self.cursor_factor
+    }
+
+    /// Returns the inflated outline of the footprint at the pose of the robot, if configured.
+    fn get_footprint_buffer_lines(
+        &self,
+        robot_pose: &rosrust_msg::geometry_msgs::Transform,
+    ) -> Vec<Line> {
+        let buffer = match &self.footprint_buffer {
+            Some(buffer) => buffer,
+            None => return Vec::new(),
+        };
+        let outline = get_inflated_polygon(&self.footprint.polygon, buffer.distance);
+        if outline.is_empty() {
+            return Vec::new();
+        }
+        let color = Color::Rgb(buffer.color.r, buffer.color.g, buffer.color.b);
+        get_current_footprint(robot_pose, &outline)
+            .into_iter()
+            .map(|elem| Line {
+                x1: elem.0,
+                y1: elem.1,
+                x2: elem.2,
+                y2: elem.3,
+                color,
+            })
+            .collect()
+    }
+
+    /// Returns the outline of the heading wedge at the pose of the robot, if configured.
+    fn get_heading_wedge_lines(
+        &self,
+        robot_pose: &rosrust_msg::geometry_msgs::Transform,
+    ) -> Vec<Line> {
+        let wedge = match &self.heading_wedge {
+            Some(wedge) => wedge,
+            None => return Vec::new(),
+        };
+        let color = Color::Rgb(wedge.color.r, wedge.color.g, wedge.color.b);
+        get_heading_wedge(robot_pose, wedge.half_angle.to_radians(), wedge.length)
+            .into_iter()
+            .map(|elem| Line {
+                x1: elem.0,
+                y1: elem.1,
+                x2: elem.2,
+                y2: elem.3,
+                color,
+            })
+            .collect()
+    }
+
+    /// Draws a line, snapped to the terminal cells if snap_lines is set.
+    pub fn draw_line(&self, ctx: &mut Context, line: &Line) {
+        // the layers warn about their non-finite lines, this only keeps them off the canvas
+        if !is_finite_line(line) {
+            return;
+        }
+        if self.snap_lines {
+            ctx.draw(&CellSnappedLine(line));
+        } else {
+            ctx.draw(line);
+        }
+    }
+
+    /// Fades a color of a layer toward the background color, according to its opacity.
+    fn fade(&self, color: Color, opacity: f64) -> Color {
+        dim_color(color, opacity, &self.background_color)
+    }
+
+    /// Draws a line of a layer with the given opacity, see draw_line.
+    fn draw_faded_line(&self, ctx: &mut Context, line: &Line, opacity: f64) {
+        self.draw_line(
+            ctx,
+            &Line {
+                color: self.fade(line.color, opacity),
+                ..line.clone()
+            },
+        );
+    }
+
+    /// Captures the lines and the points of all the layers, or forgets the snapshot if
+    /// there is one. The points drawn with a glyph are captured as dots.
+    fn toggle_snapshot(&mut self) {
+        if self.snapshot.take().is_some() {
+            return;
+        }
+        let mut lines = Vec::new();
+        let mut points = ColoredPoints::new();
+        for map in self.get_drawn_maps() {
+            points.extend(map.points.read().unwrap().iter().cloned());
+            lines.extend(map.lines.read().unwrap().iter().cloned());
+        }
+        for heatmap in &self.listeners.heatmaps {
+            points.extend(heatmap.points.read().unwrap().iter().cloned());
+        }
+        for pointcloud in &self.listeners.pointclouds {
+            for pt in pointcloud.points.read().unwrap().iter() {
+                // consecutive points of a cloud often have the same color
+                match points.last_mut() {
+                    Some((color, coords)) if *color == pt.color => {
+                        coords.push((pt.point.x, pt.point.y))
+                    }
+                    _ => points.push((pt.color, vec![(pt.point.x, pt.point.y)])),
+                }
+            }
+        }
+        for laser in &self.listeners.lasers {
+            let color = &laser.config.color;
+            points.push((
+                Color::Rgb(color.r, color.g, color.b),
+                laser.points.read().unwrap().clone(),
+            ));
+        }
+        lines.extend(self.listeners.markers.get_lines());
+        for interactive_marker in &self.listeners.interactive_markers {
+            lines.extend(interactive_marker.get_lines());
+        }
+        for polygon in &self.listeners.polygons {
+            lines.extend(polygon.get_lines());
+        }
+        lines.extend(self.listeners.robot_model.get_lines());
+        if let Ok(tf) = self.lookup_drawn_transform(&self.static_frame, &self.robot_frame) {
+            lines.extend(self.get_footprint_buffer_lines(&tf.transform));
+            lines.extend(
+                get_current_footprint(&tf.transform, &self.footprint.polygon)
+                    .into_iter()
+                    .map(|elem| Line {
+                        x1: elem.0,
+                        y1: elem.1,
+                        x2: elem.2,
+                        y2: elem.3,
+                        color: Color::Blue,
+                    }),
+            );
+            lines.extend(self.get_heading_wedge_lines(&tf.transform));
+            lines.extend(Viewport::get_frame_lines(&tf.transform, self.axis_length));
+        }
+        for pose_stamped in &self.listeners.pose_stamped {
+            lines.extend(pose_stamped.get_lines());
+            lines.extend(
+                pose_stamped
+                    .get_history_lines()
+                    .into_iter()
+                    .map(|(_, line)| line),
+            );
+        }
+        for path in &self.listeners.paths {
+            lines.extend(path.get_lines());
+        }
+        for pose_array in &self.listeners.pose_array {
+            lines.extend(pose_array.get_lines());
+        }
+        let x_bounds = self.x_bounds();
+        let cell_width = (x_bounds[1] - x_bounds[0]) / self.terminal_size.0 as f64;
+        for navsat in &self.listeners.navsats {
+            lines.extend(navsat.get_lines(cell_width));
+        }
+        for accel in &self.listeners.accels {
+            lines.extend(accel.get_lines());
+        }
+        for listener in &self.listeners.detections {
+            for detection in listener.detections.read().unwrap().iter() {
+                lines.extend(detection.lines.iter().cloned());
+            }
+        }
+        for listener in &self.listeners.segments {
+            for segment in listener.segments.read().unwrap().iter() {
+                lines.push(segment.line.clone());
+            }
+        }
+        self.snapshot = Some(Snapshot {
+            time: Instant::now(),
+            lines,
+            points,
+        });
+    }
+
+    /// Draws a past element of a layer, faded with the age ramp and then with the opacity
+    /// of the layer, see draw_faded_line.
+    pub fn draw_aged_line(&self, ctx: &mut Context, line: &Line, age: f64, opacity: f64) {
+        let color = get_age_color(line.color, age, &self.age_ramp, &self.background_color);
+        self.draw_faded_line(
+            ctx,
+            &Line {
+                color,
+                ..line.clone()
+            },
+            opacity,
+        );
+    }
+
+    /// Stores the current view in the slot if STORE_VIEW was pressed before, recalls the
+    /// view of the slot otherwise. Recalling an empty slot does nothing.
+    fn use_view_slot(&mut self, slot: usize) {
+        if slot >= VIEW_SLOTS {
+            return;
+        }
+        if self.storing_view {
+            self.storing_view = false;
+            self.view_slots[slot] = Some(View {
+                follow_frame: self.follow_frame.clone(),
+                offset: self.view_offset,
+                initial_bounds: self.initial_bounds.clone(),
+                zoom: self.zoom,
+            });
+            return;
+        }
+        if let Some(view) = self.view_slots[slot].clone() {
+            self.follow_frame = view.follow_frame;
+            self.view_offset = view.offset;
+            self.initial_bounds = view.initial_bounds;
+            self.zoom = view.zoom;
+            // the recalled view is not overridden by a pending fit
+            self.pending_auto_fit = None;
+        }
+    }
+
+    /// Returns the axes of the frames known to TF, solid for the static frames and dashed
+    /// for the dynamic ones.
+    fn get_tf_frames_lines(&self) -> Vec<Line> {
+        let mut lines = Vec::new();
+        let dash_length = self.axis_length / 5.0;
+        for frame in self.listeners.frames.get_frame_names() {
+            let tf = match self.lookup_drawn_transform(&self.static_frame, &frame) {
+                Ok(tf) => tf.transform,
+                Err(_e) => continue,
+            };
+            let axes = Viewport::get_frame_lines(&tf, self.axis_length);
+            if self.listeners.frames.is_static(&frame) {
+                lines.extend(axes);
+            } else {
+                lines.extend(axes.iter().flat_map(|axis| dash_line(axis, dash_length)));
+            }
+        }
+        lines
+    }
+
+    /// Follows the next frame known to TF which can be transformed to the static frame.
+    fn cycle_follow_frame(&mut self) {
+        let frames = self.listeners.frames.get_frame_names();
+        let start = frames
+            .iter()
+            .position(|f| f == &self.follow_frame)
+            .map_or(0, |i| i + 1);
+        for i in 0..frames.len() {
+            let candidate = &frames[(start + i) % frames.len()];
+            if self
+                .tf_listener
+                .lookup_transform(&self.static_frame, candidate, rosrust::Time::new())
+                .is_ok()
+            {
+                self.follow_frame = candidate.clone();
+                self.view_offset = [0.0, 0.0];
+                return;
+            }
+        }
+    }
+
+    /// Returns the only map drawn, None if all the maps are drawn.
+    pub fn get_active_map(&self) -> Option<&MapListener> {
+        self.listeners.maps.get(self.active_map?)
+    }
+
+    /// Returns the maps drawn: the active one if any, otherwise all of them.
+    fn get_drawn_maps(&self) -> Vec<&MapListener> {
+        match self.get_active_map() {
+            Some(map) => vec![map],
+            None => self.listeners.maps.iter().collect(),
+        }
+    }
+
+    /// Draws only the next map, or the first one if all the maps are drawn. The hidden
+    /// maps keep their grids, so the switch is immediate.
+    fn cycle_active_map(&mut self) {
+        let count = self.listeners.maps.len();
+        if count == 0 {
+            return;
+        }
+        self.active_map = Some(self.active_map.map_or(0, |i| (i + 1) % count));
+    }
+
+    /// Saves the last grid of each map in the map_server format.
+    fn export_maps(&self) {
+        for map in &self.listeners.maps {
+            if let Err(e) = map.export() {
+                log::error!("Could not export map {}: {}", map.config.topic, e);
+            }
+        }
+    }
+
+    /// Writes the settings changed at runtime to a configuration: the display toggles and
+    /// parameters, the colors of the lasers, the styles of the maps, and the current view
+    /// as the home view.
+    pub fn apply_to_config(&self, config: &mut TermvizConfig) {
+        config.axis_length = self.axis_length;
+        config.zoom_factor = self.zoom_factor;
+        config.show_scale_bar = self.show_scale_bar;
+        config.show_tf_frames = self.show_tf_frames;
+        config.minimap.show = self.show_minimap;
+        config.map_hud.show = self.show_map_hud;
+        config.pose_readout.show = self.show_pose_readout;
+        config.cursor.relative = self.cursor.relative;
+        config.time_sync.enabled = self.synchronized;
+        config.exclusive_maps = self.active_map.is_some();
+        config.laser_topics = self
+            .listeners
+            .lasers
+            .iter()
+            .map(|laser| laser.config.clone())
+            .collect();
+        for map_config in &mut config.map_topics {
+            if let Some(map) = self
+                .listeners
+                .maps
+                .iter()
+                .find(|map| map.config.topic == map_config.topic)
+            {
+                map_config.style = map.get_style();
+            }
+        }
+        config.visible_area = self.initial_bounds.clone();
+        config.home_view = Some(HomeViewConfig {
+            follow_frame: self.follow_frame.clone(),
+            offset: self.view_offset,
+            zoom: self.zoom,
+        });
+    }
+
+    /// Applies the live parameters that changed since the last update.
+    fn apply_live_parameters(&mut self) {
+        let parameters = self.live_parameters.get();
+        if parameters == self.applied_parameters {
+            return;
+        }
+        if parameters.axis_length != self.applied_parameters.axis_length {
+            self.axis_length = parameters.axis_length.unwrap_or(self.axis_length);
+        }
+        if parameters.zoom_factor != self.applied_parameters.zoom_factor {
+            self.zoom_factor = parameters.zoom_factor.unwrap_or(self.zoom_factor);
+        }
+        if parameters.show_scale_bar != self.applied_parameters.show_scale_bar {
+            self.show_scale_bar = parameters.show_scale_bar.unwrap_or(self.show_scale_bar);
+        }
+        if parameters.follow_frame != self.applied_parameters.follow_frame {
+            if let Some(frame) = &parameters.follow_frame {
+                self.follow_frame = frame.clone();
+                self.view_offset = [0.0, 0.0];
+            }
+        }
+        for laser in self.listeners.lasers.iter_mut() {
+            let color = parameters.laser_colors.get(&laser.config.topic);
+            if color
+                == self
+                    .applied_parameters
+                    .laser_colors
+                    .get(&laser.config.topic)
+            {
+                continue;
+            }
+            match color.map(|c| c.as_slice()) {
+                Some([r, g, b]) => {
+                    laser.config.color = ConfigColor {
+                        r: *r,
+                        g: *g,
+                        b: *b,
+                    }
+                }
+                Some(_) => {
+                    log::warn!(
+                        "Invalid color for {} in ~laser_colors, expected [r, g, b].",
+                        laser.config.topic
+                    );
+                }
+                None => (),
+            }
+        }
+        self.applied_parameters = parameters;
+    }
+
+    /// Converts a point from screen space to world space.
+    ///
+    /// Screen space coordinates are fractions of the viewport, from (0, 0) in the bottom
+    /// left corner to (1, 1) in the top right corner. Elements drawn in screen space keep
+    /// their position on the screen when the view moves.
+    fn screen_to_world(&self, x: f64, y: f64) -> (f64, f64) {
+        let x_bounds = self.x_bounds();
+        let y_bounds = self.y_bounds();
+        (
+            x_bounds[0] + x * (x_bounds[1] - x_bounds[0]),
+            y_bounds[0] + y * (y_bounds[1] - y_bounds[0]),
+        )
+    }
+
+    /// Returns the color of the scale bar and the north arrow: the one of the map HUD if
+    /// set, otherwise white or black, whichever contrasts with the background.
+    fn get_hud_color(&self) -> Color {
+        if let Some(color) = &self.map_hud.color {
+            return Color::Rgb(color.r, color.g, color.b);
+        }
+        let background = &self.background_color;
+        let luminance =
+            0.299 * background.r as f64 + 0.587 * background.g as f64 + 0.114 * background.b as f64;
+        if luminance > 127.5 {
+            Color::Black
+        } else {
+            Color::White
+        }
+    }
+
+    /// Draws an arrow pointing to the north, labeled N, above the scale bar.
+    fn draw_north_arrow(&self, ctx: &mut Context, color: Color) {
+        // the view is not rotated, so the north has the same direction on the screen
+        let angle = self.map_hud.north_angle.to_radians();
+        let (x, y) = self.screen_to_world(0.08, 0.2);
+        let (_, length) = self.screen_to_world(0.0, 0.06);
+        let length = length - self.y_bounds()[0];
+        let tip = (x + length * angle.cos(), y + length * angle.sin());
+        let mut lines = vec![(x - length * angle.cos(), y - length * angle.sin(), tip)];
+        for side in [-1.0, 1.0] {
+            let head_angle = angle + std::f64::consts::PI + side * 0.5;
+            lines.push((
+                tip.0 + length * 0.5 * head_angle.cos(),
+                tip.1 + length * 0.5 * head_angle.sin(),
+                tip,
+            ));
+        }
+        for (x1, y1, (x2, y2)) in lines {
+            self.draw_line(
+                ctx,
+                &Line {
+                    x1,
+                    y1,
+                    x2,
+                    y2,
+                    color,
+                },
+            );
+        }
+        ctx.print(
+            x + length * 1.5 * angle.cos(),
+            y + length * 1.5 * angle.sin(),
+            Span::styled("N", Style::default().fg(color).add_modifier(Modifier::BOLD)),
+        );
+    }
+
+    /// Returns the common time of the layers for the time sync, i.e. the oldest of the
+    /// latest stamps of the layers, at which all of them have data. None if the sync is
+    /// off or no layer has a stamp yet.
+    fn get_common_time(&self) -> Option<rosrust::Time> {
+        if !self.synchronized {
+            return None;
+        }
+        get_common_stamp(
+            self.listeners
+                .get_rates()
+                .iter()
+                .filter(|(topic, rate)| {
+                    rate.is_subscribed()
+                        && (self.time_sync.topics.is_empty()
+                            || self.time_sync.topics.contains(topic))
+                })
+                .filter_map(|(_, rate)| rate.get_stamp()),
+        )
+    }
+
+    /// Returns the time sync for the status bar, None if it is off.
+    pub fn get_time_sync_note(&self) -> Option<String> {
+        if !self.synchronized {
+            return None;
+        }
+        Some(match self.sync_time {
+            Some(time) => format!(
+                "sync: {:.2} s behind",
+                rosrust::now().seconds() - time.seconds()
+            ),
+            None => "sync: no stamp yet".to_string(),
+        })
+    }
+
+    /// Returns the opacity of a layer, dimmed with the time sync if its latest data is not
+    /// at the common time. The layers without stamps are never dimmed.
+    fn get_synced_opacity(&self, rate: &RateMonitor, opacity: f64) -> f64 {
+        match (self.sync_time, rate.get_stamp()) {
+            (Some(time), Some(stamp))
+                if !is_at_common_time(stamp, time, self.time_sync.tolerance) =>
+            {
+                opacity * self.time_sync.dim_opacity
+            }
+            _ => opacity,
+        }
+    }
+
+    /// Looks up a transform to draw, at the common time of the layers with the time sync,
+    /// otherwise or if it is not available at that time at the latest time.
+    fn lookup_drawn_transform(
+        &self,
+        target: &str,
+        source: &str,
+    ) -> Result<rosrust_msg::geometry_msgs::TransformStamped, rustros_tf::TfError> {
+        if let Some(time) = self.sync_time {
+            if let Ok(tf) = self.tf_listener.lookup_transform(target, source, time) {
+                return Ok(tf);
+            }
+        }
+        self.tf_listener
+            .lookup_transform(target, source, rosrust::Time::new())
+    }
+
+    /// Formats a position of the static frame for the cursor readouts, followed by its
+    /// frame. With the relative coordinates, the position is expressed in the relative frame,
+    /// or kept in the static frame with a note if its transform is unavailable.
+    pub fn format_position(&self, x: f64, y: f64) -> String {
+        if !self.cursor.relative {
+            return format!("({:.2}, {:.2}) in {}", x, y, self.static_frame);
+        }
+        let frame = self
+            .cursor
+            .relative_frame
+            .as_deref()
+            .unwrap_or(&self.robot_frame);
+        match self
+            .tf_listener
+            .lookup_transform(frame, &self.static_frame, rosrust::Time::new())
+        {
+            Ok(tf) => {
+                let position = transformation::ros_to_iso2d(&tf.transform) * Point2::new(x, y);
+                format!("({:.2}, {:.2}) rel. {}", position.x, position.y, frame)
+            }
+            Err(_) => format!(
+                "({:.2}, {:.2}) in {}, no TF to {}",
+                x, y, self.static_frame, frame
+            ),
+        }
+    }
+
+    /// Returns the frame of the pose readout and its pose (x, y, yaw) in the static frame,
+    /// None if its transform is unavailable.
+    fn get_readout_pose(&self) -> (&str, Option<(f64, f64, f64)>) {
+        let frame = self
+            .pose_readout
+            .frame
+            .as_deref()
+            .unwrap_or(&self.robot_frame);
+        let pose = self
+            .lookup_drawn_transform(&self.static_frame, frame)
+            .ok()
+            .map(|tf| {
+                let pose = transformation::ros_to_iso2d(&tf.transform);
+                (
+                    pose.translation.x,
+                    pose.translation.y,
+                    pose.rotation.angle(),
+                )
+            });
+        (frame, pose)
+    }
+
+    /// Draws the pose of the readout frame in the configured corner, with the lengths in
+    /// the configured units.
+    fn draw_pose_readout(&self, ctx: &mut Context, color: Color) {
+        let precision = self.pose_readout.precision;
+        let text = match self.get_readout_pose() {
+            (frame, Some((x, y, yaw))) => format!(
+                "{}: x {:.*} {}, y {:.*} {}, yaw {:.*}°",
+                frame,
+                precision,
+                self.units.in_unit(x),
+                self.units.symbol(),
+                precision,
+                self.units.in_unit(y),
+                self.units.symbol(),
+                precision,
+                yaw.to_degrees()
+            ),
+            (frame, None) => format!("{}: N/A", frame),
+        };
+        // the text starts at its position, so it is shifted by its width on the right
+        let width = (text.chars().count() + 1) as f64 / self.terminal_size.0.max(1) as f64;
+        let (x, y) = match self.pose_readout.corner.as_str() {
+            "top_left" => (0.01, 0.97),
+            "bottom_left" => (0.01, 0.02),
+            "bottom_right" => (1.0 - width, 0.02),
+            _ => (1.0 - width, 0.97),
+        };
+        let (x, y) = self.screen_to_world(x, y);
+        ctx.print(x, y, Span::styled(text, Style::default().fg(color)));
+    }
+
+    /// Draws the elements positioned in screen space, on top of the world.
+    fn draw_screen_overlays(&self, ctx: &mut Context) {
+        if self.show_pose_readout {
+            self.draw_pose_readout(ctx, self.get_hud_color());
+        }
+        if !self.show_scale_bar && !self.show_map_hud {
+            return;
+        }
+        let color = self.get_hud_color();
+        if self.show_map_hud {
+            self.draw_north_arrow(ctx, color);
+        }
+        // The bar is about a fifth of the width, rounded to 1, 2 or 5 times a power of 10
+        // in the displayed unit
+        let x_bounds = self.x_bounds();
+        let width = x_bounds[1] - x_bounds[0];
+        let displayed_width = self.units.in_unit(width);
+        let magnitude = 10_f64.powf((displayed_width / 5.0).log10().floor());
+        let length = self.units.to_meters(
+            [5.0, 2.0, 1.0]
+                .iter()
+                .map(|m| m * magnitude)
+                .find(|l| *l <= displayed_width / 5.0)
+                .unwrap_or(magnitude),
+        );
+        let (x1, y) = self.screen_to_world(0.05, 0.05);
+        let (_, tick) = self.screen_to_world(0.0, 0.02);
+        let tick = tick - self.y_bounds()[0];
+        let x2 = x1 + length;
+        for line in [
+            (x1, y, x2, y),
+            (x1, y - tick, x1, y + tick),
+            (x2, y - tick, x2, y + tick),
+        ] {
+            self.draw_line(
+                ctx,
+                &Line {
+                    x1: line.0,
+                    y1: line.1,
+                    x2: line.2,
+                    y2: line.3,
+                    color,
+                },
+            );
+        }
+        let (label_x, label_y) = self.screen_to_world(0.05, 0.08);
+        ctx.print(
+            label_x,
+            label_y,
+            Span::styled(
+                format!(
+                    "{} (width: {})",
+                    self.units.format_length(length),
+                    self.units.format_length(width)
+                ),
+                Style::default().fg(color),
+            ),
+        );
+    }
 }
 
 impl AppMode for Viewport {
-    fn run(&mut self) {}
+    fn run(&mut self) {
+        self.apply_live_parameters();
+        self.footprint.update();
+        self.auto_fit();
+        self.center_on_first_data();
+        self.sync_time = self.get_common_time();
+    }
     fn reset(&mut self) {}
+    fn content_changed(&mut self) -> bool {
+        let mut state = vec![
+            self.zoom,
+            self.axis_length,
+            self.show_scale_bar as u8 as f64,
+            self.show_map_hud as u8 as f64,
+            self.show_pose_readout as u8 as f64,
+            self.cursor.relative as u8 as f64,
+            self.synchronized as u8 as f64,
+            self.active_map.map_or(-1.0, |i| i as f64),
+            self.snapshot.is_some() as u8 as f64,
+            self.listeners.get_message_count() as f64,
+        ];
+        state.extend(self.x_bounds());
+        state.extend(self.y_bounds());
+        state.extend(self.footprint.polygon.iter().flat_map(|pt| [pt.0, pt.1]));
+        if let Ok(tf) = self.tf_listener.lookup_transform(
+            &self.static_frame,
+            &self.robot_frame,
+            rosrust::Time::new(),
+        ) {
+            let tf = tf.transform;
+            state.extend([
+                tf.translation.x,
+                tf.translation.y,
+                tf.rotation.z,
+                tf.rotation.w,
+            ]);
+        }
+        // the readout can show another frame than the robot one
+        if self.show_pose_readout {
+            if let (_, Some((x, y, yaw))) = self.get_readout_pose() {
+                state.extend([x, y, yaw]);
+            }
+        }
+        let changed = state != self.last_content_state;
+        self.last_content_state = state;
+        changed
+    }
     fn handle_input(&mut self, input: &String) {
+        if let Some(slot) = input.strip_prefix(input::VIEW_SLOT) {
+            if let Ok(slot) = slot.parse::<usize>() {
+                self.use_view_slot(slot);
+            }
+            return;
+        }
         match input.as_str() {
+            input::STORE_VIEW => self.storing_view = true,
             input::ZOOM_IN => self.zoom += self.zoom_factor,
             input::ZOOM_OUT => self.zoom -= self.zoom_factor,
+            input::CYCLE_FOLLOW_FRAME => self.cycle_follow_frame(),
+            input::EXPORT_MAP => self.export_maps(),
+            input::TOGGLE_SCALE_BAR => self.show_scale_bar = !self.show_scale_bar,
+            input::TOGGLE_MAP_HUD => self.show_map_hud = !self.show_map_hud,
+            input::TOGGLE_POSE_READOUT => self.show_pose_readout = !self.show_pose_readout,
+            input::TOGGLE_RELATIVE_COORDINATES => self.cursor.relative = !self.cursor.relative,
+            input::TOGGLE_TIME_SYNC => self.synchronized = !self.synchronized,
+            input::CYCLE_ACTIVE_MAP => self.cycle_active_map(),
+            input::TOGGLE_POSE_DENSITY => {
+                for pose_array in &mut self.listeners.pose_array {
+                    pose_array.toggle_density();
+                }
+            }
+            input::TOGGLE_LAZY_LAYERS => {
+                for pointcloud in &mut self.listeners.pointclouds {
+                    if !pointcloud.config.lazy {
+                        continue;
+                    }
+                    if pointcloud.is_active() {
+                        pointcloud.deactivate();
+                    } else {
+                        pointcloud.activate();
+                    }
+                }
+            }
+            input::TOGGLE_MINIMAP => self.show_minimap = !self.show_minimap,
+            input::CYCLE_MAP_STYLE => {
+                for map in &self.listeners.maps {
+                    map.cycle_style();
+                }
+            }
+            input::NEXT_FOOTPRINT => self.footprint.select_next(),
+            input::TOGGLE_SNAPSHOT => self.toggle_snapshot(),
             _ => return,
         }
     }
@@ -154,6 +1338,78 @@ impl AppMode for Viewport {
                 input::ZOOM_OUT.to_string(),
                 "Decreases the zoom.".to_string(),
             ],
+            [
+                input::CYCLE_FOLLOW_FRAME.to_string(),
+                "Centers the view on the next frame known to TF.".to_string(),
+            ],
+            [
+                input::EXPORT_MAP.to_string(),
+                "Saves the maps as PGM and YAML files in the current directory.".to_string(),
+            ],
+            [
+                input::TOGGLE_SCALE_BAR.to_string(),
+                "Shows/hides the scale bar.".to_string(),
+            ],
+            [
+                input::TOGGLE_MAP_HUD.to_string(),
+                "Shows/hides the north arrow with the scale bar.".to_string(),
+            ],
+            [
+                input::TOGGLE_POSE_READOUT.to_string(),
+                "Shows/hides the text of the pose of the robot.".to_string(),
+            ],
+            [
+                input::TOGGLE_TIME_SYNC.to_string(),
+                "Draws the robot and the frames at the common time of the layers, dimming the layers ahead of it, or each layer at its latest data."
+                    .to_string(),
+            ],
+            [
+                input::TOGGLE_RELATIVE_COORDINATES.to_string(),
+                "Shows the cursor coordinates relative to the robot, or in the static frame."
+                    .to_string(),
+            ],
+            [
+                input::TOGGLE_POSE_DENSITY.to_string(),
+                "Draws the pose arrays with a density configured as heatmaps, or as poses."
+                    .to_string(),
+            ],
+            [
+                input::TOGGLE_LAZY_LAYERS.to_string(),
+                "Subscribes to the lazy layers and shows them, or unsubscribes and hides them."
+                    .to_string(),
+            ],
+            [
+                input::TOGGLE_MINIMAP.to_string(),
+                "Shows/hides the overview of the maps.".to_string(),
+            ],
+            [
+                input::CYCLE_MAP_STYLE.to_string(),
+                "Draws the maps filled, as contours, or not at all.".to_string(),
+            ],
+            [
+                input::CYCLE_ACTIVE_MAP.to_string(),
+                "Draws only the next map, hiding the others.".to_string(),
+            ],
+            [
+                input::NEXT_FOOTPRINT.to_string(),
+                format!(
+                    "Reads the footprint from the next parameter starting with {}.",
+                    self.footprint.prefix
+                ),
+            ],
+            [
+                input::TOGGLE_SNAPSHOT.to_string(),
+                "Captures the layers, drawn faded under the live data, or clears the capture."
+                    .to_string(),
+            ],
+            [
+                "Alt+0-9".to_string(),
+                "Recalls the view of the slot (0 is the home view), if any.".to_string(),
+            ],
+            [
+                input::STORE_VIEW.to_string(),
+                "Stores the current view in the slot selected next with Alt+0-9.".to_string(),
+            ],
         ]
     }
 }
@@ -161,129 +1417,537 @@ impl AppMode for Viewport {
 impl UseViewport for Viewport {
     fn x_bounds(&self) -> [f64; 2] {
         let scale_factor = self.terminal_size.0 as f64 / self.terminal_size.1 as f64 * 0.5;
-        let res = self.tf_listener.clone().lookup_transform(
-            &self.static_frame,
-            &self.robot_frame,
-            rosrust::Time::new(),
-        );
+        let res = self.lookup_drawn_transform(&self.static_frame, &self.follow_frame);
         match &res {
             Ok(res) => res,
             Err(_e) => {
                 return [
-                    self.initial_bounds[0] / self.zoom * scale_factor,
-                    self.initial_bounds[1] / self.zoom * scale_factor,
+                    self.view_offset[0] + self.initial_bounds[0] / self.zoom * scale_factor,
+                    self.view_offset[0] + self.initial_bounds[1] / self.zoom * scale_factor,
                 ]
             }
         };
         let tf = res.as_ref().unwrap();
 
         [
-            tf.transform.translation.x + self.initial_bounds[0] / self.zoom * scale_factor,
-            tf.transform.translation.x + self.initial_bounds[1] / self.zoom * scale_factor,
+            tf.transform.translation.x
+                + self.view_offset[0]
+                + self.initial_bounds[0] / self.zoom * scale_factor,
+            tf.transform.translation.x
+                + self.view_offset[0]
+                + self.initial_bounds[1] / self.zoom * scale_factor,
         ]
     }
     fn y_bounds(&self) -> [f64; 2] {
-        let scale_factor = self.terminal_size.0 as f64 / self.terminal_size.1 as f64 * 0.5;
-        let res = self.tf_listener.clone().lookup_transform(
-            &self.static_frame,
-            &self.robot_frame,
-            rosrust::Time::new(),
-        );
+        let res = self.lookup_drawn_transform(&self.static_frame, &self.follow_frame);
         match &res {
             Ok(res) => res,
             Err(_e) => {
                 return [
-                    self.initial_bounds[2] / self.zoom * scale_factor,
-                    self.initial_bounds[3] / self.zoom * scale_factor,
+                    self.view_offset[1] + self.initial_bounds[2] / self.zoom,
+                    self.view_offset[1] + self.initial_bounds[3] / self.zoom,
                 ]
             }
         };
         let tf = res.as_ref().unwrap();
         [
-            tf.transform.translation.y + self.initial_bounds[2] / self.zoom,
-            tf.transform.translation.y + self.initial_bounds[3] / self.zoom,
+            tf.transform.translation.y + self.view_offset[1] + self.initial_bounds[2] / self.zoom,
+            tf.transform.translation.y + self.view_offset[1] + self.initial_bounds[3] / self.zoom,
         ]
     }
 
     fn info(&self) -> String {
         "".to_string()
     }
+
+    fn get_minimap(&self) -> Option<(MinimapData, MinimapConfig)> {
+        if !self.show_minimap {
+            return None;
+        }
+        // recomputed only when a new map is received
+        let message_count = self
+            .listeners
+            .maps
+            .iter()
+            .map(|map| map.rate.get_total())
+            .sum();
+        let mut cache = self.minimap_cache.borrow_mut();
+        if cache.as_ref().map(|(count, _)| *count) != Some(message_count) {
+            let minimap = get_minimap(
+                &self.listeners.maps,
+                self.minimap_config.width as usize * 2,
+                self.minimap_config.height as usize * 4,
+            );
+            *cache = Some((message_count, minimap));
+        }
+        let minimap = cache.as_ref()?.1.clone()?;
+        Some((minimap, self.minimap_config.clone()))
+    }
+
+    fn get_thumbnail(&self) -> Option<(RgbaImage, ThumbnailConfig)> {
+        let thumbnail = self.listeners.thumbnail.as_ref()?;
+        let img = thumbnail.img.read().unwrap().clone()?;
+        Some((img, thumbnail.config.clone()))
+    }
     fn draw_in_viewport(&self, ctx: &mut Context) {
-        for map in &self.listeners.maps {
-            ctx.draw(&Points {
-                coords: &map.points.read().unwrap(),
-                color: Color::Rgb(map.config.color.r, map.config.color.g, map.config.color.b),
-            });
+        if !self.listeners.overlays.is_empty() {
+            let x_bounds = self.x_bounds();
+            let y_bounds = self.y_bounds();
+            // a braille character has 2x4 dots
+            let step = (
+                (x_bounds[1] - x_bounds[0]) / (self.terminal_size.0 as f64 * 2.0),
+                (y_bounds[1] - y_bounds[0]) / (self.terminal_size.1 as f64 * 4.0),
+            );
+            for overlay in &self.listeners.overlays {
+                for (color, coords) in overlay.get_points(x_bounds, y_bounds, step) {
+                    ctx.draw(&Points {
+                        coords: &coords,
+                        color: self.fade(color, overlay.config.opacity),
+                    });
+                }
+            }
+            ctx.layer();
+        }
+
+        if let Some(snapshot) = &self.snapshot {
+            let age = snapshot.time.elapsed().as_secs_f64();
+            for (color, coords) in &snapshot.points {
+                let color = get_age_color(*color, age, &self.age_ramp, &self.background_color);
+                ctx.draw(&Points {
+                    coords,
+                    color: self.fade(color, self.snapshot_opacity),
+                });
+            }
+            for line in &snapshot.lines {
+                self.draw_aged_line(ctx, line, age, self.snapshot_opacity);
+            }
+            ctx.layer();
+        }
+
+        for map in self.get_drawn_maps() {
+            for (color, coords) in map.points.read().unwrap().iter() {
+                ctx.draw(&Points {
+                    coords,
+                    color: self.fade(
+                        *color,
+                        self.get_synced_opacity(&map.rate, map.config.opacity),
+                    ),
+                });
+            }
+            for line in map.lines.read().unwrap().iter() {
+                self.draw_faded_line(
+                    ctx,
+                    line,
+                    self.get_synced_opacity(&map.rate, map.config.opacity),
+                );
+            }
+            if let Some((color, coords)) = map.get_frontier_points(self.x_bounds(), self.y_bounds())
+            {
+                ctx.draw(&Points {
+                    coords: &coords,
+                    color: self.fade(
+                        color,
+                        self.get_synced_opacity(&map.rate, map.config.opacity),
+                    ),
+                });
+            }
+        }
+        for heatmap in &self.listeners.heatmaps {
+            for (color, coords) in heatmap.points.read().unwrap().iter() {
+                ctx.draw(&Points {
+                    coords,
+                    color: self.fade(
+                        *color,
+                        self.get_synced_opacity(&heatmap.rate, heatmap.config.opacity),
+                    ),
+                });
+            }
+        }
+        let (x_bounds, y_bounds) = (self.x_bounds(), self.y_bounds());
+        let cell_size = (
+            (x_bounds[1] - x_bounds[0]) / self.terminal_size.0 as f64,
+            (y_bounds[1] - y_bounds[0]) / self.terminal_size.1 as f64,
+        );
+        for pose_array in &self.listeners.pose_array {
+            if let Some(points) = pose_array.get_density_points(x_bounds, y_bounds, cell_size) {
+                for (color, coords) in points.iter() {
+                    ctx.draw(&Points {
+                        coords,
+                        color: self.fade(
+                            *color,
+                            self.get_synced_opacity(&pose_array.rate, pose_array.config.opacity),
+                        ),
+                    });
+                }
+            }
+        }
+        for gradient in &self.listeners.gradients {
+            for line in gradient.get_lines(x_bounds, y_bounds, cell_size.0) {
+                self.draw_faded_line(ctx, &line, gradient.config.opacity);
+            }
         }
 
         ctx.layer();
+        let x_bounds = self.x_bounds();
+        let cell_width = (x_bounds[1] - x_bounds[0]) / self.terminal_size.0 as f64;
         for pointcloud in &self.listeners.pointclouds {
             let points = &pointcloud.points.read().unwrap().clone();
+            if pointcloud.config.hull_only {
+                let coords: Vec<(f64, f64)> =
+                    points.iter().map(|pt| (pt.point.x, pt.point.y)).collect();
+                let color = self.fade(
+                    Color::White,
+                    self.get_synced_opacity(&pointcloud.rate, pointcloud.config.opacity),
+                );
+                for line in get_hull_lines(&coords, color) {
+                    self.draw_line(ctx, &line);
+                }
+                continue;
+            }
+            let style = &pointcloud.config.point_style;
+            let size = get_glyph_width(
+                pointcloud.config.point_size,
+                &pointcloud.config.size_mode,
+                cell_width,
+            );
             for pt in points {
-                ctx.draw(&Points {
-                    coords: &[(pt.point.x, pt.point.y)],
-                    color: pt.color,
-                })
+                let color = self.fade(
+                    pt.color,
+                    self.get_synced_opacity(&pointcloud.rate, pointcloud.config.opacity),
+                );
+                if is_dot(style) {
+                    ctx.draw(&Points {
+                        coords: &[(pt.point.x, pt.point.y)],
+                        color,
+                    });
+                    continue;
+                }
+                for line in get_point_lines(pt.point.x, pt.point.y, style, size, color) {
+                    self.draw_line(ctx, &line);
+                }
             }
         }
 
         ctx.layer();
         for line in self.listeners.markers.get_lines() {
-            ctx.draw(&line);
+            self.draw_line(ctx, &line);
+        }
+        for interactive_marker in &self.listeners.interactive_markers {
+            for line in interactive_marker.get_lines() {
+                self.draw_line(ctx, &line);
+            }
+        }
+        for polygon in &self.listeners.polygons {
+            for line in polygon.get_lines() {
+                self.draw_faded_line(
+                    ctx,
+                    &line,
+                    self.get_synced_opacity(&polygon.rate, polygon.config.opacity),
+                );
+            }
         }
 
         ctx.layer();
-        for laser in &self.listeners.lasers {
-            ctx.draw(&Points {
-                coords: &laser.points.read().unwrap(),
-                color: Color::Rgb(
+        let mut lasers: Vec<&LaserListener> = self.listeners.lasers.iter().collect();
+        lasers.sort_by_key(|laser| laser.config.priority);
+        let mut blended_points: Vec<((f64, f64), Color)> = Vec::new();
+        for laser in lasers {
+            let color = self.fade(
+                Color::Rgb(
                     laser.config.color.r,
                     laser.config.color.g,
                     laser.config.color.b,
                 ),
-            });
+                self.get_synced_opacity(&laser.rate, laser.config.opacity),
+            );
+            // the fan is below the points of the scans
+            if let Some(fan) = &laser.config.fan {
+                let fan_color = self.fade(
+                    Color::Rgb(fan.color.r, fan.color.g, fan.color.b),
+                    fan.opacity * self.get_synced_opacity(&laser.rate, laser.config.opacity),
+                );
+                for scan in laser.scans.read().unwrap().iter() {
+                    for end in scan.fan.iter() {
+                        self.draw_line(
+                            ctx,
+                            &Line {
+                                x1: scan.origin.0,
+                                y1: scan.origin.1,
+                                x2: end.0,
+                                y2: end.1,
+                                color: fan_color,
+                            },
+                        );
+                    }
+                }
+            }
+            if let Some(min_range) = &laser.config.min_range {
+                let circle_color = self.fade(
+                    Color::Rgb(min_range.color.r, min_range.color.g, min_range.color.b),
+                    min_range.opacity * self.get_synced_opacity(&laser.rate, laser.config.opacity),
+                );
+                for scan in laser.scans.read().unwrap().iter() {
+                    for segment in scan.min_range.windows(2) {
+                        self.draw_line(
+                            ctx,
+                            &Line {
+                                x1: segment[0].0,
+                                y1: segment[0].1,
+                                x2: segment[1].0,
+                                y2: segment[1].1,
+                                color: circle_color,
+                            },
+                        );
+                    }
+                }
+            }
+            if laser.config.hull_only {
+                for line in get_hull_lines(&laser.points.read().unwrap(), color) {
+                    self.draw_line(ctx, &line);
+                }
+                continue;
+            }
+            for (index, scan) in laser.scans.read().unwrap().iter().enumerate() {
+                let source_color = laser.get_source_color(index);
+                let color = self.fade(
+                    Color::Rgb(source_color.r, source_color.g, source_color.b),
+                    self.get_synced_opacity(&laser.rate, laser.config.opacity),
+                );
+                if laser.config.connect_gap.is_some() {
+                    for chain in scan.chains.iter() {
+                        // an isolated beam is still drawn
+                        if chain.len() == 1 {
+                            ctx.draw(&Points {
+                                coords: chain,
+                                color,
+                            });
+                        }
+                        for segment in chain.windows(2) {
+                            self.draw_line(
+                                ctx,
+                                &Line {
+                                    x1: segment[0].0,
+                                    y1: segment[0].1,
+                                    x2: segment[1].0,
+                                    y2: segment[1].1,
+                                    color,
+                                },
+                            );
+                        }
+                    }
+                    continue;
+                }
+                if is_dot(&laser.config.point_style) {
+                    if self.blend_points {
+                        blended_points.extend(scan.points.iter().map(|pt| (*pt, color)));
+                        continue;
+                    }
+                    ctx.draw(&Points {
+                        coords: &scan.points,
+                        color,
+                    });
+                    continue;
+                }
+                let size =
+                    get_glyph_width(laser.config.point_size, &laser.config.size_mode, cell_width);
+                for (x, y) in scan.points.iter() {
+                    for line in get_point_lines(*x, *y, &laser.config.point_style, size, color) {
+                        self.draw_line(ctx, &line);
+                    }
+                }
+            }
+        }
+        if !blended_points.is_empty() {
+            let y_bounds = self.y_bounds();
+            let cell_height = (y_bounds[1] - y_bounds[0]) / self.terminal_size.1 as f64;
+            for (color, coords) in blend_coincident_points(
+                &blended_points,
+                (x_bounds[0], y_bounds[0]),
+                (cell_width, cell_height),
+            ) {
+                ctx.draw(&Points {
+                    coords: &coords,
+                    color,
+                });
+            }
         }
 
         ctx.layer();
-        let base_link_pose = self
-            .tf_listener
-            .lookup_transform(&self.static_frame, &self.robot_frame, rosrust::Time::new())
-            .unwrap()
-            .transform;
-        get_current_footprint(&base_link_pose, &self.footprint);
+        for line in self.listeners.robot_model.get_lines() {
+            self.draw_line(ctx, &line);
+        }
 
-        for elem in get_current_footprint(&base_link_pose, &self.footprint) {
-            ctx.draw(&Line {
-                x1: elem.0,
-                y1: elem.1,
-                x2: elem.2,
-                y2: elem.3,
-                color: Color::Blue,
-            });
+        if let Ok(tf) = self.lookup_drawn_transform(&self.static_frame, &self.robot_frame) {
+            let base_link_pose = tf.transform;
+            for line in self.get_footprint_buffer_lines(&base_link_pose) {
+                self.draw_line(ctx, &line);
+            }
+            for elem in get_current_footprint(&base_link_pose, &self.footprint.polygon) {
+                self.draw_line(
+                    ctx,
+                    &Line {
+                        x1: elem.0,
+                        y1: elem.1,
+                        x2: elem.2,
+                        y2: elem.3,
+                        color: Color::Blue,
+                    },
+                );
+            }
+            for line in self.get_heading_wedge_lines(&base_link_pose) {
+                self.draw_line(ctx, &line);
+            }
+
+            for line in Viewport::get_frame_lines(&base_link_pose, self.axis_length) {
+                self.draw_line(ctx, &line);
+            }
         }
 
-        for line in Viewport::get_frame_lines(&base_link_pose, self.axis_length) {
-            ctx.draw(&line);
+        if self.show_tf_frames {
+            for line in self.get_tf_frames_lines() {
+                self.draw_line(ctx, &line);
+            }
+        }
+
+        if let Some(tf_comparison) = &self.listeners.tf_comparison {
+            for line in tf_comparison.get_lines(self.axis_length) {
+                self.draw_line(ctx, &line);
+            }
         }
 
         for pose_stamped in &self.listeners.pose_stamped {
+            let opacity = self.get_synced_opacity(&pose_stamped.rate, pose_stamped.config.opacity);
+            for (age, line) in pose_stamped.get_history_lines() {
+                self.draw_aged_line(ctx, &line, age, opacity);
+            }
             for line in pose_stamped.get_lines() {
-                ctx.draw(&line);
+                self.draw_faded_line(ctx, &line, opacity);
+            }
+            let color = &pose_stamped.config.color;
+            let color = Color::Rgb(color.r, color.g, color.b);
+            let style = Style::default().fg(self.fade(color, opacity));
+            if let Some((x, y, label)) = pose_stamped.get_label() {
+                // the label of the current pose goes stale with it, unlike the breadcrumbs
+                let style = style.fg(self.fade(pose_stamped.get_stale_color(color), opacity));
+                ctx.print(x, y, Span::styled(label, style));
+            }
+            for (x, y, label) in pose_stamped.get_breadcrumb_labels() {
+                ctx.print(x, y, Span::styled(label, style));
             }
         }
 
+        // a braille character is 2 dots wide
+        let dot_size = cell_width / 2.0;
         for path in &self.listeners.paths {
             for line in path.get_lines() {
-                ctx.draw(&line)
+                for dash in dash_line(&line, path.config.dash_length) {
+                    for line in thicken_line(&dash, path.config.thickness, dot_size) {
+                        self.draw_faded_line(
+                            ctx,
+                            &line,
+                            self.get_synced_opacity(&path.rate, path.config.opacity),
+                        );
+                    }
+                }
             }
         }
 
         for pose_array in &self.listeners.pose_array {
             for line in pose_array.get_lines() {
-                ctx.draw(&line);
+                self.draw_faded_line(
+                    ctx,
+                    &line,
+                    self.get_synced_opacity(&pose_array.rate, pose_array.config.opacity),
+                );
+            }
+        }
+
+        for navsat in &self.listeners.navsats {
+            for line in navsat.get_lines(cell_width) {
+                self.draw_faded_line(
+                    ctx,
+                    &line,
+                    self.get_synced_opacity(&navsat.rate, navsat.config.opacity),
+                );
+            }
+        }
+
+        for accel in &self.listeners.accels {
+            for line in accel.get_lines() {
+                self.draw_faded_line(
+                    ctx,
+                    &line,
+                    self.get_synced_opacity(&accel.rate, accel.config.opacity),
+                );
+            }
+        }
+
+        for listener in &self.listeners.detections {
+            for detection in listener.detections.read().unwrap().iter() {
+                let opacity =
+                    self.get_synced_opacity(&listener.rate, listener.get_opacity(detection));
+                for line in &detection.lines {
+                    self.draw_faded_line(ctx, line, opacity);
+                }
+                if let Some(label) = listener.get_label(detection) {
+                    let color = &listener.config.color;
+                    let style = Style::default()
+                        .fg(self.fade(Color::Rgb(color.r, color.g, color.b), opacity));
+                    ctx.print(
+                        detection.position.0,
+                        detection.position.1,
+                        Span::styled(label, style),
+                    );
+                }
+            }
+        }
+
+        for listener in &self.listeners.segments {
+            for segment in listener.segments.read().unwrap().iter() {
+                self.draw_faded_line(
+                    ctx,
+                    &segment.line,
+                    self.get_synced_opacity(&listener.rate, listener.config.opacity),
+                );
+                if listener.config.show_lengths {
+                    let style = Style::default().fg(self.fade(
+                        segment.line.color,
+                        self.get_synced_opacity(&listener.rate, listener.config.opacity),
+                    ));
+                    ctx.print(
+                        (segment.line.x1 + segment.line.x2) / 2.0,
+                        (segment.line.y1 + segment.line.y2) / 2.0,
+                        Span::styled(self.units.format_length(segment.length), style),
+                    );
+                }
+            }
+        }
+
+        for correspondence in &self.listeners.correspondences {
+            for line in correspondence.get_lines(&self.listeners.lasers, &self.listeners.maps) {
+                self.draw_faded_line(ctx, &line, correspondence.config.opacity);
             }
         }
 
         ctx.layer();
+        self.draw_screen_overlays(ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn common_time_of_layers() {
+        let seconds = |s: f64| rosrust::Time::from_nanos((s * 1e9) as i64);
+        let stamps = [seconds(10.0), seconds(9.5), seconds(10.2)];
+        let time = get_common_stamp(stamps.iter().copied()).unwrap();
+        assert_eq!(time, seconds(9.5));
+        assert_eq!(get_common_stamp(std::iter::empty()), None);
+        // the newer layers are dimmed beyond the tolerance
+        assert!(is_at_common_time(stamps[1], time, 0.05));
+        assert!(!is_at_common_time(stamps[0], time, 0.05));
+        assert!(is_at_common_time(stamps[0], time, 0.5));
     }
 }
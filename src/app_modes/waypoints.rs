@@ -0,0 +1,347 @@
+//! Waypoints mode allows to place a sequence of poses and to send them as a path.
+
+use crate::app_modes::viewport::{UseViewport, Viewport};
+use crate::app_modes::{input, AppMode, BaseMode};
+use crate::config::{MinimapConfig, ThumbnailConfig, WaypointsConfig};
+use crate::footprint::get_current_footprint;
+use crate::minimap::MinimapData;
+use crate::transformation;
+use image::RgbaImage;
+use nalgebra::{Isometry2, Vector2};
+use std::cell::RefCell;
+use std::rc::Rc;
+use tui::backend::Backend;
+use tui::style::Color;
+use tui::widgets::canvas::{Context, Line};
+
+/// Returns the poses of the waypoints as sent in the path.
+///
+/// If `yaw_from_segments` is set, each waypoint is oriented towards the next one, and the
+/// last one along the last segment. The yaw set when placing a waypoint is kept if it
+/// is the only one or if it coincides with its neighbour.
+fn get_path_poses(waypoints: &[Isometry2<f64>], yaw_from_segments: bool) -> Vec<Isometry2<f64>> {
+    if !yaw_from_segments {
+        return waypoints.to_vec();
+    }
+    (0..waypoints.len())
+        .map(|i| {
+            let (start, end) = if i + 1 < waypoints.len() {
+                (&waypoints[i], &waypoints[i + 1])
+            } else if i > 0 {
+                (&waypoints[i - 1], &waypoints[i])
+            } else {
+                return waypoints[i];
+            };
+            let direction = end.translation.vector - start.translation.vector;
+            if direction.norm() < 1e-6 {
+                return waypoints[i];
+            }
+            Isometry2::new(
+                waypoints[i].translation.vector,
+                direction.y.atan2(direction.x),
+            )
+        })
+        .collect()
+}
+
+/// Represents the waypoints mode.
+pub struct Waypoints {
+    viewport: Rc<RefCell<Viewport>>,
+    topic: String,
+    publisher: rosrust::Publisher<rosrust_msg::nav_msgs::Path>,
+    yaw_from_segments: bool,
+    increment: f64,
+    robot_pose: Isometry2<f64>,
+    cursor: Isometry2<f64>,
+    cursor_active: bool,
+    waypoints: Vec<Isometry2<f64>>,
+}
+
+impl Waypoints {
+    pub fn new(config: WaypointsConfig, viewport: Rc<RefCell<Viewport>>) -> Waypoints {
+        let base_link_pose = viewport
+            .borrow()
+            .tf_listener
+            .lookup_transform(
+                &viewport.borrow().static_frame,
+                &viewport.borrow().robot_frame,
+                rosrust::Time::new(),
+            )
+            .unwrap()
+            .transform;
+        let robot_pose = transformation::ros_to_iso2d(&base_link_pose);
+
+        let increment = viewport.borrow().cursor.step;
+        Waypoints {
+            viewport,
+            publisher: rosrust::publish(&config.topic, 1).unwrap(),
+            topic: config.topic,
+            yaw_from_segments: config.yaw_from_segments,
+            increment,
+            robot_pose,
+            cursor: robot_pose,
+            cursor_active: false,
+            waypoints: Vec::new(),
+        }
+    }
+
+    fn move_cursor(&mut self, x: f64, y: f64, yaw: f64) {
+        let new_yaw = self.cursor.rotation.angle() + yaw;
+        let new_x = x * new_yaw.cos() - y * new_yaw.sin() + self.cursor.translation.x;
+        let new_y = x * new_yaw.sin() + y * new_yaw.cos() + self.cursor.translation.y;
+        self.cursor = Isometry2::new(Vector2::new(new_x, new_y), new_yaw);
+        self.cursor_active = true;
+    }
+
+    fn send_path(&mut self) {
+        if self.waypoints.is_empty() {
+            return;
+        }
+        let mut msg = rosrust_msg::nav_msgs::Path::default();
+        msg.header.frame_id = self.viewport.borrow().static_frame.to_string();
+        msg.header.stamp = rosrust::now();
+        for pose in get_path_poses(&self.waypoints, self.yaw_from_segments) {
+            let pose = transformation::iso2d_to_ros(&pose);
+            let mut pose_stamped = rosrust_msg::geometry_msgs::PoseStamped {
+                header: msg.header.clone(),
+                ..Default::default()
+            };
+            pose_stamped.pose.orientation.x = pose.rotation.x;
+            pose_stamped.pose.orientation.y = pose.rotation.y;
+            pose_stamped.pose.orientation.z = pose.rotation.z;
+            pose_stamped.pose.orientation.w = pose.rotation.w;
+            pose_stamped.pose.position.x = pose.translation.x;
+            pose_stamped.pose.position.y = pose.translation.y;
+            pose_stamped.pose.position.z = 0.0;
+            msg.poses.push(pose_stamped);
+        }
+        self.publisher.send(msg).unwrap();
+        self.waypoints.clear();
+        self.cursor_active = false;
+    }
+}
+
+impl<B: Backend> BaseMode<B> for Waypoints {}
+
+impl AppMode for Waypoints {
+    fn run(&mut self) {
+        self.viewport.borrow_mut().run();
+        let base_link_pose = self
+            .viewport
+            .borrow()
+            .tf_listener
+            .lookup_transform(
+                &self.viewport.borrow().static_frame,
+                &self.viewport.borrow().robot_frame,
+                rosrust::Time::new(),
+            )
+            .unwrap()
+            .transform;
+        self.robot_pose = transformation::ros_to_iso2d(&base_link_pose);
+        if !self.cursor_active {
+            self.cursor = self.robot_pose;
+        }
+    }
+    fn reset(&mut self) {
+        self.cursor_active = false;
+        self.run(); // Update the robot pose
+    }
+    fn handle_input(&mut self, input: &String) {
+        self.viewport.borrow_mut().handle_input(input);
+        let step = self.viewport.borrow().cursor_step(self.increment);
+        let angle_step = self.viewport.borrow().rotation_step(self.increment);
+        match input.as_str() {
+            input::UP => self.move_cursor(step, 0.0, 0.0),
+            input::DOWN => self.move_cursor(-step, 0.0, 0.0),
+            input::LEFT => self.move_cursor(0.0, step, 0.0),
+            input::RIGHT => self.move_cursor(0.0, -step, 0.0),
+            input::ROTATE_LEFT => self.move_cursor(0.0, 0.0, angle_step),
+            input::ROTATE_RIGHT => self.move_cursor(0.0, 0.0, -angle_step),
+            input::INCREMENT_STEP => self.increment += 0.1,
+            input::DECREMENT_STEP => self.increment -= 0.1,
+            input::ADD_WAYPOINT => {
+                self.waypoints.push(self.cursor);
+                self.cursor_active = true;
+            }
+            input::UNDO => {
+                self.waypoints.pop();
+            }
+            input::CANCEL => {
+                self.waypoints.clear();
+                self.reset();
+            }
+            input::CONFIRM => self.send_path(),
+            _ => (),
+        }
+    }
+
+    fn content_changed(&mut self) -> bool {
+        self.viewport.borrow_mut().content_changed()
+    }
+
+    fn get_name(&self) -> String {
+        "Waypoints".to_string()
+    }
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            "This mode allows to publish a sequence of waypoints as a path.".to_string(),
+            "The waypoints are placed one after the other at the position of the gray outline, and sent together in the static frame."
+                .to_string(),
+            "The top bar shows the topic and the number of placed waypoints.".to_string(),
+        ]
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        let mut keymap = vec![
+            [
+                input::UP.to_string(),
+                "Shifts the cursor positively along the x axis.".to_string(),
+            ],
+            [
+                input::DOWN.to_string(),
+                "Shifts the cursor negatively along the x axis.".to_string(),
+            ],
+            [
+                input::RIGHT.to_string(),
+                "Shifts the cursor positively along the y axis.".to_string(),
+            ],
+            [
+                input::LEFT.to_string(),
+                "Shifts the cursor negatively along the y axis.".to_string(),
+            ],
+            [
+                input::ROTATE_LEFT.to_string(),
+                "Rotates the cursor counter-clockwise.".to_string(),
+            ],
+            [
+                input::ROTATE_RIGHT.to_string(),
+                "Rotates the cursor clockwise.".to_string(),
+            ],
+            [
+                input::ADD_WAYPOINT.to_string(),
+                "Adds a waypoint at the cursor.".to_string(),
+            ],
+            [
+                input::UNDO.to_string(),
+                "Removes the last waypoint.".to_string(),
+            ],
+            [
+                input::CANCEL.to_string(),
+                "Removes all the waypoints and resets the cursor.".to_string(),
+            ],
+            [
+                input::CONFIRM.to_string(),
+                "Sends the waypoints as a path.".to_string(),
+            ],
+            [
+                input::INCREMENT_STEP.to_string(),
+                "Increases the step size for moving the cursor.".to_string(),
+            ],
+            [
+                input::DECREMENT_STEP.to_string(),
+                "Decreases the step size for moving the cursor.".to_string(),
+            ],
+        ];
+        keymap.extend(self.viewport.borrow().get_keymap());
+        keymap
+    }
+}
+
+impl UseViewport for Waypoints {
+    fn draw_in_viewport(&self, ctx: &mut Context) {
+        self.viewport.borrow().draw_in_viewport(ctx);
+        let axis_length = self.viewport.borrow().axis_length;
+        let poses = get_path_poses(&self.waypoints, self.yaw_from_segments);
+        for (i, pose) in poses.iter().enumerate() {
+            if let Some(next) = poses.get(i + 1) {
+                self.viewport.borrow().draw_line(
+                    ctx,
+                    &Line {
+                        x1: pose.translation.x,
+                        y1: pose.translation.y,
+                        x2: next.translation.x,
+                        y2: next.translation.y,
+                        color: Color::LightYellow,
+                    },
+                );
+            }
+            for line in Viewport::get_frame_lines(&transformation::iso2d_to_ros(pose), axis_length)
+            {
+                self.viewport.borrow().draw_line(ctx, &line);
+            }
+        }
+        // the segment that would be added with the next waypoint
+        if let Some(last) = poses.last() {
+            self.viewport.borrow().draw_line(
+                ctx,
+                &Line {
+                    x1: last.translation.x,
+                    y1: last.translation.y,
+                    x2: self.cursor.translation.x,
+                    y2: self.cursor.translation.y,
+                    color: Color::Gray,
+                },
+            );
+        }
+        if self.cursor_active {
+            let cursor_ros = transformation::iso2d_to_ros(&self.cursor);
+            for elem in
+                &get_current_footprint(&cursor_ros, &self.viewport.borrow().footprint.polygon)
+            {
+                self.viewport.borrow().draw_line(
+                    ctx,
+                    &Line {
+                        x1: elem.0,
+                        y1: elem.1,
+                        x2: elem.2,
+                        y2: elem.3,
+                        color: Color::Gray,
+                    },
+                );
+            }
+            for mut line in Viewport::get_frame_lines(&cursor_ros, axis_length) {
+                line.color = Color::Gray;
+                self.viewport.borrow().draw_line(ctx, &line);
+            }
+        }
+    }
+    fn x_bounds(&self) -> [f64; 2] {
+        let scale_factor = self.viewport.borrow().terminal_size.0 as f64
+            / self.viewport.borrow().terminal_size.1 as f64
+            * 0.5;
+        [
+            self.cursor.translation.x
+                + self.viewport.borrow().initial_bounds[0] / self.viewport.borrow().zoom
+                    * scale_factor,
+            self.cursor.translation.x
+                + self.viewport.borrow().initial_bounds[1] / self.viewport.borrow().zoom
+                    * scale_factor,
+        ]
+    }
+    fn y_bounds(&self) -> [f64; 2] {
+        [
+            self.cursor.translation.y
+                + self.viewport.borrow().initial_bounds[2] / self.viewport.borrow().zoom,
+            self.cursor.translation.y
+                + self.viewport.borrow().initial_bounds[3] / self.viewport.borrow().zoom,
+        ]
+    }
+
+    fn get_thumbnail(&self) -> Option<(RgbaImage, ThumbnailConfig)> {
+        self.viewport.borrow().get_thumbnail()
+    }
+
+    fn get_minimap(&self) -> Option<(MinimapData, MinimapConfig)> {
+        self.viewport.borrow().get_minimap()
+    }
+
+    fn info(&self) -> String {
+        format!(
+            "Topic: /{}, Waypoints: {}, Cursor step: {:.2}",
+            &self.topic,
+            self.waypoints.len(),
+            &self.increment
+        )
+    }
+}
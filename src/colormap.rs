@@ -0,0 +1,81 @@
+//! Colormaps used to render scalar values (e.g., occupancy probabilities) as colors.
+
+use tui::style::Color;
+
+pub const SUPPORTED_COLORMAPS: [&str; 6] = [
+    "viridis",
+    "magma",
+    "inferno",
+    "plasma",
+    "turbo",
+    "grayscale",
+];
+
+pub struct Colormap {
+    gradient: colorgrad::Gradient,
+}
+
+impl Colormap {
+    /// Creates the colormap with the given name.
+    ///
+    /// Falls back to viridis if the name is not one of SUPPORTED_COLORMAPS.
+    pub fn new(name: &str) -> Colormap {
+        let gradient = match name {
+            "viridis" => colorgrad::viridis(),
+            "magma" => colorgrad::magma(),
+            "inferno" => colorgrad::inferno(),
+            "plasma" => colorgrad::plasma(),
+            "turbo" => colorgrad::turbo(),
+            "grayscale" => colorgrad::greys(),
+            _ => {
+                log::warn!(
+                    "Colormap {:?} not supported (supported: {:?}), using viridis.",
+                    name,
+                    SUPPORTED_COLORMAPS
+                );
+                colorgrad::viridis()
+            }
+        };
+        Colormap { gradient }
+    }
+
+    /// Maps a normalized value in [0, 1] to a color. Values outside the range are clamped.
+    pub fn color_at(&self, value: f64) -> Color {
+        let c = self.gradient.at(value.clamp(0.0, 1.0)).to_rgba8();
+        Color::Rgb(c[0], c[1], c[2])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Asserts that each component of the color is within `tolerance` of the expected one.
+    fn assert_close(color: Color, expected: (u8, u8, u8), tolerance: i16) {
+        match color {
+            Color::Rgb(r, g, b) => {
+                for (value, expected) in [(r, expected.0), (g, expected.1), (b, expected.2)] {
+                    assert!(
+                        (value as i16 - expected as i16).abs() <= tolerance,
+                        "{:?} is not close to {:?}",
+                        color,
+                        expected
+                    );
+                }
+            }
+            _ => panic!("{:?} is not an RGB color", color),
+        }
+    }
+
+    #[test]
+    fn viridis_stops() {
+        let colormap = Colormap::new("viridis");
+        // the ends match the stops of matplotlib, the middle is interpolated by colorgrad
+        assert_close(colormap.color_at(0.0), (68, 1, 84), 3);
+        assert_close(colormap.color_at(0.5), (33, 145, 140), 16);
+        assert_close(colormap.color_at(1.0), (253, 231, 37), 3);
+        // the values outside of [0, 1] are clamped
+        assert_eq!(colormap.color_at(-1.0), colormap.color_at(0.0));
+        assert_eq!(colormap.color_at(2.0), colormap.color_at(1.0));
+    }
+}
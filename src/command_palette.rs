@@ -0,0 +1,142 @@
+//! Command palette: a searchable list of the input actions, to trigger them by name
+//! without knowing their keys.
+
+use crossterm::event::KeyCode;
+use std::collections::HashMap;
+use tui::backend::Backend;
+use tui::layout::Rect;
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Clear, Paragraph};
+use tui::Frame;
+
+/// What the application has to do after a key is handled by the palette.
+pub enum PaletteEvent {
+    None,
+    Close,
+    /// Triggers the action, in the form of app_modes::input.
+    Execute(String),
+}
+
+/// Returns true if the characters of the query appear in the name in the same order,
+/// ignoring the case, e.g. "tsb" matches "Toggle scale bar".
+fn fuzzy_match(query: &str, name: &str) -> bool {
+    let mut name_chars = name.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !c.is_whitespace())
+        .all(|q| name_chars.any(|c| c == q))
+}
+
+pub struct CommandPalette {
+    actions: Vec<String>,
+    query: String,
+    /// Index of the selected action among the matches.
+    selected: usize,
+}
+
+impl CommandPalette {
+    /// Opens the palette.
+    ///
+    /// # Arguments
+    /// - `actions`: the actions that can be triggered, in the order in which they are listed.
+    pub fn new(actions: Vec<String>) -> CommandPalette {
+        CommandPalette {
+            actions,
+            query: String::new(),
+            selected: 0,
+        }
+    }
+
+    /// Returns the actions matching the query.
+    fn get_matches(&self) -> Vec<&String> {
+        self.actions
+            .iter()
+            .filter(|action| fuzzy_match(&self.query, action))
+            .collect()
+    }
+
+    /// Edits the query or the selection, Enter executes the selected action and Esc
+    /// closes the palette.
+    pub fn handle_key(&mut self, key: KeyCode) -> PaletteEvent {
+        let match_count = self.get_matches().len();
+        match key {
+            KeyCode::Esc => return PaletteEvent::Close,
+            KeyCode::Enter => {
+                return match self.get_matches().get(self.selected) {
+                    Some(action) => PaletteEvent::Execute(action.to_string()),
+                    None => PaletteEvent::Close,
+                }
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down if self.selected + 1 < match_count => self.selected += 1,
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.selected = 0;
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.selected = 0;
+            }
+            _ => (),
+        }
+        PaletteEvent::None
+    }
+
+    /// Draws the palette in the middle of the screen.
+    ///
+    /// # Arguments
+    /// - `keymap`: key of each action, shown next to its name.
+    pub fn draw<B: Backend>(&self, f: &mut Frame<B>, keymap: &HashMap<String, String>) {
+        let size = f.size();
+        let width = (size.width * 3 / 5).max(20).min(size.width);
+        let height = (size.height * 3 / 5).max(5).min(size.height);
+        let area = Rect::new(
+            size.x + (size.width - width) / 2,
+            size.y + (size.height - height) / 2,
+            width,
+            height,
+        );
+        // 2 borders and the line of the query
+        let visible = height.saturating_sub(3) as usize;
+        let first = (self.selected + 1).saturating_sub(visible);
+
+        let mut lines = vec![Spans::from(vec![
+            Span::styled("> ", Style::default().fg(Color::Red)),
+            Span::raw(self.query.clone()),
+        ])];
+        for (i, action) in self
+            .get_matches()
+            .into_iter()
+            .enumerate()
+            .skip(first)
+            .take(visible)
+        {
+            let style = if i == self.selected {
+                Style::default().fg(Color::Black).bg(Color::Gray)
+            } else {
+                Style::default()
+            };
+            let key = match keymap.get(action) {
+                Some(key) => format!(" ({})", key),
+                None => String::new(),
+            };
+            lines.push(Spans::from(vec![
+                Span::styled(action.clone(), style),
+                Span::styled(key, Style::default().fg(Color::DarkGray)),
+            ]));
+        }
+
+        let palette = Paragraph::new(lines).block(
+            Block::default()
+                .title(Span::styled(
+                    "Command palette",
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                ))
+                .borders(Borders::ALL),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(palette, area);
+    }
+}
@@ -1,23 +1,280 @@
 use crate::app_modes::input;
 use confy;
-use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::io;
 use std::io::Write;
 use std::path::Path;
+use std::time::Duration;
+
+fn default_point_blending() -> String {
+    "overwrite".to_string()
+}
+
+fn default_snapshot_opacity() -> f64 {
+    0.3
+}
+
+fn default_twist_estimate_dt() -> f64 {
+    1.0
+}
+
+fn default_diagnostic_timeout() -> f64 {
+    5.0
+}
+
+fn default_clear_cost_size() -> f64 {
+    1.0
+}
+
+fn default_wedge_half_angle() -> f64 {
+    30.0
+}
+
+fn default_wedge_length() -> f64 {
+    1.0
+}
+
+fn default_buffer_distance() -> f64 {
+    0.1
+}
+
+fn default_true() -> bool {
+    true
+}
 
 fn default_int() -> i64 {
     0
 }
 
-fn default_map_threshold() -> i8 {
+fn default_map_threshold() -> i16 {
+    1
+}
+
+fn default_map_value_domain() -> [f64; 2] {
+    [0.0, 100.0]
+}
+
+fn default_map_max_cells() -> usize {
+    // e.g. a 5000x5000 grid
+    25_000_000
+}
+
+fn default_map_unknown_value() -> i16 {
+    -1
+}
+
+fn default_opacity() -> f64 {
+    1.0
+}
+
+fn default_gradient_spacing() -> u16 {
+    3
+}
+
+fn default_tube_half_width() -> f64 {
+    0.3
+}
+
+fn default_tube_join() -> String {
+    "miter".to_string()
+}
+
+fn default_band_sigmas() -> f64 {
+    2.0
+}
+
+fn default_density_cells() -> u16 {
     1
 }
 
+fn default_heatmap_colormap() -> String {
+    "turbo".to_string()
+}
+
+fn default_timer_format() -> String {
+    "clock".to_string()
+}
+
+fn default_timer_corner() -> String {
+    "top_right".to_string()
+}
+
+fn default_readout_precision() -> usize {
+    2
+}
+
+fn default_sync_tolerance() -> f64 {
+    0.05
+}
+
+fn default_sync_opacity() -> f64 {
+    0.3
+}
+
+fn default_readout_corner() -> String {
+    "top_right".to_string()
+}
+
+fn default_fan_decimation() -> usize {
+    10
+}
+
+fn default_fan_opacity() -> f64 {
+    0.3
+}
+
+fn default_binarize_threshold() -> i16 {
+    50
+}
+
+fn default_map_style() -> String {
+    "filled".to_string()
+}
+
+fn default_polygon_z_style() -> String {
+    "project".to_string()
+}
+
+fn default_polygon_fill() -> String {
+    "none".to_string()
+}
+
+fn default_hatch_spacing() -> f64 {
+    0.2
+}
+
+fn default_hatch_angle() -> f64 {
+    45.0
+}
+
+fn default_vertex_style() -> String {
+    "square".to_string()
+}
+
+fn default_vertex_size() -> f64 {
+    0.1
+}
+
+fn default_live_parameters_interval() -> f64 {
+    1.0
+}
+
+fn default_tf_tree_path() -> String {
+    "tf_tree.dot".to_string()
+}
+
+fn default_publisher_check_interval() -> f64 {
+    5.0
+}
+
+fn default_status_bar() -> Vec<String> {
+    [
+        "mode",
+        "static_frame",
+        "follow_frame",
+        "zoom",
+        "map_style",
+        "active_map",
+        "markers",
+        "fps",
+    ]
+    .iter()
+    .map(|field| field.to_string())
+    .collect()
+}
+
+fn default_thumbnail_width() -> u16 {
+    40
+}
+
+fn default_thumbnail_height() -> u16 {
+    12
+}
+
+fn default_footprint_param() -> String {
+    "/footprint".to_string()
+}
+
+fn default_cost_colormap() -> String {
+    "turbo".to_string()
+}
+
+fn default_color_by() -> String {
+    "auto".to_string()
+}
+
+fn default_height_range() -> [f64; 2] {
+    [0.0, 2.0]
+}
+
+fn default_error_interval() -> f64 {
+    0.5
+}
+
+fn default_trail_spacing() -> f64 {
+    0.05
+}
+
+fn default_max_trail() -> usize {
+    5000
+}
+
+fn default_point_style() -> String {
+    "dot".to_string()
+}
+
+fn default_point_size() -> f64 {
+    1.0
+}
+
+fn default_point_size_mode() -> String {
+    "screen".to_string()
+}
+
+fn default_time_jump_threshold() -> f64 {
+    1.0
+}
+
+fn default_zoom() -> f64 {
+    1.0
+}
+
+fn default_auto_fit_padding() -> f64 {
+    0.1
+}
+
+fn default_velocity_scale() -> f64 {
+    1.0
+}
+
 fn default_pose_length() -> f64 {
     0.2
 }
 
+fn default_breadcrumb_distance() -> f64 {
+    1.0
+}
+
+fn default_breadcrumb_max_count() -> usize {
+    20
+}
+
+fn default_breadcrumb_glyph() -> String {
+    "circle".to_string()
+}
+
+fn default_breadcrumb_size() -> f64 {
+    0.1
+}
+
+fn default_breadcrumb_label() -> String {
+    "none".to_string()
+}
+
 fn color_white() -> Color {
     Color {
         r: 255,
@@ -26,20 +283,149 @@ fn color_white() -> Color {
     }
 }
 
+fn color_dark_gray() -> Color {
+    Color {
+        r: 64,
+        g: 64,
+        b: 64,
+    }
+}
+
+fn color_black() -> Color {
+    Color { r: 0, g: 0, b: 0 }
+}
+
 fn color_red() -> Color {
     Color { r: 255, g: 0, b: 0 }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+fn color_green() -> Color {
+    Color { r: 0, g: 255, b: 0 }
+}
+
+fn color_yellow() -> Color {
+    Color {
+        r: 255,
+        g: 255,
+        b: 0,
+    }
+}
+
+fn color_cyan() -> Color {
+    Color {
+        r: 0,
+        g: 255,
+        b: 255,
+    }
+}
+
+fn color_magenta() -> Color {
+    Color {
+        r: 255,
+        g: 0,
+        b: 255,
+    }
+}
+
+fn default_accel_scale() -> f64 {
+    1.0
+}
+
+fn default_accel_arc_radius() -> f64 {
+    0.3
+}
+
+fn default_tf_comparison_tolerance() -> f64 {
+    0.01
+}
+
+fn default_correspondence_threshold() -> f64 {
+    0.5
+}
+
+fn default_correspondence_max_lines() -> usize {
+    500
+}
+
+#[derive(Debug, Serialize, Clone)]
 pub struct Color {
     pub r: u8,
     pub b: u8,
     pub g: u8,
 }
 
+thread_local! {
+    /// Colors of the palette of the configuration being loaded, by name.
+    static PALETTE: RefCell<HashMap<String, Color>> = RefCell::new(HashMap::new());
+}
+
+/// A color as written in the configuration: its components, or the name of an entry of
+/// the palette.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorEntry {
+    Rgb { r: u8, g: u8, b: u8 },
+    Name(String),
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        match ColorEntry::deserialize(deserializer)? {
+            ColorEntry::Rgb { r, g, b } => Ok(Color { r, g, b }),
+            ColorEntry::Name(name) => PALETTE
+                .with(|palette| palette.borrow().get(&name).cloned())
+                .ok_or_else(|| de::Error::custom(format!("undefined palette color {:?}", name))),
+        }
+    }
+}
+
+/// The palette of a configuration, loaded before the rest of it so that the colors can
+/// refer to its entries.
+#[derive(Serialize, Deserialize, Default)]
+struct PaletteEntries {
+    #[serde(default)]
+    palette: HashMap<String, Color>,
+}
+
+/// Loads a configuration file whose colors can be names of the entries of its palette.
+///
+/// The palette has to be known before the colors are deserialized, and confy only loads
+/// from a file, so the file is read twice: once for the palette, whose entries are
+/// components, then for the whole configuration.
+fn load_with_palette<T: Serialize + DeserializeOwned + Default>(
+    path: &Path,
+) -> Result<T, confy::ConfyError> {
+    let entries: PaletteEntries = confy::load_path(path)?;
+    PALETTE.with(|palette| *palette.borrow_mut() = entries.palette);
+    let config = confy::load_path(path);
+    // the palette only applies to the file being loaded
+    PALETTE.with(|palette| palette.borrow_mut().clear());
+    config
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ListenerConfig {
     pub topic: String,
+    #[serde(default)]
+    pub frame_filter: Option<String>,
+    #[serde(default)]
+    pub lookup_tolerance: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MarkerArrayListenerConfig {
+    pub topic: String,
+    #[serde(default)]
+    pub frame_filter: Option<String>,
+    #[serde(default)]
+    pub lookup_tolerance: f64,
+    #[serde(default = "bool::default")]
+    pub color_by_namespace: bool,
+    #[serde(default)]
+    pub namespace_colors: HashMap<String, Color>,
+    /// Maximum number of markers of each array that are drawn, None to draw all of them.
+    #[serde(default)]
+    pub max_markers: Option<usize>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -47,16 +433,458 @@ pub struct PointCloud2ListenerConfig {
     pub topic: String,
     #[serde(default = "bool::default")]
     pub use_rgb: bool,
+    /// Coloring of the points without use_rgb. Supported: auto (along their height,
+    /// between the lowest and the highest point of each cloud), z (along their height,
+    /// between the bounds of z_range).
+    #[serde(default = "default_color_by")]
+    pub color_by: String,
+    /// Heights of the two ends of the colormap with color_by z, in the static frame.
+    #[serde(default = "default_height_range")]
+    pub z_range: [f64; 2],
+    #[serde(default = "default_cost_colormap")]
+    pub colormap: String,
+    /// Bounds of the heights of the drawn points, None to draw all of them.
+    #[serde(default)]
+    pub z_window: Option<[f64; 2]>,
+    #[serde(default)]
+    pub frame_filter: Option<String>,
+    /// Frame in which the data is transformed instead of the frame of its header, e.g. to
+    /// debug a publisher with a wrong frame_id.
+    #[serde(default)]
+    pub override_frame_id: Option<String>,
+    #[serde(default)]
+    pub lookup_tolerance: f64,
+    #[serde(default = "default_point_style")]
+    pub point_style: String,
+    #[serde(default = "default_point_size")]
+    pub point_size: f64,
+    #[serde(default = "default_point_size_mode")]
+    pub size_mode: String,
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+    #[serde(default = "bool::default")]
+    pub hull_only: bool,
+    /// If true, the view is centered on the data of the layer when it is first received.
+    #[serde(default = "bool::default")]
+    pub center_on_first: bool,
+    /// If true, the topic is only subscribed while the layer is enabled with "Toggle lazy
+    /// layers", and the layer is disabled at startup.
+    #[serde(default = "bool::default")]
+    pub lazy: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(try_from = "PoseListenerEntry")]
 pub struct PoseListenerConfig {
     pub topic: String,
     pub style: String,
-    #[serde(default = "color_red")]
     pub color: Color,
-    #[serde(default = "default_pose_length")]
     pub length: f64,
+    pub frame_filter: Option<String>,
+    /// Frame in which the poses are transformed instead of the frame of their header.
+    pub override_frame_id: Option<String>,
+    pub ghost_lag: Option<f64>,
+    pub cost: Option<PathCostConfig>,
+    pub edges: Option<GraphEdgesConfig>,
+    pub velocities: Option<PoseVelocitiesConfig>,
+    /// Number of segments drawn between two poses of the line style along a Catmull-Rom
+    /// spline, None for straight segments.
+    pub spline_samples: Option<usize>,
+    pub breadcrumbs: Option<BreadcrumbsConfig>,
+    pub timeout: Option<f64>,
+    /// Color toward which the pose transitions as its age approaches the timeout, None to
+    /// keep its color until it is hidden.
+    pub stale_color: Option<Color>,
+    /// Weight of a new pose in the exponential moving average of the displayed pose, 1
+    /// for no smoothing.
+    pub smoothing: f64,
+    /// Length of the dashes of the lines, 0 for solid lines.
+    pub dash_length: f64,
+    /// Width of the lines, in dots of the canvas.
+    pub thickness: u8,
+    pub opacity: f64,
+    /// If true, the view is centered on the poses when they are first received.
+    pub center_on_first: bool,
+    pub density: Option<PoseDensityConfig>,
+    pub tube: Option<PathTubeConfig>,
+    pub band: Option<PathBandConfig>,
+}
+
+/// Confidence band drawn around a path from the lateral variance of its poses, e.g. for
+/// the uncertainty of a predicted trajectory.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PathBandConfig {
+    /// std_msgs::Float32MultiArray topic with the lateral variance of each pose, in m^2.
+    pub topic: String,
+    /// Number of standard deviations from the path to each side of the band.
+    #[serde(default = "default_band_sigmas")]
+    pub sigmas: f64,
+    /// Color of the band, None for the color of the path.
+    #[serde(default)]
+    pub color: Option<Color>,
+}
+
+/// Corridor drawn on both sides of a path, e.g. for the width swept by the robot along it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PathTubeConfig {
+    /// Distance from the path to each side of the tube, in meters.
+    #[serde(default = "default_tube_half_width")]
+    pub half_width: f64,
+    /// Join of the sides at the corners of the path. Supported: miter, bevel.
+    #[serde(default = "default_tube_join")]
+    pub join: String,
+    /// Color of the sides, None for the color of the path.
+    #[serde(default)]
+    pub color: Option<Color>,
+}
+
+/// Density heatmap of the positions of the poses of an array, drawn instead of the poses,
+/// e.g. for the particles of a localization filter.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PoseDensityConfig {
+    /// If true, the heatmap is drawn at startup instead of the poses.
+    #[serde(default)]
+    pub show: bool,
+    /// Side of the bins of the histogram, in terminal cells.
+    #[serde(default = "default_density_cells")]
+    pub cells: u16,
+    #[serde(default = "default_heatmap_colormap")]
+    pub colormap: String,
+}
+
+/// Defaults of a pose topic given by name with `preset`.
+struct PosePreset {
+    style: &'static str,
+    color: Color,
+    dash_length: f64,
+    thickness: u8,
+}
+
+pub const SUPPORTED_POSE_PRESETS: [&str; 2] = ["global_plan", "local_plan"];
+
+fn get_pose_preset(name: &str) -> Option<PosePreset> {
+    match name {
+        "global_plan" => Some(PosePreset {
+            style: "line",
+            color: Color { r: 0, g: 200, b: 0 },
+            dash_length: 0.0,
+            thickness: 2,
+        }),
+        "local_plan" => Some(PosePreset {
+            style: "line",
+            color: Color {
+                r: 255,
+                g: 200,
+                b: 0,
+            },
+            dash_length: 0.1,
+            thickness: 1,
+        }),
+        _ => None,
+    }
+}
+
+/// A pose topic as written in the configuration file, where the fields not given are
+/// taken from the preset, if any.
+#[derive(Deserialize)]
+struct PoseListenerEntry {
+    topic: String,
+    #[serde(default)]
+    preset: Option<String>,
+    #[serde(default)]
+    style: Option<String>,
+    #[serde(default)]
+    color: Option<Color>,
+    #[serde(default)]
+    length: Option<f64>,
+    #[serde(default)]
+    frame_filter: Option<String>,
+    #[serde(default)]
+    override_frame_id: Option<String>,
+    #[serde(default)]
+    ghost_lag: Option<f64>,
+    #[serde(default)]
+    cost: Option<PathCostConfig>,
+    #[serde(default)]
+    edges: Option<GraphEdgesConfig>,
+    #[serde(default)]
+    velocities: Option<PoseVelocitiesConfig>,
+    #[serde(default)]
+    spline_samples: Option<usize>,
+    #[serde(default)]
+    breadcrumbs: Option<BreadcrumbsConfig>,
+    #[serde(default)]
+    timeout: Option<f64>,
+    #[serde(default)]
+    stale_color: Option<Color>,
+    #[serde(default)]
+    smoothing: Option<f64>,
+    #[serde(default)]
+    dash_length: Option<f64>,
+    #[serde(default)]
+    thickness: Option<u8>,
+    #[serde(default = "default_opacity")]
+    opacity: f64,
+    #[serde(default)]
+    center_on_first: bool,
+    #[serde(default)]
+    density: Option<PoseDensityConfig>,
+    #[serde(default)]
+    tube: Option<PathTubeConfig>,
+    #[serde(default)]
+    band: Option<PathBandConfig>,
+}
+
+impl TryFrom<PoseListenerEntry> for PoseListenerConfig {
+    type Error = String;
+
+    fn try_from(entry: PoseListenerEntry) -> Result<Self, Self::Error> {
+        let preset = match &entry.preset {
+            Some(name) => Some(get_pose_preset(name).ok_or(format!(
+                "unknown preset {:?} of topic {}, supported are {:?}",
+                name, entry.topic, SUPPORTED_POSE_PRESETS
+            ))?),
+            None => None,
+        };
+        let style = match (entry.style, &preset) {
+            (Some(style), _) => style,
+            (None, Some(preset)) => preset.style.to_string(),
+            (None, None) => return Err(format!("missing style of topic {}", entry.topic)),
+        };
+        Ok(PoseListenerConfig {
+            style,
+            color: entry
+                .color
+                .or(preset.as_ref().map(|p| p.color.clone()))
+                .unwrap_or_else(color_red),
+            length: entry.length.unwrap_or_else(default_pose_length),
+            frame_filter: entry.frame_filter,
+            override_frame_id: entry.override_frame_id,
+            ghost_lag: entry.ghost_lag,
+            cost: entry.cost,
+            edges: entry.edges,
+            velocities: entry.velocities,
+            spline_samples: entry.spline_samples,
+            breadcrumbs: entry.breadcrumbs,
+            timeout: entry.timeout,
+            stale_color: entry.stale_color,
+            smoothing: entry.smoothing.unwrap_or(1.0),
+            dash_length: entry
+                .dash_length
+                .or(preset.as_ref().map(|p| p.dash_length))
+                .unwrap_or(0.0),
+            thickness: entry
+                .thickness
+                .or(preset.as_ref().map(|p| p.thickness))
+                .unwrap_or(1),
+            opacity: entry.opacity,
+            center_on_first: entry.center_on_first,
+            density: entry.density,
+            tube: entry.tube,
+            band: entry.band,
+            topic: entry.topic,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PoseDiffConfig {
+    pub first: PoseListenerConfig,
+    pub second: PoseListenerConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TwistEstimateConfig {
+    /// Topic on which to publish the geometry_msgs::Twist.
+    pub topic: String,
+    /// Time between the start and the end poses, in seconds.
+    #[serde(default = "default_twist_estimate_dt")]
+    pub dt: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrackingErrorConfig {
+    /// Topic of the reference as a nav_msgs::Path.
+    #[serde(default)]
+    pub topic: Option<String>,
+    /// File of the reference as points in the static frame, one "x y" per line.
+    #[serde(default)]
+    pub file: Option<String>,
+    /// Distance travelled by the robot between two error lines, in meters.
+    #[serde(default = "default_error_interval")]
+    pub error_interval: f64,
+    /// Minimum distance between two poses of the trail, in meters.
+    #[serde(default = "default_trail_spacing")]
+    pub trail_spacing: f64,
+    /// Number of poses of the trail, the oldest ones being dropped.
+    #[serde(default = "default_max_trail")]
+    pub max_trail: usize,
+    #[serde(default = "color_green")]
+    pub reference_color: Color,
+    #[serde(default = "color_cyan")]
+    pub trail_color: Color,
+    #[serde(default = "color_red")]
+    pub error_color: Color,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AlignmentConfig {
+    /// Frame of the ground truth, e.g. of a motion capture system.
+    pub ground_truth_frame: String,
+    /// Frame of the estimate, e.g. of the localization.
+    pub estimate_frame: String,
+    #[serde(default = "color_green")]
+    pub ground_truth_color: Color,
+    #[serde(default = "color_cyan")]
+    pub estimate_color: Color,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiagnosticStatusConfig {
+    /// Topic of the diagnostic_msgs::DiagnosticArray.
+    pub topic: String,
+    /// Time in seconds after which a status that was not refreshed is stale, 0 to never be.
+    #[serde(default = "default_diagnostic_timeout")]
+    pub timeout: f64,
+    /// If true, the stale statuses are hidden instead of dimmed.
+    #[serde(default)]
+    pub drop_stale: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ClearCostConfig {
+    /// std_srvs::Empty service to call, e.g. move_base/clear_costmaps.
+    #[serde(default)]
+    pub service: Option<String>,
+    /// Topic on which to publish the region as a geometry_msgs::PolygonStamped.
+    #[serde(default)]
+    pub topic: Option<String>,
+    /// Side of the square region centered on the cursor, in meters.
+    #[serde(default = "default_clear_cost_size")]
+    pub size: f64,
+}
+
+/// Triangle drawn from the robot frame along its heading, e.g. for the field of view of a
+/// sensor.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HeadingWedgeConfig {
+    /// Half of the opening angle of the wedge, in degrees.
+    #[serde(default = "default_wedge_half_angle")]
+    pub half_angle: f64,
+    /// Distance from the robot frame to the far side of the wedge, along the heading.
+    #[serde(default = "default_wedge_length")]
+    pub length: f64,
+    #[serde(default = "color_yellow")]
+    pub color: Color,
+}
+
+/// Outline of the footprint inflated by a margin, e.g. the one of the collision checking.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FootprintBufferConfig {
+    /// Distance of the outline from the footprint, in meters.
+    #[serde(default = "default_buffer_distance")]
+    pub distance: f64,
+    #[serde(default = "color_red")]
+    pub color: Color,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PathCostConfig {
+    pub topic: String,
+    #[serde(default = "default_cost_colormap")]
+    pub colormap: String,
+    #[serde(default)]
+    pub range: Option<[f64; 2]>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GraphEdgesConfig {
+    pub topic: String,
+    #[serde(default = "color_white")]
+    pub color: Color,
+}
+
+/// Speeds of the poses of an array, drawn as arrows along their orientation.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PoseVelocitiesConfig {
+    /// std_msgs::Float64MultiArray topic with one speed per pose, in m/s.
+    pub topic: String,
+    #[serde(default = "color_cyan")]
+    pub color: Color,
+    /// Length of the arrow of a speed of 1 m/s, in meters.
+    #[serde(default = "default_velocity_scale")]
+    pub scale: f64,
+}
+
+/// Markers dropped along the received poses.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BreadcrumbsConfig {
+    /// Distance from the last breadcrumb after which a new one is dropped, in meters, 0
+    /// to drop them only after `interval`.
+    #[serde(default = "default_breadcrumb_distance")]
+    pub distance: f64,
+    /// Time after which a new breadcrumb is dropped, in seconds, 0 to drop them only after
+    /// `distance`.
+    #[serde(default)]
+    pub interval: f64,
+    /// Number of breadcrumbs kept, the oldest one is removed past it.
+    #[serde(default = "default_breadcrumb_max_count")]
+    pub max_count: usize,
+    #[serde(default = "default_breadcrumb_glyph")]
+    pub glyph: String,
+    /// Width of the glyphs, in meters.
+    #[serde(default = "default_breadcrumb_size")]
+    pub size: f64,
+    /// Text printed next to each breadcrumb: none, index or time (elapsed since it was
+    /// dropped).
+    #[serde(default = "default_breadcrumb_label")]
+    pub label: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PolygonListenerConfig {
+    pub topic: String,
+    #[serde(default = "color_white")]
+    pub color: Color,
+    #[serde(default = "default_polygon_z_style")]
+    pub z_style: String,
+    #[serde(default = "default_polygon_fill")]
+    pub fill: String,
+    #[serde(default = "default_hatch_spacing")]
+    pub hatch_spacing: f64,
+    #[serde(default = "default_hatch_angle")]
+    pub hatch_angle: f64,
+    /// If true, the edges are drawn over the fill, otherwise the fill covers them.
+    #[serde(default = "default_true")]
+    pub outline_on_top: bool,
+    #[serde(default)]
+    pub vertices: Option<PolygonVerticesConfig>,
+    #[serde(default)]
+    pub frame_filter: Option<String>,
+    /// Frame in which the data is transformed instead of the frame of its header, e.g. to
+    /// debug a publisher with a wrong frame_id.
+    #[serde(default)]
+    pub override_frame_id: Option<String>,
+    #[serde(default)]
+    pub lookup_tolerance: f64,
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+    /// If true, the view is centered on the data of the layer when it is first received.
+    #[serde(default = "bool::default")]
+    pub center_on_first: bool,
+}
+
+/// Glyphs drawn at the vertices of a polygon, in addition to its edges.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PolygonVerticesConfig {
+    #[serde(default = "color_yellow")]
+    pub color: Color,
+    /// Glyph of the vertices. Supported: cross, square, circle.
+    #[serde(default = "default_vertex_style")]
+    pub point_style: String,
+    /// Width of the glyphs, in meters.
+    #[serde(default = "default_vertex_size")]
+    pub size: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -66,16 +894,120 @@ pub struct ImageListenerConfig {
     pub rotation: i64,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ThumbnailConfig {
+    pub topic: String,
+    #[serde(default = "default_thumbnail_width")]
+    pub width: u16,
+    #[serde(default = "default_thumbnail_height")]
+    pub height: u16,
+    #[serde(default = "bool::default")]
+    pub grayscale: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SendPoseConfig {
     pub topic: String,
     pub msg_type: String,
+    #[serde(default)]
+    pub snap_radius: f64,
+    #[serde(default)]
+    pub tolerance: Option<GoalToleranceConfig>,
+    /// Topic of the actionlib_msgs::GoalID cancelling the goals sent on the topic, e.g.
+    /// move_base/cancel.
+    #[serde(default)]
+    pub cancel_topic: Option<String>,
 }
 
+/// Tolerances accepted by the planner around a goal, drawn around the desired pose. They
+/// are only visual aids and don't change the sent pose.
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct ListenerConfigColor {
+pub struct GoalToleranceConfig {
+    /// Distance from the goal, in meters.
+    #[serde(default)]
+    pub xy: f64,
+    /// Difference from the heading of the goal, in degrees.
+    #[serde(default)]
+    pub yaw: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LaserListenerConfig {
     pub topic: String,
     pub color: Color,
+    #[serde(default)]
+    pub frame_filter: Option<String>,
+    /// Frame in which the data is transformed instead of the frame of its header, e.g. to
+    /// debug a publisher with a wrong frame_id.
+    #[serde(default)]
+    pub override_frame_id: Option<String>,
+    #[serde(default)]
+    pub lookup_tolerance: f64,
+    #[serde(default = "bool::default")]
+    pub normalize_angles: bool,
+    #[serde(default = "default_point_style")]
+    pub point_style: String,
+    #[serde(default = "default_point_size")]
+    pub point_size: f64,
+    #[serde(default = "default_point_size_mode")]
+    pub size_mode: String,
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+    #[serde(default = "bool::default")]
+    pub hull_only: bool,
+    /// Window [min, max] of the angles of the drawn beams in the sensor frame, in degrees.
+    #[serde(default)]
+    pub angle_window: Option<[f64; 2]>,
+    /// The lasers are drawn by increasing priority, the last one being on top.
+    #[serde(default)]
+    pub priority: i32,
+    /// If set, the endpoints of consecutive beams closer than this distance, in meters,
+    /// are connected by a segment.
+    #[serde(default)]
+    pub connect_gap: Option<f64>,
+    /// Additional scan topics drawn as part of the layer, e.g. the rear lidar of a robot
+    /// with a front one.
+    #[serde(default)]
+    pub sources: Vec<LaserSourceConfig>,
+    #[serde(default)]
+    pub fan: Option<LaserFanConfig>,
+    #[serde(default)]
+    pub min_range: Option<LaserMinRangeConfig>,
+    /// If true, the view is centered on the data of the layer when it is first received.
+    #[serde(default = "bool::default")]
+    pub center_on_first: bool,
+}
+
+/// Rays from the sensor to the endpoints of the beams, showing the free space seen by a
+/// scan.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LaserFanConfig {
+    /// One beam in this number gets a ray, since drawing all of them is expensive.
+    #[serde(default = "default_fan_decimation")]
+    pub decimation: usize,
+    #[serde(default = "color_dark_gray")]
+    pub color: Color,
+    /// Opacity of the rays, multiplied by the opacity of the layer.
+    #[serde(default = "default_fan_opacity")]
+    pub opacity: f64,
+}
+
+/// Circle of the range_min of the scans around the sensor, showing where it can't see.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LaserMinRangeConfig {
+    #[serde(default = "color_dark_gray")]
+    pub color: Color,
+    /// Opacity of the circle, multiplied by the opacity of the layer.
+    #[serde(default = "default_fan_opacity")]
+    pub opacity: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LaserSourceConfig {
+    pub topic: String,
+    /// Color of the points of the source, the color of the layer if not set.
+    #[serde(default)]
+    pub color: Option<Color>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -84,7 +1016,553 @@ pub struct MapListenerConfig {
     #[serde(default = "color_white")]
     pub color: Color,
     #[serde(default = "default_map_threshold")]
-    pub threshold: i8,
+    pub threshold: i16,
+    #[serde(default = "default_map_style")]
+    pub style: String,
+    #[serde(default)]
+    pub colormap: Option<String>,
+    #[serde(default = "color_dark_gray")]
+    pub unknown_color: Color,
+    #[serde(default)]
+    pub unsigned_values: bool,
+    #[serde(default = "default_map_value_domain")]
+    pub value_domain: [f64; 2],
+    #[serde(default = "default_map_unknown_value")]
+    pub unknown_value: i16,
+    /// Maximum number of cells of a stored grid, larger grids are downsampled to fit. 0
+    /// to never downsample.
+    #[serde(default = "default_map_max_cells")]
+    pub max_cells: usize,
+    #[serde(default)]
+    pub binarize: Option<MapBinarizeConfig>,
+    /// Color of the frontier cells, the free cells next to an unknown cell, None to not
+    /// highlight them.
+    #[serde(default)]
+    pub frontier_color: Option<Color>,
+    #[serde(default)]
+    pub frame_filter: Option<String>,
+    /// Frame in which the data is transformed instead of the frame of its header, e.g. to
+    /// debug a publisher with a wrong frame_id.
+    #[serde(default)]
+    pub override_frame_id: Option<String>,
+    #[serde(default)]
+    pub lookup_tolerance: f64,
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+    /// If true, the view is centered on the data of the layer when it is first received.
+    #[serde(default = "bool::default")]
+    pub center_on_first: bool,
+}
+
+/// Reduction of a map to occupied, free and unknown cells, replacing the threshold and the
+/// colormap of the map.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MapBinarizeConfig {
+    /// Value from which a known cell is occupied, drawn in the color of the map.
+    #[serde(default = "default_binarize_threshold")]
+    pub threshold: i16,
+    /// Color of the free cells, None to not draw them.
+    #[serde(default)]
+    pub free_color: Option<Color>,
+    /// Color of the unknown cells, None to not draw them.
+    #[serde(default)]
+    pub unknown_color: Option<Color>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImageOverlayConfig {
+    pub path: String,
+    pub resolution: f64,
+    pub origin: [f64; 3],
+    #[serde(default)]
+    pub colormap: Option<String>,
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HeatmapListenerConfig {
+    pub topic: String,
+    pub resolution: f64,
+    #[serde(default)]
+    pub origin: [f64; 3],
+    #[serde(default = "default_heatmap_colormap")]
+    pub colormap: String,
+    #[serde(default)]
+    pub value_range: Option<[f64; 2]>,
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+}
+
+/// Vector field over a grid, e.g. the gradient of a potential field planner, whose x and y
+/// components are published as std_msgs::Float32MultiArray.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GradientListenerConfig {
+    pub x_topic: String,
+    pub y_topic: String,
+    pub resolution: f64,
+    #[serde(default)]
+    pub origin: [f64; 3],
+    #[serde(default = "default_heatmap_colormap")]
+    pub colormap: String,
+    /// Magnitudes mapped to the ends of the colormap, the range of the field if not set.
+    #[serde(default)]
+    pub magnitude_range: Option<[f64; 2]>,
+    /// Minimum distance between two arrows, in terminal cells.
+    #[serde(default = "default_gradient_spacing")]
+    pub spacing: u16,
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NavSatListenerConfig {
+    pub topic: String,
+    #[serde(default = "color_white")]
+    pub color: Color,
+    #[serde(default)]
+    pub datum: Option<[f64; 3]>,
+    #[serde(default)]
+    pub enu_frame: Option<String>,
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+    /// If true, the view is centered on the data of the layer when it is first received.
+    #[serde(default = "bool::default")]
+    pub center_on_first: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccelListenerConfig {
+    pub topic: String,
+    /// Frame at whose origin the acceleration is drawn, the frame of the messages if not set.
+    #[serde(default)]
+    pub frame: Option<String>,
+    #[serde(default = "color_magenta")]
+    pub color: Color,
+    /// Length of the arrow per m/s^2, in meters.
+    #[serde(default = "default_accel_scale")]
+    pub linear_scale: f64,
+    /// Sweep of the arc per rad/s^2, in radians.
+    #[serde(default = "default_accel_scale")]
+    pub angular_scale: f64,
+    #[serde(default = "default_accel_arc_radius")]
+    pub arc_radius: f64,
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CorrespondenceConfig {
+    /// Laser topic whose points are matched.
+    pub source: String,
+    /// Laser or map topic of the points they are matched to.
+    pub reference: String,
+    /// Maximum distance between two matched points, in meters.
+    #[serde(default = "default_correspondence_threshold")]
+    pub threshold: f64,
+    #[serde(default = "default_correspondence_max_lines")]
+    pub max_lines: usize,
+    /// Color of the lines between coincident points.
+    #[serde(default = "color_green")]
+    pub near_color: Color,
+    /// Color of the lines between points at the threshold distance.
+    #[serde(default = "color_red")]
+    pub far_color: Color,
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DetectionListenerConfig {
+    pub topic: String,
+    #[serde(default = "color_yellow")]
+    pub color: Color,
+    /// If true, the boxes are labeled with the class and the score of their best hypothesis.
+    #[serde(default = "default_true")]
+    pub show_labels: bool,
+    /// Names of the classes, indexed by id. The id is shown for the classes without a name.
+    #[serde(default)]
+    pub class_names: Vec<String>,
+    /// If true, the opacity of the boxes is multiplied by their score.
+    #[serde(default)]
+    pub score_intensity: bool,
+    #[serde(default)]
+    pub frame_filter: Option<String>,
+    /// Frame in which the data is transformed instead of the frame of its header, e.g. to
+    /// debug a publisher with a wrong frame_id.
+    #[serde(default)]
+    pub override_frame_id: Option<String>,
+    #[serde(default)]
+    pub lookup_tolerance: f64,
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SegmentListenerConfig {
+    pub topic: String,
+    /// Color of the segments, if the marker has no color per point.
+    #[serde(default = "color_white")]
+    pub color: Color,
+    /// If true, the segments are labeled with their length.
+    #[serde(default)]
+    pub show_lengths: bool,
+    #[serde(default)]
+    pub frame_filter: Option<String>,
+    /// Frame in which the data is transformed instead of the frame of its header, e.g. to
+    /// debug a publisher with a wrong frame_id.
+    #[serde(default)]
+    pub override_frame_id: Option<String>,
+    #[serde(default)]
+    pub lookup_tolerance: f64,
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TfComparisonConfig {
+    /// Topics of the secondary TF tree, e.g. remapped from a bag.
+    pub tf_topic: String,
+    pub tf_static_topic: String,
+    /// Frames of the secondary tree to draw, all of them if empty.
+    #[serde(default)]
+    pub frames: Vec<String>,
+    #[serde(default = "color_cyan")]
+    pub color: Color,
+    #[serde(default = "color_red")]
+    pub divergence_color: Color,
+    /// Minimum distance between the origins of a frame in both trees to draw a line
+    /// between them, in meters.
+    #[serde(default = "default_tf_comparison_tolerance")]
+    pub tolerance: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RobotLinkConfig {
+    pub frame: String,
+    pub shape: String,
+    pub size: Vec<f64>,
+    #[serde(default)]
+    pub origin: [f64; 3],
+    #[serde(default = "color_white")]
+    pub color: Color,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GifRecordingConfig {
+    pub path: String,
+    pub duration: f64,
+    pub fps: u32,
+    pub max_width: u32,
+}
+
+impl Default for GifRecordingConfig {
+    fn default() -> GifRecordingConfig {
+        GifRecordingConfig {
+            path: "termviz.gif".to_string(),
+            duration: 5.0,
+            fps: 10,
+            max_width: 800,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TimerConfig {
+    /// Duration of the countdown in seconds, 0 to show the elapsed time instead.
+    #[serde(default)]
+    pub duration: f64,
+    /// Format of the time. Supported: clock ([h:]mm:ss.d), seconds.
+    #[serde(default = "default_timer_format")]
+    pub format: String,
+    /// Corner of the terminal. Supported: top_left, top_right, bottom_left, bottom_right.
+    #[serde(default = "default_timer_corner")]
+    pub corner: String,
+}
+
+impl Default for TimerConfig {
+    fn default() -> TimerConfig {
+        TimerConfig {
+            duration: 0.0,
+            format: default_timer_format(),
+            corner: default_timer_corner(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HomeViewConfig {
+    pub follow_frame: String,
+    #[serde(default)]
+    pub offset: [f64; 2],
+    #[serde(default = "default_zoom")]
+    pub zoom: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MinimapConfig {
+    /// If true, the minimap is shown at startup.
+    pub show: bool,
+    /// Size of the panel, in terminal cells, without its borders.
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Default for MinimapConfig {
+    fn default() -> MinimapConfig {
+        MinimapConfig {
+            show: false,
+            width: 30,
+            height: 10,
+        }
+    }
+}
+
+/// Combined HUD of a north arrow and a scale bar, drawn in the bottom left corner of the
+/// viewport.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MapHudConfig {
+    /// If true, the HUD is shown at startup.
+    #[serde(default)]
+    pub show: bool,
+    /// Angle of the north from the x axis of the static frame, counter-clockwise in degrees.
+    #[serde(default)]
+    pub north_angle: f64,
+    /// Color of the HUD, None to contrast with the background color.
+    #[serde(default)]
+    pub color: Option<Color>,
+}
+
+/// Text of the pose of the robot in the static frame, drawn in a corner of the viewport.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PoseReadoutConfig {
+    /// If true, the readout is shown at startup.
+    #[serde(default)]
+    pub show: bool,
+    /// Frame whose pose is shown, None for the robot frame.
+    #[serde(default)]
+    pub frame: Option<String>,
+    /// Number of decimals of the coordinates and the yaw.
+    #[serde(default = "default_readout_precision")]
+    pub precision: usize,
+    /// Corner of the viewport. Supported: top_left, top_right, bottom_left, bottom_right.
+    #[serde(default = "default_readout_corner")]
+    pub corner: String,
+}
+
+impl Default for PoseReadoutConfig {
+    fn default() -> PoseReadoutConfig {
+        PoseReadoutConfig {
+            show: false,
+            frame: None,
+            precision: default_readout_precision(),
+            corner: default_readout_corner(),
+        }
+    }
+}
+
+/// Drawing of the robot and the frames at a common time of the layers instead of at their
+/// latest pose, e.g. to debug a sensor fusion. The common time is the oldest of the latest
+/// stamps of the layers. The layers keep their latest data, transformed at its own stamp,
+/// and the ones with no data at the common time are dimmed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TimeSyncConfig {
+    /// If true, the time sync is on at startup.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Topics of the layers the common time is computed from, all the stamped layers if
+    /// empty. E.g. to leave out a latched map with an old stamp.
+    #[serde(default)]
+    pub topics: Vec<String>,
+    /// Difference between the stamp of a layer and the common time from which the layer
+    /// is dimmed, in seconds.
+    #[serde(default = "default_sync_tolerance")]
+    pub tolerance: f64,
+    /// Factor of the opacity of the dimmed layers.
+    #[serde(default = "default_sync_opacity")]
+    pub dim_opacity: f64,
+}
+
+impl Default for TimeSyncConfig {
+    fn default() -> TimeSyncConfig {
+        TimeSyncConfig {
+            enabled: false,
+            topics: Vec::new(),
+            tolerance: default_sync_tolerance(),
+            dim_opacity: default_sync_opacity(),
+        }
+    }
+}
+
+/// Fading of the elements of the features keeping a history (breadcrumbs, ghost poses
+/// and snapshots) according to their age.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgeRampConfig {
+    /// Age at which the end of the ramp is reached, in seconds.
+    pub duration: f64,
+    /// Color of a new element, None to keep the color of its feature.
+    pub start_color: Option<Color>,
+    pub start_opacity: f64,
+    /// Color of an element older than the duration, None to keep the color of its feature.
+    pub end_color: Option<Color>,
+    pub end_opacity: f64,
+}
+
+impl Default for AgeRampConfig {
+    fn default() -> AgeRampConfig {
+        AgeRampConfig {
+            duration: 10.0,
+            start_color: None,
+            start_opacity: 1.0,
+            end_color: None,
+            end_opacity: 0.3,
+        }
+    }
+}
+
+/// Steps of the cursor of the interactive modes (send pose, waypoints, polygon, inspect
+/// cell and twist estimate).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CursorConfig {
+    /// Initial step of the cursor, in the unit of step_mode, and of its rotation in radians.
+    pub step: f64,
+    /// Unit of the step: world (meters) or screen (terminal cells, so that the cursor moves
+    /// at the same speed on screen whatever the zoom).
+    pub step_mode: String,
+    /// Factor of the step when moving with Shift.
+    pub fine_factor: f64,
+    /// Factor of the step when moving with Alt.
+    pub coarse_factor: f64,
+    /// Frame of the relative coordinates of the cursor readouts, None for the robot frame.
+    #[serde(default)]
+    pub relative_frame: Option<String>,
+    /// If true, the cursor readouts show the relative coordinates at startup.
+    #[serde(default)]
+    pub relative: bool,
+}
+
+impl Default for CursorConfig {
+    fn default() -> CursorConfig {
+        CursorConfig {
+            step: 0.1,
+            step_mode: "world".to_string(),
+            fine_factor: 0.1,
+            coarse_factor: 10.0,
+            relative_frame: None,
+            relative: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StartupConfig {
+    /// Frame whose transform from the static frame is awaited before the first render, in
+    /// addition to the robot frame.
+    pub wait_frame: Option<String>,
+    /// Maximum time to wait for it, in seconds.
+    pub wait_timeout: f64,
+}
+
+impl Default for StartupConfig {
+    fn default() -> StartupConfig {
+        StartupConfig {
+            wait_frame: None,
+            wait_timeout: 10.0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UnitsConfig {
+    pub system: String,
+    pub precision: usize,
+}
+
+impl Default for UnitsConfig {
+    fn default() -> UnitsConfig {
+        UnitsConfig {
+            system: "metric".to_string(),
+            precision: 2,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LoggingConfig {
+    /// File to which the messages are appended, None to only show them in the interface.
+    pub path: Option<String>,
+    pub level: String,
+    /// Minimum time between two messages logged from the same place, in seconds.
+    pub throttle: f64,
+    /// Number of recent messages shown in the interface, 0 to hide them.
+    pub notifications: usize,
+    /// For how long a message is shown in the interface, in seconds.
+    pub notification_duration: f64,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> LoggingConfig {
+        LoggingConfig {
+            path: None,
+            level: "warn".to_string(),
+            throttle: 5.0,
+            notifications: 3,
+            notification_duration: 10.0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WaypointsConfig {
+    pub topic: String,
+    pub yaw_from_segments: bool,
+}
+
+impl Default for WaypointsConfig {
+    fn default() -> WaypointsConfig {
+        WaypointsConfig {
+            topic: "waypoints".to_string(),
+            yaw_from_segments: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PolygonEditorConfig {
+    pub topic: String,
+    pub color: Color,
+}
+
+impl Default for PolygonEditorConfig {
+    fn default() -> PolygonEditorConfig {
+        PolygonEditorConfig {
+            topic: "keepout_zone".to_string(),
+            color: Color {
+                r: 255,
+                g: 255,
+                b: 0,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TfEchoConfig {
+    /// Initial source frame, the static frame if not set.
+    pub source: Option<String>,
+    /// Initial target frame, the robot frame if not set.
+    pub target: Option<String>,
+    pub show_axes: bool,
+}
+
+impl Default for TfEchoConfig {
+    fn default() -> TfEchoConfig {
+        TfEchoConfig {
+            source: None,
+            target: None,
+            show_axes: true,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -110,22 +1588,140 @@ impl Default for TeleopConfig {
 pub struct TermvizConfig {
     pub fixed_frame: String,
     pub robot_frame: String,
+    #[serde(default = "default_footprint_param")]
+    pub footprint_param: String,
+    #[serde(default = "default_footprint_param")]
+    pub footprint_param_prefix: String,
+    #[serde(default)]
+    pub footprint_poll_interval: f64,
+    #[serde(default)]
+    pub heading_wedge: Option<HeadingWedgeConfig>,
+    #[serde(default)]
+    pub footprint_buffer: Option<FootprintBufferConfig>,
+    #[serde(default)]
+    pub robot_model: Vec<RobotLinkConfig>,
     pub map_topics: Vec<MapListenerConfig>,
-    pub laser_topics: Vec<ListenerConfigColor>,
+    #[serde(default)]
+    pub image_overlays: Vec<ImageOverlayConfig>,
+    #[serde(default)]
+    pub heatmap_topics: Vec<HeatmapListenerConfig>,
+    #[serde(default)]
+    pub gradient_topics: Vec<GradientListenerConfig>,
+    pub laser_topics: Vec<LaserListenerConfig>,
     pub marker_topics: Vec<ListenerConfig>,
     pub image_topics: Vec<ImageListenerConfig>,
-    pub marker_array_topics: Vec<ListenerConfig>,
+    #[serde(default)]
+    pub thumbnail: Option<ThumbnailConfig>,
+    pub marker_array_topics: Vec<MarkerArrayListenerConfig>,
+    #[serde(default)]
+    pub interactive_marker_topics: Vec<ListenerConfig>,
     pub path_topics: Vec<PoseListenerConfig>,
     pub pointcloud2_topics: Vec<PointCloud2ListenerConfig>,
     pub pose_array_topics: Vec<PoseListenerConfig>,
     pub pose_stamped_topics: Vec<PoseListenerConfig>,
+    #[serde(default)]
+    pub polygon_topics: Vec<PolygonListenerConfig>,
+    #[serde(default)]
+    pub navsat_topics: Vec<NavSatListenerConfig>,
+    #[serde(default)]
+    pub accel_topics: Vec<AccelListenerConfig>,
+    #[serde(default)]
+    pub correspondence_overlays: Vec<CorrespondenceConfig>,
+    #[serde(default)]
+    pub detection_topics: Vec<DetectionListenerConfig>,
+    #[serde(default)]
+    pub segment_topics: Vec<SegmentListenerConfig>,
+    #[serde(default)]
+    pub tf_comparison: Option<TfComparisonConfig>,
     pub send_pose_topics: Vec<SendPoseConfig>,
     pub target_framerate: i64,
     pub axis_length: f64,
     pub visible_area: Vec<f64>, //Borders of map from center in Meter
     pub zoom_factor: f64,
+    #[serde(default = "bool::default")]
+    pub show_scale_bar: bool,
+    #[serde(default = "bool::default")]
+    pub show_tf_frames: bool,
+    #[serde(default = "bool::default")]
+    pub snap_lines: bool,
+    #[serde(default = "color_black")]
+    pub background_color: Color,
+    /// Named colors, which the colors of the configuration can refer to by name.
+    #[serde(default)]
+    pub palette: HashMap<String, Color>,
+    #[serde(default = "default_true")]
+    pub auto_fit: bool,
+    #[serde(default = "default_auto_fit_padding")]
+    pub auto_fit_padding: f64,
+    #[serde(default)]
+    pub home_view: Option<HomeViewConfig>,
+    #[serde(default)]
+    pub minimap: MinimapConfig,
+    #[serde(default)]
+    pub map_hud: MapHudConfig,
+    #[serde(default)]
+    pub pose_readout: PoseReadoutConfig,
+    /// If true, only one map is drawn at a time, selected with CYCLE_ACTIVE_MAP.
+    #[serde(default)]
+    pub exclusive_maps: bool,
+    #[serde(default = "default_point_blending")]
+    pub point_blending: String,
+    #[serde(default = "default_snapshot_opacity")]
+    pub snapshot_opacity: f64,
+    #[serde(default)]
+    pub age_ramp: AgeRampConfig,
+    #[serde(default)]
+    pub cursor: CursorConfig,
+    #[serde(default)]
+    pub time_sync: TimeSyncConfig,
+    #[serde(default)]
+    pub startup: StartupConfig,
+    #[serde(default = "bool::default")]
+    pub suppress_idle_redraw: bool,
+    #[serde(default = "default_status_bar")]
+    pub status_bar: Vec<String>,
+    #[serde(default = "default_live_parameters_interval")]
+    pub live_parameters_interval: f64,
+    #[serde(default = "default_publisher_check_interval")]
+    pub publisher_check_interval: f64,
+    #[serde(default = "default_time_jump_threshold")]
+    pub time_jump_threshold: f64,
+    #[serde(default)]
+    pub future_stamp_threshold: Option<f64>,
+    #[serde(default)]
+    pub units: UnitsConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
     pub key_mapping: HashMap<String, String>,
     pub teleop: TeleopConfig,
+    #[serde(default)]
+    pub waypoints: WaypointsConfig,
+    #[serde(default)]
+    pub polygon_editor: PolygonEditorConfig,
+    #[serde(default)]
+    pub tf_echo: TfEchoConfig,
+    #[serde(default)]
+    pub pose_diff: Option<PoseDiffConfig>,
+    #[serde(default)]
+    pub twist_estimate: Option<TwistEstimateConfig>,
+    #[serde(default)]
+    pub diagnostic_status: Option<DiagnosticStatusConfig>,
+    #[serde(default)]
+    pub tracking_error: Option<TrackingErrorConfig>,
+    #[serde(default)]
+    pub alignment: Option<AlignmentConfig>,
+    #[serde(default)]
+    pub clear_cost: Option<ClearCostConfig>,
+    #[serde(default)]
+    pub gif_recording: GifRecordingConfig,
+    /// URIs of the ROS masters to switch between at runtime, e.g. one per robot.
+    #[serde(default)]
+    pub ros_masters: Vec<String>,
+    /// File to which the TF tree is exported, as a Graphviz DOT graph.
+    #[serde(default = "default_tf_tree_path")]
+    pub tf_tree_path: String,
+    #[serde(default)]
+    pub timer: TimerConfig,
 }
 
 impl Default for TermvizConfig {
@@ -133,6 +1729,12 @@ impl Default for TermvizConfig {
         TermvizConfig {
             fixed_frame: "map".to_string(),
             robot_frame: "base_link".to_string(),
+            footprint_param: default_footprint_param(),
+            footprint_param_prefix: default_footprint_param(),
+            footprint_poll_interval: 0.0,
+            heading_wedge: None,
+            footprint_buffer: None,
+            robot_model: Vec::new(),
             map_topics: vec![MapListenerConfig {
                 topic: "map".to_string(),
                 color: Color {
@@ -141,51 +1743,211 @@ impl Default for TermvizConfig {
                     g: 255,
                 },
                 threshold: 1,
+                style: "filled".to_string(),
+                colormap: None,
+                unknown_color: color_dark_gray(),
+                unsigned_values: false,
+                value_domain: default_map_value_domain(),
+                unknown_value: default_map_unknown_value(),
+                max_cells: default_map_max_cells(),
+                binarize: None,
+                frontier_color: None,
+                frame_filter: None,
+                override_frame_id: None,
+                lookup_tolerance: 0.0,
+                opacity: 1.0,
+                center_on_first: false,
             }],
-            laser_topics: vec![ListenerConfigColor {
+            image_overlays: Vec::new(),
+            heatmap_topics: Vec::new(),
+            gradient_topics: Vec::new(),
+            laser_topics: vec![LaserListenerConfig {
                 topic: "scan".to_string(),
                 color: Color { r: 200, b: 0, g: 0 },
+                frame_filter: None,
+                override_frame_id: None,
+                lookup_tolerance: 0.0,
+                normalize_angles: false,
+                point_style: "dot".to_string(),
+                point_size: 1.0,
+                size_mode: "screen".to_string(),
+                opacity: 1.0,
+                hull_only: false,
+                angle_window: None,
+                priority: 0,
+                connect_gap: None,
+                sources: Vec::new(),
+                fan: None,
+                min_range: None,
+                center_on_first: false,
             }],
-            marker_array_topics: vec![ListenerConfig {
+            marker_array_topics: vec![MarkerArrayListenerConfig {
                 topic: "marker_array".to_string(),
+                frame_filter: None,
+                lookup_tolerance: 0.0,
+                color_by_namespace: false,
+                namespace_colors: HashMap::new(),
+                max_markers: None,
             }],
+            interactive_marker_topics: Vec::new(),
             marker_topics: vec![ListenerConfig {
                 topic: "marker".to_string(),
+                frame_filter: None,
+                lookup_tolerance: 0.0,
             }],
             image_topics: vec![ImageListenerConfig {
                 topic: "image_rect".to_string(),
                 rotation: 0,
             }],
+            thumbnail: None,
             pose_stamped_topics: vec![PoseListenerConfig {
                 topic: "pose_stamped".to_string(),
                 style: "axis".to_string(),
                 color: Color { r: 255, g: 0, b: 0 },
                 length: 0.2,
+                frame_filter: None,
+                override_frame_id: None,
+                ghost_lag: None,
+                cost: None,
+                edges: None,
+                velocities: None,
+                spline_samples: None,
+                breadcrumbs: None,
+                timeout: None,
+                stale_color: None,
+                smoothing: 1.0,
+                dash_length: 0.0,
+                thickness: 1,
+                opacity: 1.0,
+                center_on_first: false,
+                density: None,
+                tube: None,
+                band: None,
             }],
             pose_array_topics: vec![PoseListenerConfig {
                 topic: "pose_array".to_string(),
                 style: "arrow".to_string(),
                 color: Color { r: 255, g: 0, b: 0 },
                 length: 0.2,
+                frame_filter: None,
+                override_frame_id: None,
+                ghost_lag: None,
+                cost: None,
+                edges: None,
+                velocities: None,
+                spline_samples: None,
+                breadcrumbs: None,
+                timeout: None,
+                stale_color: None,
+                smoothing: 1.0,
+                dash_length: 0.0,
+                thickness: 1,
+                opacity: 1.0,
+                center_on_first: false,
+                density: None,
+                tube: None,
+                band: None,
             }],
             path_topics: vec![PoseListenerConfig {
                 topic: "path".to_string(),
                 style: "line".to_string(),
                 color: Color { r: 0, g: 255, b: 0 },
                 length: 0.2,
+                frame_filter: None,
+                override_frame_id: None,
+                ghost_lag: None,
+                cost: None,
+                edges: None,
+                velocities: None,
+                spline_samples: None,
+                breadcrumbs: None,
+                timeout: None,
+                stale_color: None,
+                smoothing: 1.0,
+                dash_length: 0.0,
+                thickness: 1,
+                opacity: 1.0,
+                center_on_first: false,
+                density: None,
+                tube: None,
+                band: None,
             }],
             pointcloud2_topics: vec![PointCloud2ListenerConfig {
                 topic: "pointcloud2".to_string(),
                 use_rgb: false,
+                color_by: default_color_by(),
+                z_range: default_height_range(),
+                colormap: default_cost_colormap(),
+                z_window: None,
+                point_style: "dot".to_string(),
+                point_size: 1.0,
+                size_mode: "screen".to_string(),
+                frame_filter: None,
+                override_frame_id: None,
+                lookup_tolerance: 0.0,
+                opacity: 1.0,
+                hull_only: false,
+                center_on_first: false,
+                lazy: false,
+            }],
+            polygon_topics: vec![PolygonListenerConfig {
+                topic: "polygon".to_string(),
+                color: color_white(),
+                z_style: "project".to_string(),
+                fill: "none".to_string(),
+                hatch_spacing: 0.2,
+                hatch_angle: 45.0,
+                outline_on_top: true,
+                vertices: None,
+                frame_filter: None,
+                override_frame_id: None,
+                lookup_tolerance: 0.0,
+                opacity: 1.0,
+                center_on_first: false,
             }],
+            navsat_topics: Vec::new(),
+            accel_topics: Vec::new(),
+            correspondence_overlays: Vec::new(),
+            detection_topics: Vec::new(),
+            segment_topics: Vec::new(),
+            tf_comparison: None,
             send_pose_topics: vec![SendPoseConfig {
                 topic: "initialpose".to_string(),
                 msg_type: "PoseWithCovarianceStamped".to_string(),
+                snap_radius: 0.0,
+                tolerance: None,
+                cancel_topic: None,
             }],
             target_framerate: 30,
             axis_length: 0.5,
             visible_area: vec![-5., 5., -5., 5.],
             zoom_factor: 0.1,
+            show_scale_bar: false,
+            show_tf_frames: false,
+            snap_lines: false,
+            background_color: color_black(),
+            palette: HashMap::new(),
+            auto_fit: true,
+            auto_fit_padding: 0.1,
+            home_view: None,
+            minimap: MinimapConfig::default(),
+            map_hud: MapHudConfig::default(),
+            pose_readout: PoseReadoutConfig::default(),
+            exclusive_maps: false,
+            point_blending: default_point_blending(),
+            snapshot_opacity: default_snapshot_opacity(),
+            age_ramp: AgeRampConfig::default(),
+            cursor: CursorConfig::default(),
+            time_sync: TimeSyncConfig::default(),
+            startup: StartupConfig::default(),
+            suppress_idle_redraw: false,
+            status_bar: default_status_bar(),
+            live_parameters_interval: 1.0,
+            publisher_check_interval: 5.0,
+            time_jump_threshold: 1.0,
+            future_stamp_threshold: None,
+            units: UnitsConfig::default(),
+            logging: LoggingConfig::default(),
             key_mapping: HashMap::from([
                 (input::UP.to_string(), "w".to_string()),
                 (input::DOWN.to_string(), "s".to_string()),
@@ -202,14 +1964,51 @@ impl Default for TermvizConfig {
                 (input::NEXT.to_string(), "n".to_string()),
                 (input::PREVIOUS.to_string(), "b".to_string()),
                 (input::SHOW_HELP.to_string(), "h".to_string()),
+                (input::CYCLE_FOLLOW_FRAME.to_string(), "f".to_string()),
+                (input::EXPORT_MAP.to_string(), "x".to_string()),
+                (input::TOGGLE_SCALE_BAR.to_string(), "r".to_string()),
+                (input::TOGGLE_MAP_HUD.to_string(), "l".to_string()),
+                (input::TOGGLE_POSE_DENSITY.to_string(), "z".to_string()),
+                (input::CYCLE_MAP_STYLE.to_string(), "m".to_string()),
+                (input::TOGGLE_MINIMAP.to_string(), "o".to_string()),
+                (input::NEXT_FOOTPRINT.to_string(), "p".to_string()),
+                (input::RECORD_GIF.to_string(), "g".to_string()),
+                (input::ADD_WAYPOINT.to_string(), "Space".to_string()),
+                (input::UNDO.to_string(), "u".to_string()),
+                (input::STORE_VIEW.to_string(), "v".to_string()),
+                (input::TOGGLE_SNAPSHOT.to_string(), "c".to_string()),
+                (input::OPEN_PALETTE.to_string(), ":".to_string()),
+                (input::EXPORT_CONFIG.to_string(), "y".to_string()),
                 (input::MODE_2.to_string(), "t".to_string()),
                 (input::MODE_3.to_string(), "i".to_string()),
             ]),
             teleop: TeleopConfig::default(),
+            waypoints: WaypointsConfig::default(),
+            polygon_editor: PolygonEditorConfig::default(),
+            tf_echo: TfEchoConfig::default(),
+            pose_diff: None,
+            twist_estimate: None,
+            diagnostic_status: None,
+            tracking_error: None,
+            alignment: None,
+            clear_cost: None,
+            gif_recording: GifRecordingConfig::default(),
+            ros_masters: Vec::new(),
+            tf_tree_path: default_tf_tree_path(),
+            timer: TimerConfig::default(),
         }
     }
 }
 
+/// Returns the duration of a setting in seconds, zero if it's negative or NaN, and the longest
+/// duration if it's too long to be represented, e.g. infinite.
+pub fn get_duration(seconds: f64) -> Duration {
+    if seconds.is_nan() || seconds <= 0.0 {
+        return Duration::ZERO;
+    }
+    Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX)
+}
+
 pub fn ask_store() -> bool {
     let mut x = String::with_capacity(5);
     print!("Store default config? (y|N): ");
@@ -222,6 +2021,13 @@ pub fn ask_store() -> bool {
     }
 }
 
+/// Writes a configuration to a YAML file, and checks that it loads back.
+pub fn export_config(config: &TermvizConfig, path: &Path) -> Result<(), confy::ConfyError> {
+    confy::store_path(path, config)?;
+    let _: TermvizConfig = confy::load_path(path)?;
+    Ok(())
+}
+
 pub fn get_config(config_path: Option<&String>) -> Result<TermvizConfig, confy::ConfyError> {
     let user_path = confy::get_configuration_file_path("termviz", "termviz")?;
 
@@ -240,7 +2046,7 @@ pub fn get_config(config_path: Option<&String>) -> Result<TermvizConfig, confy::
     let mut cfg = TermvizConfig::default();
     if load_config_path.exists() {
         println!("Loading config from: {:?}", load_config_path);
-        cfg = confy::load_path(load_config_path)?;
+        cfg = load_with_palette(load_config_path)?;
     } else {
         // no config found, generate default
         println!("No config found, using default");
@@ -255,3 +2061,71 @@ pub fn get_config(config_path: Option<&String>) -> Result<TermvizConfig, confy::
     };
     Ok(cfg)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Default)]
+    struct ColorsFile {
+        #[serde(default)]
+        palette: HashMap<String, Color>,
+        #[serde(default)]
+        colors: Vec<Color>,
+    }
+
+    /// Loads a file with the given content through the palette.
+    fn load_colors(name: &str, content: &str) -> Result<ColorsFile, confy::ConfyError> {
+        let path =
+            std::env::temp_dir().join(format!("termviz_{}_{}.yml", name, std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        let colors = load_with_palette(&path);
+        std::fs::remove_file(&path).unwrap();
+        colors
+    }
+
+    #[test]
+    fn palette_colors() {
+        let file = load_colors(
+            "palette",
+            "palette:\n  warning: {r: 255, g: 128, b: 0}\ncolors:\n  - warning\n  - {r: 1, g: 2, b: 3}\n",
+        )
+        .unwrap();
+        let components: Vec<(u8, u8, u8)> = file.colors.iter().map(|c| (c.r, c.g, c.b)).collect();
+        assert_eq!(components, vec![(255, 128, 0), (1, 2, 3)]);
+        // the palette doesn't leak to the next file
+        assert!(load_colors("no_palette", "colors:\n  - warning\n").is_err());
+    }
+
+    #[test]
+    fn unknown_palette_color() {
+        let error = match load_colors("unknown", "palette: {}\ncolors:\n  - danger\n") {
+            Ok(_) => panic!("an unknown palette color is loaded"),
+            Err(e) => format!("{:?}", e),
+        };
+        assert!(error.contains("undefined palette color"), "{}", error);
+    }
+
+    #[test]
+    fn durations_of_settings() {
+        assert_eq!(get_duration(1.5), Duration::from_millis(1500));
+        assert_eq!(get_duration(0.0), Duration::ZERO);
+        assert_eq!(get_duration(-2.0), Duration::ZERO);
+        assert_eq!(get_duration(f64::NAN), Duration::ZERO);
+        assert_eq!(get_duration(f64::INFINITY), Duration::MAX);
+        assert_eq!(get_duration(1e300), Duration::MAX);
+    }
+
+    #[test]
+    fn auto_fit_on_without_key() {
+        let path =
+            std::env::temp_dir().join(format!("termviz_auto_fit_{}.yml", std::process::id()));
+        confy::store_path(&path, TermvizConfig::default()).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("auto_fit: true\n"));
+        std::fs::write(&path, content.replace("auto_fit: true\n", "")).unwrap();
+        let config: TermvizConfig = load_with_palette(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(config.auto_fit);
+    }
+}
@@ -0,0 +1,218 @@
+//! Correspondences between two point sets, e.g. a scan and a reference scan or map, to
+//! show how well they are aligned, like the matching step of ICP.
+//!
+//! Each point of the source set is connected to its nearest neighbor in the reference set
+//! if it is closer than the threshold, the line being colored according to the distance.
+//! The reference points are binned into cells of the size of the threshold, so that only
+//! the neighboring cells are searched, and the lines are computed once per received
+//! message and not at each frame.
+
+use crate::config::{Color as RgbColor, CorrespondenceConfig};
+use crate::laser::LaserListener;
+use crate::map::MapListener;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use tui::style::Color;
+use tui::widgets::canvas::Line;
+
+/// The reference points, binned into square cells.
+struct ReferenceIndex {
+    cell_size: f64,
+    cells: HashMap<(i64, i64), Vec<(f64, f64)>>,
+}
+
+impl ReferenceIndex {
+    fn new(points: &[(f64, f64)], cell_size: f64) -> ReferenceIndex {
+        let mut cells: HashMap<(i64, i64), Vec<(f64, f64)>> = HashMap::new();
+        for pt in points {
+            cells
+                .entry(ReferenceIndex::get_cell(pt, cell_size))
+                .or_default()
+                .push(*pt);
+        }
+        ReferenceIndex { cell_size, cells }
+    }
+
+    fn get_cell(pt: &(f64, f64), cell_size: f64) -> (i64, i64) {
+        (
+            (pt.0 / cell_size).floor() as i64,
+            (pt.1 / cell_size).floor() as i64,
+        )
+    }
+
+    /// Returns the nearest reference point closer than the cell size, with its distance.
+    fn get_nearest(&self, pt: &(f64, f64)) -> Option<((f64, f64), f64)> {
+        let (column, row) = ReferenceIndex::get_cell(pt, self.cell_size);
+        let mut nearest: Option<((f64, f64), f64)> = None;
+        for i in column - 1..=column + 1 {
+            for j in row - 1..=row + 1 {
+                for reference in self.cells.get(&(i, j)).into_iter().flatten() {
+                    let distance = (reference.0 - pt.0).hypot(reference.1 - pt.1);
+                    if distance < self.cell_size
+                        && nearest.is_none_or(|(_, nearest)| distance < nearest)
+                    {
+                        nearest = Some((*reference, distance));
+                    }
+                }
+            }
+        }
+        nearest
+    }
+}
+
+/// Returns the color between `near` and `far` for a distance relative to the threshold.
+fn get_residual_color(near: &RgbColor, far: &RgbColor, fraction: f64) -> Color {
+    let fraction = fraction.clamp(0.0, 1.0);
+    let blend = |near: u8, far: u8| -> u8 {
+        (near as f64 + (far as f64 - near as f64) * fraction).round() as u8
+    };
+    Color::Rgb(
+        blend(near.r, far.r),
+        blend(near.g, far.g),
+        blend(near.b, far.b),
+    )
+}
+
+/// Returns the points of a laser or map topic in the static frame, with the number of
+/// messages received on it. None if no such topic is configured.
+fn get_points(
+    topic: &str,
+    lasers: &[LaserListener],
+    maps: &[MapListener],
+) -> Option<(u64, Vec<(f64, f64)>)> {
+    if let Some(laser) = lasers.iter().find(|laser| laser.config.topic == topic) {
+        return Some((laser.rate.get_total(), laser.points.read().unwrap().clone()));
+    }
+    let map = maps.iter().find(|map| map.config.topic == topic)?;
+    let mut points = Vec::new();
+    map.for_each_occupied_cell(|x, y| points.push((x, y)));
+    Some((map.rate.get_total(), points))
+}
+
+/// Returns the number of messages received on a laser or map topic.
+fn get_message_count(topic: &str, lasers: &[LaserListener], maps: &[MapListener]) -> u64 {
+    lasers
+        .iter()
+        .filter(|laser| laser.config.topic == topic)
+        .map(|laser| laser.rate.get_total())
+        .chain(
+            maps.iter()
+                .filter(|map| map.config.topic == topic)
+                .map(|map| map.rate.get_total()),
+        )
+        .sum()
+}
+
+/// Number of messages received on the source and on the reference topics.
+type MessageCounts = (u64, u64);
+
+pub struct CorrespondenceOverlay {
+    pub config: CorrespondenceConfig,
+    /// Last index of the reference points, with the number of reference messages.
+    reference: RefCell<Option<(u64, ReferenceIndex)>>,
+    /// Last lines computed, with the number of source and reference messages.
+    lines: RefCell<Option<(MessageCounts, Vec<Line>)>>,
+}
+
+impl CorrespondenceOverlay {
+    /// Creates the overlay, warns if its topics are not among the laser and map topics.
+    pub fn new(
+        config: CorrespondenceConfig,
+        lasers: &[LaserListener],
+        maps: &[MapListener],
+    ) -> CorrespondenceOverlay {
+        if !lasers
+            .iter()
+            .any(|laser| laser.config.topic == config.source)
+        {
+            log::warn!(
+                "Correspondence source {} is not a laser topic, nothing will be drawn.",
+                config.source
+            );
+        }
+        if !lasers
+            .iter()
+            .any(|laser| laser.config.topic == config.reference)
+            && !maps.iter().any(|map| map.config.topic == config.reference)
+        {
+            log::warn!(
+                "Correspondence reference {} is neither a laser nor a map topic, nothing will be drawn.",
+                config.reference
+            );
+        }
+        CorrespondenceOverlay {
+            config,
+            reference: RefCell::new(None),
+            lines: RefCell::new(None),
+        }
+    }
+
+    /// Forgets the computed lines, e.g. after the data was cleared.
+    pub fn clear(&self) {
+        *self.reference.borrow_mut() = None;
+        *self.lines.borrow_mut() = None;
+    }
+
+    /// Updates the index of the reference points if a reference message was received.
+    fn update_reference(&self, lasers: &[LaserListener], maps: &[MapListener]) {
+        let count = get_message_count(&self.config.reference, lasers, maps);
+        if matches!(&*self.reference.borrow(), Some((cached, _)) if *cached == count) {
+            return;
+        }
+        *self.reference.borrow_mut() = get_points(&self.config.reference, lasers, maps)
+            .map(|(count, points)| (count, ReferenceIndex::new(&points, self.config.threshold)));
+    }
+
+    /// Returns the lines between the source points and their nearest reference points, at
+    /// most max_lines of them evenly picked among the correspondences.
+    pub fn get_lines(&self, lasers: &[LaserListener], maps: &[MapListener]) -> Vec<Line> {
+        if self.config.threshold <= 0.0 || self.config.max_lines == 0 {
+            return Vec::new();
+        }
+        let counts = (
+            get_message_count(&self.config.source, lasers, maps),
+            get_message_count(&self.config.reference, lasers, maps),
+        );
+        if let Some((cached, lines)) = &*self.lines.borrow() {
+            if *cached == counts {
+                return lines.clone();
+            }
+        }
+        self.update_reference(lasers, maps);
+        let source = match get_points(&self.config.source, lasers, maps) {
+            Some((_, points)) => points,
+            None => return Vec::new(),
+        };
+        let reference = self.reference.borrow();
+        let reference = match reference.as_ref() {
+            Some((_, reference)) => reference,
+            None => return Vec::new(),
+        };
+        let matches: Vec<_> = source
+            .iter()
+            .filter_map(|pt| {
+                reference
+                    .get_nearest(pt)
+                    .map(|(nearest, distance)| (*pt, nearest, distance))
+            })
+            .collect();
+        let step = matches.len().div_ceil(self.config.max_lines);
+        let lines: Vec<Line> = matches
+            .iter()
+            .step_by(step.max(1))
+            .map(|(pt, nearest, distance)| Line {
+                x1: pt.0,
+                y1: pt.1,
+                x2: nearest.0,
+                y2: nearest.1,
+                color: get_residual_color(
+                    &self.config.near_color,
+                    &self.config.far_color,
+                    distance / self.config.threshold,
+                ),
+            })
+            .collect();
+        *self.lines.borrow_mut() = Some((counts, lines.clone()));
+        lines
+    }
+}
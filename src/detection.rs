@@ -0,0 +1,174 @@
+//! Module dealing with the visualization of detected objects, published as
+//! vision_msgs::Detection3DArray, e.g. by a perception pipeline.
+//!
+//! Each detection is drawn as the outline of its bounding box, projected on the ground
+//! plane, and can be labeled with the class and the score of its best hypothesis. The
+//! boxes are transformed to the static frame when received.
+
+use crate::config::DetectionListenerConfig;
+use crate::listeners::{get_source_frame, warn_frame_override, FrameFilter};
+use crate::rate::RateMonitor;
+use crate::transformation::{
+    lookup_transform_with_tolerance, ros_pose_to_isometry, ros_transform_to_isometry,
+};
+use nalgebra::geometry::{Isometry3, Point3};
+use std::convert::TryFrom;
+use std::sync::{Arc, RwLock};
+use tui::style::Color;
+use tui::widgets::canvas::Line;
+
+/// A detected object, in the static frame.
+pub struct Detection {
+    pub lines: Vec<Line>,
+    /// Position of the label, at a corner of the box.
+    pub position: (f64, f64),
+    /// Class and score of the best hypothesis, None if the detection has none.
+    pub hypothesis: Option<(i64, f64)>,
+}
+
+/// Returns the outline of a bounding box, projected on the ground plane.
+///
+/// # Arguments
+/// - `pose`: pose of the center of the box in the static frame.
+/// - `size_x`, `size_y`: dimensions of the box along its own axes.
+fn get_box_lines(pose: &Isometry3<f64>, size_x: f64, size_y: f64, color: Color) -> Vec<Line> {
+    let (half_x, half_y) = (size_x / 2.0, size_y / 2.0);
+    let corners: Vec<Point3<f64>> = [
+        (half_x, half_y),
+        (-half_x, half_y),
+        (-half_x, -half_y),
+        (half_x, -half_y),
+    ]
+    .iter()
+    .map(|(x, y)| pose.transform_point(&Point3::new(*x, *y, 0.0)))
+    .collect();
+    (0..corners.len())
+        .map(|i| {
+            let (p1, p2) = (&corners[i], &corners[(i + 1) % corners.len()]);
+            Line {
+                x1: p1.x,
+                y1: p1.y,
+                x2: p2.x,
+                y2: p2.y,
+                color,
+            }
+        })
+        .collect()
+}
+
+pub struct DetectionListener {
+    pub config: DetectionListenerConfig,
+    pub detections: Arc<RwLock<Vec<Detection>>>,
+    pub rate: RateMonitor,
+    _subscriber: rosrust::Subscriber,
+}
+
+impl DetectionListener {
+    pub fn new(
+        config: DetectionListenerConfig,
+        tf_listener: Arc<rustros_tf::TfListener>,
+        static_frame: String,
+    ) -> DetectionListener {
+        let detections = Arc::new(RwLock::new(Vec::new()));
+        let cb_detections = detections.clone();
+        let frame_filter = FrameFilter::new(&config.frame_filter);
+        warn_frame_override(&config.topic, &config.override_frame_id);
+        let override_frame_id = config.override_frame_id.clone();
+        let lookup_tolerance = config.lookup_tolerance;
+        let color = Color::Rgb(config.color.r, config.color.g, config.color.b);
+        let rate = RateMonitor::new();
+        let cb_rate = rate.clone();
+        let sub = rosrust::subscribe(
+            &config.topic,
+            2,
+            move |msg: rosrust_msg::vision_msgs::Detection3DArray| {
+                cb_rate.record();
+                cb_rate.record_stamp(msg.header.stamp);
+                let mut detections = Vec::new();
+                for detection in &msg.detections {
+                    // the detections can have their own header, otherwise the array's one
+                    let header = if detection.header.frame_id.is_empty() {
+                        &msg.header
+                    } else {
+                        &detection.header
+                    };
+                    if !frame_filter.accepts(&header.frame_id) {
+                        continue;
+                    }
+                    let transform = match lookup_transform_with_tolerance(
+                        &tf_listener,
+                        &static_frame,
+                        get_source_frame(&header.frame_id, &override_frame_id),
+                        header.stamp,
+                        lookup_tolerance,
+                    ) {
+                        Ok(transform) => transform.transform,
+                        Err(_e) => continue,
+                    };
+                    let pose = ros_transform_to_isometry(&transform)
+                        * ros_pose_to_isometry(&detection.bbox.center);
+                    let corner = pose.transform_point(&Point3::new(
+                        detection.bbox.size.x / 2.0,
+                        detection.bbox.size.y / 2.0,
+                        0.0,
+                    ));
+                    let hypothesis = detection
+                        .results
+                        .iter()
+                        .max_by(|a, b| a.score.total_cmp(&b.score))
+                        .map(|result| (result.id, result.score));
+                    detections.push(Detection {
+                        lines: get_box_lines(
+                            &pose,
+                            detection.bbox.size.x,
+                            detection.bbox.size.y,
+                            color,
+                        ),
+                        position: (corner.x, corner.y),
+                        hypothesis,
+                    });
+                }
+                // an empty array clears the previous detections
+                *cb_detections.write().unwrap() = detections;
+            },
+        )
+        .unwrap();
+
+        DetectionListener {
+            config,
+            detections,
+            rate,
+            _subscriber: sub,
+        }
+    }
+
+    /// Forgets the received detections.
+    pub fn clear(&self) {
+        self.detections.write().unwrap().clear();
+    }
+
+    /// Returns the label of a detection: the name of its class if known, otherwise its
+    /// id, and its score. None if labels are disabled or the detection has no hypothesis.
+    pub fn get_label(&self, detection: &Detection) -> Option<String> {
+        if !self.config.show_labels {
+            return None;
+        }
+        let (id, score) = detection.hypothesis?;
+        let class = usize::try_from(id)
+            .ok()
+            .and_then(|i| self.config.class_names.get(i))
+            .cloned()
+            .unwrap_or_else(|| id.to_string());
+        Some(format!("{} {:.2}", class, score))
+    }
+
+    /// Returns the opacity of a detection, scaled by its score if score_intensity is set.
+    pub fn get_opacity(&self, detection: &Detection) -> f64 {
+        match detection.hypothesis {
+            Some((_, score)) if self.config.score_intensity => {
+                self.config.opacity * score.clamp(0.0, 1.0)
+            }
+            _ => self.config.opacity,
+        }
+    }
+}
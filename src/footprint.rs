@@ -2,10 +2,16 @@ use crate::transformation;
 
 use rosrust;
 use rosrust_msg;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 
 const DEFAULT_FOOTPRINT: [[f64; 2]; 4] =
     [[0.01, 0.01], [-0.01, 0.01], [-0.01, -0.01], [0.01, -0.01]];
 
+/// Maximum angle between two points of the arcs of an inflated polygon, in radians.
+const INFLATION_ARC_STEP: f64 = std::f64::consts::PI / 8.0;
+
 pub fn get_default_footprint() -> Vec<(f64, f64)> {
     let mut result = Vec::<(f64, f64)>::new();
     for pt in DEFAULT_FOOTPRINT {
@@ -14,34 +20,225 @@ pub fn get_default_footprint() -> Vec<(f64, f64)> {
     result
 }
 
-pub fn get_footprint() -> Vec<(f64, f64)> {
-    let param = rosrust::param("/footprint");
-    let mut result = Vec::<(f64, f64)>::new();
-    match param {
-        Some(footprint) => {
-            let fb = footprint.get::<Vec<Vec<f64>>>();
-            match fb {
-                Ok(f) => {
-                    for pt in f {
-                        result.push((pt[0], pt[1]));
-                    }
-                    if result.is_empty() {
-                        println!("/footprint is empty, using default footprint.");
-                        return get_default_footprint();
-                    }
-                    result
+/// Reads a polygon, as a list of [x, y] points, from a ROS parameter.
+///
+/// Returns None if the parameter doesn't exist, is not a list of points or is empty.
+pub fn get_polygon_from_ros_param(name: &str) -> Option<Vec<(f64, f64)>> {
+    let points = rosrust::param(name)?.get::<Vec<Vec<f64>>>().ok()?;
+    if points.is_empty() || points.iter().any(|pt| pt.len() < 2) {
+        return None;
+    }
+    Some(points.iter().map(|pt| (pt[0], pt[1])).collect())
+}
+
+/// A parameter read by the polling thread, with the polygon read from it if valid.
+type PolledPolygon = (String, Option<Vec<(f64, f64)>>);
+
+/// A polygon read from a ROS parameter, e.g. the footprint of the robot.
+///
+/// The parameter can be switched at runtime among those whose name starts with a prefix,
+/// for example to compare the footprints of several costmaps. The current parameter can also
+/// be polled in a background thread, to notice when it is changed.
+pub struct ParameterPolygon {
+    /// Parameter of the current polygon.
+    pub param: String,
+    /// Prefix of the parameters that can be selected.
+    pub prefix: String,
+    pub polygon: Vec<(f64, f64)>,
+    /// Last parameter selected, even if it was not a valid polygon.
+    last_selected: String,
+    /// Parameter read by the polling thread, i.e. the current one.
+    polled_param: Arc<Mutex<String>>,
+    /// Last poll, with the parameter it was read from. None once applied.
+    polled: Arc<Mutex<Option<PolledPolygon>>>,
+    /// Whether the last read of the current parameter was a valid polygon, to warn only
+    /// once when it becomes invalid.
+    is_valid: bool,
+}
+
+impl ParameterPolygon {
+    /// Reads the polygon from the given parameter, uses the default footprint if it can't
+    /// be read.
+    ///
+    /// # Arguments
+    /// - `poll_interval`: time between two reads of the current parameter, in seconds. If
+    ///   not positive, the parameter is read only when selected.
+    pub fn new(param: &str, prefix: &str, poll_interval: f64) -> ParameterPolygon {
+        let read = get_polygon_from_ros_param(param);
+        let is_valid = read.is_some();
+        let polygon = match read {
+            Some(polygon) => polygon,
+            None => {
+                log::warn!("{} not found or invalid, using default footprint.", param);
+                get_default_footprint()
+            }
+        };
+        let polled_param = Arc::new(Mutex::new(param.to_string()));
+        let polled = Arc::new(Mutex::new(None));
+        if poll_interval > 0.0 {
+            let thread_param = polled_param.clone();
+            let thread_polled = polled.clone();
+            thread::spawn(move || {
+                while rosrust::is_ok() {
+                    thread::sleep(Duration::from_secs_f64(poll_interval));
+                    let param = thread_param.lock().unwrap().clone();
+                    let polygon = get_polygon_from_ros_param(&param);
+                    *thread_polled.lock().unwrap() = Some((param, polygon));
                 }
-                Err(_e) => {
-                    println!("/footprint not found, using default footprint.");
-                    get_default_footprint()
+            });
+        }
+        ParameterPolygon {
+            param: param.to_string(),
+            prefix: prefix.to_string(),
+            polygon,
+            last_selected: param.to_string(),
+            polled_param,
+            polled,
+            is_valid,
+        }
+    }
+
+    /// Applies the last poll of the current parameter, if any. If the parameter is no
+    /// longer a valid polygon, the last valid one is kept and a warning is logged.
+    pub fn update(&mut self) {
+        let (param, polygon) = match self.polled.lock().unwrap().take() {
+            Some(poll) => poll,
+            None => return,
+        };
+        // the poll may have started before another parameter was selected
+        if param != self.param {
+            return;
+        }
+        match polygon {
+            Some(polygon) => {
+                self.is_valid = true;
+                self.polygon = polygon;
+            }
+            None => {
+                if self.is_valid {
+                    log::warn!(
+                        "{} is no longer a valid polygon, keeping the last one.",
+                        param
+                    );
                 }
+                self.is_valid = false;
             }
         }
-        None => {
-            println!("/footprint not found, using default footprint.");
-            get_default_footprint()
+    }
+
+    /// Lists the parameters starting with the prefix, sorted by name.
+    pub fn list_params(&self) -> Vec<String> {
+        let mut params: Vec<String> = rosrust::parameters()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|name| name.starts_with(&self.prefix))
+            .collect();
+        params.sort();
+        params
+    }
+
+    /// Re-reads the polygon from another parameter. If the parameter is not a valid
+    /// polygon, the previous one is kept and a warning is logged.
+    pub fn select(&mut self, param: &str) {
+        self.last_selected = param.to_string();
+        match get_polygon_from_ros_param(param) {
+            Some(polygon) => {
+                self.param = param.to_string();
+                self.polygon = polygon;
+                self.is_valid = true;
+                *self.polled_param.lock().unwrap() = param.to_string();
+            }
+            None => {
+                log::warn!("{} is not a valid polygon, keeping {}.", param, self.param);
+            }
+        }
+    }
+
+    /// Selects the parameter following the last selected one in list_params.
+    pub fn select_next(&mut self) {
+        let params = self.list_params();
+        if params.is_empty() {
+            log::warn!("No parameter starting with {}.", self.prefix);
+            return;
+        }
+        let next = match params.iter().position(|name| *name == self.last_selected) {
+            Some(i) => (i + 1) % params.len(),
+            None => 0,
+        };
+        self.select(&params[next]);
+    }
+}
+
+/// Returns the outline of a wedge, i.e. a triangle from the origin of the frame with the
+/// given half-angle (in radians) around its x axis, whose far side is at `length`.
+pub fn get_heading_wedge(
+    tf: &rosrust_msg::geometry_msgs::Transform,
+    half_angle: f64,
+    length: f64,
+) -> Vec<(f64, f64, f64, f64)> {
+    let half_width = length * half_angle.tan();
+    get_current_footprint(
+        tf,
+        &vec![(0.0, 0.0), (length, half_width), (length, -half_width)],
+    )
+}
+
+/// Returns the outline of a polygon inflated by a distance, i.e. of the points within the
+/// distance of the polygon, with the rounded corners approximated by a few points.
+///
+/// The edges are offset outward by the distance. At a convex vertex, the offset edges are
+/// joined by an arc around the vertex. At a concave vertex, they are cut at their
+/// intersection, so that the outline doesn't cross itself as long as the distance is small
+/// compared to the edges around the concave vertices. Empty if the polygon has no area.
+pub fn get_inflated_polygon(polygon: &[(f64, f64)], distance: f64) -> Vec<(f64, f64)> {
+    // the repeated vertices have no edge to offset
+    let mut vertices: Vec<(f64, f64)> = Vec::new();
+    for pt in polygon {
+        if vertices.last() != Some(pt) {
+            vertices.push(*pt);
+        }
+    }
+    while vertices.len() > 1 && vertices.first() == vertices.last() {
+        vertices.pop();
+    }
+    let n = vertices.len();
+    let area: f64 = (0..n)
+        .map(|i| {
+            let (a, b) = (vertices[i], vertices[(i + 1) % n]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum();
+    if n < 3 || area == 0.0 || !area.is_finite() {
+        return Vec::new();
+    }
+    // the outward side of the edges depends on the orientation of the polygon
+    let orientation = area.signum();
+    let normal = |i: usize| {
+        let (a, b) = (vertices[i], vertices[(i + 1) % n]);
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let length = dx.hypot(dy);
+        (orientation * dy / length, -orientation * dx / length)
+    };
+    let mut outline = Vec::new();
+    for (i, p) in vertices.iter().enumerate() {
+        let (n0, n1) = (normal((i + n - 1) % n), normal(i));
+        let cross = n0.0 * n1.1 - n0.1 * n1.0;
+        let dot = n0.0 * n1.0 + n0.1 * n1.1;
+        if orientation * cross >= 0.0 {
+            let turn = (orientation * cross).abs().atan2(dot);
+            let start = n0.1.atan2(n0.0);
+            let steps = ((turn / INFLATION_ARC_STEP).ceil() as usize).max(1);
+            for k in 0..=steps {
+                let angle = start + orientation * turn * k as f64 / steps as f64;
+                outline.push((p.0 + distance * angle.cos(), p.1 + distance * angle.sin()));
+            }
+        } else {
+            // intersection of the offset edges, on the bisector of the vertex
+            let factor = distance / (1.0 + dot);
+            outline.push((p.0 + factor * (n0.0 + n1.0), p.1 + factor * (n0.1 + n1.1)));
         }
     }
+    outline
 }
 
 pub fn get_current_footprint(
@@ -0,0 +1,234 @@
+//! Keeps track of the frames published on the TF topics.
+//!
+//! The TfListener only allows to look up transforms between known frames, this listener
+//! collects the structure of the tree, i.e. which frames exist and what is their parent.
+//!
+//! It also records which frames are dynamic, i.e. whose transform is published on `/tf`.
+//! The other ones are static, published on `/tf_static`. This relies only on the topic:
+//! a constant transform published periodically on `/tf` is considered dynamic.
+//!
+//! The tree can be exported as a Graphviz DOT graph, like the `view_frames` tool of tf2.
+
+use nalgebra::{Quaternion, UnitQuaternion};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+/// An edge of the TF tree, from a parent frame to a child frame.
+pub struct TfEdge {
+    pub parent: String,
+    pub child: String,
+    /// Latest transform of the child frame in the parent frame, None if it is unavailable.
+    pub transform: Option<rosrust_msg::geometry_msgs::Transform>,
+    pub is_static: bool,
+}
+
+/// Returns a text quoted as a DOT identifier, its line breaks becoming DOT line breaks.
+fn quote(text: &str) -> String {
+    format!(
+        "\"{}\"",
+        text.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+/// Returns the label of an edge: the translation in meters, and the rotation as roll,
+/// pitch and yaw in degrees.
+fn get_edge_label(transform: &Option<rosrust_msg::geometry_msgs::Transform>) -> String {
+    let tf = match transform {
+        Some(tf) => tf,
+        None => return "N/A".to_string(),
+    };
+    let (roll, pitch, yaw) = UnitQuaternion::from_quaternion(Quaternion::new(
+        tf.rotation.w,
+        tf.rotation.x,
+        tf.rotation.y,
+        tf.rotation.z,
+    ))
+    .euler_angles();
+    // adding zero turns the negative zeros of the angles into positive ones
+    format!(
+        "xyz: {:.3}, {:.3}, {:.3}\nrpy: {:.1}, {:.1}, {:.1} deg",
+        tf.translation.x,
+        tf.translation.y,
+        tf.translation.z,
+        roll.to_degrees() + 0.0,
+        pitch.to_degrees() + 0.0,
+        yaw.to_degrees() + 0.0
+    )
+}
+
+/// Formats edges as a DOT graph, with one cluster per tree, i.e. per root frame, so that
+/// the disconnected trees are separate components. The dynamic edges are dashed.
+pub fn format_dot(edges: &[TfEdge]) -> String {
+    let parents: HashMap<&str, &str> = edges
+        .iter()
+        .map(|edge| (edge.child.as_str(), edge.parent.as_str()))
+        .collect();
+    let get_root = |frame: &str| {
+        let mut root = frame;
+        // at most one step per edge, in case the parents form a loop
+        for _ in 0..=parents.len() {
+            match parents.get(root) {
+                Some(parent) => root = parent,
+                None => break,
+            }
+        }
+        root.to_string()
+    };
+    let mut trees: BTreeMap<String, Vec<&TfEdge>> = BTreeMap::new();
+    for edge in edges {
+        trees.entry(get_root(&edge.child)).or_default().push(edge);
+    }
+    let mut dot = "digraph tf_tree {\n".to_string();
+    for (i, (root, edges)) in trees.iter().enumerate() {
+        dot += &format!("  subgraph cluster_{} {{\n", i);
+        dot += &format!("    label={};\n", quote(root));
+        for edge in edges {
+            dot += &format!(
+                "    {} -> {} [label={}{}];\n",
+                quote(&edge.parent),
+                quote(&edge.child),
+                quote(&get_edge_label(&edge.transform)),
+                if edge.is_static { "" } else { ", style=dashed" }
+            );
+        }
+        dot += "  }\n";
+    }
+    dot += "}\n";
+    dot
+}
+
+pub struct FramesListener {
+    /// Parent of each frame, indexed by child frame.
+    parents: Arc<RwLock<HashMap<String, String>>>,
+    /// Frames received on /tf.
+    dynamic_frames: Arc<RwLock<HashSet<String>>>,
+    _subscribers: Vec<rosrust::Subscriber>,
+}
+
+impl FramesListener {
+    pub fn new() -> FramesListener {
+        let parents = Arc::new(RwLock::new(HashMap::<String, String>::new()));
+        let dynamic_frames = Arc::new(RwLock::new(HashSet::<String>::new()));
+        let subscribers = ["tf", "tf_static"]
+            .iter()
+            .map(|topic| {
+                let cb_parents = parents.clone();
+                let cb_dynamic_frames = dynamic_frames.clone();
+                let is_dynamic = *topic == "tf";
+                rosrust::subscribe(
+                    topic,
+                    100,
+                    move |tf_msg: rosrust_msg::tf2_msgs::TFMessage| {
+                        let mut parents = cb_parents.write().unwrap();
+                        let mut dynamic_frames = cb_dynamic_frames.write().unwrap();
+                        for tf in tf_msg.transforms {
+                            if is_dynamic {
+                                dynamic_frames.insert(tf.child_frame_id.clone());
+                            }
+                            parents.insert(tf.child_frame_id, tf.header.frame_id);
+                        }
+                    },
+                )
+                .unwrap()
+            })
+            .collect();
+
+        FramesListener {
+            parents,
+            dynamic_frames,
+            _subscribers: subscribers,
+        }
+    }
+
+    /// Returns the names of all the frames seen so far, sorted alphabetically.
+    pub fn get_frame_names(&self) -> Vec<String> {
+        let parents = self.parents.read().unwrap();
+        let names: BTreeSet<&String> = parents.keys().chain(parents.values()).collect();
+        names.into_iter().cloned().collect()
+    }
+
+    /// Returns the edges of the tree, sorted by child frame, with the latest transforms.
+    pub fn get_edges(&self, tf_listener: &rustros_tf::TfListener) -> Vec<TfEdge> {
+        let parents: BTreeMap<String, String> = self
+            .parents
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(child, parent)| (child.clone(), parent.clone()))
+            .collect();
+        parents
+            .into_iter()
+            .map(|(child, parent)| TfEdge {
+                transform: tf_listener
+                    .lookup_transform(&parent, &child, rosrust::Time::new())
+                    .ok()
+                    .map(|tf| tf.transform),
+                is_static: self.is_static(&child),
+                parent,
+                child,
+            })
+            .collect()
+    }
+
+    /// Returns the tree as a DOT graph, see format_dot.
+    pub fn get_dot(&self, tf_listener: &rustros_tf::TfListener) -> String {
+        format_dot(&self.get_edges(tf_listener))
+    }
+
+    /// Returns true if the transform of the frame was never published on /tf.
+    ///
+    /// The root of the tree has no transform, it is considered static.
+    pub fn is_static(&self, frame: &str) -> bool {
+        !self.dynamic_frames.read().unwrap().contains(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_edge(parent: &str, child: &str, x: f64, is_static: bool) -> TfEdge {
+        let mut transform = rosrust_msg::geometry_msgs::Transform::default();
+        transform.translation.x = x;
+        transform.rotation.w = 1.0;
+        TfEdge {
+            parent: parent.to_string(),
+            child: child.to_string(),
+            transform: Some(transform),
+            is_static,
+        }
+    }
+
+    #[test]
+    fn dot_of_tree() {
+        let mut edges = vec![
+            get_edge("map", "odom", 1.0, true),
+            get_edge("odom", "base_link", 0.5, false),
+            get_edge("world", "camera", 0.0, true),
+        ];
+        edges[2].transform = None;
+        let dot = format_dot(&edges);
+        assert!(dot.starts_with("digraph tf_tree {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert_eq!(dot.matches('{').count(), dot.matches('}').count());
+        assert!(dot.contains(
+            "\"map\" -> \"odom\" [label=\"xyz: 1.000, 0.000, 0.000\\nrpy: 0.0, 0.0, 0.0 deg\"];"
+        ));
+        assert!(dot.contains("\"odom\" -> \"base_link\" [label=\"xyz: 0.500, 0.000, 0.000\\nrpy: 0.0, 0.0, 0.0 deg\", style=dashed];"));
+        assert!(dot.contains("\"world\" -> \"camera\" [label=\"N/A\"];"));
+        // one cluster per tree, named after its root
+        assert_eq!(dot.matches("subgraph cluster_").count(), 2);
+        assert!(dot.contains("label=\"map\";"));
+        assert!(dot.contains("label=\"world\";"));
+    }
+
+    #[test]
+    fn dot_quoting() {
+        assert_eq!(quote("base_link"), "\"base_link\"");
+        assert_eq!(quote("a\"b"), "\"a\\\"b\"");
+        assert_eq!(quote("a\\b"), "\"a\\\\b\"");
+        assert_eq!(quote("a\nb"), "\"a\\nb\"");
+    }
+}
@@ -0,0 +1,203 @@
+//! Module dealing with the visualization of vector fields over a grid, e.g. the gradient of
+//! a potential field planner, whose x and y components are published as two
+//! std_msgs::Float32MultiArray.
+//!
+//! The grids are placed in the static frame like the heatmaps, with the configured origin
+//! and resolution. The field is drawn as arrows along its direction, colored by its
+//! magnitude, and downsampled to the view so that the arrows keep the same spacing on the
+//! screen when zooming.
+
+use crate::colormap::Colormap;
+use crate::config::GradientListenerConfig;
+use crate::heatmap::{read_grid, Grid};
+use crate::rate::RateMonitor;
+use std::sync::{Arc, RwLock};
+use tui::style::Color;
+use tui::widgets::canvas::Line;
+
+/// Length of the arrows, as a fraction of the distance between two arrows.
+const ARROW_LENGTH: f64 = 0.8;
+
+/// Returns the lines of an arrow from its start, with the head at its end.
+fn get_arrow(x: f64, y: f64, angle: f64, length: f64, color: Color) -> Vec<Line> {
+    let (x2, y2) = (x + length * angle.cos(), y + length * angle.sin());
+    let mut lines = vec![Line {
+        x1: x,
+        y1: y,
+        x2,
+        y2,
+        color,
+    }];
+    for side in [-1.0, 1.0] {
+        let head_angle = angle + std::f64::consts::PI + side * 0.5;
+        lines.push(Line {
+            x1: x2,
+            y1: y2,
+            x2: x2 + length * 0.3 * head_angle.cos(),
+            y2: y2 + length * 0.3 * head_angle.sin(),
+            color,
+        });
+    }
+    lines
+}
+
+/// Returns a description of the size of a grid, as rows x columns.
+fn get_size(grid: &Grid) -> String {
+    let height = grid.values.len().checked_div(grid.width).unwrap_or(0);
+    format!("{}x{}", height, grid.width)
+}
+
+pub struct GradientListener {
+    pub config: GradientListenerConfig,
+    /// Last grids received of the x and y components.
+    components: Arc<RwLock<[Option<Grid>; 2]>>,
+    colormap: Colormap,
+    rates: [RateMonitor; 2],
+    _subscribers: Vec<rosrust::Subscriber>,
+}
+
+impl GradientListener {
+    pub fn new(config: GradientListenerConfig) -> GradientListener {
+        let components = Arc::new(RwLock::new([None::<Grid>, None]));
+        let rates = [RateMonitor::new(), RateMonitor::new()];
+        let subscribers = [&config.x_topic, &config.y_topic]
+            .iter()
+            .enumerate()
+            .map(|(i, topic)| {
+                let cb_components = components.clone();
+                let cb_rate = rates[i].clone();
+                let cb_topic = topic.to_string();
+                let cb_config = config.clone();
+                rosrust::subscribe(
+                    topic,
+                    1,
+                    move |array: rosrust_msg::std_msgs::Float32MultiArray| {
+                        cb_rate.record();
+                        let grid = match read_grid(&array) {
+                            Some(grid) => grid,
+                            None => {
+                                log::warn!(
+                                    "Ignoring the array received on {}, its layout is not a 2D grid matching its data",
+                                    cb_topic
+                                );
+                                return;
+                            }
+                        };
+                        let mut components = cb_components.write().unwrap();
+                        if let Some(other) = &components[1 - i] {
+                            if other.width != grid.width || other.values.len() != grid.values.len()
+                            {
+                                // the other component may be updated next, so the grid is kept
+                                log::warn!(
+                                    "The components of {} and {} have different sizes ({} and {}), the field is not drawn",
+                                    cb_config.x_topic,
+                                    cb_config.y_topic,
+                                    get_size(&grid),
+                                    get_size(other)
+                                );
+                            }
+                        }
+                        components[i] = Some(grid);
+                    },
+                )
+                .unwrap()
+            })
+            .collect();
+
+        GradientListener {
+            colormap: Colormap::new(&config.colormap),
+            config,
+            components,
+            rates,
+            _subscribers: subscribers,
+        }
+    }
+
+    /// Returns the rate monitors of the x and y topics, with their names.
+    pub fn get_rates(&self) -> Vec<(String, RateMonitor)> {
+        vec![
+            (self.config.x_topic.clone(), self.rates[0].clone()),
+            (self.config.y_topic.clone(), self.rates[1].clone()),
+        ]
+    }
+
+    /// Returns the arrows of the field in the view, one every `spacing` terminal cells,
+    /// colored by the magnitude of the field. Empty until both components are received, or
+    /// if their sizes differ.
+    ///
+    /// # Arguments
+    /// - `x_bounds`, `y_bounds`: bounds of the view, outside of which no arrow is drawn.
+    /// - `cell_width`: width of a terminal cell in the static frame.
+    pub fn get_lines(&self, x_bounds: [f64; 2], y_bounds: [f64; 2], cell_width: f64) -> Vec<Line> {
+        let components = self.components.read().unwrap();
+        let (x_grid, y_grid) = match &*components {
+            [Some(x_grid), Some(y_grid)]
+                if x_grid.width == y_grid.width
+                    && x_grid.values.len() == y_grid.values.len()
+                    && x_grid.width > 0 =>
+            {
+                (x_grid, y_grid)
+            }
+            _ => return Vec::new(),
+        };
+        let magnitudes: Vec<f64> = x_grid
+            .values
+            .iter()
+            .zip(y_grid.values.iter())
+            .map(|(x, y)| (*x as f64).hypot(*y as f64))
+            .collect();
+        let finite = magnitudes.iter().filter(|m| m.is_finite());
+        let [min, max] = match self.config.magnitude_range {
+            Some(range) => range,
+            None => [
+                finite.clone().fold(f64::INFINITY, |a, b| a.min(*b)),
+                finite.fold(f64::NEG_INFINITY, |a, b| a.max(*b)),
+            ],
+        };
+
+        // the cells between two arrows, at least one so that the whole field is drawn
+        let stride = ((self.config.spacing.max(1) as f64 * cell_width / self.config.resolution)
+            .ceil() as usize)
+            .max(1);
+        let length = stride as f64 * self.config.resolution * ARROW_LENGTH;
+        let (sin, cos) = self.config.origin[2].sin_cos();
+        let width = x_grid.width;
+        let height = x_grid.values.len() / width;
+        let mut lines = Vec::new();
+        for line in (0..height).step_by(stride) {
+            for column in (0..width).step_by(stride) {
+                let i = line * width + column;
+                let magnitude = magnitudes[i];
+                if !magnitude.is_finite() || magnitude == 0.0 {
+                    continue;
+                }
+                let (x, y) = (
+                    column as f64 * self.config.resolution,
+                    line as f64 * self.config.resolution,
+                );
+                let (x, y) = (
+                    self.config.origin[0] + cos * x - sin * y,
+                    self.config.origin[1] + sin * x + cos * y,
+                );
+                if x < x_bounds[0] || x > x_bounds[1] || y < y_bounds[0] || y > y_bounds[1] {
+                    continue;
+                }
+                let fraction = if max > min {
+                    (magnitude - min) / (max - min)
+                } else {
+                    0.5
+                };
+                let angle = (y_grid.values[i] as f64).atan2(x_grid.values[i] as f64)
+                    + self.config.origin[2];
+                lines.extend(get_arrow(
+                    x,
+                    y,
+                    angle,
+                    length,
+                    self.colormap.color_at(fraction),
+                ));
+            }
+        }
+        lines
+    }
+}
@@ -0,0 +1,196 @@
+//! Module dealing with the visualization of 2D arrays, e.g. traversability or prediction
+//! grids published as std_msgs::Float32MultiArray.
+//!
+//! The array has no header, so the grid is placed in the static frame with the configured
+//! origin and resolution. Like a map, the first row of the grid is at the origin and the
+//! rows go along the y axis.
+
+use crate::colormap::Colormap;
+use crate::config::HeatmapListenerConfig;
+use crate::map::ColoredPoints;
+use crate::rate::RateMonitor;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+/// Number of colors of a heatmap, the values are grouped by color to limit the number of
+/// draw calls.
+pub const COLOR_LEVELS: f64 = 255.0;
+
+/// A 2D grid read from an array.
+pub struct Grid {
+    pub width: usize,
+    /// The values, row-major.
+    pub values: Vec<f32>,
+}
+
+/// Reconstructs the 2D grid of a row-major array from its layout.
+///
+/// The first dimension gives the rows and the second one the columns. The stride of the
+/// second dimension is the distance between two rows, the number of columns if it is not
+/// set. Returns None if the array has less than 2 dimensions or not enough data.
+pub fn read_grid(array: &rosrust_msg::std_msgs::Float32MultiArray) -> Option<Grid> {
+    let dim = &array.layout.dim;
+    if dim.len() < 2 {
+        return None;
+    }
+    let height = dim[0].size as usize;
+    let width = dim[1].size as usize;
+    let row_step = match dim[1].stride as usize {
+        0 => width,
+        stride => stride,
+    };
+    let offset = array.layout.data_offset as usize;
+    if width == 0 || height == 0 {
+        return Some(Grid {
+            width,
+            values: Vec::new(),
+        });
+    }
+    if row_step < width || array.data.len() < offset + (height - 1) * row_step + width {
+        return None;
+    }
+    let values = (0..height)
+        .flat_map(|line| {
+            let start = offset + line * row_step;
+            array.data[start..start + width].iter().copied()
+        })
+        .collect();
+    Some(Grid { width, values })
+}
+
+/// Computes the points of the cells of a grid in the static frame, grouped by color.
+/// The cells that are not finite (e.g. NaN for no data) are not drawn.
+///
+/// # Arguments
+/// - `grid`: the grid to draw.
+/// - `config`: origin and resolution of the grid, and its colors.
+/// - `colormap`: colormap of the values, from the minimum to the maximum of the range.
+pub fn get_points(
+    grid: &Grid,
+    config: &HeatmapListenerConfig,
+    colormap: &Colormap,
+) -> ColoredPoints {
+    let finite = grid.values.iter().filter(|v| v.is_finite());
+    let [min, max] = match config.value_range {
+        Some(range) => range,
+        None => [
+            finite.clone().fold(f32::INFINITY, |a, b| a.min(*b)) as f64,
+            finite.fold(f32::NEG_INFINITY, |a, b| a.max(*b)) as f64,
+        ],
+    };
+    let (sin, cos) = config.origin[2].sin_cos();
+
+    let mut points: BTreeMap<u8, Vec<(f64, f64)>> = BTreeMap::new();
+    for (i, value) in grid.values.iter().enumerate() {
+        if !value.is_finite() {
+            continue;
+        }
+        let fraction = if max > min {
+            ((*value as f64 - min) / (max - min)).clamp(0.0, 1.0)
+        } else {
+            0.5
+        };
+        let (column, line) = ((i % grid.width) as f64, (i / grid.width) as f64);
+        let (x, y) = (column * config.resolution, line * config.resolution);
+        points
+            .entry((fraction * COLOR_LEVELS).round() as u8)
+            .or_default()
+            .push((
+                config.origin[0] + cos * x - sin * y,
+                config.origin[1] + sin * x + cos * y,
+            ));
+    }
+    points
+        .into_iter()
+        .map(|(level, coords)| (colormap.color_at(level as f64 / COLOR_LEVELS), coords))
+        .collect()
+}
+
+pub struct HeatmapListener {
+    pub config: HeatmapListenerConfig,
+    pub points: Arc<RwLock<ColoredPoints>>,
+    pub rate: RateMonitor,
+    _subscriber: rosrust::Subscriber,
+}
+
+impl HeatmapListener {
+    pub fn new(config: HeatmapListenerConfig) -> HeatmapListener {
+        let points = Arc::new(RwLock::new(ColoredPoints::new()));
+        let cb_points = points.clone();
+        let rate = RateMonitor::new();
+        let cb_rate = rate.clone();
+        let cb_config = config.clone();
+        let colormap = Colormap::new(&config.colormap);
+        let sub = rosrust::subscribe(
+            &config.topic,
+            1,
+            move |array: rosrust_msg::std_msgs::Float32MultiArray| {
+                cb_rate.record();
+                match read_grid(&array) {
+                    Some(grid) => {
+                        *cb_points.write().unwrap() = get_points(&grid, &cb_config, &colormap)
+                    }
+                    None => {
+                        log::warn!(
+                            "Ignoring the array received on {}, its layout is not a 2D grid matching its data",
+                            cb_config.topic
+                        );
+                    }
+                }
+            },
+        )
+        .unwrap();
+
+        HeatmapListener {
+            config,
+            points,
+            rate,
+            _subscriber: sub,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rosrust_msg::std_msgs::{Float32MultiArray, MultiArrayDimension};
+
+    /// Returns an array of 2 rows of 3 columns, with the given stride between rows.
+    fn get_array(data: Vec<f32>, data_offset: u32, row_stride: u32) -> Float32MultiArray {
+        let mut array = Float32MultiArray {
+            data,
+            ..Default::default()
+        };
+        array.layout.data_offset = data_offset;
+        array.layout.dim = vec![
+            MultiArrayDimension {
+                label: "height".to_string(),
+                size: 2,
+                stride: 2 * row_stride,
+            },
+            MultiArrayDimension {
+                label: "width".to_string(),
+                size: 3,
+                stride: row_stride,
+            },
+        ];
+        array
+    }
+
+    #[test]
+    fn grid_row_major() {
+        let grid = read_grid(&get_array(vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0], 0, 3)).unwrap();
+        assert_eq!(grid.width, 3);
+        assert_eq!(grid.values, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn grid_with_offset_and_padding() {
+        // two values before the grid, and one of padding at the end of each row
+        let data = vec![9.0, 9.0, 0.0, 1.0, 2.0, 9.0, 3.0, 4.0, 5.0];
+        let grid = read_grid(&get_array(data, 2, 4)).unwrap();
+        assert_eq!(grid.values, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+        // the last row doesn't fit in the data
+        assert!(read_grid(&get_array(vec![0.0; 5], 1, 3)).is_none());
+    }
+}
@@ -0,0 +1,214 @@
+//! View-only support for interactive markers.
+//!
+//! This module does NOT implement the interaction protocol: no feedback is ever sent to
+//! the server, so the markers can't be moved or clicked from termviz. It only follows the
+//! `update_full` and `update` topics of a server to show where its markers are, drawing
+//! the axes of each interactive marker and the markers embedded in its controls.
+
+use crate::config::ListenerConfig;
+use crate::listeners::FrameFilter;
+use crate::marker::get_marker_lines;
+use crate::rate::RateMonitor;
+use crate::transformation::{lookup_transform_with_tolerance, ros_pose_to_isometry};
+use nalgebra::geometry::Isometry3;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tui::style::Color;
+use tui::widgets::canvas::Line;
+
+fn isometry_to_ros_pose(iso: &Isometry3<f64>) -> rosrust_msg::geometry_msgs::Pose {
+    let q = iso.rotation.quaternion();
+    rosrust_msg::geometry_msgs::Pose {
+        position: rosrust_msg::geometry_msgs::Point {
+            x: iso.translation.x,
+            y: iso.translation.y,
+            z: iso.translation.z,
+        },
+        orientation: rosrust_msg::geometry_msgs::Quaternion {
+            x: q.i,
+            y: q.j,
+            z: q.k,
+            w: q.w,
+        },
+    }
+}
+
+/// Projects an interactive marker in the static frame: the axes of its pose and the
+/// markers of its controls, whose poses are relative to the interactive marker.
+fn get_interactive_marker_lines(
+    marker: &rosrust_msg::visualization_msgs::InteractiveMarker,
+    tf_listener: &rustros_tf::TfListener,
+    static_frame: &str,
+    lookup_tolerance: f64,
+) -> Vec<Line> {
+    let tf = match lookup_transform_with_tolerance(
+        tf_listener,
+        &marker.header.frame_id,
+        static_frame,
+        marker.header.stamp,
+        lookup_tolerance,
+    ) {
+        Ok(tf) => tf.transform,
+        Err(_e) => return Vec::new(),
+    };
+    let marker_pose = ros_pose_to_isometry(&marker.pose);
+
+    let mut lines = Vec::new();
+    for control in &marker.controls {
+        for control_marker in &control.markers {
+            let mut control_marker = control_marker.clone();
+            control_marker.header = marker.header.clone();
+            control_marker.pose =
+                isometry_to_ros_pose(&(marker_pose * ros_pose_to_isometry(&control_marker.pose)));
+            lines.extend(get_marker_lines(&control_marker, &tf));
+        }
+    }
+
+    // The axes are projected as a line list marker, x then y.
+    let scale = marker.scale as f64;
+    let origin = rosrust_msg::geometry_msgs::Point::default();
+    let axes = rosrust_msg::visualization_msgs::Marker {
+        header: marker.header.clone(),
+        pose: marker.pose.clone(),
+        type_: rosrust_msg::visualization_msgs::Marker::LINE_LIST as i32,
+        points: vec![
+            origin.clone(),
+            rosrust_msg::geometry_msgs::Point {
+                x: scale,
+                y: 0.0,
+                z: 0.0,
+            },
+            origin,
+            rosrust_msg::geometry_msgs::Point {
+                x: 0.0,
+                y: scale,
+                z: 0.0,
+            },
+        ],
+        ..Default::default()
+    };
+    for (mut line, color) in get_marker_lines(&axes, &tf)
+        .into_iter()
+        .zip([Color::Red, Color::Green])
+    {
+        line.color = color;
+        lines.push(line);
+    }
+    lines
+}
+
+struct InteractiveMarkerData {
+    marker: rosrust_msg::visualization_msgs::InteractiveMarker,
+    lines: Vec<Line>,
+}
+
+pub struct InteractiveMarkersListener {
+    pub config: ListenerConfig,
+    pub rate: RateMonitor,
+    markers: Arc<RwLock<HashMap<String, InteractiveMarkerData>>>,
+    _subscribers: Vec<rosrust::Subscriber>,
+}
+
+impl InteractiveMarkersListener {
+    /// Follows an interactive marker server.
+    ///
+    /// # Arguments
+    /// - `config`: configuration whose topic is the namespace of the server, i.e., the
+    ///   topics `<topic>/update_full` and `<topic>/update` are used.
+    pub fn new(
+        config: ListenerConfig,
+        tf_listener: Arc<rustros_tf::TfListener>,
+        static_frame: String,
+    ) -> InteractiveMarkersListener {
+        let markers = Arc::new(RwLock::new(HashMap::<String, InteractiveMarkerData>::new()));
+        let rate = RateMonitor::new();
+        let topic = config.topic.trim_end_matches('/');
+        let lookup_tolerance = config.lookup_tolerance;
+
+        let full_markers = markers.clone();
+        let full_tf_listener = tf_listener.clone();
+        let full_static_frame = static_frame.clone();
+        let full_frame_filter = FrameFilter::new(&config.frame_filter);
+        let full_sub = rosrust::subscribe(
+            &format!("{}/update_full", topic),
+            1,
+            move |init: rosrust_msg::visualization_msgs::InteractiveMarkerInit| {
+                let mut markers = full_markers.write().unwrap();
+                markers.clear();
+                for marker in init.markers {
+                    if !full_frame_filter.accepts(&marker.header.frame_id) {
+                        continue;
+                    }
+                    let lines = get_interactive_marker_lines(
+                        &marker,
+                        &full_tf_listener,
+                        &full_static_frame,
+                        lookup_tolerance,
+                    );
+                    markers.insert(marker.name.clone(), InteractiveMarkerData { marker, lines });
+                }
+            },
+        )
+        .unwrap();
+
+        let update_markers = markers.clone();
+        let update_frame_filter = FrameFilter::new(&config.frame_filter);
+        let cb_rate = rate.clone();
+        let update_sub = rosrust::subscribe(
+            &format!("{}/update", topic),
+            10,
+            move |update: rosrust_msg::visualization_msgs::InteractiveMarkerUpdate| {
+                cb_rate.record();
+                if update.type_ != rosrust_msg::visualization_msgs::InteractiveMarkerUpdate::UPDATE
+                {
+                    return; // keep alive
+                }
+                let mut markers = update_markers.write().unwrap();
+                for marker in update.markers {
+                    if !update_frame_filter.accepts(&marker.header.frame_id) {
+                        continue;
+                    }
+                    let lines = get_interactive_marker_lines(
+                        &marker,
+                        &tf_listener,
+                        &static_frame,
+                        lookup_tolerance,
+                    );
+                    markers.insert(marker.name.clone(), InteractiveMarkerData { marker, lines });
+                }
+                for pose in update.poses {
+                    if let Some(data) = markers.get_mut(&pose.name) {
+                        data.marker.header = pose.header;
+                        data.marker.pose = pose.pose;
+                        data.lines = get_interactive_marker_lines(
+                            &data.marker,
+                            &tf_listener,
+                            &static_frame,
+                            lookup_tolerance,
+                        );
+                    }
+                }
+                for name in update.erases {
+                    markers.remove(&name);
+                }
+            },
+        )
+        .unwrap();
+
+        InteractiveMarkersListener {
+            config,
+            rate,
+            markers,
+            _subscribers: vec![full_sub, update_sub],
+        }
+    }
+
+    pub fn get_lines(&self) -> Vec<Line> {
+        self.markers
+            .read()
+            .unwrap()
+            .values()
+            .flat_map(|data| data.lines.iter().cloned())
+            .collect()
+    }
+}
@@ -1,68 +1,432 @@
-use crate::config::ListenerConfigColor;
+use crate::config::{Color as ConfigColor, LaserListenerConfig};
+use crate::listeners::{get_source_frame, warn_frame_override, FrameFilter};
+use crate::rate::RateMonitor;
 use crate::transformation;
+use crate::transformation::lookup_transform_with_tolerance;
+use std::f64::consts::PI;
 use std::sync::{Arc, RwLock};
 
 use rosrust;
 use rustros_tf;
 
+/// Returns true if angle_max matches the angle of the last beam computed from angle_min
+/// and angle_increment, up to one increment.
+fn has_consistent_angles(scan: &rosrust_msg::sensor_msgs::LaserScan) -> bool {
+    if scan.ranges.len() < 2 {
+        return true;
+    }
+    let last_angle = scan.angle_min + (scan.ranges.len() - 1) as f32 * scan.angle_increment;
+    (last_angle - scan.angle_max).abs() <= scan.angle_increment.abs().max(1e-3)
+}
+
+/// Computes the angle of each beam as angle_min + i * angle_increment.
+///
+/// If `single_turn` is true, the angles are wrapped in [-pi, pi) and the beams beyond
+/// a full turn from angle_min are discarded (None), so that they don't overlap.
+fn get_beam_angles(
+    scan: &rosrust_msg::sensor_msgs::LaserScan,
+    single_turn: bool,
+) -> Vec<Option<f64>> {
+    (0..scan.ranges.len())
+        .map(|i| {
+            let offset = i as f64 * scan.angle_increment as f64;
+            let angle = scan.angle_min as f64 + offset;
+            if !angle.is_finite() {
+                return None;
+            }
+            if !single_turn {
+                return Some(angle);
+            }
+            if offset.abs() >= 2.0 * PI {
+                return None;
+            }
+            Some((angle + PI).rem_euclid(2.0 * PI) - PI)
+        })
+        .collect()
+}
+
+/// Returns true if an angle is within the window [min, max], in radians.
+///
+/// The angles are wrapped in [-pi, pi), so a window whose min is greater than its max
+/// crosses the pi boundary, e.g. [3pi/4, -3pi/4] keeps the beams toward the rear.
+fn is_in_window(angle: f64, window: [f64; 2]) -> bool {
+    let wrap = |angle: f64| (angle + PI).rem_euclid(2.0 * PI) - PI;
+    let (angle, min, max) = (wrap(angle), wrap(window[0]), wrap(window[1]));
+    if min <= max {
+        min <= angle && angle <= max
+    } else {
+        angle >= min || angle <= max
+    }
+}
+
+/// Number of segments of the polyline approximating the range_min circle.
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// Returns the endpoint of a beam in the static frame, so that the scans of the sources
+/// of a layer, each from its own sensor frame, land in the same frame.
+///
+/// # Arguments
+/// - `transform`: transform of the frame of the scan in the static frame.
+/// - `range`: range of the beam.
+/// - `angle`: angle of the beam in the frame of the scan, in radians.
+fn get_beam_endpoint(
+    transform: &rosrust_msg::geometry_msgs::Transform,
+    range: f32,
+    angle: f64,
+) -> (f64, f64) {
+    transformation::transform_relative_pt(
+        transform,
+        (range as f64 * angle.cos(), range as f64 * angle.sin()),
+    )
+}
+
+/// Returns the closed polyline of the range_min circle of a scan, i.e. of the given radius
+/// around the sensor, in the static frame. The circle is in the plane of the scan, like its
+/// beams.
+///
+/// # Arguments
+/// - `transform`: transform of the frame of the scan in the static frame.
+/// - `radius`: range_min of the scan.
+fn get_min_range_circle(
+    transform: &rosrust_msg::geometry_msgs::Transform,
+    radius: f64,
+) -> Vec<(f64, f64)> {
+    (0..=CIRCLE_SEGMENTS)
+        .map(|i| {
+            let angle = 2.0 * PI * i as f64 / CIRCLE_SEGMENTS as f64;
+            transformation::transform_relative_pt(
+                transform,
+                (radius * angle.cos(), radius * angle.sin()),
+            )
+        })
+        .collect()
+}
+
+/// Endpoints of consecutive beams connected by segments.
+type Chain = Vec<(f64, f64)>;
+
+/// Splits the endpoints of consecutive beams into chains to draw as connected segments.
+///
+/// A chain is broken by a beam without endpoint (None), e.g. out of range, and where the
+/// distance between two consecutive endpoints is larger than `gap`, e.g. between two
+/// objects at different ranges.
+fn split_into_chains(endpoints: &[Option<(f64, f64)>], gap: f64) -> Vec<Chain> {
+    let mut chains: Vec<Chain> = Vec::new();
+    let mut chain_open = false;
+    for endpoint in endpoints {
+        let pt = match endpoint {
+            Some(pt) => *pt,
+            None => {
+                chain_open = false;
+                continue;
+            }
+        };
+        match chains.last_mut() {
+            Some(chain)
+                if chain_open
+                    && chain
+                        .last()
+                        .is_some_and(|last| (pt.0 - last.0).hypot(pt.1 - last.1) <= gap) =>
+            {
+                chain.push(pt)
+            }
+            _ => chains.push(vec![pt]),
+        }
+        chain_open = true;
+    }
+    chains
+}
+
+/// Points of one of the scan topics of a layer, in the static frame.
+#[derive(Default)]
+pub struct SourceScan {
+    pub points: Vec<(f64, f64)>,
+    /// Endpoints of the beams split into chains if connect_gap is set, see
+    /// split_into_chains.
+    pub chains: Vec<Chain>,
+    /// Position of the sensor, from which the rays of the fan start.
+    pub origin: (f64, f64),
+    /// Endpoints of the decimated beams if the fan is set.
+    pub fan: Vec<(f64, f64)>,
+    /// Circle of the range_min of the scan around the sensor if min_range is set, closed.
+    pub min_range: Vec<(f64, f64)>,
+}
+
 pub struct LaserListener {
-    pub config: ListenerConfigColor,
+    pub config: LaserListenerConfig,
+    /// Points of all the sources.
     pub points: Arc<RwLock<Vec<(f64, f64)>>>,
+    /// Last scan of each source: the topic of the layer, then its additional sources.
+    pub scans: Arc<RwLock<Vec<SourceScan>>>,
+    pub rate: RateMonitor,
     _tf_listener: Arc<rustros_tf::TfListener>,
     _static_frame: String,
-    _subscriber: rosrust::Subscriber,
+    _subscribers: Vec<rosrust::Subscriber>,
 }
 
 impl LaserListener {
     pub fn new(
-        config: ListenerConfigColor,
+        config: LaserListenerConfig,
         tf_listener: Arc<rustros_tf::TfListener>,
         static_frame: String,
     ) -> LaserListener {
         let scan_points = Arc::new(RwLock::new(Vec::<(f64, f64)>::new()));
-        let cb_scan_points = scan_points.clone();
-        let str_ = static_frame.clone();
-
-        let local_listener = tf_listener.clone();
-        let laser_sub = rosrust::subscribe(
-            &config.topic,
-            2,
-            move |scan: rosrust_msg::sensor_msgs::LaserScan| {
-                let mut points: Vec<(f64, f64)> = Vec::new();
-                let res = local_listener.lookup_transform(
-                    &str_,
-                    &scan.header.frame_id,
-                    scan.header.stamp,
-                );
-                match &res {
-                    Ok(res) => res,
-                    Err(_e) => return,
-                };
-                for (i, range) in scan.ranges.iter().enumerate() {
-                    let angle = scan.angle_min + i as f32 * scan.angle_increment;
-                    let pt = transformation::transform_relative_pt(
-                        &res.as_ref().unwrap().transform,
-                        (
-                            *range as f64 * angle.cos() as f64,
-                            *range as f64 * angle.sin() as f64,
-                        ),
-                    );
-                    if range > &scan.range_min {
-                        points.push(pt);
-                    }
-                }
-                let mut cb_scan_points = cb_scan_points.write().unwrap();
-                *cb_scan_points = points;
-            },
-        )
-        .unwrap();
+        let topics: Vec<String> = std::iter::once(config.topic.clone())
+            .chain(config.sources.iter().map(|source| source.topic.clone()))
+            .collect();
+        let scans = Arc::new(RwLock::new(
+            topics
+                .iter()
+                .map(|_| SourceScan::default())
+                .collect::<Vec<_>>(),
+        ));
+        let frame_filter = Arc::new(FrameFilter::new(&config.frame_filter));
+        warn_frame_override(&config.topic, &config.override_frame_id);
+        let angle_window = config
+            .angle_window
+            .map(|window| [window[0].to_radians(), window[1].to_radians()]);
+        let rate = RateMonitor::new();
+        // each source is transformed from its own frame, and filtered with its own ranges
+        let subscribers = topics
+            .iter()
+            .enumerate()
+            .map(|(index, topic)| {
+                let cb_scan_points = scan_points.clone();
+                let cb_scans = scans.clone();
+                let str_ = static_frame.clone();
+                let local_listener = tf_listener.clone();
+                let frame_filter = frame_filter.clone();
+                let override_frame_id = config.override_frame_id.clone();
+                let connect_gap = config.connect_gap;
+                let lookup_tolerance = config.lookup_tolerance;
+                let normalize_angles = config.normalize_angles;
+                let fan_decimation = config.fan.as_ref().map(|fan| fan.decimation.max(1));
+                let has_min_range = config.min_range.is_some();
+                let cb_rate = rate.clone();
+                rosrust::subscribe(
+                    topic,
+                    2,
+                    move |scan: rosrust_msg::sensor_msgs::LaserScan| {
+                        cb_rate.record();
+                        cb_rate.record_stamp(scan.header.stamp);
+                        if !frame_filter.accepts(&scan.header.frame_id) {
+                            return;
+                        }
+                        let mut points: Vec<(f64, f64)> = Vec::new();
+                        let res = lookup_transform_with_tolerance(
+                            &local_listener,
+                            &str_,
+                            get_source_frame(&scan.header.frame_id, &override_frame_id),
+                            scan.header.stamp,
+                            lookup_tolerance,
+                        );
+                        match &res {
+                            Ok(res) => res,
+                            Err(_e) => return,
+                        };
+                        if !has_consistent_angles(&scan) {
+                            log::warn!(
+                                "Inconsistent angles in scan from {}: angle_max is {} but the last beam is at {}.",
+                                scan.header.frame_id,
+                                scan.angle_max,
+                                scan.angle_min
+                                    + (scan.ranges.len() - 1) as f32 * scan.angle_increment
+                            );
+                        }
+                        let angles = get_beam_angles(&scan, normalize_angles);
+                        // the endpoint of each beam in order, None for the beams breaking a chain
+                        let mut endpoints: Vec<Option<(f64, f64)>> = Vec::new();
+                        let mut fan = Vec::new();
+                        for (beam, (range, angle)) in scan.ranges.iter().zip(angles).enumerate() {
+                            let angle = match angle {
+                                Some(angle) => angle,
+                                None => continue,
+                            };
+                            if angle_window.is_some_and(|window| !is_in_window(angle, window)) {
+                                continue;
+                            }
+                            let pt =
+                                get_beam_endpoint(&res.as_ref().unwrap().transform, *range, angle);
+                            if range > &scan.range_min {
+                                points.push(pt);
+                            }
+                            let in_range = range.is_finite()
+                                && *range > scan.range_min
+                                && *range <= scan.range_max;
+                            endpoints.push(if in_range { Some(pt) } else { None });
+                            // decimated by beam index, so that the rays stay evenly spread
+                            if in_range && fan_decimation.is_some_and(|n| beam % n == 0) {
+                                fan.push(pt);
+                            }
+                        }
+                        let origin = transformation::transform_relative_pt(
+                            &res.as_ref().unwrap().transform,
+                            (0.0, 0.0),
+                        );
+                        let min_range = if has_min_range && scan.range_min > 0.0 {
+                            get_min_range_circle(
+                                &res.as_ref().unwrap().transform,
+                                scan.range_min as f64,
+                            )
+                        } else {
+                            Vec::new()
+                        };
+                        let chains = match connect_gap {
+                            Some(gap) => split_into_chains(&endpoints, gap),
+                            None => Vec::new(),
+                        };
+                        let mut scans = cb_scans.write().unwrap();
+                        scans[index] = SourceScan {
+                            points,
+                            chains,
+                            origin,
+                            fan,
+                            min_range,
+                        };
+                        *cb_scan_points.write().unwrap() = scans
+                            .iter()
+                            .flat_map(|scan| scan.points.iter().cloned())
+                            .collect();
+                    },
+                )
+                .unwrap()
+            })
+            .collect();
 
         LaserListener {
             config,
             points: scan_points,
+            scans,
+            rate,
             _tf_listener: tf_listener.clone(),
             _static_frame: static_frame.to_string(),
-            _subscriber: laser_sub,
+            _subscribers: subscribers,
+        }
+    }
+
+    /// Returns the color of a source, the color of the layer if it has none.
+    pub fn get_source_color(&self, index: usize) -> ConfigColor {
+        index
+            .checked_sub(1)
+            .and_then(|i| self.config.sources.get(i))
+            .and_then(|source| source.color.clone())
+            .unwrap_or_else(|| self.config.color.clone())
+    }
+
+    /// Forgets the received scans.
+    pub fn clear(&self) {
+        self.points.write().unwrap().clear();
+        for scan in self.scans.write().unwrap().iter_mut() {
+            *scan = SourceScan::default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_beyond_a_turn() {
+        // 12 beams every 45° span more than a turn, while angle_max claims half a turn
+        let scan = rosrust_msg::sensor_msgs::LaserScan {
+            angle_min: 0.0,
+            angle_max: std::f32::consts::PI,
+            angle_increment: std::f32::consts::FRAC_PI_4,
+            ranges: vec![1.0; 12],
+            ..Default::default()
+        };
+        assert!(!has_consistent_angles(&scan));
+        let angles = get_beam_angles(&scan, true);
+        assert!(angles[..8].iter().all(|angle| angle.is_some()));
+        assert!(angles[8..].iter().all(|angle| angle.is_none()));
+        assert!(get_beam_angles(&scan, false)
+            .iter()
+            .all(|angle| angle.is_some()));
+    }
+
+    #[test]
+    fn beams_in_window() {
+        let degrees = |angle: f64| angle.to_radians();
+        // a beam every 30° over a turn, from -180°
+        let angles: Vec<f64> = (0..12).map(|i| degrees(-180.0 + 30.0 * i as f64)).collect();
+        let kept = |window: [f64; 2]| -> Vec<usize> {
+            (0..angles.len())
+                .filter(|i| is_in_window(angles[*i], window))
+                .collect()
+        };
+        // -30°, 0° and 30°
+        assert_eq!(kept([degrees(-45.0), degrees(45.0)]), vec![5, 6, 7]);
+        // a window crossing ±180° keeps -180°, -150° and 150°
+        assert_eq!(kept([degrees(135.0), degrees(-135.0)]), vec![0, 1, 11]);
+        // the angles beyond a turn are wrapped
+        assert!(is_in_window(
+            degrees(360.0),
+            [degrees(-45.0), degrees(45.0)]
+        ));
+    }
+
+    #[test]
+    fn chains_split_at_gaps() {
+        let endpoints = [
+            Some((0.0, 0.0)),
+            Some((0.1, 0.0)),
+            Some((0.2, 0.0)),
+            // a jump larger than the gap
+            Some((1.0, 0.0)),
+            Some((1.1, 0.0)),
+        ];
+        let chains = split_into_chains(&endpoints, 0.5);
+        assert_eq!(chains.len(), 2);
+        assert_eq!(chains[0], vec![(0.0, 0.0), (0.1, 0.0), (0.2, 0.0)]);
+        assert_eq!(chains[1], vec![(1.0, 0.0), (1.1, 0.0)]);
+        // a beam without endpoint breaks the chain, even between close endpoints
+        let endpoints = [Some((0.0, 0.0)), None, Some((0.1, 0.0))];
+        assert_eq!(split_into_chains(&endpoints, 0.5).len(), 2);
+    }
+
+    /// Returns the transform of a sensor in the static frame, with a yaw in radians.
+    fn get_transform(x: f64, y: f64, yaw: f64) -> rosrust_msg::geometry_msgs::Transform {
+        let mut transform = rosrust_msg::geometry_msgs::Transform::default();
+        transform.translation.x = x;
+        transform.translation.y = y;
+        transform.rotation.z = (yaw / 2.0).sin();
+        transform.rotation.w = (yaw / 2.0).cos();
+        transform
+    }
+
+    #[test]
+    fn min_range_circle_around_sensor() {
+        let transform = get_transform(2.0, -1.0, 0.7);
+        let circle = get_min_range_circle(&transform, 0.3);
+        assert_eq!(circle.len(), CIRCLE_SEGMENTS + 1);
+        let (first, last) = (circle[0], circle[CIRCLE_SEGMENTS]);
+        assert!((first.0 - last.0).hypot(first.1 - last.1) < 1e-9);
+        let origin = transformation::transform_relative_pt(&transform, (0.0, 0.0));
+        assert_eq!(origin, (2.0, -1.0));
+        for (x, y) in circle {
+            assert!(((x - origin.0).hypot(y - origin.1) - 0.3).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn sources_from_different_frames_align() {
+        // two sensors see the same obstacle at (3, 2) in the static frame
+        let front = get_transform(1.0, 0.0, 0.0);
+        let rear = get_transform(4.0, 4.0, std::f64::consts::FRAC_PI_2);
+        // in the frame of the rear sensor, turned by 90°, the obstacle is at (-2, 1)
+        let beams = [
+            (&front, 8f32.sqrt(), std::f64::consts::FRAC_PI_4),
+            (&rear, 5f32.sqrt(), 1f64.atan2(-2.0)),
+        ];
+        for (transform, range, angle) in beams {
+            let (x, y) = get_beam_endpoint(transform, range, angle);
+            assert!(
+                (x - 3.0).abs() < 1e-6 && (y - 2.0).abs() < 1e-6,
+                "({}, {})",
+                x,
+                y
+            );
         }
     }
 }
@@ -1,37 +1,185 @@
+use crate::accel;
 use crate::config::{
-    ListenerConfig, ListenerConfigColor, MapListenerConfig, PointCloud2ListenerConfig,
-    PoseListenerConfig,
+    AccelListenerConfig, CorrespondenceConfig, DetectionListenerConfig, GradientListenerConfig,
+    HeatmapListenerConfig, ImageOverlayConfig, LaserListenerConfig, ListenerConfig,
+    MapListenerConfig, MarkerArrayListenerConfig, NavSatListenerConfig, PointCloud2ListenerConfig,
+    PolygonListenerConfig, PoseListenerConfig, RobotLinkConfig, SegmentListenerConfig,
+    TfComparisonConfig, ThumbnailConfig,
 };
+use crate::correspondence;
+use crate::detection;
+use crate::frames;
+use crate::gradient;
+use crate::heatmap;
+use crate::interactive_marker;
 use crate::laser;
 use crate::map;
 use crate::marker;
+use crate::navsat;
+use crate::overlay;
 use crate::pointcloud;
+use crate::polygon;
 use crate::pose;
+use crate::rate::RateMonitor;
+use crate::robot_model;
+use crate::segment;
+use crate::tf_compare;
+use crate::thumbnail;
 
+use regex::Regex;
 use std::sync::Arc;
+use tui::widgets::canvas::Line;
+
+/// Filters the received messages on their frame_id.
+///
+/// This is a display-side filter, not a remap: messages whose header.frame_id doesn't
+/// match are simply ignored and don't update the stored state of the listener.
+/// The filter is either a frame name or a regex, and must match the whole frame_id. An
+/// invalid regex is taken as a frame name, with an error.
+#[derive(Clone)]
+pub struct FrameFilter {
+    regex: Option<Regex>,
+}
+
+impl FrameFilter {
+    pub fn new(filter: &Option<String>) -> FrameFilter {
+        FrameFilter {
+            regex: filter.as_ref().map(|f| {
+                Regex::new(&format!("^(?:{})$", f)).unwrap_or_else(|e| {
+                    log::error!(
+                        "Invalid frame_filter {:?}, matching it as a frame name: {}",
+                        f,
+                        e
+                    );
+                    Regex::new(&format!("^{}$", regex::escape(f))).unwrap()
+                })
+            }),
+        }
+    }
+
+    /// Returns true if a message with the given frame_id should be processed.
+    pub fn accepts(&self, frame_id: &str) -> bool {
+        match &self.regex {
+            Some(regex) => regex.is_match(frame_id),
+            None => true,
+        }
+    }
+}
+
+/// Returns the frame in which the data of a message is transformed: the override of the
+/// layer if set, otherwise the frame of its header.
+pub fn get_source_frame<'a>(
+    header_frame: &'a str,
+    override_frame_id: &'a Option<String>,
+) -> &'a str {
+    override_frame_id.as_deref().unwrap_or(header_frame)
+}
+
+/// Warns that the frame of the headers of a layer is overridden, so that a debugging
+/// override isn't forgotten in a configuration.
+pub fn warn_frame_override(topic: &str, override_frame_id: &Option<String>) {
+    if let Some(frame) = override_frame_id {
+        log::warn!(
+            "The frame_id of the messages on {} is overridden with {}, their headers are ignored",
+            topic,
+            frame
+        );
+    }
+}
+
+/// Returns the endpoints of the lines.
+fn get_line_points(lines: &[Line]) -> Vec<(f64, f64)> {
+    lines
+        .iter()
+        .flat_map(|line| [(line.x1, line.y1), (line.x2, line.y2)])
+        .collect()
+}
+
+/// Returns true if both endpoints of the line are finite.
+pub fn is_finite_line(line: &Line) -> bool {
+    [line.x1, line.y1, line.x2, line.y2]
+        .iter()
+        .all(|v| v.is_finite())
+}
+
+/// Removes the lines with a non-finite endpoint, e.g. from an invalid transform, and
+/// warns about them, since the canvas can't place them.
+pub fn retain_finite_lines(lines: &mut Vec<Line>, topic: &str) {
+    let count = lines.len();
+    lines.retain(is_finite_line);
+    if lines.len() < count {
+        log::warn!(
+            "Skipping {} lines of {} with non-finite coordinates, e.g. from an invalid transform",
+            count - lines.len(),
+            topic
+        );
+    }
+}
+
+/// Returns the bounds [min_x, max_x, min_y, max_y] of the points, None if there are none.
+pub fn get_points_bounds(points: &[(f64, f64)]) -> Option<[f64; 4]> {
+    // a single non-finite point would make the bounds, and the fitted view, unusable
+    points
+        .iter()
+        .filter(|(x, y)| x.is_finite() && y.is_finite())
+        .fold(None, |bounds, (x, y)| match bounds {
+            None => Some([*x, *x, *y, *y]),
+            Some([min_x, max_x, min_y, max_y]) => {
+                Some([min_x.min(*x), max_x.max(*x), min_y.min(*y), max_y.max(*y)])
+            }
+        })
+}
 
 pub struct Listeners {
+    pub frames: frames::FramesListener,
     pub lasers: Vec<laser::LaserListener>,
     pub markers: marker::MarkersListener,
+    pub interactive_markers: Vec<interactive_marker::InteractiveMarkersListener>,
     pub maps: Vec<map::MapListener>,
+    pub overlays: Vec<overlay::ImageOverlay>,
+    pub heatmaps: Vec<heatmap::HeatmapListener>,
+    pub gradients: Vec<gradient::GradientListener>,
     pub pose_stamped: Vec<pose::PoseStampedListener>,
     pub pose_array: Vec<pose::PoseArrayListener>,
     pub pointclouds: Vec<pointcloud::PointCloud2Listener>,
     pub paths: Vec<pose::PathListener>,
+    pub polygons: Vec<polygon::PolygonListener>,
+    pub navsats: Vec<navsat::NavSatListener>,
+    pub accels: Vec<accel::AccelListener>,
+    pub correspondences: Vec<correspondence::CorrespondenceOverlay>,
+    pub detections: Vec<detection::DetectionListener>,
+    pub segments: Vec<segment::SegmentListener>,
+    pub tf_comparison: Option<tf_compare::TfComparison>,
+    pub robot_model: robot_model::RobotModel,
+    pub thumbnail: Option<thumbnail::ThumbnailListener>,
 }
 
 impl Listeners {
     pub fn new(
         tf_listener: Arc<rustros_tf::TfListener>,
         static_frame: String,
-        laser_topics: Vec<ListenerConfigColor>,
+        laser_topics: Vec<LaserListenerConfig>,
         marker_topics: Vec<ListenerConfig>,
-        marker_array_topics: Vec<ListenerConfig>,
+        marker_array_topics: Vec<MarkerArrayListenerConfig>,
+        interactive_marker_topics: Vec<ListenerConfig>,
         map_topics: Vec<MapListenerConfig>,
+        image_overlays: Vec<ImageOverlayConfig>,
+        heatmap_topics: Vec<HeatmapListenerConfig>,
+        gradient_topics: Vec<GradientListenerConfig>,
         pose_stamped_topics: Vec<PoseListenerConfig>,
         pose_array_topics: Vec<PoseListenerConfig>,
         pointcloud2_topics: Vec<PointCloud2ListenerConfig>,
         path_topics: Vec<PoseListenerConfig>,
+        polygon_topics: Vec<PolygonListenerConfig>,
+        navsat_topics: Vec<NavSatListenerConfig>,
+        accel_topics: Vec<AccelListenerConfig>,
+        correspondence_overlays: Vec<CorrespondenceConfig>,
+        detection_topics: Vec<DetectionListenerConfig>,
+        segment_topics: Vec<SegmentListenerConfig>,
+        tf_comparison: Option<TfComparisonConfig>,
+        robot_links: Vec<RobotLinkConfig>,
+        thumbnail: Option<ThumbnailConfig>,
+        framerate: i64,
     ) -> Listeners {
         let mut lasers: Vec<laser::LaserListener> = Vec::new();
         for laser_config in laser_topics {
@@ -51,6 +199,17 @@ impl Listeners {
             markers.add_marker_array_listener(&m_config);
         }
 
+        let interactive_markers = interactive_marker_topics
+            .into_iter()
+            .map(|config| {
+                interactive_marker::InteractiveMarkersListener::new(
+                    config,
+                    tf_listener.clone(),
+                    static_frame.clone(),
+                )
+            })
+            .collect();
+
         let mut maps: Vec<map::MapListener> = Vec::new();
         for map_config in map_topics {
             maps.push(map::MapListener::new(
@@ -60,6 +219,19 @@ impl Listeners {
             ));
         }
 
+        let overlays = image_overlays
+            .into_iter()
+            .filter_map(overlay::ImageOverlay::new)
+            .collect();
+        let heatmaps = heatmap_topics
+            .into_iter()
+            .map(heatmap::HeatmapListener::new)
+            .collect();
+        let gradients = gradient_topics
+            .into_iter()
+            .map(gradient::GradientListener::new)
+            .collect();
+
         let mut pointclouds: Vec<pointcloud::PointCloud2Listener> = Vec::new();
         for pc_config in pointcloud2_topics {
             pointclouds.push(pointcloud::PointCloud2Listener::new(
@@ -71,7 +243,9 @@ impl Listeners {
 
         let pose_stamped = pose_stamped_topics
             .into_iter()
-            .map(|topic| pose::PoseStampedListener::new(topic))
+            .map(|topic| {
+                pose::PoseStampedListener::new(topic, tf_listener.clone(), static_frame.clone())
+            })
             .collect();
         let pose_array = pose_array_topics
             .into_iter()
@@ -81,14 +255,307 @@ impl Listeners {
             .into_iter()
             .map(|topic| pose::PathListener::new(topic))
             .collect();
+        let polygons = polygon_topics
+            .into_iter()
+            .map(|topic| {
+                polygon::PolygonListener::new(topic, tf_listener.clone(), static_frame.clone())
+            })
+            .collect();
+        let navsats = navsat_topics
+            .into_iter()
+            .map(|topic| {
+                navsat::NavSatListener::new(topic, tf_listener.clone(), static_frame.clone())
+            })
+            .collect();
+        let accels = accel_topics
+            .into_iter()
+            .map(|topic| {
+                accel::AccelListener::new(topic, tf_listener.clone(), static_frame.clone())
+            })
+            .collect();
+        let correspondences = correspondence_overlays
+            .into_iter()
+            .map(|config| correspondence::CorrespondenceOverlay::new(config, &lasers, &maps))
+            .collect();
+        let detections = detection_topics
+            .into_iter()
+            .map(|topic| {
+                detection::DetectionListener::new(topic, tf_listener.clone(), static_frame.clone())
+            })
+            .collect();
+        let segments = segment_topics
+            .into_iter()
+            .map(|topic| {
+                segment::SegmentListener::new(topic, tf_listener.clone(), static_frame.clone())
+            })
+            .collect();
+        let tf_comparison = tf_comparison.map(|config| {
+            tf_compare::TfComparison::new(config, tf_listener.clone(), static_frame.clone())
+        });
+        let robot_model = robot_model::RobotModel::new(robot_links, tf_listener, static_frame);
+        let thumbnail =
+            thumbnail.map(|config| thumbnail::ThumbnailListener::new(config, framerate));
         Listeners {
+            frames: frames::FramesListener::new(),
             lasers,
             markers,
+            interactive_markers,
             maps,
+            overlays,
+            heatmaps,
+            gradients,
             pose_stamped,
             pose_array,
             pointclouds,
             paths,
+            polygons,
+            navsats,
+            accels,
+            correspondences,
+            detections,
+            segments,
+            tf_comparison,
+            robot_model,
+            thumbnail,
+        }
+    }
+
+    /// Returns the points in the static frame of the data of each layer counted in the
+    /// bounds, with its center_on_first flag, in the order of the configuration. With
+    /// `only_centered`, the points of the layers without the flag are left empty.
+    pub fn get_layer_points(&self, only_centered: bool) -> Vec<(bool, Vec<(f64, f64)>)> {
+        let mut layers: Vec<(bool, Vec<(f64, f64)>)> = Vec::new();
+        let mut add = |center_on_first: bool, get_points: &dyn Fn() -> Vec<(f64, f64)>| {
+            let points = if only_centered && !center_on_first {
+                Vec::new()
+            } else {
+                get_points()
+            };
+            layers.push((center_on_first, points));
+        };
+        for map in &self.maps {
+            add(map.config.center_on_first, &|| {
+                let mut points: Vec<(f64, f64)> = Vec::new();
+                for (_, coords) in map.points.read().unwrap().iter() {
+                    points.extend(coords);
+                }
+                points.extend(get_line_points(&map.lines.read().unwrap()));
+                points
+            });
+        }
+        for heatmap in &self.heatmaps {
+            add(false, &|| {
+                let mut points: Vec<(f64, f64)> = Vec::new();
+                for (_, coords) in heatmap.points.read().unwrap().iter() {
+                    points.extend(coords);
+                }
+                points
+            });
+        }
+        for laser in &self.lasers {
+            add(laser.config.center_on_first, &|| {
+                laser.points.read().unwrap().clone()
+            });
         }
+        for pointcloud in &self.pointclouds {
+            add(pointcloud.config.center_on_first, &|| {
+                pointcloud
+                    .points
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .map(|pt| (pt.point.x, pt.point.y))
+                    .collect()
+            });
+        }
+        add(false, &|| get_line_points(&self.markers.get_lines()));
+        for polygon in &self.polygons {
+            add(polygon.config.center_on_first, &|| {
+                get_line_points(&polygon.get_lines())
+            });
+        }
+        for path in &self.paths {
+            add(path.config.center_on_first, &|| {
+                get_line_points(&path.get_lines())
+            });
+        }
+        for pose_array in &self.pose_array {
+            add(pose_array.config.center_on_first, &|| {
+                get_line_points(&pose_array.get_lines())
+            });
+        }
+        for navsat in &self.navsats {
+            add(navsat.config.center_on_first, &|| {
+                navsat
+                    .position
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .map(|position| (position.x, position.y))
+                    .collect()
+            });
+        }
+        layers
+    }
+
+    /// Returns the bounds [min_x, max_x, min_y, max_y] in the static frame of the data
+    /// received on all the layers, None if there is no data yet.
+    pub fn get_bounds(&self) -> Option<[f64; 4]> {
+        let points: Vec<(f64, f64)> = self
+            .get_layer_points(false)
+            .into_iter()
+            .flat_map(|(_, points)| points)
+            .collect();
+        get_points_bounds(&points)
+    }
+
+    /// Clears the data transformed in the static frame with TF when it was received, e.g.
+    /// after a backward jump of the ROS time. Maps and markers are kept, they are
+    /// usually not published periodically.
+    pub fn clear_transformed_data(&self) {
+        for laser in &self.lasers {
+            laser.clear();
+        }
+        for pointcloud in &self.pointclouds {
+            pointcloud.points.write().unwrap().clear();
+        }
+        for polygon in &self.polygons {
+            polygon.data.write().unwrap().lines_in_static_frame.clear();
+        }
+        for pose in &self.pose_stamped {
+            pose.clear();
+        }
+        for navsat in &self.navsats {
+            *navsat.position.write().unwrap() = None;
+        }
+        for accel in &self.accels {
+            accel.clear();
+        }
+        for correspondence in &self.correspondences {
+            correspondence.clear();
+        }
+        for detection in &self.detections {
+            detection.clear();
+        }
+        for segment in &self.segments {
+            segment.clear();
+        }
+        if let Some(tf_comparison) = &self.tf_comparison {
+            tf_comparison.clear();
+        }
+    }
+
+    /// Returns the number of messages received on all the topics.
+    pub fn get_message_count(&self) -> u64 {
+        self.get_rates()
+            .iter()
+            .map(|(_, rate)| rate.get_total())
+            .sum()
+    }
+
+    /// Returns the rate monitor of each topic, with the topic name.
+    pub fn get_rates(&self) -> Vec<(String, RateMonitor)> {
+        let mut rates = Vec::new();
+        for map in &self.maps {
+            rates.push((map.config.topic.clone(), map.rate.clone()));
+        }
+        for heatmap in &self.heatmaps {
+            rates.push((heatmap.config.topic.clone(), heatmap.rate.clone()));
+        }
+        for gradient in &self.gradients {
+            rates.extend(gradient.get_rates());
+        }
+        for laser in &self.lasers {
+            rates.push((laser.config.topic.clone(), laser.rate.clone()));
+        }
+        rates.extend(self.markers.rates.iter().cloned());
+        for interactive_marker in &self.interactive_markers {
+            rates.push((
+                interactive_marker.config.topic.clone(),
+                interactive_marker.rate.clone(),
+            ));
+        }
+        for pointcloud in &self.pointclouds {
+            rates.push((pointcloud.config.topic.clone(), pointcloud.rate.clone()));
+        }
+        for pose in &self.pose_stamped {
+            rates.push((pose.config.topic.clone(), pose.rate.clone()));
+        }
+        for pose_array in &self.pose_array {
+            rates.push((pose_array.config.topic.clone(), pose_array.rate.clone()));
+        }
+        for path in &self.paths {
+            rates.push((path.config.topic.clone(), path.rate.clone()));
+        }
+        for polygon in &self.polygons {
+            rates.push((polygon.config.topic.clone(), polygon.rate.clone()));
+        }
+        for navsat in &self.navsats {
+            rates.push((navsat.config.topic.clone(), navsat.rate.clone()));
+        }
+        for accel in &self.accels {
+            rates.push((accel.config.topic.clone(), accel.rate.clone()));
+        }
+        for detection in &self.detections {
+            rates.push((detection.config.topic.clone(), detection.rate.clone()));
+        }
+        for segment in &self.segments {
+            rates.push((segment.config.topic.clone(), segment.rate.clone()));
+        }
+        rates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_filter_accepts_matching_frames() {
+        let filter = FrameFilter::new(&Some("robot1/.*".to_string()));
+        assert!(filter.accepts("robot1/base_laser"));
+        assert!(!filter.accepts("robot2/base_laser"));
+        // the filter has to match the whole frame_id
+        assert!(!filter.accepts("other/robot1/base_laser"));
+        assert!(FrameFilter::new(&None).accepts("any"));
+    }
+
+    #[test]
+    fn invalid_frame_filter_matches_frame_name() {
+        let filter = FrameFilter::new(&Some("laser(".to_string()));
+        assert!(filter.accepts("laser("));
+        assert!(!filter.accepts("laser"));
+    }
+
+    #[test]
+    fn non_finite_lines_removed() {
+        let line = |x1: f64, y2: f64| Line {
+            x1,
+            y1: 0.0,
+            x2: 1.0,
+            y2,
+            color: tui::style::Color::White,
+        };
+        assert!(is_finite_line(&line(0.0, 1.0)));
+        assert!(!is_finite_line(&line(f64::NAN, 1.0)));
+        assert!(!is_finite_line(&line(0.0, f64::INFINITY)));
+        let mut lines = vec![
+            line(0.0, 1.0),
+            line(f64::NAN, 1.0),
+            line(2.0, f64::NEG_INFINITY),
+            line(3.0, 1.0),
+        ];
+        retain_finite_lines(&mut lines, "lines");
+        let starts: Vec<f64> = lines.iter().map(|line| line.x1).collect();
+        assert_eq!(starts, vec![0.0, 3.0]);
+    }
+
+    #[test]
+    fn override_frame_wins() {
+        assert_eq!(
+            get_source_frame("laser", &Some("base_laser".to_string())),
+            "base_laser"
+        );
+        assert_eq!(get_source_frame("laser", &None), "laser");
     }
 }
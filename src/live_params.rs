@@ -0,0 +1,72 @@
+//! Display settings that can be changed at runtime through ROS parameters.
+//!
+//! The private parameters of the node (e.g., `/termviz/axis_length`) are polled in a
+//! background thread, so that the rendering is never blocked by the ROS master. Only the
+//! settings listed in DisplayParameters are hot-reloadable, the others need a restart.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Last values of the parameters, None if the parameter is not set.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct DisplayParameters {
+    /// ~axis_length: length of the axes of the robot frame.
+    pub axis_length: Option<f64>,
+    /// ~zoom_factor: step for increasing/decreasing the zoom.
+    pub zoom_factor: Option<f64>,
+    /// ~show_scale_bar: visibility of the scale bar.
+    pub show_scale_bar: Option<bool>,
+    /// ~follow_frame: frame on which the view is centered.
+    pub follow_frame: Option<String>,
+    /// ~laser_colors: color of the laser scans as [r, g, b], by topic.
+    pub laser_colors: HashMap<String, Vec<u8>>,
+}
+
+fn read_param<T: serde::de::DeserializeOwned>(name: &str) -> Option<T> {
+    rosrust::param(name)?.get::<T>().ok()
+}
+
+impl DisplayParameters {
+    fn read() -> DisplayParameters {
+        DisplayParameters {
+            axis_length: read_param("~axis_length"),
+            zoom_factor: read_param("~zoom_factor"),
+            show_scale_bar: read_param("~show_scale_bar"),
+            follow_frame: read_param("~follow_frame"),
+            laser_colors: read_param("~laser_colors").unwrap_or_default(),
+        }
+    }
+}
+
+pub struct LiveParameters {
+    parameters: Arc<Mutex<DisplayParameters>>,
+}
+
+impl LiveParameters {
+    /// Starts polling the parameters.
+    ///
+    /// # Arguments
+    /// - `interval`: time between two polls, in seconds. If not positive, the parameters
+    ///   are never read.
+    pub fn new(interval: f64) -> LiveParameters {
+        let parameters = Arc::new(Mutex::new(DisplayParameters::default()));
+        if interval > 0.0 {
+            let thread_parameters = parameters.clone();
+            thread::spawn(move || {
+                while rosrust::is_ok() {
+                    let new_parameters = DisplayParameters::read();
+                    *thread_parameters.lock().unwrap() = new_parameters;
+                    thread::sleep(Duration::from_secs_f64(interval));
+                }
+            });
+        }
+        LiveParameters { parameters }
+    }
+
+    /// Returns the last values read.
+    pub fn get(&self) -> DisplayParameters {
+        self.parameters.lock().unwrap().clone()
+    }
+}
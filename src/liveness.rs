@@ -0,0 +1,61 @@
+//! Checks which topics have publishers, to tell a node that is down from a node that is
+//! up but doesn't publish.
+//!
+//! The system state is polled from the ROS master in a background thread, at a low rate
+//! so that neither the rendering nor the master are loaded.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Returns the name of a topic without the leading slash, the topics of termviz being
+/// resolved in the root namespace.
+fn normalize(topic: &str) -> &str {
+    topic.trim_start_matches('/')
+}
+
+pub struct PublisherMonitor {
+    /// Topics having at least one publisher at the last poll, None before the first
+    /// successful poll.
+    published: Arc<Mutex<Option<HashSet<String>>>>,
+}
+
+impl PublisherMonitor {
+    /// Starts polling the publishers.
+    ///
+    /// # Arguments
+    /// - `interval`: time between two polls, in seconds. If not positive, the master is
+    ///   never polled.
+    pub fn new(interval: f64) -> PublisherMonitor {
+        let published = Arc::new(Mutex::new(None));
+        if interval > 0.0 {
+            let thread_published = published.clone();
+            thread::spawn(move || {
+                while rosrust::is_ok() {
+                    if let Ok(state) = rosrust::state() {
+                        let topics = state
+                            .publishers
+                            .iter()
+                            .filter(|topic| !topic.connections.is_empty())
+                            .map(|topic| normalize(&topic.name).to_string())
+                            .collect();
+                        *thread_published.lock().unwrap() = Some(topics);
+                    }
+                    thread::sleep(Duration::from_secs_f64(interval));
+                }
+            });
+        }
+        PublisherMonitor { published }
+    }
+
+    /// Returns true if the topic had publishers at the last poll, None if it is unknown,
+    /// i.e. before the first poll or if the check is disabled.
+    pub fn has_publishers(&self, topic: &str) -> Option<bool> {
+        self.published
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|published| published.contains(normalize(topic)))
+    }
+}
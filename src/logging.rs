@@ -0,0 +1,159 @@
+//! Logging of the diagnostic messages, e.g. the warnings about invalid messages.
+//!
+//! Printing to the terminal would clutter the interface, so the messages logged with the
+//! `log` macros are written to the configured file, if any, and the most recent ones are
+//! kept to be shown in a small notification area. A message logged again from the same
+//! place within the throttle period is dropped.
+
+use crate::config::{get_duration, LoggingConfig};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A logged message, with the time it was logged.
+#[derive(Clone)]
+pub struct Notification {
+    pub time: Instant,
+    pub level: Level,
+    pub message: String,
+}
+
+/// The most recent messages, shared between the logger and the interface.
+#[derive(Clone)]
+pub struct Notifications {
+    recent: Arc<Mutex<VecDeque<Notification>>>,
+    capacity: usize,
+}
+
+impl Notifications {
+    fn new(capacity: usize) -> Notifications {
+        Notifications {
+            recent: Arc::new(Mutex::new(VecDeque::new())),
+            capacity,
+        }
+    }
+
+    fn push(&self, notification: Notification) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut recent = self.recent.lock().unwrap();
+        recent.push_back(notification);
+        while recent.len() > self.capacity {
+            recent.pop_front();
+        }
+    }
+
+    /// Returns the messages logged less than `max_age` ago, the oldest first.
+    pub fn get(&self, max_age: Duration) -> Vec<Notification> {
+        self.recent
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|notification| notification.time.elapsed() < max_age)
+            .cloned()
+            .collect()
+    }
+}
+
+struct Logger {
+    file: Option<Mutex<File>>,
+    /// Minimum time between two messages logged from the same place.
+    throttle: Duration,
+    /// Last time a message was logged from each place, identified by file and line.
+    last_logged: Mutex<HashMap<(String, u32), Instant>>,
+    notifications: Notifications,
+}
+
+impl Logger {
+    /// Returns true if a message was logged from the same place within the throttle
+    /// period, otherwise records that one is logged now.
+    fn is_throttled(&self, record: &Record) -> bool {
+        if self.throttle.is_zero() {
+            return false;
+        }
+        let place = (
+            record.file().unwrap_or_default().to_string(),
+            record.line().unwrap_or_default(),
+        );
+        let now = Instant::now();
+        let mut last_logged = self.last_logged.lock().unwrap();
+        match last_logged.get(&place) {
+            Some(last) if now.duration_since(*last) < self.throttle => true,
+            _ => {
+                last_logged.insert(place, now);
+                false
+            }
+        }
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) || self.is_throttled(record) {
+            return;
+        }
+        let message = record.args().to_string();
+        if let Some(file) = &self.file {
+            let _ = writeln!(
+                file.lock().unwrap(),
+                "{} {} [{}] {}",
+                chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                record.level(),
+                record.target(),
+                message
+            );
+        }
+        self.notifications.push(Notification {
+            time: Instant::now(),
+            level: record.level(),
+            message,
+        });
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            let _ = file.lock().unwrap().flush();
+        }
+    }
+}
+
+/// Installs the logger, and returns the most recent messages to show in the interface.
+/// Must be called once, before the messages to capture are logged.
+pub fn init(config: &LoggingConfig) -> Notifications {
+    let level = LevelFilter::from_str(&config.level).unwrap_or_else(|_| {
+        println!(
+            "Unknown log level {:?}, using warn. Supported: off, error, warn, info, debug, trace.",
+            config.level
+        );
+        LevelFilter::Warn
+    });
+    let file = config.path.as_ref().and_then(|path| {
+        match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => Some(Mutex::new(file)),
+            Err(e) => {
+                println!("Could not open log file {}: {}", path, e);
+                None
+            }
+        }
+    });
+    let notifications = Notifications::new(config.notifications);
+    let logger = Logger {
+        file,
+        throttle: get_duration(config.throttle),
+        last_logged: Mutex::new(HashMap::new()),
+        notifications: notifications.clone(),
+    };
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(level);
+    }
+    notifications
+}
@@ -1,19 +1,47 @@
+mod accel;
 mod app;
 mod app_modes;
+mod colormap;
+mod command_palette;
 mod config;
+mod correspondence;
+mod detection;
 mod footprint;
+mod frames;
+mod gradient;
+mod heatmap;
 mod image;
+mod interactive_marker;
 mod laser;
 mod listeners;
+mod live_params;
+mod liveness;
+mod logging;
 mod map;
 mod marker;
+mod minimap;
+mod navsat;
+mod opacity;
+mod overlay;
+mod point_style;
 mod pointcloud;
+mod polygon;
 mod pose;
+mod rate;
+mod recorder;
+mod robot_model;
+mod ros_master;
+mod segment;
+mod tf_compare;
+mod thumbnail;
+mod time_watchdog;
+mod timer;
 mod transformation;
+mod units;
 use futures::{future::FutureExt, select, StreamExt};
 use futures_timer::Delay;
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::time::Duration;
 
 use clap::{Arg, Command};
@@ -25,6 +53,64 @@ use crossterm::{
 use rosrust;
 use rustros_tf::TfListener;
 use std::error::Error;
+use std::io::Write;
+use std::time::Instant;
+
+/// Waits until the transform between two frames is available, printing the elapsed time.
+///
+/// Returns false if the timeout elapsed first, or if ROS is shutting down.
+fn wait_for_transform(
+    listener: &TfListener,
+    from: &str,
+    to: &str,
+    timeout: Option<Duration>,
+) -> bool {
+    let start = Instant::now();
+    while rosrust::is_ok() {
+        if listener
+            .lookup_transform(from, to, rosrust::Time::new())
+            .is_ok()
+        {
+            println!();
+            return true;
+        }
+        let elapsed = start.elapsed();
+        print!(
+            "\rWaiting for tf from {:?} to {:?} to become available... {:.0} s",
+            from,
+            to,
+            elapsed.as_secs_f64()
+        );
+        let _ = std::io::stdout().flush();
+        if timeout.is_some_and(|timeout| elapsed >= timeout) {
+            println!();
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+/// Returns the cursor movement mapped to a key pressed with Alt (coarse step) or Shift
+/// (fine step), with the matching prefix, None for the other keys.
+fn get_modified_input(key_to_input: &HashMap<KeyCode, String>, key: &KeyEvent) -> Option<String> {
+    let prefix = if key.modifiers.contains(KeyModifiers::ALT) {
+        app_modes::input::COARSE
+    } else if key.modifiers.contains(KeyModifiers::SHIFT) {
+        app_modes::input::FINE
+    } else {
+        return None;
+    };
+    // with Shift, the key is received as the uppercase letter
+    let input = match key.code {
+        KeyCode::Char(c) => key_to_input.get(&KeyCode::Char(c.to_ascii_lowercase()))?,
+        _ => return None,
+    };
+    if !app_modes::input::CURSOR_MOVES.contains(&input.as_str()) {
+        return None;
+    }
+    Some(format!("{}{}", prefix, input))
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
@@ -39,9 +125,11 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .get_matches();
 
     let conf = config::get_config(matches.get_one("config"))?;
+    let notifications = logging::init(&conf.logging);
 
     println!("Connecting to ros...");
     rosrust::init("termviz");
+    transformation::set_future_stamp_threshold(conf.future_stamp_threshold);
 
     let mut key_to_input: HashMap<KeyCode, String> = conf
         .key_mapping
@@ -49,62 +137,82 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .map(|(v, k)| match k.as_str() {
             "Enter" => (KeyCode::Enter, v.clone()),
             "Esc" => (KeyCode::Esc, v.clone()),
+            "Space" => (KeyCode::Char(' '), v.clone()),
             _ => (KeyCode::Char(k.chars().next().unwrap()), v.clone()),
         })
         .collect();
-    for i in 0..9 {
+    for i in 1..10 {
         key_to_input.insert(
             KeyCode::Char(std::char::from_digit(i, 10).unwrap()),
             i.to_string(),
         );
     }
 
-    // Initialize listener and wait for it to come up
-    println!(
-        "Waiting for tf from {:?} to {:?} to become available...",
-        conf.fixed_frame, conf.robot_frame
-    );
+    // Initialize listener and wait for it to come up. The robot frame is required by the
+    // modes, the other awaited frame only avoids a blank screen at startup.
     let listener = Arc::new(TfListener::new());
-    while rosrust::is_ok() {
-        let res =
-            listener.lookup_transform(&conf.fixed_frame, &conf.robot_frame, rosrust::Time::new());
-        match res {
-            Ok(_res) => break,
-            Err(_e) => {
-                std::thread::sleep(std::time::Duration::from_millis(100));
-                continue;
-            }
-        };
+    wait_for_transform(&listener, &conf.fixed_frame, &conf.robot_frame, None);
+    if let Some(frame) = &conf.startup.wait_frame {
+        let timeout = config::get_duration(conf.startup.wait_timeout);
+        if !wait_for_transform(&listener, &conf.fixed_frame, frame, Some(timeout)) {
+            println!(
+                "No tf from {:?} to {:?} after {} s, starting anyway.",
+                conf.fixed_frame, frame, conf.startup.wait_timeout
+            );
+            log::warn!(
+                "No tf from {} to {} after {} s at startup.",
+                conf.fixed_frame,
+                frame,
+                conf.startup.wait_timeout
+            );
+        }
     }
 
     println!("Initiating terminal");
 
     let rate = Duration::from_millis(1000 / conf.target_framerate as u64);
 
-    let default_app_config = Arc::new(Mutex::new(app::App::new(listener.clone(), conf)));
-
-    let mut running_app = default_app_config.lock().unwrap();
+    let mut running_app = app::App::new(listener.clone(), conf, notifications);
 
     let mut terminal = running_app.init_terminal().unwrap();
 
     let mut reader = EventStream::new();
-    loop {
+    let master_switch = loop {
         let mut event = reader.next().fuse();
         let mut delay = Delay::new(rate).fuse();
+        let mut event_received = false;
 
         select! {
             _ = delay => {
                 running_app.run();
             },
             maybe_event = event => {
+                event_received = true;
                 match maybe_event {
                     Some(Ok(event)) => {
                         if event == Event::Key(KeyEvent{code:KeyCode::Char('c'), modifiers: KeyModifiers::CONTROL}) {
-                            break;
+                            break None;
                         }
                         if let Event::Key(input) = event {
+                            if running_app.is_palette_open() {
+                                running_app.handle_palette_key(input.code);
+                                continue;
+                            }
 
-                            if key_to_input.contains_key(&input.code) {
+                            // Alt+digit selects a view slot
+                            let view_slot = match input.code {
+                                KeyCode::Char(c) if input.modifiers.contains(KeyModifiers::ALT) => {
+                                    c.to_digit(10)
+                                }
+                                _ => None,
+                            };
+                            if let Some(slot) = view_slot {
+                                running_app.handle_input(
+                                    &format!("{}{}", app_modes::input::VIEW_SLOT, slot),
+                                );
+                            } else if let Some(modified) = get_modified_input(&key_to_input, &input) {
+                                running_app.handle_input(&modified);
+                            } else if key_to_input.contains_key(&input.code) {
                                 running_app.handle_input(&key_to_input[&input.code]);
                             } else {
                                 running_app.handle_input(&app_modes::input::UNMAPPED.to_string());
@@ -113,14 +221,20 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
                     }
                     Some(Err(e)) => println!("Error: {:?}\r", e),
-                    None => break,
+                    None => break None,
                 }
             }
         };
-        terminal.draw(|f| {
-            running_app.draw(f);
-        })?;
-    }
+        if let Some(uri) = running_app.take_master_switch() {
+            break Some(uri);
+        }
+        if running_app.needs_redraw(event_received) {
+            let frame = terminal.draw(|f| {
+                running_app.draw(f);
+            })?;
+            running_app.record(frame.buffer);
+        }
+    };
     // restore terminal
     disable_raw_mode()?;
     execute!(
@@ -129,5 +243,9 @@ async fn main() -> Result<(), Box<dyn Error>> {
         DisableMouseCapture
     )?;
     terminal.show_cursor()?;
+    if let Some(uri) = master_switch {
+        println!("Switching to the ROS master {}...", uri);
+        return Err(ros_master::restart_with_master(&uri).into());
+    }
     Ok(())
 }
@@ -1,15 +1,472 @@
-use crate::config::MapListenerConfig;
-use crate::transformation;
+use crate::colormap::Colormap;
+use crate::config::{MapBinarizeConfig, MapListenerConfig};
+use crate::listeners::{get_source_frame, warn_frame_override, FrameFilter};
+use crate::rate::RateMonitor;
+use crate::transformation::lookup_transform_with_tolerance;
+use crate::transformation::ros_transform_to_isometry;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
 use std::sync::{Arc, RwLock};
 
 use nalgebra::geometry::{Isometry3, Point3, Quaternion, Translation3, UnitQuaternion};
+use tui::style::Color;
+use tui::widgets::canvas::Line;
 
 use rosrust;
 use rustros_tf;
 
+/// Reads the value of a cell, as an unsigned byte (0..255) if configured.
+pub fn read_value(raw: i8, unsigned: bool) -> i16 {
+    if unsigned {
+        raw as u8 as i16
+    } else {
+        raw as i16
+    }
+}
+
+/// Returns the position of a value in the domain, between 0 and 1. The values outside of
+/// the domain are clamped to its ends.
+///
+/// # Arguments
+/// - `value`: the value of the cell.
+/// - `domain`: values mapped to 0 and 1, the first one can be the largest to invert the
+///   mapping.
+pub fn get_domain_fraction(value: i16, domain: [f64; 2]) -> f64 {
+    let span = domain[1] - domain[0];
+    if span == 0.0 || !span.is_finite() {
+        return if value as f64 >= domain[1] { 1.0 } else { 0.0 };
+    }
+    ((value as f64 - domain[0]) / span).clamp(0.0, 1.0)
+}
+
+/// Returns the indices of the frontier cells of a grid, i.e. of the free cells next to an
+/// unknown cell on the same line or column.
+///
+/// # Arguments
+/// - `data`: the grid values, row-major, see `read_value`.
+/// - `width`: number of columns of the grid.
+/// - `threshold`: value from which a known cell is considered occupied.
+/// - `unknown_value`: value of the unknown cells.
+pub fn get_frontier_cells(
+    data: &[i16],
+    width: usize,
+    threshold: i16,
+    unknown_value: i16,
+) -> Vec<usize> {
+    if width == 0 {
+        return Vec::new();
+    }
+    let height = data.len() / width;
+    let is_unknown = |i: usize| data[i] == unknown_value;
+    (0..width * height)
+        .filter(|i| {
+            let value = data[*i];
+            if value == unknown_value || value >= threshold {
+                return false;
+            }
+            let (line, column) = (i / width, i % width);
+            (column > 0 && is_unknown(i - 1))
+                || (column + 1 < width && is_unknown(i + 1))
+                || (line > 0 && is_unknown(i - width))
+                || (line + 1 < height && is_unknown(i + width))
+        })
+        .collect()
+}
+
+/// Computes the outlines of the occupied regions of a grid with marching squares.
+///
+/// The cells outside of the grid are considered free, so that the outlines of regions
+/// touching the border of the map are closed as well.
+///
+/// # Arguments
+/// - `data`: the grid values, row-major, see `read_value`.
+/// - `width`: number of columns of the grid.
+/// - `height`: number of lines of the grid.
+/// - `threshold`: value from which a cell is considered occupied.
+///
+/// Returns the contour segments as (x1, y1, x2, y2) in cell coordinates.
+pub fn get_contour_segments(
+    data: &[i16],
+    width: usize,
+    height: usize,
+    threshold: i16,
+) -> Vec<(f64, f64, f64, f64)> {
+    let is_occupied = |column: i64, line: i64| -> bool {
+        if column < 0 || line < 0 || column >= width as i64 || line >= height as i64 {
+            return false;
+        }
+        data[line as usize * width + column as usize] >= threshold
+    };
+
+    let mut segments: Vec<(f64, f64, f64, f64)> = Vec::new();
+    for line in -1..height as i64 {
+        for column in -1..width as i64 {
+            // Corners of the square, counter-clockwise from the bottom left one
+            let corners = [
+                is_occupied(column, line),
+                is_occupied(column + 1, line),
+                is_occupied(column + 1, line + 1),
+                is_occupied(column, line + 1),
+            ];
+            let (x, y) = (column as f64, line as f64);
+            // Middle points of the edges: bottom, right, top, left
+            let edges = [
+                (x + 0.5, y),
+                (x + 1.0, y + 0.5),
+                (x + 0.5, y + 1.0),
+                (x, y + 0.5),
+            ];
+            let crossed: Vec<usize> = (0..4)
+                .filter(|&i| corners[i] != corners[(i + 1) % 4])
+                .collect();
+            let pairs = match crossed.len() {
+                2 => vec![(crossed[0], crossed[1])],
+                // Saddle, keep the occupied corners separated
+                4 if corners[0] => vec![(3, 0), (1, 2)],
+                4 => vec![(0, 1), (2, 3)],
+                _ => Vec::new(),
+            };
+            for (start, end) in pairs {
+                segments.push((edges[start].0, edges[start].1, edges[end].0, edges[end].1));
+            }
+        }
+    }
+    segments
+}
+
+/// Returns the class of a cell of a binarized map: 0 if occupied, i.e. from the threshold,
+/// 1 if free and 2 if unknown.
+fn get_binarized_class(value: i16, threshold: i16, unknown_value: i16) -> usize {
+    if value == unknown_value {
+        2
+    } else if value >= threshold {
+        0
+    } else {
+        1
+    }
+}
+
+/// Occupancy from which a cell is written as occupied in the exported image.
+const EXPORT_OCCUPIED_THRESH: f64 = 0.65;
+/// Occupancy up to which a cell is written as free in the exported image.
+const EXPORT_FREE_THRESH: f64 = 0.25;
+
+/// Writes a grid in the `map_server` format, i.e. a PGM image and its YAML metadata.
+///
+/// As done by `map_saver`, occupied cells are black, free cells are white and unknown
+/// cells are gray. The first row of the image is the last row of the grid.
+///
+/// # Arguments
+/// - `map`: the grid to export.
+/// - `base_path`: path of the output files, without extension.
+pub fn save_map(map: &rosrust_msg::nav_msgs::OccupancyGrid, base_path: &Path) -> io::Result<()> {
+    let width = map.info.width as usize;
+    let height = map.info.height as usize;
+    if map.data.len() < width * height {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the map has less cells than width * height",
+        ));
+    }
+    let image_path = base_path.with_extension("pgm");
+    let mut image = io::BufWriter::new(File::create(&image_path)?);
+    write!(image, "P5\n{} {}\n255\n", width, height)?;
+    for line in (0..height).rev() {
+        let row: Vec<u8> = map.data[line * width..(line + 1) * width]
+            .iter()
+            .map(|value| match *value as f64 / 100.0 {
+                v if v < 0.0 => 205,
+                v if v <= EXPORT_FREE_THRESH => 254,
+                v if v >= EXPORT_OCCUPIED_THRESH => 0,
+                _ => 205,
+            })
+            .collect();
+        image.write_all(&row)?;
+    }
+    image.flush()?;
+
+    let origin = &map.info.origin;
+    let q = &origin.orientation;
+    let yaw = (2.0 * (q.w * q.z + q.x * q.y)).atan2(1.0 - 2.0 * (q.y * q.y + q.z * q.z));
+    let mut metadata = File::create(base_path.with_extension("yaml"))?;
+    write!(
+        metadata,
+        "image: {}\nresolution: {}\norigin: [{}, {}, {}]\nnegate: 0\noccupied_thresh: {}\nfree_thresh: {}\n",
+        image_path.file_name().unwrap().to_string_lossy(),
+        map.info.resolution,
+        origin.position.x,
+        origin.position.y,
+        yaw,
+        EXPORT_OCCUPIED_THRESH,
+        EXPORT_FREE_THRESH
+    )?;
+    Ok(())
+}
+
+/// Returns the smallest stride such that keeping every stride-th cell of a grid along
+/// both axes gives at most `max_cells` cells.
+fn get_downsampling_stride(width: usize, height: usize, max_cells: usize) -> usize {
+    let mut stride = ((width * height) as f64 / max_cells as f64)
+        .sqrt()
+        .ceil()
+        .max(1.0) as usize;
+    while width.div_ceil(stride) * height.div_ceil(stride) > max_cells {
+        stride += 1;
+    }
+    stride
+}
+
+/// Keeps every stride-th cell of a grid along both axes. The kept cells are `stride`
+/// times larger, so the downsampled grid covers the same area.
+fn downsample_grid(
+    map: rosrust_msg::nav_msgs::OccupancyGrid,
+    stride: usize,
+) -> rosrust_msg::nav_msgs::OccupancyGrid {
+    let width = map.info.width as usize;
+    let height = map.info.height as usize;
+    let data = map
+        .data
+        .chunks(width)
+        .step_by(stride)
+        .flat_map(|line| line.iter().step_by(stride).copied())
+        .collect();
+    let mut info = map.info;
+    info.width = width.div_ceil(stride) as u32;
+    info.height = height.div_ceil(stride) as u32;
+    info.resolution *= stride as f32;
+    rosrust_msg::nav_msgs::OccupancyGrid {
+        header: map.header,
+        info,
+        data,
+    }
+}
+
+/// A grid with its pose in the static frame.
+pub struct GridData {
+    pub map: rosrust_msg::nav_msgs::OccupancyGrid,
+    /// Transform from the grid coordinates (in meters) to the static frame.
+    pub grid_to_static: Isometry3<f64>,
+}
+
+/// Points grouped by the color they are drawn with.
+pub type ColoredPoints = Vec<(Color, Vec<(f64, f64)>)>;
+
+/// A cell of a grid, as inspected under a point of the static frame.
+pub struct MapCell {
+    pub column: usize,
+    pub line: usize,
+    /// The value of the cell, see `read_value`.
+    pub value: i16,
+    /// The corners of the cell in the static frame, counter-clockwise.
+    pub corners: [(f64, f64); 4],
+}
+
+/// The styles a map can be drawn with, in the order in which they are cycled.
+const MAP_STYLES: [&str; 3] = ["filled", "contour", "off"];
+
+/// Computes what is drawn of a grid in each style.
+struct MapRenderer {
+    config: MapListenerConfig,
+    colormap: Option<Colormap>,
+}
+
+impl MapRenderer {
+    /// Returns the position in the static frame of a corner of a cell.
+    fn to_static_frame(grid: &GridData, column: f64, line: f64) -> (f64, f64) {
+        let resolution = grid.map.info.resolution as f64;
+        let pt = grid.grid_to_static.transform_point(&Point3::new(
+            column * resolution,
+            line * resolution,
+            0.,
+        ));
+        (pt.x, pt.y)
+    }
+
+    /// Returns the threshold from which a known cell is occupied.
+    fn get_occupied_threshold(&self) -> i16 {
+        match &self.config.binarize {
+            Some(binarize) => binarize.threshold,
+            None => self.config.threshold,
+        }
+    }
+
+    /// Returns the points of the cells classified as occupied, free or unknown, each class
+    /// in its color. The classes without a color are not drawn.
+    fn get_binarized_points(&self, grid: &GridData, binarize: &MapBinarizeConfig) -> ColoredPoints {
+        let config = &self.config;
+        let map = &grid.map;
+        let classes = [
+            Some(&config.color),
+            binarize.free_color.as_ref(),
+            binarize.unknown_color.as_ref(),
+        ];
+        let mut points: [Vec<(f64, f64)>; 3] = Default::default();
+        for (i, pt) in map.data.iter().enumerate() {
+            let value = read_value(*pt, config.unsigned_values);
+            let class = get_binarized_class(value, binarize.threshold, config.unknown_value);
+            if classes[class].is_none() {
+                continue;
+            }
+            let line = i / map.info.width as usize;
+            let column = i - line * map.info.width as usize;
+            points[class].push(MapRenderer::to_static_frame(
+                grid,
+                column as f64,
+                line as f64,
+            ));
+        }
+        classes
+            .iter()
+            .zip(points)
+            .filter_map(|(color, coords)| {
+                let color = (*color)?;
+                Some((Color::Rgb(color.r, color.g, color.b), coords))
+            })
+            .filter(|(_, coords)| !coords.is_empty())
+            .collect()
+    }
+
+    /// Returns the points of the cells above the threshold (and of the unknown cells with
+    /// a colormap), or of the classes of the cells if the map is binarized.
+    fn get_filled_points(&self, grid: &GridData) -> ColoredPoints {
+        let config = &self.config;
+        if let Some(binarize) = &config.binarize {
+            return self.get_binarized_points(grid, binarize);
+        }
+        let map = &grid.map;
+        // Without colormap all the cells share the same color, otherwise
+        // they are grouped by value to limit the number of draw calls.
+        let mut points: BTreeMap<i16, Vec<(f64, f64)>> = BTreeMap::new();
+        for (i, pt) in map.data.iter().enumerate() {
+            let line = i / map.info.width as usize;
+            let column = i - line * map.info.width as usize;
+            let value = read_value(*pt, config.unsigned_values);
+            let key = match &self.colormap {
+                Some(_) if value == config.unknown_value => value,
+                Some(_) if value >= config.threshold => value,
+                None if value >= config.threshold && value != config.unknown_value => {
+                    config.threshold
+                }
+                _ => continue,
+            };
+            points
+                .entry(key)
+                .or_default()
+                .push(MapRenderer::to_static_frame(
+                    grid,
+                    column as f64,
+                    line as f64,
+                ));
+        }
+        points
+            .into_iter()
+            .map(|(value, coords)| {
+                let point_color = match &self.colormap {
+                    Some(_) if value == config.unknown_value => Color::Rgb(
+                        config.unknown_color.r,
+                        config.unknown_color.g,
+                        config.unknown_color.b,
+                    ),
+                    Some(colormap) => {
+                        colormap.color_at(get_domain_fraction(value, config.value_domain))
+                    }
+                    None => Color::Rgb(config.color.r, config.color.g, config.color.b),
+                };
+                (point_color, coords)
+            })
+            .collect()
+    }
+
+    /// Returns the outlines of the regions above the threshold.
+    fn get_contour_lines(&self, grid: &GridData) -> Vec<Line> {
+        let config = &self.config;
+        let color = Color::Rgb(config.color.r, config.color.g, config.color.b);
+        // the unknown cells are never outlined
+        let values: Vec<i16> = grid
+            .map
+            .data
+            .iter()
+            .map(|v| match read_value(*v, config.unsigned_values) {
+                value if value == config.unknown_value => i16::MIN,
+                value => value,
+            })
+            .collect();
+        get_contour_segments(
+            &values,
+            grid.map.info.width as usize,
+            grid.map.info.height as usize,
+            self.get_occupied_threshold(),
+        )
+        .into_iter()
+        .map(|segment| {
+            let start = MapRenderer::to_static_frame(grid, segment.0, segment.1);
+            let end = MapRenderer::to_static_frame(grid, segment.2, segment.3);
+            Line {
+                x1: start.0,
+                y1: start.1,
+                x2: end.0,
+                y2: end.1,
+                color,
+            }
+        })
+        .collect()
+    }
+
+    /// Returns the points of the frontier cells, see `get_frontier_cells`.
+    fn get_frontier_points(&self, grid: &GridData) -> Vec<(f64, f64)> {
+        let config = &self.config;
+        let values: Vec<i16> = grid
+            .map
+            .data
+            .iter()
+            .map(|v| read_value(*v, config.unsigned_values))
+            .collect();
+        let width = grid.map.info.width as usize;
+        get_frontier_cells(
+            &values,
+            width,
+            self.get_occupied_threshold(),
+            config.unknown_value,
+        )
+        .into_iter()
+        .map(|i| MapRenderer::to_static_frame(grid, (i % width) as f64, (i / width) as f64))
+        .collect()
+    }
+
+    /// Replaces what is drawn of the map by the grid drawn with the given style. Only the
+    /// data of this style is computed, and nothing is drawn with the "off" style.
+    fn render(
+        &self,
+        grid: Option<&GridData>,
+        style: &str,
+        points: &RwLock<ColoredPoints>,
+        lines: &RwLock<Vec<Line>>,
+    ) {
+        let (new_points, new_lines) = match (grid, style) {
+            (None, _) | (_, "off") => (ColoredPoints::new(), Vec::new()),
+            (Some(grid), "contour") => (ColoredPoints::new(), self.get_contour_lines(grid)),
+            (Some(grid), _) => (self.get_filled_points(grid), Vec::new()),
+        };
+        *points.write().unwrap() = new_points;
+        *lines.write().unwrap() = new_lines;
+    }
+}
+
 pub struct MapListener {
     pub config: MapListenerConfig,
-    pub points: Arc<RwLock<Vec<(f64, f64)>>>,
+    pub points: Arc<RwLock<ColoredPoints>>,
+    pub lines: Arc<RwLock<Vec<Line>>>,
+    /// Last grid received.
+    pub grid: Arc<RwLock<Option<GridData>>>,
+    /// Points of the frontier cells of the last grid, computed only with a frontier color.
+    frontier: Arc<RwLock<Vec<(f64, f64)>>>,
+    pub rate: RateMonitor,
+    /// Current style, initially the configured one and changed at runtime with
+    /// `cycle_style`.
+    style: Arc<RwLock<String>>,
+    renderer: Arc<MapRenderer>,
     _tf_listener: Arc<rustros_tf::TfListener>,
     _static_frame: String,
     _subscriber: rosrust::Subscriber,
@@ -21,22 +478,48 @@ impl MapListener {
         tf_listener: Arc<rustros_tf::TfListener>,
         static_frame: String,
     ) -> MapListener {
-        let occ_points = Arc::new(RwLock::new(Vec::<(f64, f64)>::new()));
+        let occ_points = Arc::new(RwLock::new(ColoredPoints::new()));
         let cb_occ_points = occ_points.clone();
+        let contour_lines = Arc::new(RwLock::new(Vec::<Line>::new()));
+        let cb_contour_lines = contour_lines.clone();
+        let grid = Arc::new(RwLock::new(None));
+        let cb_grid = grid.clone();
+        let frontier = Arc::new(RwLock::new(Vec::new()));
+        let cb_frontier = frontier.clone();
+        let has_frontier = config.frontier_color.is_some();
+        let rate = RateMonitor::new();
+        let cb_rate = rate.clone();
+        let style = Arc::new(RwLock::new(config.style.clone()));
+        let cb_style = style.clone();
+        let renderer = Arc::new(MapRenderer {
+            config: config.clone(),
+            colormap: config.colormap.as_ref().map(|name| Colormap::new(name)),
+        });
+        let cb_renderer = renderer.clone();
         let str_ = static_frame.clone();
         let local_listener = tf_listener.clone();
-        let threshold = config.threshold.clone();
+        let frame_filter = FrameFilter::new(&config.frame_filter);
+        warn_frame_override(&config.topic, &config.override_frame_id);
+        let override_frame_id = config.override_frame_id.clone();
+        let lookup_tolerance = config.lookup_tolerance;
+        let max_cells = config.max_cells;
+        let topic = config.topic.clone();
         let _map_sub = rosrust::subscribe(
             &config.topic,
             1,
             move |map: rosrust_msg::nav_msgs::OccupancyGrid| {
-                let mut points: Vec<(f64, f64)> = Vec::new();
-                let res = local_listener.clone().lookup_transform(
+                cb_rate.record();
+                cb_rate.record_stamp(map.header.stamp);
+                if !frame_filter.accepts(&map.header.frame_id) {
+                    return;
+                }
+                let res = match lookup_transform_with_tolerance(
+                    &local_listener,
                     &str_,
-                    &map.header.frame_id,
+                    get_source_frame(&map.header.frame_id, &override_frame_id),
                     map.header.stamp,
-                );
-                match &res {
+                    lookup_tolerance,
+                ) {
                     Ok(res) => res,
                     Err(_e) => return,
                 };
@@ -53,25 +536,35 @@ impl MapListener {
                     map.info.origin.orientation.z,
                 ));
                 let isometry = Isometry3::from_parts(tra, rot);
-
-                for (i, pt) in map.data.iter().enumerate() {
-                    let line = i / map.info.width as usize;
-                    let column = i - line * map.info.width as usize;
-                    if pt >= &threshold {
-                        let trans_point = isometry.transform_point(&Point3::new(
-                            (column as f64) * map.info.resolution as f64,
-                            line as f64 * map.info.resolution as f64,
-                            0.,
-                        ));
-                        let global_point = transformation::transform_relative_pt(
-                            &res.as_ref().unwrap().transform,
-                            (trans_point[0], trans_point[1]),
-                        );
-                        points.push(global_point);
-                    }
+                let (width, height) = (map.info.width as usize, map.info.height as usize);
+                let map = if max_cells > 0 && width * height > max_cells {
+                    let stride = get_downsampling_stride(width, height, max_cells);
+                    log::warn!(
+                        "The map received on {} has {}x{} cells, more than max_cells, only one cell in {} is kept along each axis",
+                        topic,
+                        width,
+                        height,
+                        stride
+                    );
+                    downsample_grid(map, stride)
+                } else {
+                    map
+                };
+                let grid_data = GridData {
+                    map,
+                    grid_to_static: ros_transform_to_isometry(&res.transform) * isometry,
+                };
+                cb_renderer.render(
+                    Some(&grid_data),
+                    &cb_style.read().unwrap(),
+                    &cb_occ_points,
+                    &cb_contour_lines,
+                );
+                // the frontier only changes with the grid, not with the view
+                if has_frontier {
+                    *cb_frontier.write().unwrap() = cb_renderer.get_frontier_points(&grid_data);
                 }
-                let mut cb_occ_points = cb_occ_points.write().unwrap();
-                *cb_occ_points = points;
+                *cb_grid.write().unwrap() = Some(grid_data);
             },
         )
         .unwrap();
@@ -79,9 +572,332 @@ impl MapListener {
         MapListener {
             config,
             points: occ_points,
+            lines: contour_lines,
+            grid,
+            frontier,
+            rate,
+            style,
+            renderer,
             _tf_listener: tf_listener,
             _static_frame: static_frame.to_string(),
             _subscriber: _map_sub,
         }
     }
+
+    /// Returns the style the map is currently drawn with.
+    pub fn get_style(&self) -> String {
+        self.style.read().unwrap().clone()
+    }
+
+    /// Switches to the next style of MAP_STYLES. Only the data of the new style is
+    /// computed, once, from the last grid received.
+    pub fn cycle_style(&self) {
+        let mut style = self.style.write().unwrap();
+        let index = MAP_STYLES.iter().position(|s| *s == style.as_str());
+        *style = MAP_STYLES[index.map_or(0, |i| (i + 1) % MAP_STYLES.len())].to_string();
+        self.renderer.render(
+            self.grid.read().unwrap().as_ref(),
+            &style,
+            &self.points,
+            &self.lines,
+        );
+    }
+
+    /// Returns the frontier points in the view, in the frontier color. None without a
+    /// frontier color or when the map is not drawn.
+    pub fn get_frontier_points(
+        &self,
+        x_bounds: [f64; 2],
+        y_bounds: [f64; 2],
+    ) -> Option<(Color, Vec<(f64, f64)>)> {
+        let color = self.config.frontier_color.as_ref()?;
+        if self.get_style() == "off" {
+            return None;
+        }
+        let coords = self
+            .frontier
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(x, y)| {
+                *x >= x_bounds[0] && *x <= x_bounds[1] && *y >= y_bounds[0] && *y <= y_bounds[1]
+            })
+            .cloned()
+            .collect();
+        Some((Color::Rgb(color.r, color.g, color.b), coords))
+    }
+
+    /// Exports the last grid received in the `map_server` format, see `save_map`.
+    ///
+    /// The files are written in the current directory and named after the topic.
+    pub fn export(&self) -> io::Result<()> {
+        let grid = self.grid.read().unwrap();
+        let map = grid
+            .as_ref()
+            .map(|grid| &grid.map)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no map received yet"))?;
+        let name = self.config.topic.trim_start_matches('/').replace('/', "_");
+        save_map(map, Path::new(&name))
+    }
+
+    /// Returns the grid coordinates (column, line) of a point of the static frame.
+    fn cell_at(grid: &GridData, x: f64, y: f64) -> Option<(usize, usize)> {
+        let pt = grid
+            .grid_to_static
+            .inverse_transform_point(&Point3::new(x, y, 0.0));
+        let column = (pt.x / grid.map.info.resolution as f64).floor();
+        let line = (pt.y / grid.map.info.resolution as f64).floor();
+        if column < 0.0
+            || line < 0.0
+            || column >= grid.map.info.width as f64
+            || line >= grid.map.info.height as f64
+        {
+            return None;
+        }
+        Some((column as usize, line as usize))
+    }
+
+    /// Returns the value of the cell containing a point of the static frame, None if the
+    /// point is outside of the map or no map was received yet.
+    pub fn value_at(&self, x: f64, y: f64) -> Option<i16> {
+        let grid = self.grid.read().unwrap();
+        let grid = grid.as_ref()?;
+        let (column, line) = MapListener::cell_at(grid, x, y)?;
+        grid.map
+            .data
+            .get(line * grid.map.info.width as usize + column)
+            .map(|value| read_value(*value, self.config.unsigned_values))
+    }
+
+    /// Returns the cell containing a point of the static frame, None if the point is
+    /// outside of the map or no map was received yet.
+    pub fn cell_at_point(&self, x: f64, y: f64) -> Option<MapCell> {
+        let grid = self.grid.read().unwrap();
+        let grid = grid.as_ref()?;
+        let (column, line) = MapListener::cell_at(grid, x, y)?;
+        let value = grid
+            .map
+            .data
+            .get(line * grid.map.info.width as usize + column)
+            .map(|value| read_value(*value, self.config.unsigned_values))?;
+        let (c, l) = (column as f64, line as f64);
+        Some(MapCell {
+            column,
+            line,
+            value,
+            corners: [(c, l), (c + 1.0, l), (c + 1.0, l + 1.0), (c, l + 1.0)]
+                .map(|(c, l)| MapRenderer::to_static_frame(grid, c, l)),
+        })
+    }
+
+    /// Returns true if a value is known and below the threshold.
+    pub fn is_free(&self, value: i16) -> bool {
+        value != self.config.unknown_value && value < self.config.threshold
+    }
+
+    /// Calls `f` with the center in the static frame of each cell of the last grid that is
+    /// known and not free.
+    pub fn for_each_occupied_cell(&self, mut f: impl FnMut(f64, f64)) {
+        let grid = self.grid.read().unwrap();
+        let grid = match grid.as_ref() {
+            Some(grid) => grid,
+            None => return,
+        };
+        let width = grid.map.info.width as usize;
+        let resolution = grid.map.info.resolution as f64;
+        for (i, raw) in grid.map.data.iter().enumerate() {
+            let value = read_value(*raw, self.config.unsigned_values);
+            if value == self.config.unknown_value || self.is_free(value) {
+                continue;
+            }
+            let pt = grid.grid_to_static.transform_point(&Point3::new(
+                ((i % width) as f64 + 0.5) * resolution,
+                ((i / width) as f64 + 0.5) * resolution,
+                0.0,
+            ));
+            f(pt.x, pt.y);
+        }
+    }
+
+    /// Returns the bounds [min_x, max_x, min_y, max_y] of the last grid in the static
+    /// frame, None if no grid was received yet.
+    pub fn get_grid_bounds(&self) -> Option<[f64; 4]> {
+        let grid = self.grid.read().unwrap();
+        let grid = grid.as_ref()?;
+        let resolution = grid.map.info.resolution as f64;
+        let width = grid.map.info.width as f64 * resolution;
+        let height = grid.map.info.height as f64 * resolution;
+        let corners = [(0.0, 0.0), (width, 0.0), (0.0, height), (width, height)]
+            .map(|(x, y)| grid.grid_to_static.transform_point(&Point3::new(x, y, 0.0)));
+        Some(corners.iter().fold(
+            [
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+            ],
+            |[min_x, max_x, min_y, max_y], pt| {
+                [
+                    min_x.min(pt.x),
+                    max_x.max(pt.x),
+                    min_y.min(pt.y),
+                    max_y.max(pt.y),
+                ]
+            },
+        ))
+    }
+
+    /// Returns the center of the free cell closest to a point of the static frame, within
+    /// the given radius. A cell is free if its value is known and below the threshold.
+    pub fn nearest_free_cell(&self, x: f64, y: f64, radius: f64) -> Option<(f64, f64)> {
+        let grid = self.grid.read().unwrap();
+        let grid = grid.as_ref()?;
+        let resolution = grid.map.info.resolution as f64;
+        let center = grid
+            .grid_to_static
+            .inverse_transform_point(&Point3::new(x, y, 0.0));
+        let cells = (radius / resolution).ceil() as i64;
+        let column = (center.x / resolution).floor() as i64;
+        let line = (center.y / resolution).floor() as i64;
+
+        let mut nearest: Option<(f64, Point3<f64>)> = None;
+        for l in (line - cells)..=(line + cells) {
+            for c in (column - cells)..=(column + cells) {
+                if c < 0
+                    || l < 0
+                    || c >= grid.map.info.width as i64
+                    || l >= grid.map.info.height as i64
+                {
+                    continue;
+                }
+                let value = read_value(
+                    grid.map.data[l as usize * grid.map.info.width as usize + c as usize],
+                    self.config.unsigned_values,
+                );
+                if !self.is_free(value) {
+                    continue;
+                }
+                let cell_center = Point3::new(
+                    (c as f64 + 0.5) * resolution,
+                    (l as f64 + 0.5) * resolution,
+                    0.0,
+                );
+                let distance = ((cell_center.x - center.x).powi(2)
+                    + (cell_center.y - center.y).powi(2))
+                .sqrt();
+                if distance <= radius && nearest.is_none_or(|(d, _)| distance < d) {
+                    nearest = Some((distance, cell_center));
+                }
+            }
+        }
+        nearest.map(|(_, cell_center)| {
+            let pt = grid.grid_to_static.transform_point(&cell_center);
+            (pt.x, pt.y)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn contour_closes_around_block() {
+        // 4x4 grid with the 2x2 block in the middle occupied
+        let mut data = vec![0; 16];
+        for i in [5, 6, 9, 10] {
+            data[i] = 100;
+        }
+        let segments = get_contour_segments(&data, 4, 4, 50);
+        assert!(!segments.is_empty());
+        // the endpoints are on half cells, so they are compared exactly in half cells
+        let mut endpoints: HashMap<(i64, i64), usize> = HashMap::new();
+        for (x1, y1, x2, y2) in &segments {
+            for (x, y) in [(x1, y1), (x2, y2)] {
+                let key = ((x * 2.0).round() as i64, (y * 2.0).round() as i64);
+                *endpoints.entry(key).or_default() += 1;
+            }
+        }
+        for (endpoint, count) in endpoints {
+            assert_eq!(
+                count, 2,
+                "endpoint {:?} is not shared by two segments",
+                endpoint
+            );
+        }
+    }
+
+    #[test]
+    fn saved_map_reads_back() {
+        let mut map = rosrust_msg::nav_msgs::OccupancyGrid::default();
+        map.info.width = 3;
+        map.info.height = 2;
+        map.info.resolution = 0.05;
+        map.info.origin.position.x = -1.5;
+        map.info.origin.orientation.w = 1.0;
+        // the thresholds of map_saver are inclusive
+        map.data = vec![0, 25, 26, -1, 64, 65];
+        let base_path = std::env::temp_dir().join(format!("termviz_map_{}", std::process::id()));
+        save_map(&map, &base_path).unwrap();
+
+        let image = std::fs::read(base_path.with_extension("pgm")).unwrap();
+        let header = b"P5\n3 2\n255\n";
+        assert_eq!(&image[..header.len()], header);
+        // the first row of the image is the last row of the grid
+        assert_eq!(&image[header.len()..], &[205, 205, 0, 254, 254, 205]);
+
+        let metadata = std::fs::read_to_string(base_path.with_extension("yaml")).unwrap();
+        for line in [
+            "resolution: 0.05",
+            "origin: [-1.5, 0, 0]",
+            "occupied_thresh: 0.65",
+            "free_thresh: 0.25",
+        ] {
+            assert!(
+                metadata.lines().any(|l| l == line),
+                "{:?} not in {}",
+                line,
+                metadata
+            );
+        }
+        std::fs::remove_file(base_path.with_extension("pgm")).unwrap();
+        std::fs::remove_file(base_path.with_extension("yaml")).unwrap();
+    }
+
+    #[test]
+    fn unsigned_costmap_domain() {
+        let domain = [0.0, 255.0];
+        let fraction = |raw: u8| get_domain_fraction(read_value(raw as i8, true), domain);
+        assert_eq!(fraction(0), 0.0);
+        assert!((fraction(128) - 0.5).abs() < 0.01);
+        assert_eq!(fraction(255), 1.0);
+        // read as signed, 255 is -1, below the domain
+        assert_eq!(read_value(-1, false), -1);
+        assert_eq!(get_domain_fraction(read_value(-1, false), domain), 0.0);
+        assert_eq!(get_domain_fraction(100, [0.0, 50.0]), 1.0);
+        // an inverted domain
+        assert_eq!(get_domain_fraction(0, [100.0, 0.0]), 1.0);
+    }
+
+    #[test]
+    fn binarized_classes() {
+        assert_eq!(get_binarized_class(65, 65, -1), 0);
+        assert_eq!(get_binarized_class(100, 65, -1), 0);
+        assert_eq!(get_binarized_class(64, 65, -1), 1);
+        assert_eq!(get_binarized_class(0, 65, -1), 1);
+        assert_eq!(get_binarized_class(-1, 65, -1), 2);
+        // the unknown value is unknown even above the threshold
+        assert_eq!(get_binarized_class(255, 65, 255), 2);
+    }
+
+    #[test]
+    fn frontier_cells_of_grid() {
+        // 4x3 grid, row-major: free (0), occupied (100) and unknown (-1) cells
+        let data = vec![0, 0, -1, -1, 0, 100, 100, -1, 0, 0, 0, 0];
+        // the occupied cells next to the unknown ones are not frontiers, and the last cell
+        // of the first line is not next to the first cell of the second one
+        assert_eq!(get_frontier_cells(&data, 4, 50, -1), vec![1, 11]);
+        assert!(get_frontier_cells(&data, 0, 50, -1).is_empty());
+    }
 }
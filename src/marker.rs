@@ -3,11 +3,16 @@
 //! ROS has a type of message dedicated to visualization: visualization_msgs::Marker.
 //! This module allows to subsribe to topics that publish them and project them into the
 //! 2D plane. Finally, it takes care of their lifecycle: ADD, DELETE and timeout.
-use crate::config::ListenerConfig;
+use crate::config::{ListenerConfig, MarkerArrayListenerConfig};
+use crate::listeners::FrameFilter;
+use crate::rate::RateMonitor;
+use crate::transformation::lookup_transform_with_tolerance;
 use nalgebra::base::Vector3;
 use nalgebra::geometry::Isometry3;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::f64::consts::PI;
+use std::hash::{Hash, Hasher};
 use std::sync::{Arc, Mutex, RwLock};
 
 use rosrust;
@@ -17,6 +22,33 @@ use rustros_tf::transforms::{isometry_from_pose, isometry_from_transform};
 use tui::style::Color;
 use tui::widgets::canvas::Line;
 
+/// Colors assigned to the marker namespaces when coloring by namespace.
+const NAMESPACE_PALETTE: [Color; 10] = [
+    Color::Rgb(31, 119, 180),
+    Color::Rgb(255, 127, 14),
+    Color::Rgb(44, 160, 44),
+    Color::Rgb(214, 39, 40),
+    Color::Rgb(148, 103, 189),
+    Color::Rgb(140, 86, 75),
+    Color::Rgb(227, 119, 194),
+    Color::Rgb(127, 127, 127),
+    Color::Rgb(188, 189, 34),
+    Color::Rgb(23, 190, 207),
+];
+
+/// Returns true if the marker is kept when one marker out of `stride` is drawn.
+///
+/// The choice depends only on the namespace and the id of the marker, so that the same
+/// markers are kept from an array to the next and the sampled ones don't flicker.
+fn is_sampled(marker: &rosrust_msg::visualization_msgs::Marker, stride: usize) -> bool {
+    let mut hasher = DefaultHasher::new();
+    (&marker.ns, marker.id).hash(&mut hasher);
+    hasher.finish().is_multiple_of(stride as u64)
+}
+
+/// Number of markers drawn and received in the last array of a topic.
+type SamplingCounts = Arc<Mutex<(usize, usize)>>;
+
 struct TermvizMarker {
     pub lines: Vec<Line>,
     pub id: i32,
@@ -336,11 +368,17 @@ fn parse_line_list_msg(
     msg: &rosrust_msg::visualization_msgs::Marker,
     color: &tui::style::Color,
     iso: &Isometry3<f64>,
+    use_point_colors: bool,
 ) -> Vec<Line> {
     let mut lines: Vec<Line> = Vec::new();
 
     let mut point_it = msg.points.iter();
-    let mut color_it = msg.colors.iter();
+    let no_colors = Vec::new();
+    let mut color_it = if use_point_colors {
+        msg.colors.iter()
+    } else {
+        no_colors.iter()
+    };
 
     while let Some(msg_p1) = point_it.next() {
         let msg_color = color_it.next();
@@ -477,20 +515,27 @@ fn parse_sphere_msg(
     lines
 }
 
+/// Projects a marker in the static frame.
+///
+/// # Arguments
+/// - `msg`: the marker.
+/// - `tf`: transform from the frame of the marker to the static frame.
+/// - `color_override`: color replacing the ones of the message, if any.
 fn parse_marker_msg(
     msg: &rosrust_msg::visualization_msgs::Marker,
     tf: &rosrust_msg::geometry_msgs::Transform,
+    color_override: Option<Color>,
 ) -> TermvizMarker {
     let trans_marker_to_static_frame = isometry_from_transform(tf);
     let trans_to_marker = isometry_from_pose(&msg.pose);
 
     let iso = trans_marker_to_static_frame.inverse() * trans_to_marker;
 
-    let color = Color::Rgb(
+    let color = color_override.unwrap_or(Color::Rgb(
         (msg.color.r * 255.0) as u8,
         (msg.color.g * 255.0) as u8,
         (msg.color.b * 255.0) as u8,
-    );
+    ));
 
     let res = match msg.type_ as u8 {
         rosrust_msg::visualization_msgs::Marker::ARROW => parse_arrow_msg(msg, &color, &iso),
@@ -503,7 +548,7 @@ fn parse_marker_msg(
             parse_line_strip_msg(msg, &color, &iso)
         }
         rosrust_msg::visualization_msgs::Marker::LINE_LIST => {
-            parse_line_list_msg(msg, &color, &iso)
+            parse_line_list_msg(msg, &color, &iso, color_override.is_none())
         }
         rosrust_msg::visualization_msgs::Marker::SPHERE => parse_sphere_msg(msg, &color, &iso),
         _ => Vec::new(),
@@ -515,6 +560,19 @@ fn parse_marker_msg(
     }
 }
 
+/// Projects a marker in the static frame, with the colors of the message.
+///
+/// # Arguments
+/// - `msg`: the marker.
+/// - `tf`: transform between the frame of the marker and the static frame, as looked up
+///   from the frame of the marker to the static frame.
+pub fn get_marker_lines(
+    msg: &rosrust_msg::visualization_msgs::Marker,
+    tf: &rosrust_msg::geometry_msgs::Transform,
+) -> Vec<Line> {
+    parse_marker_msg(msg, tf, None).lines
+}
+
 /// Class that holds all the markers currently active.
 ///
 /// The markers are ordered in a double dictionary, which allows to manage namespaces.
@@ -539,11 +597,18 @@ impl TermvizMarkerContainer {
         }
     }
 
-    fn add_marker(&mut self, marker: &rosrust_msg::visualization_msgs::Marker) {
-        let transform = &self.tf_listener.clone().lookup_transform(
+    fn add_marker(
+        &mut self,
+        marker: &rosrust_msg::visualization_msgs::Marker,
+        color_override: Option<Color>,
+        lookup_tolerance: f64,
+    ) {
+        let transform = &lookup_transform_with_tolerance(
+            &self.tf_listener,
             &marker.header.frame_id,
-            &self.static_frame.clone(),
+            &self.static_frame,
             marker.header.stamp,
+            lookup_tolerance,
         );
         match &transform {
             Ok(transform) => transform,
@@ -553,11 +618,19 @@ impl TermvizMarkerContainer {
         self.markers
             .entry(marker.ns.clone())
             .and_modify(|namespace| {
-                let res = parse_marker_msg(&marker, &transform.as_ref().unwrap().transform);
+                let res = parse_marker_msg(
+                    marker,
+                    &transform.as_ref().unwrap().transform,
+                    color_override,
+                );
                 namespace.insert(res.id, res);
             })
             .or_insert_with(|| {
-                let res = parse_marker_msg(&marker, &transform.as_ref().unwrap().transform);
+                let res = parse_marker_msg(
+                    marker,
+                    &transform.as_ref().unwrap().transform,
+                    color_override,
+                );
                 let mut namespace = HashMap::<i32, TermvizMarker>::new();
                 namespace.insert(res.id, res);
                 namespace
@@ -646,8 +719,17 @@ impl MarkersLifecycle {
         }
     }
 
-    fn add_marker(&mut self, marker: &rosrust_msg::visualization_msgs::Marker) {
-        self.markers_container.write().unwrap().add_marker(marker);
+    fn add_marker(
+        &mut self,
+        marker: &rosrust_msg::visualization_msgs::Marker,
+        color_override: Option<Color>,
+        lookup_tolerance: f64,
+    ) {
+        self.markers_container.write().unwrap().add_marker(
+            marker,
+            color_override,
+            lookup_tolerance,
+        );
 
         // Handle marker lifecycle
         if marker.lifetime.seconds() == 0.0 {
@@ -712,6 +794,10 @@ impl MarkersLifecycle {
 
 pub struct MarkersListener {
     markers_lifecycle: Arc<RwLock<MarkersLifecycle>>,
+    /// Rate of each marker and marker array topic.
+    pub rates: Vec<(String, RateMonitor)>,
+    /// Markers drawn and received of each marker array topic with a max_markers.
+    samplings: Vec<(String, SamplingCounts)>,
     subscribers: Vec<Arc<Mutex<rosrust::Subscriber>>>,
 }
 
@@ -720,10 +806,24 @@ impl MarkersListener {
         let marker_container = TermvizMarkerContainer::new(tf_listener, static_frame);
         Self {
             markers_lifecycle: Arc::new(RwLock::new(MarkersLifecycle::new(marker_container))),
+            rates: Vec::new(),
+            samplings: Vec::new(),
             subscribers: Vec::new(),
         }
     }
 
+    /// Returns a note for each marker array topic whose last array was subsampled, e.g.
+    /// "/markers 1000 of 50000".
+    pub fn get_sampling_notes(&self) -> Vec<String> {
+        self.samplings
+            .iter()
+            .filter_map(|(topic, counts)| {
+                let (shown, received) = *counts.lock().unwrap();
+                (shown < received).then(|| format!("{} {} of {}", topic, shown, received))
+            })
+            .collect()
+    }
+
     /// Gets all the lines currently active, to render.
     pub fn get_lines(&self) -> Vec<Line> {
         let markers_container_ref = self.markers_lifecycle.read().unwrap();
@@ -736,16 +836,25 @@ impl MarkersListener {
     /// - `config`: Configuration containing the topic name.
     pub fn add_marker_listener(&mut self, config: &ListenerConfig) {
         let markers_container_ref = self.markers_lifecycle.clone();
+        let frame_filter = FrameFilter::new(&config.frame_filter);
+        let lookup_tolerance = config.lookup_tolerance;
+        let rate = RateMonitor::new();
+        let cb_rate = rate.clone();
 
         let sub = rosrust::subscribe(
             &config.topic,
             2,
             move |msg: rosrust_msg::visualization_msgs::Marker| {
+                cb_rate.record();
+                cb_rate.record_stamp(msg.header.stamp);
+                if !frame_filter.accepts(&msg.header.frame_id) {
+                    return;
+                }
                 let mut markers_container = markers_container_ref.write().unwrap();
 
                 match msg.action as u8 {
                     rosrust_msg::visualization_msgs::Marker::ADD => {
-                        markers_container.add_marker(&msg)
+                        markers_container.add_marker(&msg, None, lookup_tolerance)
                     }
                     rosrust_msg::visualization_msgs::Marker::DELETE => {
                         markers_container.delete_marker(msg.ns.clone(), msg.id)
@@ -756,26 +865,84 @@ impl MarkersListener {
             },
         );
 
+        self.rates.push((config.topic.clone(), rate));
         self.subscribers.push(Arc::new(Mutex::new(sub.unwrap())));
     }
 
     /// Adds a subscriber for a marker array message topic.
     ///
+    /// If `color_by_namespace` is set, the markers are drawn with a color assigned to
+    /// their namespace, unless it is given in `namespace_colors`. The palette colors are
+    /// assigned in order of appearance of the namespaces, so they don't change over time.
+    ///
+    /// With `max_markers`, an array with more markers to add is subsampled: one marker out
+    /// of N is kept, chosen by its namespace and id, and the others are removed, whole.
+    /// The DELETE and DELETEALL actions are applied to all the markers.
+    ///
     /// # Arguments
     /// * `config` - Configuration containing the topic.
-    pub fn add_marker_array_listener(&mut self, config: &ListenerConfig) {
+    pub fn add_marker_array_listener(&mut self, config: &MarkerArrayListenerConfig) {
         let markers_container_ref = self.markers_lifecycle.clone();
+        let frame_filter = FrameFilter::new(&config.frame_filter);
+        let lookup_tolerance = config.lookup_tolerance;
+        let color_by_namespace = config.color_by_namespace;
+        let namespace_colors: HashMap<String, Color> = config
+            .namespace_colors
+            .iter()
+            .map(|(ns, c)| (ns.clone(), Color::Rgb(c.r, c.g, c.b)))
+            .collect();
+        let palette_indices = Mutex::new(HashMap::<String, usize>::new());
+        let max_markers = config.max_markers;
+        let sampling = SamplingCounts::default();
+        let cb_sampling = sampling.clone();
+        let rate = RateMonitor::new();
+        let cb_rate = rate.clone();
 
         let sub = rosrust::subscribe(
             &config.topic,
             2,
             move |msg: rosrust_msg::visualization_msgs::MarkerArray| {
+                cb_rate.record();
                 let mut markers_container = markers_container_ref.write().unwrap();
 
+                let is_added = |marker: &rosrust_msg::visualization_msgs::Marker| {
+                    marker.action as u8 == rosrust_msg::visualization_msgs::Marker::ADD
+                        && frame_filter.accepts(&marker.header.frame_id)
+                };
+                let received = msg.markers.iter().filter(|marker| is_added(marker)).count();
+                let stride = match max_markers {
+                    Some(max_markers) => received.div_ceil(max_markers.max(1)).max(1),
+                    None => 1,
+                };
+                let mut shown = 0;
+
                 for marker in msg.markers {
+                    if !frame_filter.accepts(&marker.header.frame_id) {
+                        continue;
+                    }
                     match marker.action as u8 {
+                        rosrust_msg::visualization_msgs::Marker::ADD
+                            if !is_sampled(&marker, stride)
+                                || max_markers.is_some_and(|max_markers| shown >= max_markers) =>
+                        {
+                            // a marker kept from a previous array would not be updated
+                            markers_container.delete_marker(marker.ns.clone(), marker.id)
+                        }
                         rosrust_msg::visualization_msgs::Marker::ADD => {
-                            markers_container.add_marker(&marker)
+                            shown += 1;
+                            let color = match namespace_colors.get(&marker.ns) {
+                                Some(color) => Some(*color),
+                                None if color_by_namespace => {
+                                    let mut palette_indices = palette_indices.lock().unwrap();
+                                    let next_index = palette_indices.len();
+                                    let index = *palette_indices
+                                        .entry(marker.ns.clone())
+                                        .or_insert(next_index);
+                                    Some(NAMESPACE_PALETTE[index % NAMESPACE_PALETTE.len()])
+                                }
+                                None => None,
+                            };
+                            markers_container.add_marker(&marker, color, lookup_tolerance)
                         }
                         rosrust_msg::visualization_msgs::Marker::DELETE => {
                             markers_container.delete_marker(marker.ns.clone(), marker.id)
@@ -786,9 +953,14 @@ impl MarkersListener {
                         _ => continue,
                     }
                 }
+                *cb_sampling.lock().unwrap() = (shown, received);
             },
         );
 
+        if max_markers.is_some() {
+            self.samplings.push((config.topic.clone(), sampling));
+        }
+        self.rates.push((config.topic.clone(), rate));
         self.subscribers.push(Arc::new(Mutex::new(sub.unwrap())));
     }
 }
@@ -0,0 +1,87 @@
+//! Overview of the maps, drawn in a small panel with the bounds of the current view.
+//!
+//! The occupied cells of all the maps are binned to the dots of the panel, so the minimap
+//! is computed once per received map whatever their size, and not at each frame.
+
+use crate::map::MapListener;
+
+/// What is drawn in the minimap panel.
+#[derive(Clone)]
+pub struct MinimapData {
+    /// One point per dot of the panel containing an occupied cell.
+    pub points: Vec<(f64, f64)>,
+    pub x_bounds: [f64; 2],
+    pub y_bounds: [f64; 2],
+}
+
+/// Returns the union of the bounds of the maps, enlarged along one axis so that its aspect
+/// ratio is the one of the panel. None if no map was received yet.
+fn get_bounds(maps: &[MapListener], columns: usize, rows: usize) -> Option<[f64; 4]> {
+    let [min_x, max_x, min_y, max_y] =
+        maps.iter()
+            .filter_map(|map| map.get_grid_bounds())
+            .reduce(|a, b| {
+                [
+                    a[0].min(b[0]),
+                    a[1].max(b[1]),
+                    a[2].min(b[2]),
+                    a[3].max(b[3]),
+                ]
+            })?;
+    let (width, height) = (max_x - min_x, max_y - min_y);
+    if width <= 0.0 || height <= 0.0 {
+        return None;
+    }
+    // the dots of a braille character are roughly square
+    let ratio = columns as f64 / rows as f64;
+    let (center_x, center_y) = ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0);
+    let (half_width, half_height) = if width / height > ratio {
+        (width / 2.0, width / ratio / 2.0)
+    } else {
+        (height * ratio / 2.0, height / 2.0)
+    };
+    Some([
+        center_x - half_width,
+        center_x + half_width,
+        center_y - half_height,
+        center_y + half_height,
+    ])
+}
+
+/// Computes the minimap of the maps.
+///
+/// # Arguments
+/// - `maps`: the maps drawn in the minimap.
+/// - `columns`, `rows`: number of dots of the panel, horizontally and vertically.
+pub fn get_minimap(maps: &[MapListener], columns: usize, rows: usize) -> Option<MinimapData> {
+    if columns == 0 || rows == 0 {
+        return None;
+    }
+    let [min_x, max_x, min_y, max_y] = get_bounds(maps, columns, rows)?;
+    let dot_width = (max_x - min_x) / columns as f64;
+    let dot_height = (max_y - min_y) / rows as f64;
+    let mut occupied = vec![false; columns * rows];
+    for map in maps {
+        map.for_each_occupied_cell(|x, y| {
+            let column = (((x - min_x) / dot_width) as usize).min(columns - 1);
+            let row = (((y - min_y) / dot_height) as usize).min(rows - 1);
+            occupied[row * columns + column] = true;
+        });
+    }
+    let points = occupied
+        .iter()
+        .enumerate()
+        .filter(|(_, occupied)| **occupied)
+        .map(|(i, _)| {
+            (
+                min_x + ((i % columns) as f64 + 0.5) * dot_width,
+                min_y + ((i / columns) as f64 + 0.5) * dot_height,
+            )
+        })
+        .collect();
+    Some(MinimapData {
+        points,
+        x_bounds: [min_x, max_x],
+        y_bounds: [min_y, max_y],
+    })
+}
@@ -0,0 +1,161 @@
+//! Module dealing with the visualization of GPS fixes, published as sensor_msgs::NavSatFix.
+//!
+//! The fixes are converted to a local East-North-Up (ENU) frame, whose origin is the datum:
+//! the configured [latitude, longitude, altitude] if any, otherwise the first fix received
+//! (so the robot starts at the origin of the ENU frame). The ENU frame is expressed in
+//! `enu_frame`, looked up with TF, or is the static frame if it is not configured.
+
+use crate::config::NavSatListenerConfig;
+use crate::point_style::get_point_lines;
+use crate::rate::RateMonitor;
+use crate::transformation::transform_relative_pt;
+use nalgebra::Vector3;
+use std::sync::{Arc, Mutex, RwLock};
+use tui::style::Color;
+use tui::widgets::canvas::Line;
+
+/// Semi-major axis of the WGS84 ellipsoid, in meters.
+const WGS84_A: f64 = 6378137.0;
+/// Square of the eccentricity of the WGS84 ellipsoid.
+const WGS84_E2: f64 = 6.69437999014e-3;
+
+/// Converts geodetic coordinates (latitude and longitude in degrees, altitude in meters)
+/// to Earth-Centered Earth-Fixed coordinates.
+fn geodetic_to_ecef(geodetic: [f64; 3]) -> Vector3<f64> {
+    let (lat, lon) = (geodetic[0].to_radians(), geodetic[1].to_radians());
+    let n = WGS84_A / (1.0 - WGS84_E2 * lat.sin().powi(2)).sqrt();
+    Vector3::new(
+        (n + geodetic[2]) * lat.cos() * lon.cos(),
+        (n + geodetic[2]) * lat.cos() * lon.sin(),
+        (n * (1.0 - WGS84_E2) + geodetic[2]) * lat.sin(),
+    )
+}
+
+/// Converts geodetic coordinates to the ENU frame whose origin is the datum.
+///
+/// # Arguments
+/// - `geodetic`: [latitude, longitude, altitude] of the point, in degrees and meters.
+/// - `datum`: [latitude, longitude, altitude] of the origin of the ENU frame.
+pub fn geodetic_to_enu(geodetic: [f64; 3], datum: [f64; 3]) -> Vector3<f64> {
+    let d = geodetic_to_ecef(geodetic) - geodetic_to_ecef(datum);
+    let (lat, lon) = (datum[0].to_radians(), datum[1].to_radians());
+    Vector3::new(
+        -lon.sin() * d.x + lon.cos() * d.y,
+        -lat.sin() * lon.cos() * d.x - lat.sin() * lon.sin() * d.y + lat.cos() * d.z,
+        lat.cos() * lon.cos() * d.x + lat.cos() * lon.sin() * d.y + lat.sin() * d.z,
+    )
+}
+
+/// Returns the radius of the horizontal accuracy (one standard deviation) of a fix, None
+/// if its covariance is unknown.
+fn get_accuracy(fix: &rosrust_msg::sensor_msgs::NavSatFix) -> Option<f64> {
+    if fix.position_covariance_type == rosrust_msg::sensor_msgs::NavSatFix::COVARIANCE_TYPE_UNKNOWN
+    {
+        return None;
+    }
+    let variance = fix.position_covariance[0].max(fix.position_covariance[4]);
+    if variance.is_nan() || variance <= 0.0 {
+        return None;
+    }
+    Some(variance.sqrt())
+}
+
+/// A fix projected in the static frame.
+#[derive(Clone)]
+pub struct Position {
+    pub x: f64,
+    pub y: f64,
+    /// Radius of the accuracy circle, in meters.
+    pub accuracy: Option<f64>,
+}
+
+pub struct NavSatListener {
+    pub config: NavSatListenerConfig,
+    /// Last fix, None before the first one or if the receiver has no fix.
+    pub position: Arc<RwLock<Option<Position>>>,
+    pub rate: RateMonitor,
+    _tf_listener: Arc<rustros_tf::TfListener>,
+    _subscriber: rosrust::Subscriber,
+}
+
+impl NavSatListener {
+    pub fn new(
+        config: NavSatListenerConfig,
+        tf_listener: Arc<rustros_tf::TfListener>,
+        static_frame: String,
+    ) -> NavSatListener {
+        let position = Arc::new(RwLock::new(None));
+        let cb_position = position.clone();
+        let rate = RateMonitor::new();
+        let cb_rate = rate.clone();
+        let local_listener = tf_listener.clone();
+        let enu_frame = config.enu_frame.clone();
+        let datum = Mutex::new(config.datum);
+        let sub = rosrust::subscribe(
+            &config.topic,
+            2,
+            move |fix: rosrust_msg::sensor_msgs::NavSatFix| {
+                cb_rate.record();
+                cb_rate.record_stamp(fix.header.stamp);
+                if fix.status.status == rosrust_msg::sensor_msgs::NavSatStatus::STATUS_NO_FIX {
+                    *cb_position.write().unwrap() = None;
+                    return;
+                }
+                let geodetic = [fix.latitude, fix.longitude, fix.altitude];
+                let enu = geodetic_to_enu(geodetic, *datum.lock().unwrap().get_or_insert(geodetic));
+                let (x, y) = match &enu_frame {
+                    Some(enu_frame) => {
+                        match local_listener.lookup_transform(
+                            &static_frame,
+                            enu_frame,
+                            rosrust::Time::new(),
+                        ) {
+                            Ok(res) => transform_relative_pt(&res.transform, (enu.x, enu.y)),
+                            Err(_e) => return,
+                        }
+                    }
+                    None => (enu.x, enu.y),
+                };
+                *cb_position.write().unwrap() = Some(Position {
+                    x,
+                    y,
+                    accuracy: get_accuracy(&fix),
+                });
+            },
+        )
+        .unwrap();
+
+        NavSatListener {
+            config,
+            position,
+            rate,
+            _tf_listener: tf_listener,
+            _subscriber: sub,
+        }
+    }
+
+    /// Returns the lines of the last fix: a cross of the given width at the position, and
+    /// the accuracy circle if the covariance is known.
+    pub fn get_lines(&self, cross_width: f64) -> Vec<Line> {
+        let position = match self.position.read().unwrap().clone() {
+            Some(position) => position,
+            None => return Vec::new(),
+        };
+        let color = Color::Rgb(
+            self.config.color.r,
+            self.config.color.g,
+            self.config.color.b,
+        );
+        let mut lines = get_point_lines(position.x, position.y, "cross", cross_width, color);
+        if let Some(accuracy) = position.accuracy {
+            lines.extend(get_point_lines(
+                position.x,
+                position.y,
+                "circle",
+                2.0 * accuracy,
+                color,
+            ));
+        }
+        lines
+    }
+}
@@ -0,0 +1,175 @@
+//! Simulated opacity of the layers.
+//!
+//! A terminal has no transparency, so a layer with an opacity below 1 is drawn with its
+//! colors blended toward the background color, which fades it while keeping it faintly
+//! visible.
+
+use crate::config::{AgeRampConfig, Color as RgbColor};
+use tui::style::Color;
+
+/// Returns the RGB components of a color, None for the default color of the terminal.
+///
+/// The named colors use the values of the xterm palette.
+pub fn get_rgb(color: Color) -> Option<(u8, u8, u8)> {
+    let rgb = match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Black => (0, 0, 0),
+        Color::Red => (205, 0, 0),
+        Color::Green => (0, 205, 0),
+        Color::Yellow => (205, 205, 0),
+        Color::Blue => (0, 0, 238),
+        Color::Magenta => (205, 0, 205),
+        Color::Cyan => (0, 205, 205),
+        Color::Gray => (229, 229, 229),
+        Color::DarkGray => (127, 127, 127),
+        Color::LightRed => (255, 0, 0),
+        Color::LightGreen => (0, 255, 0),
+        Color::LightYellow => (255, 255, 0),
+        Color::LightBlue => (92, 92, 255),
+        Color::LightMagenta => (255, 0, 255),
+        Color::LightCyan => (0, 255, 255),
+        Color::White => (255, 255, 255),
+        Color::Reset | Color::Indexed(_) => return None,
+    };
+    Some(rgb)
+}
+
+/// Blends a color toward the background color.
+///
+/// # Arguments
+/// - `color`: the color of the layer.
+/// - `opacity`: 1 keeps the color unchanged, 0 gives the background color. Values outside
+///   of [0, 1] are clamped.
+/// - `background`: the background color of the terminal.
+pub fn dim_color(color: Color, opacity: f64, background: &RgbColor) -> Color {
+    if opacity.is_nan() || opacity >= 1.0 {
+        return color;
+    }
+    let (r, g, b) = match get_rgb(color) {
+        Some(rgb) => rgb,
+        None => return color,
+    };
+    let opacity = opacity.max(0.0);
+    let blend = |value: u8, background: u8| -> u8 {
+        (background as f64 + (value as f64 - background as f64) * opacity).round() as u8
+    };
+    Color::Rgb(
+        blend(r, background.r),
+        blend(g, background.g),
+        blend(b, background.b),
+    )
+}
+
+/// Blends a color toward another one, linearly in RGB.
+///
+/// # Arguments
+/// - `color`: the initial color, returned if one of the colors is the default color of the
+///   terminal.
+/// - `target`: the color reached at the end of the transition.
+/// - `fraction`: 0 keeps the color unchanged, 1 gives the target. Values outside of [0, 1]
+///   are clamped.
+pub fn blend_color(color: Color, target: Color, fraction: f64) -> Color {
+    let (start, end) = match (get_rgb(color), get_rgb(target)) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return color,
+    };
+    let fraction = fraction.clamp(0.0, 1.0);
+    let blend = |start: u8, end: u8| -> u8 {
+        (start as f64 + (end as f64 - start as f64) * fraction).round() as u8
+    };
+    Color::Rgb(
+        blend(start.0, end.0),
+        blend(start.1, end.1),
+        blend(start.2, end.2),
+    )
+}
+
+/// Returns the color of an element of a feature keeping a history, e.g. a breadcrumb, at
+/// the given age: its color and opacity go linearly from the start to the end of the ramp
+/// over the duration of the ramp, and stay at the end afterwards.
+///
+/// # Arguments
+/// - `color`: the color of the feature.
+/// - `age`: the age of the element, in seconds.
+/// - `ramp`: the configured ramp, shared by all the features.
+/// - `background`: the background color of the terminal.
+pub fn get_age_color(color: Color, age: f64, ramp: &AgeRampConfig, background: &RgbColor) -> Color {
+    let fraction = if ramp.duration > 0.0 {
+        (age / ramp.duration).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+    let to_color = |rgb: &Option<RgbColor>| match rgb {
+        Some(rgb) => Color::Rgb(rgb.r, rgb.g, rgb.b),
+        None => color,
+    };
+    let color = blend_color(
+        to_color(&ramp.start_color),
+        to_color(&ramp.end_color),
+        fraction,
+    );
+    let opacity = ramp.start_opacity + (ramp.end_opacity - ramp.start_opacity) * fraction;
+    dim_color(color, opacity, background)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: RgbColor = RgbColor { r: 0, g: 0, b: 0 };
+
+    #[test]
+    fn dim_toward_background() {
+        let color = Color::Rgb(200, 100, 50);
+        assert_eq!(dim_color(color, 1.0, &BLACK), color);
+        assert_eq!(dim_color(color, 0.0, &BLACK), Color::Rgb(0, 0, 0));
+        assert_eq!(dim_color(color, 0.5, &BLACK), Color::Rgb(100, 50, 25));
+        let background = RgbColor {
+            r: 100,
+            g: 100,
+            b: 100,
+        };
+        assert_eq!(dim_color(color, 0.5, &background), Color::Rgb(150, 100, 75));
+        // the default color of the terminal can't be blended
+        assert_eq!(dim_color(Color::Reset, 0.5, &BLACK), Color::Reset);
+    }
+
+    #[test]
+    fn age_ramp() {
+        let ramp = AgeRampConfig {
+            duration: 10.0,
+            start_color: None,
+            start_opacity: 1.0,
+            end_color: Some(RgbColor { r: 0, g: 0, b: 200 }),
+            end_opacity: 0.5,
+        };
+        let color = Color::Rgb(200, 0, 0);
+        assert_eq!(get_age_color(color, 0.0, &ramp, &BLACK), color);
+        // halfway in color, then dimmed to 75%
+        assert_eq!(
+            get_age_color(color, 5.0, &ramp, &BLACK),
+            Color::Rgb(75, 0, 75)
+        );
+        assert_eq!(
+            get_age_color(color, 10.0, &ramp, &BLACK),
+            Color::Rgb(0, 0, 100)
+        );
+        // the end of the ramp is kept afterwards
+        assert_eq!(
+            get_age_color(color, 60.0, &ramp, &BLACK),
+            Color::Rgb(0, 0, 100)
+        );
+    }
+
+    #[test]
+    fn blend_fraction_endpoints() {
+        let (color, target) = (Color::Rgb(200, 100, 0), Color::Rgb(0, 100, 200));
+        assert_eq!(blend_color(color, target, 0.0), color);
+        assert_eq!(blend_color(color, target, 1.0), target);
+        assert_eq!(blend_color(color, target, 0.5), Color::Rgb(100, 100, 100));
+        // the fraction is clamped
+        assert_eq!(blend_color(color, target, -1.0), color);
+        assert_eq!(blend_color(color, target, 2.0), target);
+        assert_eq!(blend_color(Color::Reset, target, 1.0), Color::Reset);
+    }
+}
@@ -0,0 +1,90 @@
+//! Static images (e.g., floor plans) drawn as georeferenced background layers.
+//!
+//! Like a map, an image has a resolution and the pose of its bottom left pixel in the
+//! static frame. At each draw only the part of the image in the view is sampled, with at
+//! most one sample per dot of the canvas, so large images don't slow down the rendering.
+
+use crate::colormap::Colormap;
+use crate::config::ImageOverlayConfig;
+use crate::map::ColoredPoints;
+use image::GrayImage;
+use std::collections::BTreeMap;
+use tui::style::Color;
+
+pub struct ImageOverlay {
+    pub config: ImageOverlayConfig,
+    image: GrayImage,
+    colormap: Option<Colormap>,
+}
+
+impl ImageOverlay {
+    /// Loads the image of the overlay, returns None if it can't be read.
+    pub fn new(config: ImageOverlayConfig) -> Option<ImageOverlay> {
+        let image = match image::open(&config.path) {
+            Ok(image) => image.into_luma8(),
+            Err(e) => {
+                log::error!("Could not load image overlay {:?}: {}", config.path, e);
+                return None;
+            }
+        };
+        let colormap = config.colormap.as_ref().map(|name| Colormap::new(name));
+        Some(ImageOverlay {
+            config,
+            image,
+            colormap,
+        })
+    }
+
+    /// Samples the part of the image within the given bounds of the static frame.
+    ///
+    /// # Arguments
+    /// - `x_bounds`, `y_bounds`: the visible area, in the static frame.
+    /// - `step`: distance between two samples along x and y, i.e., the size of a dot.
+    pub fn get_points(
+        &self,
+        x_bounds: [f64; 2],
+        y_bounds: [f64; 2],
+        step: (f64, f64),
+    ) -> ColoredPoints {
+        let (width, height) = self.image.dimensions();
+        let resolution = self.config.resolution;
+        let (sin, cos) = self.config.origin[2].sin_cos();
+        // the samples are not finer than the pixels
+        let step = (step.0.max(resolution), step.1.max(resolution));
+        if !(step.0 > 0.0 && step.1 > 0.0) {
+            return ColoredPoints::new();
+        }
+
+        let mut points: BTreeMap<u8, Vec<(f64, f64)>> = BTreeMap::new();
+        let mut y = y_bounds[0];
+        while y <= y_bounds[1] {
+            let mut x = x_bounds[0];
+            while x <= x_bounds[1] {
+                // position in the image frame, with the origin in the bottom left corner
+                let dx = x - self.config.origin[0];
+                let dy = y - self.config.origin[1];
+                let column = (cos * dx + sin * dy) / resolution;
+                let line = (-sin * dx + cos * dy) / resolution;
+                if column >= 0.0 && line >= 0.0 && column < width as f64 && line < height as f64 {
+                    let value = self
+                        .image
+                        .get_pixel(column as u32, height - 1 - line as u32)[0];
+                    points.entry(value).or_default().push((x, y));
+                }
+                x += step.0;
+            }
+            y += step.1;
+        }
+
+        points
+            .into_iter()
+            .map(|(value, coords)| {
+                let color = match &self.colormap {
+                    Some(colormap) => colormap.color_at(value as f64 / 255.0),
+                    None => Color::Rgb(value, value, value),
+                };
+                (color, coords)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,312 @@
+//! Glyphs used to draw the points of a layer, so that overlapping layers can be told apart.
+
+use crate::opacity::get_rgb;
+use std::collections::HashMap;
+use tui::style::Color;
+use tui::widgets::canvas::Line;
+
+pub const SUPPORTED_POINT_STYLES: [&str; 4] = ["dot", "cross", "square", "circle"];
+
+/// Number of segments of the polyline approximating a circle.
+const CIRCLE_SEGMENTS: usize = 8;
+
+/// Returns true if the points are drawn as single canvas points, i.e., with the "dot"
+/// style or an unsupported one.
+pub fn is_dot(style: &str) -> bool {
+    style == "dot" || !SUPPORTED_POINT_STYLES.contains(&style)
+}
+
+/// Returns the width of the glyphs in meters.
+///
+/// # Arguments
+/// - `size`: configured size of the glyphs.
+/// - `size_mode`: "world" if the size is in meters, otherwise ("screen" or an unknown mode)
+///   the size is in terminal cells, so the glyphs keep their size on screen when zooming.
+/// - `cell_width`: width of a terminal cell, in meters.
+pub fn get_glyph_width(size: f64, size_mode: &str, cell_width: f64) -> f64 {
+    if size_mode == "world" {
+        size
+    } else {
+        size * cell_width
+    }
+}
+
+/// Returns the lines of the glyph of a point, empty for the "dot" style (or an unknown
+/// style), which is drawn as a single canvas point.
+///
+/// # Arguments
+/// - `x`, `y`: position of the point, in the static frame.
+/// - `style`: one of SUPPORTED_POINT_STYLES.
+/// - `size`: width of the glyph, in meters.
+/// - `color`: color of the glyph.
+pub fn get_point_lines(x: f64, y: f64, style: &str, size: f64, color: Color) -> Vec<Line> {
+    let half = size / 2.0;
+    let line = |x1: f64, y1: f64, x2: f64, y2: f64| Line {
+        x1,
+        y1,
+        x2,
+        y2,
+        color,
+    };
+    match style {
+        "cross" => vec![
+            line(x - half, y - half, x + half, y + half),
+            line(x - half, y + half, x + half, y - half),
+        ],
+        "square" => vec![
+            line(x - half, y - half, x + half, y - half),
+            line(x + half, y - half, x + half, y + half),
+            line(x + half, y + half, x - half, y + half),
+            line(x - half, y + half, x - half, y - half),
+        ],
+        "circle" => (0..CIRCLE_SEGMENTS)
+            .map(|i| {
+                let step = 2.0 * std::f64::consts::PI / CIRCLE_SEGMENTS as f64;
+                let (a0, a1) = (i as f64 * step, (i + 1) as f64 * step);
+                line(
+                    x + half * a0.cos(),
+                    y + half * a0.sin(),
+                    x + half * a1.cos(),
+                    y + half * a1.sin(),
+                )
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Adds a point to a chain of the monotone chain algorithm, removing the previous points
+/// that would make the chain turn clockwise or go straight.
+fn push_to_chain(chain: &mut Vec<(f64, f64)>, p: (f64, f64)) {
+    while let [.., o, a] = chain[..] {
+        if (a.0 - o.0) * (p.1 - o.1) - (a.1 - o.1) * (p.0 - o.0) > 0.0 {
+            break;
+        }
+        chain.pop();
+    }
+    chain.push(p);
+}
+
+/// Computes the convex hull of points with the monotone chain algorithm.
+///
+/// Returns the vertices of the hull counter-clockwise, without collinear points: two
+/// points if all the points are on a line, and one or none for a single or no point.
+pub fn get_convex_hull(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut sorted: Vec<(f64, f64)> = points
+        .iter()
+        .filter(|p| p.0.is_finite() && p.1.is_finite())
+        .cloned()
+        .collect();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+    let mut lower = Vec::new();
+    for p in sorted.iter() {
+        push_to_chain(&mut lower, *p);
+    }
+    let mut upper = Vec::new();
+    for p in sorted.iter().rev() {
+        push_to_chain(&mut upper, *p);
+    }
+    // each chain ends at the start of the other one
+    lower.pop();
+    upper.pop();
+    lower.extend(upper);
+    lower
+}
+
+/// Returns the outline of the convex hull of points, a single line if they are collinear
+/// and nothing for less than 2 points.
+pub fn get_hull_lines(points: &[(f64, f64)], color: Color) -> Vec<Line> {
+    let hull = get_convex_hull(points);
+    let line = |start: &(f64, f64), end: &(f64, f64)| Line {
+        x1: start.0,
+        y1: start.1,
+        x2: end.0,
+        y2: end.1,
+        color,
+    };
+    match hull.len() {
+        0 | 1 => Vec::new(),
+        2 => vec![line(&hull[0], &hull[1])],
+        n => (0..n).map(|i| line(&hull[i], &hull[(i + 1) % n])).collect(),
+    }
+}
+
+/// Averages the colors of the points falling in the same terminal cell, which can show a
+/// single color, instead of keeping the color of the last one drawn.
+///
+/// Returns the points grouped by color. The points whose color has no RGB value, e.g. the
+/// default color of the terminal, keep it.
+///
+/// # Arguments
+/// - `points`: the points with their color, in the static frame.
+/// - `origin`: bottom left corner of the canvas, in the static frame.
+/// - `cell_size`: width and height of a terminal cell, in meters.
+pub fn blend_coincident_points(
+    points: &[((f64, f64), Color)],
+    origin: (f64, f64),
+    cell_size: (f64, f64),
+) -> Vec<(Color, Vec<(f64, f64)>)> {
+    let get_cell = |pt: &(f64, f64)| {
+        (
+            ((pt.0 - origin.0) / cell_size.0).floor() as i64,
+            ((pt.1 - origin.1) / cell_size.1).floor() as i64,
+        )
+    };
+    let mut sums: HashMap<(i64, i64), ([u32; 3], u32)> = HashMap::new();
+    for (pt, color) in points {
+        if let Some((r, g, b)) = get_rgb(*color) {
+            let (sum, count) = sums.entry(get_cell(pt)).or_insert(([0; 3], 0));
+            sum[0] += r as u32;
+            sum[1] += g as u32;
+            sum[2] += b as u32;
+            *count += 1;
+        }
+    }
+    let mut groups: HashMap<(u8, u8, u8), Vec<(f64, f64)>> = HashMap::new();
+    let mut others: Vec<(Color, Vec<(f64, f64)>)> = Vec::new();
+    for (pt, color) in points {
+        match sums.get(&get_cell(pt)) {
+            Some((sum, count)) if get_rgb(*color).is_some() => {
+                let rgb = (
+                    (sum[0] / count) as u8,
+                    (sum[1] / count) as u8,
+                    (sum[2] / count) as u8,
+                );
+                groups.entry(rgb).or_default().push(*pt);
+            }
+            _ => others.push((*color, vec![*pt])),
+        }
+    }
+    groups
+        .into_iter()
+        .map(|((r, g, b), points)| (Color::Rgb(r, g, b), points))
+        .chain(others)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the color of a point in the groups of blend_coincident_points.
+    fn get_color(groups: &[(Color, Vec<(f64, f64)>)], point: (f64, f64)) -> Color {
+        let matches: Vec<Color> = groups
+            .iter()
+            .filter(|(_, points)| points.contains(&point))
+            .map(|(color, _)| *color)
+            .collect();
+        assert_eq!(
+            matches.len(),
+            1,
+            "{:?} is in {} groups",
+            point,
+            matches.len()
+        );
+        matches[0]
+    }
+
+    #[test]
+    fn coincident_points_blend() {
+        // cells of 1 x 0.5 m from the origin
+        let points = [
+            ((0.2, 0.1), Color::Rgb(200, 0, 100)),
+            ((0.8, 0.4), Color::Rgb(0, 100, 50)),
+            ((1.5, 0.1), Color::Rgb(10, 20, 30)),
+            ((0.5, 0.3), Color::Reset),
+        ];
+        let groups = blend_coincident_points(&points, (0.0, 0.0), (1.0, 0.5));
+        // the two points of the first cell take the average of their colors
+        assert_eq!(get_color(&groups, (0.2, 0.1)), Color::Rgb(100, 50, 75));
+        assert_eq!(get_color(&groups, (0.8, 0.4)), Color::Rgb(100, 50, 75));
+        // a point alone in its cell keeps its color
+        assert_eq!(get_color(&groups, (1.5, 0.1)), Color::Rgb(10, 20, 30));
+        // a color without RGB value is kept, and not averaged with the others
+        assert_eq!(get_color(&groups, (0.5, 0.3)), Color::Reset);
+        let drawn: usize = groups.iter().map(|(_, points)| points.len()).sum();
+        assert_eq!(drawn, points.len());
+    }
+
+    #[test]
+    fn glyph_width_modes() {
+        // zoomed out, then zoomed in by 4
+        for cell_width in [0.2, 0.05] {
+            assert!((get_glyph_width(3.0, "screen", cell_width) - 3.0 * cell_width).abs() < 1e-12);
+            assert_eq!(get_glyph_width(0.4, "world", cell_width), 0.4);
+        }
+        // an unknown mode keeps the glyphs at their size on screen
+        assert_eq!(
+            get_glyph_width(3.0, "pixels", 0.2),
+            get_glyph_width(3.0, "screen", 0.2)
+        );
+    }
+
+    /// Returns the glyph of a point at (1, 2) with the given style at two zoom levels, as
+    /// the width of the glyph and its lines.
+    fn get_zoomed_glyphs(style: &str) -> Vec<(f64, Vec<Line>)> {
+        [0.2, 0.05]
+            .iter()
+            .map(|cell_width| {
+                let size = get_glyph_width(2.0, "screen", *cell_width);
+                (size, get_point_lines(1.0, 2.0, style, size, Color::Red))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dot_glyph() {
+        assert!(is_dot("dot"));
+        assert!(is_dot("star"));
+        assert!(!is_dot("cross"));
+        for style in ["dot", "star"] {
+            for (_, lines) in get_zoomed_glyphs(style) {
+                assert!(lines.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn cross_glyph() {
+        for (size, lines) in get_zoomed_glyphs("cross") {
+            assert_eq!(lines.len(), 2);
+            for line in &lines {
+                // diagonals of the square of the glyph, through the point
+                assert!(((line.x1 + line.x2) / 2.0 - 1.0).abs() < 1e-12);
+                assert!(((line.y1 + line.y2) / 2.0 - 2.0).abs() < 1e-12);
+                assert!(((line.x2 - line.x1).abs() - size).abs() < 1e-12);
+                assert!(((line.y2 - line.y1).abs() - size).abs() < 1e-12);
+                assert_eq!(line.color, Color::Red);
+            }
+        }
+    }
+
+    #[test]
+    fn square_glyph() {
+        for (size, lines) in get_zoomed_glyphs("square") {
+            assert_eq!(lines.len(), 4);
+            for (i, line) in lines.iter().enumerate() {
+                // closed, with the corners at half the size from the point
+                let next = &lines[(i + 1) % lines.len()];
+                assert_eq!((line.x2, line.y2), (next.x1, next.y1));
+                assert!(((line.x1 - 1.0).abs() - size / 2.0).abs() < 1e-12);
+                assert!(((line.y1 - 2.0).abs() - size / 2.0).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn circle_glyph() {
+        for (size, lines) in get_zoomed_glyphs("circle") {
+            assert_eq!(lines.len(), CIRCLE_SEGMENTS);
+            for (i, line) in lines.iter().enumerate() {
+                // closed, with its vertices at half the size from the point
+                let next = &lines[(i + 1) % lines.len()];
+                assert!((line.x2 - next.x1).abs() < 1e-12 && (line.y2 - next.y1).abs() < 1e-12);
+                assert!(((line.x1 - 1.0).hypot(line.y1 - 2.0) - size / 2.0).abs() < 1e-12);
+            }
+        }
+    }
+}
@@ -1,4 +1,7 @@
+use crate::colormap::Colormap;
 use crate::config::PointCloud2ListenerConfig;
+use crate::listeners::{get_source_frame, warn_frame_override, FrameFilter};
+use crate::rate::RateMonitor;
 use byteorder::{ByteOrder, LittleEndian};
 use colorgrad;
 use std::sync::{Arc, RwLock};
@@ -6,16 +9,16 @@ use std::sync::{Arc, RwLock};
 use nalgebra::geometry::Point3;
 use tui::style::Color;
 
-use crate::transformation::ros_transform_to_isometry;
+use crate::transformation::{lookup_transform_with_tolerance, ros_transform_to_isometry};
 use rosrust;
 use rustros_tf;
 
 pub struct PointCloud2Listener {
     pub config: PointCloud2ListenerConfig,
     pub points: Arc<RwLock<Vec<ColoredPoint>>>,
-    _tf_listener: Arc<rustros_tf::TfListener>,
-    _static_frame: String,
-    _subscriber: rosrust::Subscriber,
+    pub rate: RateMonitor,
+    /// Unsubscribed while a lazy layer is disabled.
+    subscriber: LazySubscriber<rosrust::Subscriber>,
 }
 
 #[derive(Clone)]
@@ -80,6 +83,11 @@ pub fn colorize_from_rgb(
     points
 }
 
+/// Returns true if the height of the point is within the window, if any.
+fn is_in_window(point: &Point3<f64>, z_window: Option<[f64; 2]>) -> bool {
+    z_window.is_none_or(|[low, high]| point.z >= low && point.z <= high)
+}
+
 pub fn colorize_points(mut points: Vec<ColoredPoint>, min_z: f64, max_z: f64) -> Vec<ColoredPoint> {
     let grad = colorgrad::turbo();
     for pt in points.iter_mut() {
@@ -89,66 +97,241 @@ pub fn colorize_points(mut points: Vec<ColoredPoint>, min_z: f64, max_z: f64) ->
     points
 }
 
+/// Colors the points along the colormap by their height, the points below the first
+/// bound of `z_range` or above the second taking the color of the closest end.
+pub fn colorize_by_height(
+    mut points: Vec<ColoredPoint>,
+    colormap: &Colormap,
+    z_range: [f64; 2],
+) -> Vec<ColoredPoint> {
+    let [low, high] = z_range;
+    for pt in points.iter_mut() {
+        let fraction = if high > low {
+            (pt.point.z - low) / (high - low)
+        } else {
+            0.5
+        };
+        pt.color = colormap.color_at(fraction);
+    }
+    points
+}
+
+/// Subscriber of a layer, created on demand with `subscribe`, so that a lazy layer is only
+/// subscribed to its topic while it's enabled.
+struct LazySubscriber<S> {
+    subscribe: Box<dyn Fn() -> S>,
+    /// None while unsubscribed.
+    subscriber: Option<S>,
+}
+
+impl<S> LazySubscriber<S> {
+    fn new(subscribe: Box<dyn Fn() -> S>) -> LazySubscriber<S> {
+        LazySubscriber {
+            subscribe,
+            subscriber: None,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.subscriber.is_some()
+    }
+
+    /// Subscribes, if not subscribed yet, so that enabling a layer twice doesn't create a
+    /// second subscriber.
+    fn activate(&mut self) {
+        if self.subscriber.is_none() {
+            self.subscriber = Some((self.subscribe)());
+        }
+    }
+
+    /// Drops the subscriber, which unsubscribes from the topic.
+    fn deactivate(&mut self) {
+        self.subscriber = None;
+    }
+}
+
+/// Subscribes to the point clouds of a layer, transforming them to the static frame.
+fn subscribe(
+    config: &PointCloud2ListenerConfig,
+    points: &Arc<RwLock<Vec<ColoredPoint>>>,
+    tf_listener: &Arc<rustros_tf::TfListener>,
+    static_frame: &str,
+    rate: &RateMonitor,
+) -> rosrust::Subscriber {
+    let cb_occ_points = points.clone();
+    let str_ = static_frame.to_string();
+    let local_listener = tf_listener.clone();
+    let use_rgb = config.use_rgb;
+    let color_by_height = config.color_by == "z";
+    let z_range = config.z_range;
+    let z_window = config.z_window;
+    let colormap = Colormap::new(&config.colormap);
+    let frame_filter = FrameFilter::new(&config.frame_filter);
+    let override_frame_id = config.override_frame_id.clone();
+    let lookup_tolerance = config.lookup_tolerance;
+    let cb_rate = rate.clone();
+    rosrust::subscribe(
+        &config.topic,
+        1,
+        move |cloud: rosrust_msg::sensor_msgs::PointCloud2| {
+            cb_rate.record();
+            cb_rate.record_stamp(cloud.header.stamp);
+            if !frame_filter.accepts(&cloud.header.frame_id) {
+                return;
+            }
+            let mut points: Vec<ColoredPoint> = Vec::new();
+            let res = lookup_transform_with_tolerance(
+                &local_listener,
+                &str_,
+                get_source_frame(&cloud.header.frame_id, &override_frame_id),
+                cloud.header.stamp,
+                lookup_tolerance,
+            );
+            match &res {
+                Ok(res) => res,
+                Err(_e) => return,
+            };
+
+            let isometry = ros_transform_to_isometry(&res.unwrap().transform);
+            let mut max_z = f64::MIN;
+            let mut min_z = f64::MAX;
+            for pt in read_xyz(&cloud) {
+                let trans_pt = isometry.transform_point(&pt);
+                // the points out of the window don't stretch the automatic range
+                if is_in_window(&trans_pt, z_window) {
+                    max_z = max_z.max(trans_pt.z);
+                    min_z = min_z.min(trans_pt.z);
+                }
+                points.push(ColoredPoint::new(Some(trans_pt), None));
+            }
+            if use_rgb {
+                points = colorize_from_rgb(points, &cloud);
+            } else if color_by_height {
+                points = colorize_by_height(points, &colormap, z_range);
+            } else {
+                points = colorize_points(points, min_z, max_z);
+            }
+            points = points
+                .into_iter()
+                .filter(|n| !n.point.z.is_nan() && is_in_window(&n.point, z_window))
+                .collect::<Vec<_>>();
+            let mut cb_occ_points = cb_occ_points.write().unwrap();
+            *cb_occ_points = points;
+        },
+    )
+    .unwrap()
+}
+
 impl PointCloud2Listener {
     pub fn new(
         config: PointCloud2ListenerConfig,
         tf_listener: Arc<rustros_tf::TfListener>,
         static_frame: String,
     ) -> PointCloud2Listener {
-        let occ_points = Arc::new(RwLock::new(Vec::<ColoredPoint>::new()));
-        let cb_occ_points = occ_points.clone();
-        let str_ = static_frame.clone();
-        let local_listener = tf_listener.clone();
-        let use_rgb = config.use_rgb.clone();
-        let _sub = rosrust::subscribe(
-            &config.topic,
-            1,
-            move |cloud: rosrust_msg::sensor_msgs::PointCloud2| {
-                let mut points: Vec<ColoredPoint> = Vec::new();
-                let res = local_listener.clone().lookup_transform(
-                    &str_,
-                    &cloud.header.frame_id,
-                    cloud.header.stamp,
-                );
-                match &res {
-                    Ok(res) => res,
-                    Err(_e) => return,
-                };
-
-                let isometry = ros_transform_to_isometry(&res.unwrap().transform);
-                let mut max_z = f64::MIN;
-                let mut min_z = f64::MAX;
-                for pt in read_xyz(&cloud) {
-                    let trans_pt = isometry.transform_point(&pt);
-                    if trans_pt.z > max_z {
-                        max_z = trans_pt.z;
-                    }
-                    if trans_pt.z < min_z {
-                        min_z = trans_pt.z;
-                    }
-                    points.push(ColoredPoint::new(Some(trans_pt), None));
-                }
-                if use_rgb {
-                    points = colorize_from_rgb(points, &cloud);
-                } else {
-                    points = colorize_points(points, min_z, max_z);
-                }
-                points = points
-                    .into_iter()
-                    .filter(|n| !n.point.z.is_nan())
-                    .collect::<Vec<_>>();
-                let mut cb_occ_points = cb_occ_points.write().unwrap();
-                *cb_occ_points = points;
-            },
-        )
-        .unwrap();
-
-        PointCloud2Listener {
+        warn_frame_override(&config.topic, &config.override_frame_id);
+        let points = Arc::new(RwLock::new(Vec::<ColoredPoint>::new()));
+        let rate = RateMonitor::new();
+        let sub_config = config.clone();
+        let sub_points = points.clone();
+        let sub_rate = rate.clone();
+        let mut listener = PointCloud2Listener {
+            points,
+            rate,
+            subscriber: LazySubscriber::new(Box::new(move || {
+                subscribe(
+                    &sub_config,
+                    &sub_points,
+                    &tf_listener,
+                    &static_frame,
+                    &sub_rate,
+                )
+            })),
             config,
-            points: occ_points,
-            _tf_listener: tf_listener,
-            _static_frame: static_frame.to_string(),
-            _subscriber: _sub,
+        };
+        if listener.config.lazy {
+            listener.rate.set_subscribed(false);
+        } else {
+            listener.activate();
+        }
+        listener
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.subscriber.is_active()
+    }
+
+    /// Subscribes to the topic, if not subscribed yet.
+    pub fn activate(&mut self) {
+        self.subscriber.activate();
+        self.rate.set_subscribed(true);
+    }
+
+    /// Unsubscribes from the topic, the subscriber being dropped, and forgets the points.
+    pub fn deactivate(&mut self) {
+        self.subscriber.deactivate();
+        self.rate.set_subscribed(false);
+        self.points.write().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn height_colors_clamped() {
+        let colormap = Colormap::new("turbo");
+        let points: Vec<ColoredPoint> = [0.0, 1.0, 2.0, -5.0, 10.0]
+            .iter()
+            .map(|z| ColoredPoint {
+                point: Point3::new(0.0, 0.0, *z),
+                color: Color::White,
+            })
+            .collect();
+        let colors: Vec<Color> = colorize_by_height(points, &colormap, [0.0, 2.0])
+            .iter()
+            .map(|pt| pt.color)
+            .collect();
+        let (low, high) = (colormap.color_at(0.0), colormap.color_at(1.0));
+        assert_eq!(colors[0], low);
+        assert_eq!(colors[1], colormap.color_at(0.5));
+        assert_eq!(colors[2], high);
+        // the points outside of the range take the color of the closest end
+        assert_eq!(colors[3], low);
+        assert_eq!(colors[4], high);
+    }
+
+    /// Stands for a subscriber, counting the live ones.
+    struct CountedSubscriber(Rc<Cell<usize>>);
+
+    impl Drop for CountedSubscriber {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() - 1);
+        }
+    }
+
+    #[test]
+    fn lazy_subscriber_toggles() {
+        let live = Rc::new(Cell::new(0));
+        let created = Rc::new(Cell::new(0));
+        let (sub_live, sub_created) = (live.clone(), created.clone());
+        let mut subscriber = LazySubscriber::new(Box::new(move || {
+            sub_live.set(sub_live.get() + 1);
+            sub_created.set(sub_created.get() + 1);
+            CountedSubscriber(sub_live.clone())
+        }));
+        assert!(!subscriber.is_active());
+        for toggle in 1..=2 {
+            subscriber.activate();
+            // activating an active layer keeps its subscriber
+            subscriber.activate();
+            assert!(subscriber.is_active());
+            assert_eq!((live.get(), created.get()), (1, toggle));
+            subscriber.deactivate();
+            assert!(subscriber.subscriber.is_none());
+            assert_eq!(live.get(), 0);
         }
     }
 }
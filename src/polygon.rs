@@ -0,0 +1,461 @@
+//! Module dealing with the visualization of polygons, e.g., zones published as
+//! geometry_msgs::PolygonStamped.
+
+use crate::colormap::Colormap;
+use crate::config::PolygonListenerConfig;
+use crate::listeners::{get_source_frame, retain_finite_lines, warn_frame_override, FrameFilter};
+use crate::point_style::get_point_lines;
+use crate::rate::RateMonitor;
+use crate::transformation::{lookup_transform_with_tolerance, ros_transform_to_isometry};
+use nalgebra::geometry::Point3;
+use std::sync::{Arc, RwLock};
+use tui::style::Color;
+use tui::widgets::canvas::Line;
+
+/// Reads the vertices of a polygon, including their height.
+fn read_points(polygon: &rosrust_msg::geometry_msgs::Polygon) -> Vec<Point3<f64>> {
+    polygon
+        .points
+        .iter()
+        .map(|p| Point3::new(p.x as f64, p.y as f64, p.z as f64))
+        .collect()
+}
+
+/// Computes the hatching of the interior of a polygon: parallel lines with the given
+/// spacing and angle, clipped to the polygon with the even-odd rule, so concave
+/// polygons and self-intersections are handled.
+///
+/// # Arguments
+/// - `points`: vertices of the polygon, only x and y are used.
+/// - `spacing`: distance between two hatch lines.
+/// - `angle`: angle of the hatch lines with the x axis, in radians.
+fn get_hatch_segments(points: &[Point3<f64>], spacing: f64, angle: f64) -> Vec<[f64; 4]> {
+    if points.len() < 3 || spacing.is_nan() || spacing <= 0.0 {
+        return Vec::new();
+    }
+    // In a frame rotated by the angle, the hatch lines are horizontal
+    let (sin, cos) = angle.sin_cos();
+    let rotated: Vec<(f64, f64)> = points
+        .iter()
+        .map(|p| (cos * p.x + sin * p.y, -sin * p.x + cos * p.y))
+        .collect();
+    let min_v = rotated.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+    let max_v = rotated
+        .iter()
+        .map(|p| p.1)
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    let mut segments = Vec::new();
+    // lines are aligned on multiples of the spacing, so they don't move with the polygon
+    let mut v = (min_v / spacing).ceil() * spacing;
+    while v <= max_v {
+        let mut crossings: Vec<f64> = Vec::new();
+        for i in 0..rotated.len() {
+            let start = rotated[i];
+            let end = rotated[(i + 1) % rotated.len()];
+            // half-open test, so a vertex on the line is counted once
+            if (start.1 <= v) != (end.1 <= v) {
+                let t = (v - start.1) / (end.1 - start.1);
+                crossings.push(start.0 + t * (end.0 - start.0));
+            }
+        }
+        crossings.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        for pair in crossings.chunks_exact(2) {
+            segments.push([
+                cos * pair[0] - sin * v,
+                sin * pair[0] + cos * v,
+                cos * pair[1] - sin * v,
+                sin * pair[1] + cos * v,
+            ]);
+        }
+        v += spacing;
+    }
+    segments
+}
+
+/// Holds the edges of a polygon projected in the static frame.
+pub struct PolygonData {
+    pub config: PolygonListenerConfig,
+    pub lines_in_static_frame: Vec<Line>,
+}
+
+impl PolygonData {
+    pub fn new(config: PolygonListenerConfig) -> PolygonData {
+        PolygonData {
+            config,
+            lines_in_static_frame: Vec::new(),
+        }
+    }
+
+    /// Projects the edges of the polygon (closed) in the static frame.
+    ///
+    /// With the "height" z_style, each edge is colored by the average height of its
+    /// vertices, between the lowest and the highest vertex of the polygon. Otherwise the
+    /// polygon is projected on the XY plane with the configured color.
+    ///
+    /// With the "hatched" fill, the interior is also covered with diagonal lines of the
+    /// configured color, to highlight e.g. keepout zones.
+    ///
+    /// With vertices, a glyph is also drawn at each vertex, to tell the actual vertices of
+    /// a dense polygon from the corners of its edges.
+    ///
+    /// # Arguments
+    /// - `polygon`: the polygon, expressed in its own frame.
+    /// - `transform`: transform of the frame of the polygon in the static frame.
+    pub fn update(
+        &mut self,
+        polygon: &rosrust_msg::geometry_msgs::Polygon,
+        transform: &rosrust_msg::geometry_msgs::Transform,
+    ) {
+        let isometry = ros_transform_to_isometry(transform);
+        let points: Vec<Point3<f64>> = read_points(polygon)
+            .iter()
+            .map(|p| isometry.transform_point(p))
+            .collect();
+        let color = Color::Rgb(
+            self.config.color.r,
+            self.config.color.g,
+            self.config.color.b,
+        );
+        let colormap = match self.config.z_style.as_str() {
+            "height" => Some(Colormap::new("turbo")),
+            _ => None,
+        };
+        let min_z = points.iter().map(|p| p.z).fold(f64::INFINITY, f64::min);
+        let max_z = points.iter().map(|p| p.z).fold(f64::NEG_INFINITY, f64::max);
+
+        let edges: Vec<Line> = (0..points.len())
+            .map(|i| {
+                let start = &points[i];
+                let end = &points[(i + 1) % points.len()];
+                let edge_color = match &colormap {
+                    Some(colormap) if max_z > min_z => {
+                        colormap.color_at(((start.z + end.z) / 2.0 - min_z) / (max_z - min_z))
+                    }
+                    Some(colormap) => colormap.color_at(0.5),
+                    None => color,
+                };
+                Line {
+                    x1: start.x,
+                    y1: start.y,
+                    x2: end.x,
+                    y2: end.y,
+                    color: edge_color,
+                }
+            })
+            .collect();
+
+        // the scanlines of the hatch can't be bounded with a non-finite vertex
+        let finite = points.iter().all(|p| p.x.is_finite() && p.y.is_finite());
+        let mut fill = Vec::new();
+        if self.config.fill == "hatched" && finite {
+            let segments = get_hatch_segments(
+                &points,
+                self.config.hatch_spacing,
+                self.config.hatch_angle.to_radians(),
+            );
+            fill.extend(segments.iter().map(|s| Line {
+                x1: s[0],
+                y1: s[1],
+                x2: s[2],
+                y2: s[3],
+                color,
+            }));
+        }
+        // the lines drawn last take the cells they share with the previous ones
+        self.lines_in_static_frame = if self.config.outline_on_top {
+            fill.into_iter().chain(edges).collect()
+        } else {
+            edges.into_iter().chain(fill).collect()
+        };
+        if let Some(vertices) = &self.config.vertices {
+            let vertex_color = Color::Rgb(vertices.color.r, vertices.color.g, vertices.color.b);
+            for point in &points {
+                self.lines_in_static_frame.extend(get_point_lines(
+                    point.x,
+                    point.y,
+                    &vertices.point_style,
+                    vertices.size,
+                    vertex_color,
+                ));
+            }
+        }
+        retain_finite_lines(&mut self.lines_in_static_frame, &self.config.topic);
+    }
+}
+
+pub struct PolygonListener {
+    pub config: PolygonListenerConfig,
+    pub data: Arc<RwLock<PolygonData>>,
+    pub rate: RateMonitor,
+    _tf_listener: Arc<rustros_tf::TfListener>,
+    _static_frame: String,
+    _subscriber: rosrust::Subscriber,
+}
+
+impl PolygonListener {
+    pub fn new(
+        config: PolygonListenerConfig,
+        tf_listener: Arc<rustros_tf::TfListener>,
+        static_frame: String,
+    ) -> PolygonListener {
+        let data = Arc::new(RwLock::new(PolygonData::new(config.clone())));
+        let cb_data = data.clone();
+        let str_ = static_frame.clone();
+        let local_listener = tf_listener.clone();
+        let frame_filter = FrameFilter::new(&config.frame_filter);
+        warn_frame_override(&config.topic, &config.override_frame_id);
+        let override_frame_id = config.override_frame_id.clone();
+        let lookup_tolerance = config.lookup_tolerance;
+        let rate = RateMonitor::new();
+        let cb_rate = rate.clone();
+        let sub = rosrust::subscribe(
+            &config.topic,
+            2,
+            move |polygon: rosrust_msg::geometry_msgs::PolygonStamped| {
+                cb_rate.record();
+                cb_rate.record_stamp(polygon.header.stamp);
+                if !frame_filter.accepts(&polygon.header.frame_id) {
+                    return;
+                }
+                // An empty polygon clears the previous one, even if its frame can't be
+                // looked up (publishers clearing a polygon often leave the header empty).
+                if polygon.polygon.points.is_empty() {
+                    cb_data.write().unwrap().lines_in_static_frame.clear();
+                    return;
+                }
+                let res = lookup_transform_with_tolerance(
+                    &local_listener,
+                    &str_,
+                    get_source_frame(&polygon.header.frame_id, &override_frame_id),
+                    polygon.header.stamp,
+                    lookup_tolerance,
+                );
+                match &res {
+                    Ok(res) => cb_data
+                        .write()
+                        .unwrap()
+                        .update(&polygon.polygon, &res.transform),
+                    Err(_e) => (),
+                };
+            },
+        )
+        .unwrap();
+
+        PolygonListener {
+            config,
+            data,
+            rate,
+            _tf_listener: tf_listener,
+            _static_frame: static_frame,
+            _subscriber: sub,
+        }
+    }
+
+    pub fn get_lines(&self) -> Vec<Line> {
+        self.data.read().unwrap().lines_in_static_frame.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Color as RgbColor, PolygonVerticesConfig};
+
+    fn get_config(z_style: &str) -> PolygonListenerConfig {
+        PolygonListenerConfig {
+            topic: "polygon".to_string(),
+            color: RgbColor {
+                r: 255,
+                g: 255,
+                b: 255,
+            },
+            z_style: z_style.to_string(),
+            fill: "none".to_string(),
+            hatch_spacing: 0.2,
+            hatch_angle: 45.0,
+            outline_on_top: true,
+            vertices: None,
+            frame_filter: None,
+            override_frame_id: None,
+            lookup_tolerance: 0.0,
+            opacity: 1.0,
+            center_on_first: false,
+        }
+    }
+
+    fn get_polygon(points: &[(f32, f32, f32)]) -> rosrust_msg::geometry_msgs::Polygon {
+        rosrust_msg::geometry_msgs::Polygon {
+            points: points
+                .iter()
+                .map(|(x, y, z)| rosrust_msg::geometry_msgs::Point32 {
+                    x: *x,
+                    y: *y,
+                    z: *z,
+                })
+                .collect(),
+        }
+    }
+
+    fn get_identity() -> rosrust_msg::geometry_msgs::Transform {
+        let mut transform = rosrust_msg::geometry_msgs::Transform::default();
+        transform.rotation.w = 1.0;
+        transform
+    }
+
+    #[test]
+    fn edges_colored_by_height() {
+        let polygon = get_polygon(&[(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 2.0)]);
+        assert_eq!(read_points(&polygon)[2], Point3::new(0.0, 1.0, 2.0));
+        let mut data = PolygonData::new(get_config("height"));
+        data.update(&polygon, &get_identity());
+        let colormap = Colormap::new("turbo");
+        let colors: Vec<Color> = data.lines_in_static_frame.iter().map(|l| l.color).collect();
+        // the edge at the bottom, then both edges to the top vertex at mid-height
+        assert_eq!(
+            colors,
+            vec![
+                colormap.color_at(0.0),
+                colormap.color_at(0.5),
+                colormap.color_at(0.5)
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_polygon_clears_lines() {
+        let mut data = PolygonData::new(get_config("project"));
+        let square = get_polygon(&[
+            (0.0, 0.0, 0.0),
+            (1.0, 0.0, 0.0),
+            (1.0, 1.0, 0.0),
+            (0.0, 1.0, 0.0),
+        ]);
+        data.update(&square, &get_identity());
+        assert_eq!(data.lines_in_static_frame.len(), 4);
+        data.update(&get_polygon(&[]), &get_identity());
+        assert!(data.lines_in_static_frame.is_empty());
+    }
+
+    /// Returns true if a point is inside a polygon, with the even-odd rule.
+    fn is_inside(polygon: &[Point3<f64>], x: f64, y: f64) -> bool {
+        let mut inside = false;
+        for i in 0..polygon.len() {
+            let (start, end) = (&polygon[i], &polygon[(i + 1) % polygon.len()]);
+            if (start.y <= y) != (end.y <= y)
+                && x < start.x + (y - start.y) / (end.y - start.y) * (end.x - start.x)
+            {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    #[test]
+    fn hatch_stays_in_concave_polygon() {
+        // U shape, 3 m wide and high, with a notch 1 m wide from the top down to 1 m, offset
+        // so that no hatch line goes through a vertex or along an edge
+        let (ox, oy) = (0.13, 0.07);
+        let points: Vec<Point3<f64>> = [
+            (0.0, 0.0),
+            (3.0, 0.0),
+            (3.0, 3.0),
+            (2.0, 3.0),
+            (2.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 3.0),
+            (0.0, 3.0),
+        ]
+        .iter()
+        .map(|(x, y)| Point3::new(x + ox, y + oy, 0.0))
+        .collect();
+        let is_in_notch =
+            |x: f64, y: f64| x > 1.0 + ox && x < 2.0 + ox && y > 1.0 + oy && y < 3.0 + oy;
+        for angle in [0.0, 30.0, 45.0, 60.0, 90.0, 135.0] {
+            let segments = get_hatch_segments(&points, 0.25, f64::to_radians(angle));
+            assert!(!segments.is_empty());
+            for [x1, y1, x2, y2] in &segments {
+                let (x, y) = ((x1 + x2) / 2.0, (y1 + y2) / 2.0);
+                assert!(is_inside(&points, x, y), "({}, {}) at {} deg", x, y, angle);
+                for i in 1..20 {
+                    let t = i as f64 / 20.0;
+                    let (x, y) = (x1 + t * (x2 - x1), y1 + t * (y2 - y1));
+                    assert!(!is_in_notch(x, y), "({}, {}) at {} deg", x, y, angle);
+                }
+            }
+        }
+        // a horizontal line through the arms is split at the notch
+        let segments = get_hatch_segments(&points, 0.5, 0.0);
+        let at_y2: Vec<[f64; 2]> = segments
+            .iter()
+            .filter(|s| s[1] == 2.0)
+            .map(|s| [s[0] - ox, s[2] - ox])
+            .collect();
+        assert_eq!(at_y2.len(), 2);
+        for (segment, expected) in at_y2.iter().zip([[0.0, 1.0], [2.0, 3.0]]) {
+            assert!((segment[0] - expected[0]).abs() < 1e-9, "{:?}", segment);
+            assert!((segment[1] - expected[1]).abs() < 1e-9, "{:?}", segment);
+        }
+    }
+
+    #[test]
+    fn vertex_markers_of_triangle() {
+        let mut config = get_config("project");
+        config.vertices = Some(PolygonVerticesConfig {
+            color: RgbColor {
+                r: 255,
+                g: 255,
+                b: 0,
+            },
+            point_style: "cross".to_string(),
+            size: 0.2,
+        });
+        let mut data = PolygonData::new(config);
+        let vertices = [(0.0, 0.0, 0.0), (2.0, 0.0, 0.0), (1.0, 1.5, 0.0)];
+        data.update(&get_polygon(&vertices), &get_identity());
+        let lines = &data.lines_in_static_frame;
+        // the 3 edges, then the 2 lines of the cross of each vertex
+        assert_eq!(lines.len(), 3 + 3 * 2);
+        for (glyph, (x, y, _)) in lines[3..].chunks(2).zip(vertices) {
+            for line in glyph {
+                assert_eq!(line.color, Color::Rgb(255, 255, 0));
+                assert!(((line.x1 + line.x2) / 2.0 - x as f64).abs() < 1e-9);
+                assert!(((line.y1 + line.y2) / 2.0 - y as f64).abs() < 1e-9);
+                assert!(((line.x2 - line.x1).abs() - 0.2).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn outline_order() {
+        let square = get_polygon(&[
+            (0.0, 0.0, 0.0),
+            (1.0, 0.0, 0.0),
+            (1.0, 1.0, 0.0),
+            (0.0, 1.0, 0.0),
+        ]);
+        let mut config = get_config("height");
+        config.fill = "hatched".to_string();
+        // the edges are colored by height, so they are told from the fill of the config color
+        let fill_color = Color::Rgb(255, 255, 255);
+        for outline_on_top in [true, false] {
+            config.outline_on_top = outline_on_top;
+            let mut data = PolygonData::new(config.clone());
+            data.update(&square, &get_identity());
+            let is_fill: Vec<bool> = data
+                .lines_in_static_frame
+                .iter()
+                .map(|line| line.color == fill_color)
+                .collect();
+            let fill_count = is_fill.iter().filter(|is_fill| **is_fill).count();
+            assert!(fill_count > 0);
+            assert_eq!(is_fill.len(), fill_count + 4);
+            let expected: Vec<bool> = if outline_on_top {
+                [vec![true; fill_count], vec![false; 4]].concat()
+            } else {
+                [vec![false; 4], vec![true; fill_count]].concat()
+            };
+            assert_eq!(is_fill, expected, "outline_on_top: {}", outline_on_top);
+        }
+    }
+}
@@ -1,8 +1,17 @@
-use crate::config::{Color, PoseListenerConfig};
-use crate::transformation::ros_pose_to_isometry;
+use crate::colormap::Colormap;
+use crate::config::{BreadcrumbsConfig, Color, PathBandConfig, PathTubeConfig, PoseListenerConfig};
+use crate::heatmap::COLOR_LEVELS;
+use crate::listeners::{get_source_frame, warn_frame_override, FrameFilter};
+use crate::map::ColoredPoints;
+use crate::opacity::blend_color;
+use crate::point_style::get_point_lines;
+use crate::rate::RateMonitor;
+use crate::transformation::{ros_pose_to_isometry, ros_transform_to_isometry};
 use nalgebra::geometry::{Isometry3, Point3};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::option::Option;
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use tui::style;
 use tui::widgets::canvas::Line;
 
@@ -86,22 +95,362 @@ fn poses_to_lines(poses: &Vec<Isometry3<f64>>, color: &Color) -> Vec<Line> {
         .collect()
 }
 
+/// Returns the point at `t` in [0, 1] of the uniform Catmull-Rom spline between p1 and p2,
+/// p0 and p3 being the previous and the next control points.
+fn catmull_rom(
+    p0: &Point3<f64>,
+    p1: &Point3<f64>,
+    p2: &Point3<f64>,
+    p3: &Point3<f64>,
+    t: f64,
+) -> (f64, f64) {
+    let interpolate = |c0: f64, c1: f64, c2: f64, c3: f64| {
+        0.5 * (2.0 * c1
+            + (c2 - c0) * t
+            + (2.0 * c0 - 5.0 * c1 + 4.0 * c2 - c3) * t * t
+            + (3.0 * c1 - c0 - 3.0 * c2 + c3) * t * t * t)
+    };
+    (
+        interpolate(p0.x, p1.x, p2.x, p3.x),
+        interpolate(p0.y, p1.y, p2.y, p3.y),
+    )
+}
+
+/// Returns the number of lines of the spline between two poses of a path, None if the path
+/// is drawn with straight segments, e.g. if it has fewer than 4 poses.
+fn get_spline_samples(spline_samples: Option<usize>, pose_count: usize) -> Option<usize> {
+    match spline_samples {
+        Some(samples) if samples > 0 && pose_count >= 4 => Some(samples),
+        _ => None,
+    }
+}
+
+/// Returns a Catmull-Rom spline through the positions of the poses, as `samples` lines
+/// between each pair of consecutive poses. The spline passes through the poses, the first
+/// and last ones being repeated as the missing neighbors at the ends.
+fn poses_to_spline(poses: &[Isometry3<f64>], samples: usize, color: &Color) -> Vec<Line> {
+    let points: Vec<Point3<f64>> = poses
+        .iter()
+        .map(|p| p.transform_point(&Point3::origin()))
+        .collect();
+    let color = style::Color::Rgb(color.r, color.g, color.b);
+    let mut lines = Vec::new();
+    for i in 0..points.len().saturating_sub(1) {
+        let p0 = &points[i.saturating_sub(1)];
+        let p3 = &points[(i + 2).min(points.len() - 1)];
+        let mut start = (points[i].x, points[i].y);
+        for k in 1..=samples {
+            let end = catmull_rom(
+                p0,
+                &points[i],
+                &points[i + 1],
+                p3,
+                k as f64 / samples as f64,
+            );
+            lines.push(Line {
+                x1: start.0,
+                y1: start.1,
+                x2: end.0,
+                y2: end.1,
+                color,
+            });
+            start = end;
+        }
+    }
+    lines
+}
+
+/// Ratio to the half-width above which the miter join of a tube is beveled, so that the
+/// sharp corners don't produce long spikes.
+const MITER_LIMIT: f64 = 4.0;
+
+/// Returns the polyline offset by `offset` on the left of the polyline through the points,
+/// negative for the right side.
+///
+/// At the corners, the offset segments are joined at their intersection, or with a bevel
+/// on the outer side of the turn if `bevel` is set or the intersection is further than the
+/// miter limit. The inner side always uses the intersection, unless the turn is sharp
+/// enough to exceed the limit, so that it doesn't loop back over itself.
+fn get_offset_polyline(points: &[(f64, f64)], offset: f64, bevel: bool) -> Vec<(f64, f64)> {
+    let mut points = points.to_vec();
+    points.dedup();
+    if points.len() < 2 {
+        return Vec::new();
+    }
+    // the offset of each segment, perpendicular to it
+    let normals: Vec<(f64, f64)> = points
+        .windows(2)
+        .map(|w| {
+            let (dx, dy) = (w[1].0 - w[0].0, w[1].1 - w[0].1);
+            let length = dx.hypot(dy);
+            (-dy / length * offset, dx / length * offset)
+        })
+        .collect();
+    let mut polyline = vec![(points[0].0 + normals[0].0, points[0].1 + normals[0].1)];
+    for i in 1..points.len() - 1 {
+        let (p, n0, n1) = (points[i], normals[i - 1], normals[i]);
+        let sum = (n0.0 + n1.0, n0.1 + n1.1);
+        let sum_norm2 = sum.0 * sum.0 + sum.1 * sum.1;
+        // the miter is along the sum of the offsets, at offset / cos(half of the turn)
+        let miter_ratio = 2.0 * offset.abs() / sum_norm2.sqrt();
+        // the side is outer when the path turns away from it
+        let outer = (n0.0 * n1.1 - n0.1 * n1.0) * offset < 0.0;
+        if !miter_ratio.is_finite() || miter_ratio > MITER_LIMIT || (bevel && outer) {
+            polyline.push((p.0 + n0.0, p.1 + n0.1));
+            polyline.push((p.0 + n1.0, p.1 + n1.1));
+        } else {
+            let scale = 2.0 * offset * offset / sum_norm2;
+            polyline.push((p.0 + sum.0 * scale, p.1 + sum.1 * scale));
+        }
+    }
+    let (last, normal) = (points[points.len() - 1], normals[normals.len() - 1]);
+    polyline.push((last.0 + normal.0, last.1 + normal.1));
+    polyline
+}
+
+/// Returns the left and right sides of the tube of `half_width` around the centerline.
+fn get_tube_lines(centerline: &[Line], tube: &PathTubeConfig, color: style::Color) -> Vec<Line> {
+    let mut points: Vec<(f64, f64)> = centerline
+        .first()
+        .map(|line| (line.x1, line.y1))
+        .into_iter()
+        .collect();
+    points.extend(centerline.iter().map(|line| (line.x2, line.y2)));
+    let bevel = tube.join == "bevel";
+    [tube.half_width, -tube.half_width]
+        .iter()
+        .flat_map(|offset| {
+            get_offset_polyline(&points, *offset, bevel)
+                .windows(2)
+                .map(|w| Line {
+                    x1: w[0].0,
+                    y1: w[0].1,
+                    x2: w[1].0,
+                    y2: w[1].1,
+                    color,
+                })
+                .collect::<Vec<Line>>()
+        })
+        .collect()
+}
+
+/// Returns the half-widths of a band of `sigmas` standard deviations at each of `count`
+/// points, from their variances. None for the points without a valid variance.
+fn get_band_half_widths(variances: &[f64], count: usize, sigmas: f64) -> Vec<Option<f64>> {
+    (0..count)
+        .map(|i| {
+            variances
+                .get(i)
+                .filter(|variance| variance.is_finite() && **variance >= 0.0)
+                .map(|variance| sigmas * variance.sqrt())
+        })
+        .collect()
+}
+
+/// Left and right points of a band at a point of its polyline.
+type BandSides = ((f64, f64), (f64, f64));
+
+/// Returns the sides of a band around the polyline, of a half-width given for each point,
+/// with a line across the band at each point to shade it.
+///
+/// The sides are offset along the normal of the polyline at each point, the bisector of
+/// its neighboring segments. The band is interrupted at the points without a half-width.
+fn get_band_lines(
+    points: &[(f64, f64)],
+    half_widths: &[Option<f64>],
+    color: style::Color,
+) -> Vec<Line> {
+    let line = |start: (f64, f64), end: (f64, f64)| Line {
+        x1: start.0,
+        y1: start.1,
+        x2: end.0,
+        y2: end.1,
+        color,
+    };
+    let sides: Vec<Option<BandSides>> = (0..points.len())
+        .map(|i| {
+            let half_width = (*half_widths.get(i)?)?;
+            let (previous, next) = (
+                points[i.saturating_sub(1)],
+                points[(i + 1).min(points.len() - 1)],
+            );
+            let (dx, dy) = (next.0 - previous.0, next.1 - previous.1);
+            let length = dx.hypot(dy);
+            if length == 0.0 {
+                return None;
+            }
+            let normal = (-dy / length * half_width, dx / length * half_width);
+            let p = points[i];
+            Some((
+                (p.0 + normal.0, p.1 + normal.1),
+                (p.0 - normal.0, p.1 - normal.1),
+            ))
+        })
+        .collect();
+    let mut lines: Vec<Line> = sides
+        .iter()
+        .flatten()
+        .map(|(left, right)| line(*left, *right))
+        .collect();
+    for pair in sides.windows(2) {
+        if let [Some((left0, right0)), Some((left1, right1))] = pair {
+            lines.push(line(*left0, *left1));
+            lines.push(line(*right0, *right1));
+        }
+    }
+    lines
+}
+
+/// Smooths a stream of poses with an exponential moving average: the position is
+/// interpolated linearly and the orientation with a slerp.
+///
+/// # Arguments
+/// - `previous`: the last smoothed pose.
+/// - `target`: the new pose.
+/// - `alpha`: weight of the new pose, in ]0, 1]. Values outside disable the smoothing.
+pub fn smooth_pose(
+    previous: &Isometry3<f64>,
+    target: &Isometry3<f64>,
+    alpha: f64,
+) -> Isometry3<f64> {
+    if alpha.is_nan() || alpha <= 0.0 || alpha >= 1.0 {
+        return *target;
+    }
+    // opposite orientations have no unique interpolation, the new one is taken
+    previous
+        .try_lerp_slerp(target, alpha, 1.0e-6)
+        .unwrap_or(*target)
+}
+
+/// A marker dropped along the received poses.
+struct Breadcrumb {
+    time: Instant,
+    pose: Isometry3<f64>,
+    /// Number of breadcrumbs dropped before this one.
+    index: usize,
+}
+
+/// Returns true if a new breadcrumb should be dropped at the given pose.
+fn is_breadcrumb_due(
+    config: &BreadcrumbsConfig,
+    last: Option<&Breadcrumb>,
+    pose: &Isometry3<f64>,
+    now: Instant,
+) -> bool {
+    let last = match last {
+        Some(last) => last,
+        None => return true,
+    };
+    let distance = (pose.translation.vector - last.pose.translation.vector)
+        .xy()
+        .norm();
+    (config.distance > 0.0 && distance >= config.distance)
+        || (config.interval > 0.0 && now.duration_since(last.time).as_secs_f64() >= config.interval)
+}
+
+/// A pose in the static frame, with its time of reception.
+type TimedPose = (Instant, Isometry3<f64>);
+
+/// Returns true if a pose received at the given time is older than the timeout, in
+/// seconds. A pose without timeout never times out.
+fn is_timed_out(received: Instant, timeout: Option<f64>) -> bool {
+    timeout.is_some_and(|timeout| received.elapsed().as_secs_f64() > timeout)
+}
+
+/// Returns the position of the label of a pose, ahead of its arrow of the given length.
+fn get_label_position(pose: &Isometry3<f64>, length: f64) -> (f64, f64) {
+    let position = pose.transform_point(&Point3::new(length * 1.2, 0.0, 0.0));
+    (position.x, position.y)
+}
+
+/// Returns the lag of the ghost pose, None if it's not positive and finite, i.e. disabled.
+fn get_ghost_lag(ghost_lag: Option<f64>) -> Option<Duration> {
+    ghost_lag
+        .filter(|lag| *lag > 0.0 && lag.is_finite())
+        .map(Duration::from_secs_f64)
+}
+
 pub struct PoseStampedListener {
-    config: PoseListenerConfig,
-    pose: Arc<RwLock<Option<Isometry3<f64>>>>,
+    pub config: PoseListenerConfig,
+    /// Last pose in the static frame, with its time of reception.
+    pose: Arc<RwLock<Option<TimedPose>>>,
+    /// Poses received in the last ghost_lag seconds, with their time of reception.
+    history: Arc<RwLock<VecDeque<TimedPose>>>,
+    /// The last breadcrumbs, the oldest first.
+    breadcrumbs: Arc<RwLock<VecDeque<Breadcrumb>>>,
+    pub rate: RateMonitor,
     _subscriber: rosrust::Subscriber,
 }
 
 impl PoseStampedListener {
-    pub fn new(config: PoseListenerConfig) -> PoseStampedListener {
+    pub fn new(
+        config: PoseListenerConfig,
+        tf_listener: Arc<rustros_tf::TfListener>,
+        static_frame: String,
+    ) -> PoseStampedListener {
         let pose = Arc::new(RwLock::new(None));
         let cb_pose = pose.clone();
+        let history = Arc::new(RwLock::new(VecDeque::new()));
+        let cb_history = history.clone();
+        let breadcrumbs = Arc::new(RwLock::new(VecDeque::new()));
+        let cb_breadcrumbs = breadcrumbs.clone();
+        let breadcrumbs_config = config.breadcrumbs.clone();
+        let ghost_lag = get_ghost_lag(config.ghost_lag);
+        let smoothing = config.smoothing;
+        let frame_filter = FrameFilter::new(&config.frame_filter);
+        warn_frame_override(&config.topic, &config.override_frame_id);
+        let override_frame_id = config.override_frame_id.clone();
+        let rate = RateMonitor::new();
+        let cb_rate = rate.clone();
         let sub = rosrust::subscribe(
             &config.topic,
             2,
             move |pose_msg: rosrust_msg::geometry_msgs::PoseStamped| {
-                let pose_iso = ros_pose_to_isometry(&pose_msg.pose);
-                *cb_pose.write().unwrap() = Some(pose_iso);
+                cb_rate.record();
+                cb_rate.record_stamp(pose_msg.header.stamp);
+                if !frame_filter.accepts(&pose_msg.header.frame_id) {
+                    return;
+                }
+                let frame_id = get_source_frame(&pose_msg.header.frame_id, &override_frame_id);
+                let mut pose_iso = ros_pose_to_isometry(&pose_msg.pose);
+                if !frame_id.is_empty() && frame_id != static_frame {
+                    match tf_listener.lookup_transform(
+                        &static_frame,
+                        frame_id,
+                        pose_msg.header.stamp,
+                    ) {
+                        Ok(tf) => pose_iso = ros_transform_to_isometry(&tf.transform) * pose_iso,
+                        Err(_e) => return,
+                    }
+                }
+                let now = Instant::now();
+                // only the displayed pose is smoothed, not the received message
+                if let Some((_, previous)) = cb_pose.read().unwrap().as_ref() {
+                    pose_iso = smooth_pose(previous, &pose_iso, smoothing);
+                }
+                *cb_pose.write().unwrap() = Some((now, pose_iso));
+                if let Some(lag) = ghost_lag {
+                    let mut history = cb_history.write().unwrap();
+                    history.push_back((now, pose_iso));
+                    // keep only the most recent pose older than the lag, and the newer ones
+                    while history.len() > 1 && now.duration_since(history[1].0) >= lag {
+                        history.pop_front();
+                    }
+                }
+                if let Some(crumbs_config) = &breadcrumbs_config {
+                    let mut crumbs = cb_breadcrumbs.write().unwrap();
+                    if is_breadcrumb_due(crumbs_config, crumbs.back(), &pose_iso, now) {
+                        let index = crumbs.back().map_or(0, |last: &Breadcrumb| last.index + 1);
+                        crumbs.push_back(Breadcrumb {
+                            time: now,
+                            pose: pose_iso,
+                            index,
+                        });
+                        while crumbs.len() > crumbs_config.max_count {
+                            crumbs.pop_front();
+                        }
+                    }
+                }
             },
         )
         .unwrap();
@@ -109,36 +458,205 @@ impl PoseStampedListener {
         PoseStampedListener {
             config: config,
             pose: pose,
+            history,
+            breadcrumbs,
+            rate,
             _subscriber: sub,
         }
     }
 
-    pub fn get_lines(&self) -> Vec<Line> {
-        match *self.pose.read().unwrap() {
-            Some(p) => match self.config.style.as_str() {
-                "arrow" => pose_to_arrow(&p, self.config.length, &self.config.color),
-                "axes" => pose_to_axes(&p, self.config.length),
-                _ => Vec::new(),
-            },
-            None => Vec::new(),
+    /// Returns the pose received ghost_lag seconds ago, if any, with its age in seconds.
+    fn get_ghost(&self) -> Option<(f64, Isometry3<f64>)> {
+        let lag = get_ghost_lag(self.config.ghost_lag)?;
+        let history = self.history.read().unwrap();
+        let (time, pose) = history.front()?;
+        if time.elapsed() < lag {
+            return None;
+        }
+        Some((time.elapsed().as_secs_f64(), *pose))
+    }
+
+    /// Forgets the received poses.
+    pub fn clear(&self) {
+        *self.pose.write().unwrap() = None;
+        self.history.write().unwrap().clear();
+        self.breadcrumbs.write().unwrap().clear();
+    }
+
+    /// Returns the last pose, None if it's older than the timeout.
+    pub fn get_pose(&self) -> Option<Isometry3<f64>> {
+        let (time, pose) = (*self.pose.read().unwrap())?;
+        if is_timed_out(time, self.config.timeout) {
+            return None;
         }
+        Some(pose)
     }
+
+    /// Returns how stale the pose is, from 0 when it is received to 1 at the timeout, None
+    /// if it has no timeout or no stale color.
+    fn get_staleness(&self) -> Option<f64> {
+        let timeout = self.config.timeout?;
+        self.config.stale_color.as_ref()?;
+        let (time, _) = (*self.pose.read().unwrap())?;
+        if timeout <= 0.0 {
+            return Some(1.0);
+        }
+        Some((time.elapsed().as_secs_f64() / timeout).min(1.0))
+    }
+
+    /// Returns a color of the pose transitioned toward the stale color by its staleness.
+    pub fn get_stale_color(&self, color: style::Color) -> style::Color {
+        match (self.get_staleness(), &self.config.stale_color) {
+            (Some(staleness), Some(stale)) => blend_color(
+                color,
+                style::Color::Rgb(stale.r, stale.g, stale.b),
+                staleness,
+            ),
+            _ => color,
+        }
+    }
+
+    /// Returns the name of the topic and the position where to print it, next to the pose.
+    pub fn get_label(&self) -> Option<(f64, f64, String)> {
+        let (x, y) = get_label_position(&self.get_pose()?, self.config.length);
+        Some((x, y, self.config.topic.clone()))
+    }
+
+    /// Returns the labels of the breadcrumbs and the positions where to print them, next
+    /// to the breadcrumbs. Empty if the breadcrumbs have no label.
+    pub fn get_breadcrumb_labels(&self) -> Vec<(f64, f64, String)> {
+        let config = match &self.config.breadcrumbs {
+            Some(config) => config,
+            None => return Vec::new(),
+        };
+        let crumbs = self.breadcrumbs.read().unwrap();
+        crumbs
+            .iter()
+            .filter_map(|crumb| {
+                let label = match config.label.as_str() {
+                    "index" => crumb.index.to_string(),
+                    "time" => format!("{:.0}s", crumb.time.elapsed().as_secs_f64()),
+                    _ => return None,
+                };
+                let position = crumb.pose.translation;
+                Some((position.x + config.size, position.y, label))
+            })
+            .collect()
+    }
+
+    /// Returns the glyphs of the breadcrumbs, kept when the pose times out, with their
+    /// age in seconds.
+    fn get_breadcrumb_lines(&self) -> Vec<(f64, Line)> {
+        let config = match &self.config.breadcrumbs {
+            Some(config) => config,
+            None => return Vec::new(),
+        };
+        let color = &self.config.color;
+        let color = style::Color::Rgb(color.r, color.g, color.b);
+        self.breadcrumbs
+            .read()
+            .unwrap()
+            .iter()
+            .flat_map(|crumb| {
+                let age = crumb.time.elapsed().as_secs_f64();
+                let position = crumb.pose.translation;
+                get_point_lines(position.x, position.y, &config.glyph, config.size, color)
+                    .into_iter()
+                    .map(move |line| (age, line))
+            })
+            .collect()
+    }
+
+    /// Returns the lines of the past poses, i.e. the breadcrumbs and the ghost pose with
+    /// its line to the current pose, with their age in seconds, to be faded with the age
+    /// ramp. The lines are in the color of the topic.
+    pub fn get_history_lines(&self) -> Vec<(f64, Line)> {
+        let mut lines = self.get_breadcrumb_lines();
+        if let (Some((age, ghost)), Some(current)) = (self.get_ghost(), self.get_pose()) {
+            let color = &self.config.color;
+            lines.extend(
+                pose_to_arrow(&ghost, self.config.length, color)
+                    .into_iter()
+                    .chain(poses_to_lines(&vec![ghost, current], color))
+                    .map(|line| (age, line)),
+            );
+        }
+        lines
+    }
+
+    /// Returns the lines of the current pose, see get_history_lines for the past ones.
+    pub fn get_lines(&self) -> Vec<Line> {
+        let current = match self.get_pose() {
+            Some(pose) => pose,
+            None => return Vec::new(),
+        };
+        let lines = match self.config.style.as_str() {
+            "arrow" => pose_to_arrow(&current, self.config.length, &self.config.color),
+            "axis" | "axes" => pose_to_axes(&current, self.config.length),
+            _ => Vec::new(),
+        };
+        lines
+            .into_iter()
+            .map(|line| Line {
+                color: self.get_stale_color(line.color),
+                ..line
+            })
+            .collect()
+    }
+}
+
+/// Reads the edges of a graph from pairs of indices, [from_0, to_0, from_1, to_1, ...].
+/// The pairs with a negative index are skipped, as well as a last index without pair.
+///
+/// Returns the edges and the number of skipped ones.
+fn read_edges(indices: &[i32]) -> (Vec<(usize, usize)>, usize) {
+    let mut invalid = indices.len() % 2;
+    let edges = indices
+        .chunks_exact(2)
+        .filter_map(|pair| {
+            if pair[0] < 0 || pair[1] < 0 {
+                invalid += 1;
+                return None;
+            }
+            Some((pair[0] as usize, pair[1] as usize))
+        })
+        .collect();
+    (edges, invalid)
 }
 
 pub struct PoseArrayListener {
-    config: PoseListenerConfig,
+    pub config: PoseListenerConfig,
     poses: Arc<RwLock<Vec<Isometry3<f64>>>>,
+    /// Edges between the poses, as pairs of indices in the pose array.
+    edges: Arc<RwLock<Vec<(usize, usize)>>>,
+    /// Speeds of the poses, received on the velocities topic if any.
+    speeds: Arc<RwLock<Vec<f64>>>,
+    /// Colormap of the density heatmap, if configured.
+    density_colormap: Option<Colormap>,
+    /// If true, the density heatmap is drawn instead of the poses.
+    show_density: bool,
+    pub rate: RateMonitor,
     _subscriber: rosrust::Subscriber,
+    _edges_subscriber: Option<rosrust::Subscriber>,
+    _velocities_subscriber: Option<rosrust::Subscriber>,
 }
 
 impl PoseArrayListener {
     pub fn new(config: PoseListenerConfig) -> PoseArrayListener {
         let poses = Arc::new(RwLock::new(Vec::<Isometry3<f64>>::new()));
         let cb_poses = poses.clone();
+        let frame_filter = FrameFilter::new(&config.frame_filter);
+        let rate = RateMonitor::new();
+        let cb_rate = rate.clone();
         let sub = rosrust::subscribe(
             &config.topic,
             2,
             move |pose_array: rosrust_msg::geometry_msgs::PoseArray| {
+                cb_rate.record();
+                cb_rate.record_stamp(pose_array.header.stamp);
+                if !frame_filter.accepts(&pose_array.header.frame_id) {
+                    return;
+                }
                 let poses_iso = pose_array
                     .poses
                     .into_iter()
@@ -149,18 +667,192 @@ impl PoseArrayListener {
         )
         .unwrap();
 
+        let edges = Arc::new(RwLock::new(Vec::new()));
+        let edges_sub = config.edges.as_ref().map(|edges_config| {
+            let cb_edges = edges.clone();
+            let cb_poses = poses.clone();
+            let topic = edges_config.topic.clone();
+            rosrust::subscribe(
+                &edges_config.topic,
+                2,
+                move |msg: rosrust_msg::std_msgs::Int32MultiArray| {
+                    let (new_edges, mut invalid) = read_edges(&msg.data);
+                    // the edges beyond the received poses are skipped when drawn
+                    let pose_count = cb_poses.read().unwrap().len();
+                    if pose_count > 0 {
+                        invalid += new_edges
+                            .iter()
+                            .filter(|(from, to)| *from >= pose_count || *to >= pose_count)
+                            .count();
+                    }
+                    if invalid > 0 {
+                        log::warn!("Skipping {} invalid edges received on {}", invalid, topic);
+                    }
+                    *cb_edges.write().unwrap() = new_edges;
+                },
+            )
+            .unwrap()
+        });
+
+        let speeds = Arc::new(RwLock::new(Vec::new()));
+        let velocities_sub = config.velocities.as_ref().map(|velocities_config| {
+            let cb_speeds = speeds.clone();
+            rosrust::subscribe(
+                &velocities_config.topic,
+                2,
+                move |msg: rosrust_msg::std_msgs::Float64MultiArray| {
+                    *cb_speeds.write().unwrap() = msg.data;
+                },
+            )
+            .unwrap()
+        });
+
         PoseArrayListener {
+            density_colormap: config
+                .density
+                .as_ref()
+                .map(|density| Colormap::new(&density.colormap)),
+            show_density: config.density.as_ref().is_some_and(|density| density.show),
             config: config,
             poses: poses,
+            edges,
+            speeds,
+            rate,
             _subscriber: sub,
+            _edges_subscriber: edges_sub,
+            _velocities_subscriber: velocities_sub,
         }
     }
 
+    /// Returns the edges of the graph between the poses, skipping the edges whose
+    /// indices are not in the pose array.
+    fn get_edge_lines(&self) -> Vec<Line> {
+        let edges_config = match &self.config.edges {
+            Some(edges_config) => edges_config,
+            None => return Vec::new(),
+        };
+        let color = style::Color::Rgb(
+            edges_config.color.r,
+            edges_config.color.g,
+            edges_config.color.b,
+        );
+        let poses = self.poses.read().unwrap();
+        self.edges
+            .read()
+            .unwrap()
+            .iter()
+            .filter_map(|(from, to)| {
+                let (from, to) = (poses.get(*from)?, poses.get(*to)?);
+                Some(Line {
+                    x1: from.translation.x,
+                    y1: from.translation.y,
+                    x2: to.translation.x,
+                    y2: to.translation.y,
+                    color,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns an arrow along each pose, scaled by its speed, for the poses with a
+    /// received speed.
+    fn get_velocity_lines(&self) -> Vec<Line> {
+        let velocities_config = match &self.config.velocities {
+            Some(velocities_config) => velocities_config,
+            None => return Vec::new(),
+        };
+        // the arrays may differ in length, e.g. while a new pose array is not matched yet
+        self.poses
+            .read()
+            .unwrap()
+            .iter()
+            .zip(self.speeds.read().unwrap().iter())
+            .filter(|(_, speed)| **speed != 0.0)
+            .flat_map(|(pose, speed)| {
+                pose_to_arrow(
+                    pose,
+                    speed * velocities_config.scale,
+                    &velocities_config.color,
+                )
+            })
+            .collect()
+    }
+
+    /// Switches between drawing the poses and their density heatmap, if configured.
+    pub fn toggle_density(&mut self) {
+        self.show_density = self.density_colormap.is_some() && !self.show_density;
+    }
+
+    /// Returns the density heatmap of the positions of the poses in the view, grouped by
+    /// color, None if the poses are drawn instead.
+    ///
+    /// The positions are counted in square bins of the configured number of terminal cells,
+    /// and each non-empty bin is filled with the color of its count relative to the fullest
+    /// bin.
+    ///
+    /// # Arguments
+    /// - `x_bounds`, `y_bounds`: bounds of the view, from which the bins start.
+    /// - `cell_size`: width and height of a terminal cell in the static frame.
+    pub fn get_density_points(
+        &self,
+        x_bounds: [f64; 2],
+        y_bounds: [f64; 2],
+        cell_size: (f64, f64),
+    ) -> Option<ColoredPoints> {
+        let (density, colormap) = match (&self.config.density, &self.density_colormap) {
+            (Some(density), Some(colormap)) if self.show_density => (density, colormap),
+            _ => return None,
+        };
+        let cells = density.cells.max(1);
+        let bin_size = (cell_size.0 * cells as f64, cell_size.1 * cells as f64);
+        let mut counts: HashMap<(i64, i64), usize> = HashMap::new();
+        for pose in self.poses.read().unwrap().iter() {
+            let (x, y) = (pose.translation.x, pose.translation.y);
+            if x < x_bounds[0] || x > x_bounds[1] || y < y_bounds[0] || y > y_bounds[1] {
+                continue;
+            }
+            let bin = (
+                ((x - x_bounds[0]) / bin_size.0).floor() as i64,
+                ((y - y_bounds[0]) / bin_size.1).floor() as i64,
+            );
+            *counts.entry(bin).or_default() += 1;
+        }
+        let max_count = counts.values().copied().max().unwrap_or(1) as f64;
+        // a terminal cell has 2x4 braille dots, a bin is filled with one point per dot
+        let (columns, rows) = (2 * cells as usize, 4 * cells as usize);
+        let mut points: BTreeMap<u8, Vec<(f64, f64)>> = BTreeMap::new();
+        for ((column, row), count) in counts {
+            let coords = points
+                .entry((count as f64 / max_count * COLOR_LEVELS).round() as u8)
+                .or_default();
+            for i in 0..columns {
+                for j in 0..rows {
+                    coords.push((
+                        x_bounds[0]
+                            + (column as f64 + (i as f64 + 0.5) / columns as f64) * bin_size.0,
+                        y_bounds[0] + (row as f64 + (j as f64 + 0.5) / rows as f64) * bin_size.1,
+                    ));
+                }
+            }
+        }
+        Some(
+            points
+                .into_iter()
+                .map(|(level, coords)| (colormap.color_at(level as f64 / COLOR_LEVELS), coords))
+                .collect(),
+        )
+    }
+
     pub fn get_lines(&self) -> Vec<Line> {
         if self.poses.read().unwrap().is_empty() {
             return Vec::new();
         }
-        match self.config.style.as_str() {
+        let mut lines = self.get_edge_lines();
+        lines.extend(self.get_velocity_lines());
+        if self.show_density {
+            return lines;
+        }
+        lines.extend(match self.config.style.as_str() {
             "arrow" => self
                 .poses
                 .read()
@@ -182,24 +874,41 @@ impl PoseArrayListener {
                 .reduce(|a, b| a.into_iter().chain(b.into_iter()).collect())
                 .unwrap(),
             _ => Vec::new(),
-        }
+        });
+        lines
     }
 }
 
 pub struct PathListener {
-    config: PoseListenerConfig,
+    pub config: PoseListenerConfig,
     poses: Arc<RwLock<Vec<Isometry3<f64>>>>,
+    /// Costs of the poses, received on the cost topic if any.
+    costs: Arc<RwLock<Vec<f64>>>,
+    /// Lateral variances of the poses, received on the band topic if any.
+    variances: Arc<RwLock<Vec<f64>>>,
+    colormap: Option<Colormap>,
+    pub rate: RateMonitor,
     _subscriber: rosrust::Subscriber,
+    _cost_subscriber: Option<rosrust::Subscriber>,
+    _band_subscriber: Option<rosrust::Subscriber>,
 }
 
 impl PathListener {
     pub fn new(config: PoseListenerConfig) -> PathListener {
         let poses = Arc::new(RwLock::new(Vec::<Isometry3<f64>>::new()));
         let cb_poses = poses.clone();
+        let frame_filter = FrameFilter::new(&config.frame_filter);
+        let rate = RateMonitor::new();
+        let cb_rate = rate.clone();
         let sub = rosrust::subscribe(
             &config.topic,
             2,
             move |path: rosrust_msg::nav_msgs::Path| {
+                cb_rate.record();
+                cb_rate.record_stamp(path.header.stamp);
+                if !frame_filter.accepts(&path.header.frame_id) {
+                    return;
+                }
                 let poses_iso = path
                     .poses
                     .into_iter()
@@ -210,18 +919,146 @@ impl PathListener {
         )
         .unwrap();
 
+        let costs = Arc::new(RwLock::new(Vec::<f64>::new()));
+        let cost_sub = config.cost.as_ref().map(|cost_config| {
+            let cb_costs = costs.clone();
+            rosrust::subscribe(
+                &cost_config.topic,
+                2,
+                move |msg: rosrust_msg::std_msgs::Float32MultiArray| {
+                    *cb_costs.write().unwrap() = msg.data.iter().map(|c| *c as f64).collect();
+                },
+            )
+            .unwrap()
+        });
+        let variances = Arc::new(RwLock::new(Vec::<f64>::new()));
+        let band_sub = config.band.as_ref().map(|band_config| {
+            let cb_variances = variances.clone();
+            rosrust::subscribe(
+                &band_config.topic,
+                2,
+                move |msg: rosrust_msg::std_msgs::Float32MultiArray| {
+                    *cb_variances.write().unwrap() = msg.data.iter().map(|v| *v as f64).collect();
+                },
+            )
+            .unwrap()
+        });
+        let colormap = config
+            .cost
+            .as_ref()
+            .map(|cost_config| Colormap::new(&cost_config.colormap));
+
         PathListener {
             config: config,
             poses: poses,
+            costs,
+            variances,
+            colormap,
+            rate,
             _subscriber: sub,
+            _cost_subscriber: cost_sub,
+            _band_subscriber: band_sub,
+        }
+    }
+
+    /// Returns a function mapping a cost to a color, through the colormap and within the
+    /// configured range, or the range of the received costs.
+    fn get_cost_color<'a>(&'a self, costs: &[f64]) -> Option<impl Fn(f64) -> style::Color + 'a> {
+        let colormap = self.colormap.as_ref()?;
+        if costs.is_empty() {
+            return None;
+        }
+        let [min, max] = match self.config.cost.as_ref().and_then(|c| c.range) {
+            Some(range) => range,
+            None => [
+                costs.iter().cloned().fold(f64::INFINITY, f64::min),
+                costs.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+            ],
+        };
+        Some(move |cost: f64| {
+            if max > min {
+                colormap.color_at((cost - min) / (max - min))
+            } else {
+                colormap.color_at(0.5)
+            }
+        })
+    }
+
+    /// Colors the path by the costs of the poses: each segment with the average cost of
+    /// its poses and each arrow with the cost of its pose. If there are fewer costs than
+    /// poses, the remaining segments keep the configured color.
+    fn apply_costs(&self, lines: &mut [Line]) {
+        let costs = self.costs.read().unwrap();
+        let cost_color = match self.get_cost_color(&costs) {
+            Some(cost_color) => cost_color,
+            None => return,
+        };
+        match self.config.style.as_str() {
+            "line" => {
+                let samples = get_spline_samples(
+                    self.config.spline_samples,
+                    self.poses.read().unwrap().len(),
+                )
+                .unwrap_or(1);
+                for (j, line) in lines.iter_mut().enumerate() {
+                    // the segments of the spline between two poses have the same cost
+                    let i = j / samples;
+                    if let (Some(c0), Some(c1)) = (costs.get(i), costs.get(i + 1)) {
+                        line.color = cost_color((c0 + c1) / 2.0);
+                    }
+                }
+            }
+            "arrow" => {
+                // an arrow is made of 3 lines
+                for (cost, arrow) in costs.iter().zip(lines.chunks_mut(3)) {
+                    for line in arrow {
+                        line.color = cost_color(*cost);
+                    }
+                }
+            }
+            _ => (),
         }
     }
 
+    /// Returns the segments between the poses, along the spline if configured.
+    fn get_centerline(&self) -> Vec<Line> {
+        let poses = self.poses.read().unwrap();
+        match get_spline_samples(self.config.spline_samples, poses.len()) {
+            Some(samples) => poses_to_spline(&poses, samples, &self.config.color),
+            None => poses_to_lines(&poses, &self.config.color),
+        }
+    }
+
+    /// Returns the confidence band around the poses, of `sigmas` standard deviations on
+    /// each side. The poses without a valid variance interrupt the band.
+    fn get_band(&self, band: &PathBandConfig) -> Vec<Line> {
+        let variances = self.variances.read().unwrap();
+        let points: Vec<(f64, f64)> = self
+            .poses
+            .read()
+            .unwrap()
+            .iter()
+            .map(|pose| (pose.translation.x, pose.translation.y))
+            .collect();
+        let half_widths = get_band_half_widths(&variances, points.len(), band.sigmas);
+        let color = band.color.as_ref().unwrap_or(&self.config.color);
+        get_band_lines(
+            &points,
+            &half_widths,
+            style::Color::Rgb(color.r, color.g, color.b),
+        )
+    }
+
     pub fn get_lines(&self) -> Vec<Line> {
         if self.poses.read().unwrap().is_empty() {
             return Vec::new();
         }
-        match self.config.style.as_str() {
+        // the band is drawn below the path
+        let mut lines = match &self.config.band {
+            Some(band) => self.get_band(band),
+            None => Vec::new(),
+        };
+        lines.extend(match self.config.style.as_str() {
             "arrow" => self
                 .poses
                 .read()
@@ -242,8 +1079,173 @@ impl PathListener {
                 .into_iter()
                 .reduce(|a, b| a.into_iter().chain(b.into_iter()).collect())
                 .unwrap(),
-            "line" => poses_to_lines(&self.poses.read().unwrap(), &self.config.color),
+            "line" => self.get_centerline(),
             _ => Vec::new(),
+        });
+        self.apply_costs(&mut lines);
+        if let Some(tube) = &self.config.tube {
+            let color = tube.color.as_ref().unwrap_or(&self.config.color);
+            lines.extend(get_tube_lines(
+                &self.get_centerline(),
+                tube,
+                style::Color::Rgb(color.r, color.g, color.b),
+            ));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::geometry::{Translation3, UnitQuaternion};
+
+    #[test]
+    fn pose_timeout() {
+        let received = Instant::now() - Duration::from_secs(2);
+        assert!(is_timed_out(received, Some(1.0)));
+        assert!(!is_timed_out(received, Some(5.0)));
+        assert!(!is_timed_out(received, None));
+    }
+
+    #[test]
+    fn label_ahead_of_pose() {
+        let pose = Isometry3::from_parts(
+            Translation3::new(1.0, 2.0, 0.0),
+            UnitQuaternion::from_euler_angles(0.0, 0.0, std::f64::consts::FRAC_PI_2),
+        );
+        let (x, y) = get_label_position(&pose, 0.5);
+        assert!((x - 1.0).abs() < 1e-9);
+        assert!((y - 2.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn smoothing_converges_to_step() {
+        let target = Isometry3::from_parts(
+            Translation3::new(1.0, 0.0, 0.0),
+            UnitQuaternion::from_euler_angles(0.0, 0.0, 1.0),
+        );
+        let mut pose = Isometry3::identity();
+        let mut errors = Vec::new();
+        for _ in 0..50 {
+            pose = smooth_pose(&pose, &target, 0.3);
+            errors.push((pose.translation.vector - target.translation.vector).norm());
+        }
+        // the error decreases at each step, by the weight of the new pose
+        assert!((errors[0] - 0.7).abs() < 1e-9);
+        assert!(errors.windows(2).all(|pair| pair[1] < pair[0]));
+        assert!(errors[49] < 1e-6);
+        assert!(pose.rotation.angle_to(&target.rotation) < 1e-6);
+        // without smoothing, the new pose is taken
+        assert_eq!(smooth_pose(&Isometry3::identity(), &target, 1.0), target);
+    }
+
+    #[test]
+    fn spline_through_poses() {
+        let positions = [(0.0, 0.0), (1.0, 0.0), (2.0, 1.0), (3.0, 1.0)];
+        let poses: Vec<Isometry3<f64>> = positions
+            .iter()
+            .map(|(x, y)| Isometry3::translation(*x, *y, 0.0))
+            .collect();
+        let color = Color {
+            r: 255,
+            g: 255,
+            b: 255,
+        };
+        let lines = poses_to_spline(&poses, 8, &color);
+        assert_eq!(lines.len(), 3 * 8);
+        // every 8 samples, the spline reaches the next pose
+        for (i, position) in positions.iter().enumerate().skip(1) {
+            let line = &lines[i * 8 - 1];
+            assert!((line.x2 - position.0).abs() < 1e-9 && (line.y2 - position.1).abs() < 1e-9);
+        }
+        assert_eq!(get_spline_samples(Some(8), 4), Some(8));
+    }
+
+    #[test]
+    fn straight_segments_below_4_poses() {
+        assert_eq!(get_spline_samples(Some(8), 3), None);
+        assert_eq!(get_spline_samples(Some(0), 10), None);
+        assert_eq!(get_spline_samples(None, 10), None);
+    }
+
+    #[test]
+    fn band_width_from_variance() {
+        // 2 standard deviations of 0.2 m, then invalid variances and a missing one
+        let half_widths = get_band_half_widths(&[0.04, 0.0, -1.0, f64::NAN], 5, 2.0);
+        assert!((half_widths[0].unwrap() - 0.4).abs() < 1e-12);
+        assert_eq!(half_widths[1], Some(0.0));
+        assert_eq!(&half_widths[2..], &[None, None, None]);
+
+        let points = [(0.0, 0.0), (1.0, 0.0)];
+        let lines = get_band_lines(&points, &[Some(0.4), Some(0.4)], style::Color::White);
+        // a line across the band at each point, then both sides
+        assert_eq!(lines.len(), 4);
+        assert!((lines[0].y1 - 0.4).abs() < 1e-12 && (lines[0].y2 + 0.4).abs() < 1e-12);
+        assert!(lines[2].y1 > 0.0 && lines[3].y1 < 0.0);
+        // without covariance, the band collapses on the path
+        let lines = get_band_lines(&points, &[Some(0.0), Some(0.0)], style::Color::White);
+        assert!(lines.iter().all(|line| line.y1 == 0.0 && line.y2 == 0.0));
+    }
+
+    #[test]
+    fn ghost_lag_disabled_unless_positive() {
+        assert_eq!(get_ghost_lag(Some(1.5)), Some(Duration::from_millis(1500)));
+        assert_eq!(get_ghost_lag(Some(0.0)), None);
+        assert_eq!(get_ghost_lag(Some(-1.0)), None);
+        assert_eq!(get_ghost_lag(Some(f64::INFINITY)), None);
+        assert_eq!(get_ghost_lag(Some(f64::NAN)), None);
+        assert_eq!(get_ghost_lag(None), None);
+    }
+
+    fn assert_polyline(polyline: &[(f64, f64)], expected: &[(f64, f64)]) {
+        assert_eq!(polyline.len(), expected.len(), "{:?}", polyline);
+        for (point, expected) in polyline.iter().zip(expected) {
+            assert!(
+                (point.0 - expected.0).abs() < 1e-9 && (point.1 - expected.1).abs() < 1e-9,
+                "{:?} instead of {:?}",
+                polyline,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn offset_of_straight_path() {
+        let points = [(0.0, 0.0), (1.0, 0.0), (3.0, 0.0)];
+        for bevel in [false, true] {
+            assert_polyline(
+                &get_offset_polyline(&points, 0.5, bevel),
+                &[(0.0, 0.5), (1.0, 0.5), (3.0, 0.5)],
+            );
+            assert_polyline(
+                &get_offset_polyline(&points, -0.5, bevel),
+                &[(0.0, -0.5), (1.0, -0.5), (3.0, -0.5)],
+            );
+        }
+    }
+
+    #[test]
+    fn offset_of_right_angle() {
+        // turning left at (2, 0), so the left side is the inner one
+        let points = [(0.0, 0.0), (2.0, 0.0), (2.0, 2.0)];
+        let inner = [(0.0, 0.5), (1.5, 0.5), (1.5, 2.0)];
+        assert_polyline(&get_offset_polyline(&points, 0.5, false), &inner);
+        let outer = get_offset_polyline(&points, -0.5, false);
+        assert_polyline(&outer, &[(0.0, -0.5), (2.5, -0.5), (2.5, 2.0)]);
+        // the miter is at half_width * sqrt(2) from the corner
+        let miter = outer[1];
+        assert!(((miter.0 - 2.0).hypot(miter.1) - 0.5 * 2f64.sqrt()).abs() < 1e-9);
+        // with the bevel, only the outer side is cut at the ends of the offset segments
+        assert_polyline(&get_offset_polyline(&points, 0.5, true), &inner);
+        assert_polyline(
+            &get_offset_polyline(&points, -0.5, true),
+            &[(0.0, -0.5), (2.0, -0.5), (2.5, 0.0), (2.5, 2.0)],
+        );
+        // a hairpin turn exceeds the miter limit, so both sides are beveled anyway
+        let hairpin = [(0.0, 0.0), (2.0, 0.0), (0.0, 0.1)];
+        for offset in [0.5, -0.5] {
+            assert_eq!(get_offset_polyline(&hairpin, offset, false).len(), 4);
         }
     }
 }
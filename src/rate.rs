@@ -0,0 +1,104 @@
+//! Measures the rate at which the messages of a topic are received.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Number of seconds of history kept for each topic.
+pub const HISTORY_LENGTH: usize = 30;
+
+struct History {
+    /// Second (since creation) of the last element of counts.
+    current_second: u64,
+    /// Number of messages received in each second, the last one being in progress.
+    counts: VecDeque<u64>,
+    /// Number of messages received since the creation.
+    total: u64,
+}
+
+/// Counts the messages received per second over the last HISTORY_LENGTH seconds.
+///
+/// The monitor can be cloned into a subscriber callback, the clones share the same history.
+#[derive(Clone)]
+pub struct RateMonitor {
+    start: Instant,
+    history: Arc<Mutex<History>>,
+    /// False while the topic is not subscribed, e.g. for a disabled lazy layer.
+    subscribed: Arc<AtomicBool>,
+    /// Stamp of the last message, None until a stamped message is received.
+    stamp: Arc<Mutex<Option<rosrust::Time>>>,
+}
+
+impl RateMonitor {
+    pub fn new() -> RateMonitor {
+        RateMonitor {
+            start: Instant::now(),
+            history: Arc::new(Mutex::new(History {
+                current_second: 0,
+                counts: VecDeque::from(vec![0]),
+                total: 0,
+            })),
+            subscribed: Arc::new(AtomicBool::new(true)),
+            stamp: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Records whether the topic is subscribed.
+    pub fn set_subscribed(&self, subscribed: bool) {
+        self.subscribed.store(subscribed, Ordering::Relaxed);
+    }
+
+    /// Returns false while the topic is not subscribed.
+    pub fn is_subscribed(&self) -> bool {
+        self.subscribed.load(Ordering::Relaxed)
+    }
+
+    /// Moves the history to the current second, filling the elapsed seconds with zeros.
+    fn advance(&self, history: &mut History) {
+        let now = self.start.elapsed().as_secs();
+        let elapsed = (now - history.current_second).min(HISTORY_LENGTH as u64 + 1);
+        for _ in 0..elapsed {
+            history.counts.push_back(0);
+        }
+        while history.counts.len() > HISTORY_LENGTH + 1 {
+            history.counts.pop_front();
+        }
+        history.current_second = now;
+    }
+
+    /// Records the reception of a message, to be called from the subscriber callback.
+    pub fn record(&self) {
+        let mut history = self.history.lock().unwrap();
+        self.advance(&mut history);
+        *history.counts.back_mut().unwrap() += 1;
+        history.total += 1;
+    }
+
+    /// Records the stamp of the header of a message, to be called from the subscriber
+    /// callback of the stamped messages. A zero stamp, i.e. unset, is ignored.
+    pub fn record_stamp(&self, stamp: rosrust::Time) {
+        if stamp != rosrust::Time::new() {
+            *self.stamp.lock().unwrap() = Some(stamp);
+        }
+    }
+
+    /// Returns the stamp of the last stamped message, None if there is none.
+    pub fn get_stamp(&self) -> Option<rosrust::Time> {
+        *self.stamp.lock().unwrap()
+    }
+
+    /// Returns the number of messages received in each of the last complete seconds,
+    /// from the oldest to the most recent.
+    pub fn get_counts(&self) -> Vec<u64> {
+        let mut history = self.history.lock().unwrap();
+        self.advance(&mut history);
+        let complete = history.counts.len() - 1;
+        history.counts.iter().take(complete).cloned().collect()
+    }
+
+    /// Returns the number of messages received since the creation of the monitor.
+    pub fn get_total(&self) -> u64 {
+        self.history.lock().unwrap().total
+    }
+}
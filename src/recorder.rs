@@ -0,0 +1,166 @@
+//! Records the rendered frames of the terminal into an animated GIF.
+//!
+//! The frames are rasterized from the tui buffer: each cell is a block of pixels, braille
+//! cells (used by the canvas) show their dots and the other non-empty cells are filled
+//! with their foreground color. The resolution and the duration are bounded, so that
+//! the frames kept in memory stay small.
+
+use crate::config::GifRecordingConfig;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, Rgba, RgbaImage};
+use std::fs::File;
+use std::thread;
+use std::time::{Duration, Instant};
+use tui::buffer::Buffer;
+use tui::style::Color;
+
+/// Maximum duration of a recording, in seconds.
+const MAX_DURATION: f64 = 60.0;
+
+/// Maximum framerate of a recording.
+const MAX_FPS: u32 = 25;
+
+/// First character of the braille block of unicode.
+const BRAILLE_START: u32 = 0x2800;
+
+/// Bit of each dot of a braille character, by row (4) and column (2).
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+fn to_rgba(color: Color, default: [u8; 3]) -> Rgba<u8> {
+    let [r, g, b] = match color {
+        Color::Reset => default,
+        Color::Black => [0, 0, 0],
+        Color::Red => [205, 0, 0],
+        Color::Green => [0, 205, 0],
+        Color::Yellow => [205, 205, 0],
+        Color::Blue => [0, 0, 238],
+        Color::Magenta => [205, 0, 205],
+        Color::Cyan => [0, 205, 205],
+        Color::Gray => [229, 229, 229],
+        Color::DarkGray => [127, 127, 127],
+        Color::LightRed => [255, 0, 0],
+        Color::LightGreen => [0, 255, 0],
+        Color::LightYellow => [255, 255, 0],
+        Color::LightBlue => [92, 92, 255],
+        Color::LightMagenta => [255, 0, 255],
+        Color::LightCyan => [0, 255, 255],
+        Color::White => [255, 255, 255],
+        Color::Rgb(r, g, b) => [r, g, b],
+        Color::Indexed(i) => [i, i, i],
+    };
+    Rgba([r, g, b, 255])
+}
+
+/// Rasterizes a buffer, with cells of dot_size * 2 by dot_size * 4 pixels.
+fn rasterize(buffer: &Buffer, dot_size: u32) -> RgbaImage {
+    let (cell_width, cell_height) = (dot_size * 2, dot_size * 4);
+    let area = buffer.area;
+    let mut img = RgbaImage::new(
+        area.width as u32 * cell_width,
+        area.height as u32 * cell_height,
+    );
+    for row in 0..area.height {
+        for column in 0..area.width {
+            let cell = buffer.get(area.x + column, area.y + row);
+            let bg = to_rgba(cell.bg, [0, 0, 0]);
+            let fg = to_rgba(cell.fg, [255, 255, 255]);
+            let code = cell.symbol.chars().next().map_or(0, |c| c as u32);
+            let is_braille = (BRAILLE_START..BRAILLE_START + 0x100).contains(&code);
+            let is_empty = cell.symbol.trim().is_empty();
+            for y in 0..cell_height {
+                for x in 0..cell_width {
+                    let on = if is_braille {
+                        let dot = BRAILLE_DOTS[(y / dot_size) as usize][(x / dot_size) as usize];
+                        (code - BRAILLE_START) & dot != 0
+                    } else {
+                        !is_empty
+                    };
+                    img.put_pixel(
+                        column as u32 * cell_width + x,
+                        row as u32 * cell_height + y,
+                        if on { fg } else { bg },
+                    );
+                }
+            }
+        }
+    }
+    img
+}
+
+pub struct GifRecorder {
+    config: GifRecordingConfig,
+    start: Instant,
+    last_frame: Option<Instant>,
+    frames: Vec<RgbaImage>,
+}
+
+impl GifRecorder {
+    pub fn new(config: GifRecordingConfig) -> GifRecorder {
+        GifRecorder {
+            config,
+            start: Instant::now(),
+            last_frame: None,
+            frames: Vec::new(),
+        }
+    }
+
+    fn duration(&self) -> f64 {
+        self.config.duration.clamp(0.0, MAX_DURATION)
+    }
+
+    fn fps(&self) -> u32 {
+        self.config.fps.clamp(1, MAX_FPS)
+    }
+
+    /// Returns true once the configured duration is recorded.
+    pub fn is_done(&self) -> bool {
+        self.start.elapsed().as_secs_f64() >= self.duration()
+    }
+
+    /// Returns the progress of the recording, to be shown to the user.
+    pub fn get_progress(&self) -> String {
+        format!(
+            "recording {:.1}/{:.1} s",
+            self.start.elapsed().as_secs_f64().min(self.duration()),
+            self.duration()
+        )
+    }
+
+    /// Adds the rendered frame, if enough time passed since the last one.
+    pub fn record(&mut self, buffer: &Buffer) {
+        let period = Duration::from_secs_f64(1.0 / self.fps() as f64);
+        if self.is_done() || self.last_frame.is_some_and(|t| t.elapsed() < period) {
+            return;
+        }
+        self.last_frame = Some(Instant::now());
+        // the largest dots for which the image is not wider than max_width
+        let dot_size = (self.config.max_width / (buffer.area.width.max(1) as u32 * 2)).max(1);
+        self.frames.push(rasterize(buffer, dot_size));
+    }
+
+    /// Encodes the recorded frames into the GIF file, in a background thread.
+    pub fn save(self) {
+        let fps = self.fps();
+        let path = self.config.path;
+        let frames = self.frames;
+        thread::spawn(move || {
+            let result = File::create(&path)
+                .map_err(image::ImageError::from)
+                .and_then(|file| {
+                    let mut encoder = GifEncoder::new(file);
+                    encoder.set_repeat(Repeat::Infinite)?;
+                    encoder.encode_frames(frames.into_iter().map(|img| {
+                        Frame::from_parts(img, 0, 0, Delay::from_numer_denom_ms(1000, fps))
+                    }))
+                });
+            match result {
+                Ok(()) => {
+                    log::info!("Recording saved to {}", path);
+                }
+                Err(e) => {
+                    log::error!("Could not save the recording to {}: {}", path, e);
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,116 @@
+//! Simplified model of the robot, drawn as the outline of its links.
+//!
+//! Each link is a box or a cylinder attached to a frame of the robot. The outline is
+//! projected on the XY plane of the static frame at the pose resolved by TF, so the model
+//! follows the joints of the robot (e.g., an arm or a rotating turret).
+
+use crate::config::RobotLinkConfig;
+use crate::transformation::transform_relative_pt;
+use std::sync::Arc;
+use tui::style::Color;
+use tui::widgets::canvas::Line;
+
+/// Number of segments of the outline of a cylinder.
+const CYLINDER_SEGMENTS: usize = 16;
+
+/// Returns the outline of the link in its frame, as a closed polygon.
+fn get_outline(link: &RobotLinkConfig) -> Vec<(f64, f64)> {
+    let points: Vec<(f64, f64)> = match (link.shape.as_str(), link.size.as_slice()) {
+        ("box", [x, y, ..]) => {
+            let (half_x, half_y) = (x / 2.0, y / 2.0);
+            vec![
+                (half_x, half_y),
+                (-half_x, half_y),
+                (-half_x, -half_y),
+                (half_x, -half_y),
+            ]
+        }
+        ("cylinder", [radius, ..]) => (0..CYLINDER_SEGMENTS)
+            .map(|i| {
+                let angle = i as f64 * 2.0 * std::f64::consts::PI / CYLINDER_SEGMENTS as f64;
+                (radius * angle.cos(), radius * angle.sin())
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    let (sin, cos) = link.origin[2].sin_cos();
+    points
+        .iter()
+        .map(|(x, y)| {
+            (
+                link.origin[0] + cos * x - sin * y,
+                link.origin[1] + sin * x + cos * y,
+            )
+        })
+        .collect()
+}
+
+pub struct RobotModel {
+    /// Links with their outline in the link frame.
+    links: Vec<(RobotLinkConfig, Vec<(f64, f64)>)>,
+    tf_listener: Arc<rustros_tf::TfListener>,
+    static_frame: String,
+}
+
+impl RobotModel {
+    pub fn new(
+        links: Vec<RobotLinkConfig>,
+        tf_listener: Arc<rustros_tf::TfListener>,
+        static_frame: String,
+    ) -> RobotModel {
+        let links = links
+            .into_iter()
+            .filter_map(|link| {
+                let outline = get_outline(&link);
+                if outline.is_empty() {
+                    log::warn!(
+                        "Invalid shape {:?} or size {:?} for the link {}, use box or cylinder.",
+                        link.shape,
+                        link.size,
+                        link.frame
+                    );
+                    return None;
+                }
+                Some((link, outline))
+            })
+            .collect();
+        RobotModel {
+            links,
+            tf_listener,
+            static_frame,
+        }
+    }
+
+    /// Returns the outlines of the links in the static frame, the links whose frame is
+    /// unknown to TF are skipped.
+    pub fn get_lines(&self) -> Vec<Line> {
+        let mut lines = Vec::new();
+        for (link, outline) in &self.links {
+            let tf = match self.tf_listener.lookup_transform(
+                &self.static_frame,
+                &link.frame,
+                rosrust::Time::new(),
+            ) {
+                Ok(tf) => tf.transform,
+                Err(_e) => continue,
+            };
+            let points: Vec<(f64, f64)> = outline
+                .iter()
+                .map(|pt| transform_relative_pt(&tf, *pt))
+                .collect();
+            let color = Color::Rgb(link.color.r, link.color.g, link.color.b);
+            for i in 0..points.len() {
+                let start = points[i];
+                let end = points[(i + 1) % points.len()];
+                lines.push(Line {
+                    x1: start.0,
+                    y1: start.1,
+                    x2: end.0,
+                    y2: end.1,
+                    color,
+                });
+            }
+        }
+        lines
+    }
+}
@@ -0,0 +1,72 @@
+//! Switching to another ROS master at runtime, e.g. between the robots of a fleet.
+//!
+//! The rosrust node can be initialized only once per process, so termviz executes itself
+//! again with the new ROS_MASTER_URI, which re-establishes all the configured
+//! subscriptions. The new master is checked to be reachable first, so that the current
+//! connection is kept if it is not.
+
+use std::net::{TcpStream, ToSocketAddrs};
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+use std::time::Duration;
+
+/// Time to wait for the new master to accept a connection.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Default master of ROS, used when ROS_MASTER_URI is not set.
+const DEFAULT_MASTER_URI: &str = "http://localhost:11311/";
+
+/// Returns the URI of the current master.
+pub fn get_current_uri() -> String {
+    std::env::var("ROS_MASTER_URI").unwrap_or_else(|_| DEFAULT_MASTER_URI.to_string())
+}
+
+/// Returns true if both URIs designate the same master, ignoring a trailing slash.
+pub fn is_same_uri(a: &str, b: &str) -> bool {
+    a.trim_end_matches('/') == b.trim_end_matches('/')
+}
+
+/// Returns the master following the current one in the configured list, the first one if
+/// the current master is not in the list. None if the list is empty.
+pub fn get_next_uri(masters: &[String]) -> Option<&String> {
+    let current = get_current_uri();
+    let next = masters
+        .iter()
+        .position(|uri| is_same_uri(uri, &current))
+        .map_or(0, |i| (i + 1) % masters.len());
+    masters.get(next)
+}
+
+/// Checks that a master accepts connections, e.g. "http://robot1:11311/".
+pub fn probe(uri: &str) -> Result<(), String> {
+    let address = uri
+        .trim_start_matches("http://")
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    if address.is_empty() {
+        return Err(format!("{} is not a valid master URI", uri));
+    }
+    let addresses = address
+        .to_socket_addrs()
+        .map_err(|e| format!("Could not resolve {}: {}", address, e))?;
+    for socket in addresses {
+        if TcpStream::connect_timeout(&socket, PROBE_TIMEOUT).is_ok() {
+            return Ok(());
+        }
+    }
+    Err(format!("{} is not reachable", uri))
+}
+
+/// Executes termviz again, with the same arguments, connected to the master. Only returns
+/// if the execution failed.
+pub fn restart_with_master(uri: &str) -> std::io::Error {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => return e,
+    };
+    Command::new(exe)
+        .args(std::env::args_os().skip(1))
+        .env("ROS_MASTER_URI", uri)
+        .exec()
+}
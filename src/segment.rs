@@ -0,0 +1,141 @@
+//! Module dealing with the visualization of line segments, e.g. the walls detected by a
+//! SLAM or a wall-detection node, published as a visualization_msgs::Marker of type
+//! LINE_LIST.
+//!
+//! Unlike a path, the segments are independent: each pair of points of the marker is a
+//! segment. They are transformed to the static frame when received and can be labeled
+//! with their length.
+
+use crate::config::SegmentListenerConfig;
+use crate::listeners::{get_source_frame, warn_frame_override, FrameFilter};
+use crate::rate::RateMonitor;
+use crate::transformation::{
+    lookup_transform_with_tolerance, ros_pose_to_isometry, ros_transform_to_isometry,
+};
+use nalgebra::geometry::Point3;
+use std::sync::{Arc, RwLock};
+use tui::style::Color;
+use tui::widgets::canvas::Line;
+
+/// A segment in the static frame, with its length.
+pub struct Segment {
+    pub line: Line,
+    pub length: f64,
+}
+
+/// Returns the color of the segment starting at the point `i`, from the colors of the
+/// points if the marker has one per point, otherwise the default color.
+fn get_segment_color(
+    colors: &[rosrust_msg::std_msgs::ColorRGBA],
+    points_count: usize,
+    i: usize,
+    default: Color,
+) -> Color {
+    if colors.len() != points_count {
+        return default;
+    }
+    let color = &colors[i];
+    Color::Rgb(
+        (color.r * 255.0) as u8,
+        (color.g * 255.0) as u8,
+        (color.b * 255.0) as u8,
+    )
+}
+
+pub struct SegmentListener {
+    pub config: SegmentListenerConfig,
+    pub segments: Arc<RwLock<Vec<Segment>>>,
+    pub rate: RateMonitor,
+    _subscriber: rosrust::Subscriber,
+}
+
+impl SegmentListener {
+    pub fn new(
+        config: SegmentListenerConfig,
+        tf_listener: Arc<rustros_tf::TfListener>,
+        static_frame: String,
+    ) -> SegmentListener {
+        let segments = Arc::new(RwLock::new(Vec::new()));
+        let cb_segments = segments.clone();
+        let frame_filter = FrameFilter::new(&config.frame_filter);
+        warn_frame_override(&config.topic, &config.override_frame_id);
+        let override_frame_id = config.override_frame_id.clone();
+        let lookup_tolerance = config.lookup_tolerance;
+        let color = Color::Rgb(config.color.r, config.color.g, config.color.b);
+        let cb_topic = config.topic.clone();
+        let rate = RateMonitor::new();
+        let cb_rate = rate.clone();
+        let sub = rosrust::subscribe(
+            &config.topic,
+            2,
+            move |msg: rosrust_msg::visualization_msgs::Marker| {
+                cb_rate.record();
+                cb_rate.record_stamp(msg.header.stamp);
+                if msg.type_ as u8 != rosrust_msg::visualization_msgs::Marker::LINE_LIST {
+                    log::warn!(
+                        "Ignoring the marker received on {}, its type is not LINE_LIST",
+                        cb_topic
+                    );
+                    return;
+                }
+                if !frame_filter.accepts(&msg.header.frame_id) {
+                    return;
+                }
+                if !msg.points.len().is_multiple_of(2) {
+                    log::warn!(
+                        "The marker received on {} has an odd number of points, the last one is ignored",
+                        cb_topic
+                    );
+                }
+                let transform = match lookup_transform_with_tolerance(
+                    &tf_listener,
+                    &static_frame,
+                    get_source_frame(&msg.header.frame_id, &override_frame_id),
+                    msg.header.stamp,
+                    lookup_tolerance,
+                ) {
+                    Ok(transform) => transform.transform,
+                    Err(_e) => return,
+                };
+                let pose =
+                    ros_transform_to_isometry(&transform) * ros_pose_to_isometry(&msg.pose);
+                let mut segments = Vec::new();
+                for (i, pair) in msg.points.chunks_exact(2).enumerate() {
+                    let [start, end] = [&pair[0], &pair[1]]
+                        .map(|pt| pose.transform_point(&Point3::new(pt.x, pt.y, pt.z)));
+                    let length = (end.x - start.x).hypot(end.y - start.y);
+                    // a segment with coincident endpoints has nothing to draw
+                    if length == 0.0 || !length.is_finite() {
+                        continue;
+                    }
+                    let color = get_segment_color(&msg.colors, msg.points.len(), 2 * i, color);
+                    segments.push(Segment {
+                        line: Line {
+                            x1: start.x,
+                            y1: start.y,
+                            x2: end.x,
+                            y2: end.y,
+                            color,
+                        },
+                        length,
+                    });
+                }
+                // an empty marker clears the previous segments
+                *cb_segments.write().unwrap() = segments;
+            },
+        )
+        .unwrap();
+
+        SegmentListener {
+            config,
+            segments,
+            rate,
+            _subscriber: sub,
+        }
+    }
+
+    /// Forgets the received segments.
+    pub fn clear(&self) {
+        self.segments.write().unwrap().clear();
+    }
+}
@@ -0,0 +1,157 @@
+//! Comparison of the TF tree with a secondary one, e.g. a replayed bag whose TF topics
+//! are remapped, to find where they diverge.
+//!
+//! The TfListener only subscribes to `/tf` and `/tf_static`, so the secondary tree is kept
+//! here: the last transform of each frame to its parent, received on the configured topics.
+//! Its frames are looked up by chaining these transforms, without interpolation in time,
+//! and drawn in their own color, with a line to the same frame in the main tree if their
+//! origins are further apart than the tolerance.
+
+use crate::config::TfComparisonConfig;
+use crate::transformation::ros_transform_to_isometry;
+use nalgebra::geometry::{Isometry3, Point3};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tui::style::Color;
+use tui::widgets::canvas::Line;
+
+fn get_line(p1: &Point3<f64>, p2: &Point3<f64>, color: Color) -> Line {
+    Line {
+        x1: p1.x,
+        y1: p1.y,
+        x2: p2.x,
+        y2: p2.y,
+        color,
+    }
+}
+
+/// Parent of a frame, with the transform from the frame to its parent.
+type ParentTransform = (String, Isometry3<f64>);
+
+pub struct TfComparison {
+    pub config: TfComparisonConfig,
+    /// Parent and last transform to it of each frame of the secondary tree, by child frame.
+    transforms: Arc<RwLock<HashMap<String, ParentTransform>>>,
+    tf_listener: Arc<rustros_tf::TfListener>,
+    static_frame: String,
+    _subscribers: Vec<rosrust::Subscriber>,
+}
+
+impl TfComparison {
+    pub fn new(
+        config: TfComparisonConfig,
+        tf_listener: Arc<rustros_tf::TfListener>,
+        static_frame: String,
+    ) -> TfComparison {
+        let transforms = Arc::new(RwLock::new(HashMap::new()));
+        let subscribers = [&config.tf_topic, &config.tf_static_topic]
+            .iter()
+            .map(|topic| {
+                let cb_transforms = transforms.clone();
+                rosrust::subscribe(
+                    topic,
+                    100,
+                    move |tf_msg: rosrust_msg::tf2_msgs::TFMessage| {
+                        let mut transforms = cb_transforms.write().unwrap();
+                        for tf in tf_msg.transforms {
+                            transforms.insert(
+                                tf.child_frame_id,
+                                (tf.header.frame_id, ros_transform_to_isometry(&tf.transform)),
+                            );
+                        }
+                    },
+                )
+                .unwrap()
+            })
+            .collect();
+
+        TfComparison {
+            config,
+            transforms,
+            tf_listener,
+            static_frame,
+            _subscribers: subscribers,
+        }
+    }
+
+    /// Forgets the secondary tree, e.g. after a time jump.
+    pub fn clear(&self) {
+        self.transforms.write().unwrap().clear();
+    }
+
+    /// Returns the pose of a frame of the secondary tree in the static frame, None if the
+    /// static frame is not one of its ancestors.
+    fn lookup_secondary(&self, frame: &str) -> Option<Isometry3<f64>> {
+        let transforms = self.transforms.read().unwrap();
+        let mut pose = Isometry3::identity();
+        let mut current = frame;
+        // a cycle in the tree would never reach the static frame
+        for _ in 0..=transforms.len() {
+            if current == self.static_frame {
+                return Some(pose);
+            }
+            let (parent, transform) = transforms.get(current)?;
+            pose = transform * pose;
+            current = parent;
+        }
+        None
+    }
+
+    /// Returns the frames of the secondary tree to draw, sorted alphabetically.
+    fn get_frames(&self) -> Vec<String> {
+        let mut frames: Vec<String> = if self.config.frames.is_empty() {
+            self.transforms.read().unwrap().keys().cloned().collect()
+        } else {
+            self.config.frames.clone()
+        };
+        frames.sort();
+        frames
+    }
+
+    /// Returns the axes of the frames of the secondary tree, and the lines to the same
+    /// frames in the main tree where they diverge.
+    pub fn get_lines(&self, axis_length: f64) -> Vec<Line> {
+        let color = Color::Rgb(
+            self.config.color.r,
+            self.config.color.g,
+            self.config.color.b,
+        );
+        let divergence_color = Color::Rgb(
+            self.config.divergence_color.r,
+            self.config.divergence_color.g,
+            self.config.divergence_color.b,
+        );
+        let mut lines = Vec::new();
+        for frame in self.get_frames() {
+            let pose = match self.lookup_secondary(&frame) {
+                Some(pose) => pose,
+                None => continue,
+            };
+            let origin = pose.transform_point(&Point3::origin());
+            lines.push(get_line(
+                &origin,
+                &pose.transform_point(&Point3::new(axis_length, 0.0, 0.0)),
+                color,
+            ));
+            lines.push(get_line(
+                &origin,
+                &pose.transform_point(&Point3::new(0.0, axis_length, 0.0)),
+                color,
+            ));
+            if let Ok(tf) =
+                self.tf_listener
+                    .lookup_transform(&self.static_frame, &frame, rosrust::Time::new())
+            {
+                let main_origin = Point3::new(
+                    tf.transform.translation.x,
+                    tf.transform.translation.y,
+                    tf.transform.translation.z,
+                );
+                if (main_origin - origin).norm() > self.config.tolerance {
+                    lines.push(get_line(&main_origin, &origin, divergence_color));
+                }
+            }
+        }
+        lines
+    }
+}
@@ -0,0 +1,133 @@
+//! Coarse preview of a sensor_msgs::CompressedImage topic, shown as a panel of the viewport.
+//!
+//! The decoder is selected from the `format` field of the messages, e.g. "jpeg" or, as
+//! published by image_transport, "bgr8; jpeg compressed bgr8". If it names no supported
+//! format, e.g. because it is empty, the format is detected from the magic bytes of the
+//! data. Images in an unsupported format are replaced by a placeholder.
+
+use crate::config::ThumbnailConfig;
+use image::{imageops, ImageFormat, Rgba, RgbaImage};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Returns the format of a compressed image, from its format field or else from its magic
+/// bytes. None if it is neither JPEG nor PNG.
+fn detect_format(format: &str, data: &[u8]) -> Option<ImageFormat> {
+    let format = format.to_lowercase();
+    if format.contains("jpeg") || format.contains("jpg") {
+        return Some(ImageFormat::Jpeg);
+    }
+    if format.contains("png") {
+        return Some(ImageFormat::Png);
+    }
+    match image::guess_format(data) {
+        Ok(ImageFormat::Jpeg) => Some(ImageFormat::Jpeg),
+        Ok(ImageFormat::Png) => Some(ImageFormat::Png),
+        _ => None,
+    }
+}
+
+/// Returns a gray checkerboard of the given size, shown instead of an unsupported image.
+fn get_placeholder(width: u32, height: u32) -> RgbaImage {
+    RgbaImage::from_fn(width, height, |x, y| {
+        if (x / 2 + y / 2) % 2 == 0 {
+            Rgba([96, 96, 96, 255])
+        } else {
+            Rgba([48, 48, 48, 255])
+        }
+    })
+}
+
+pub struct ThumbnailListener {
+    pub config: ThumbnailConfig,
+    /// Last decoded image, already downsampled to the size of the panel.
+    pub img: Arc<RwLock<Option<RgbaImage>>>,
+    _subscriber: rosrust::Subscriber,
+}
+
+impl ThumbnailListener {
+    /// Subscribes to the compressed image topic.
+    ///
+    /// # Arguments
+    /// - `config`: topic and size of the panel, in terminal cells.
+    /// - `framerate`: refresh rate of the panel, images received faster are not decoded.
+    pub fn new(config: ThumbnailConfig, framerate: i64) -> ThumbnailListener {
+        let img = Arc::new(RwLock::new(None));
+        let cb_img = img.clone();
+        let min_period = Duration::from_secs_f64(1.0 / framerate.max(1) as f64);
+        let last_decode = Mutex::new(None::<Instant>);
+        // an unsupported format is likely to be sent by every message
+        let warned_format = Mutex::new(false);
+        // a cell shows two pixels, one above the other
+        let (width, height) = (config.width as u32, config.height as u32 * 2);
+        let sub = rosrust::subscribe(
+            &config.topic,
+            1,
+            move |msg: rosrust_msg::sensor_msgs::CompressedImage| {
+                let mut last_decode = last_decode.lock().unwrap();
+                if last_decode.is_some_and(|last| last.elapsed() < min_period) {
+                    return;
+                }
+                *last_decode = Some(Instant::now());
+                let format = match detect_format(&msg.format, &msg.data) {
+                    Some(format) => format,
+                    None => {
+                        let mut warned_format = warned_format.lock().unwrap();
+                        if !*warned_format {
+                            log::warn!(
+                                "Unsupported image format {:?}, expected jpeg or png.",
+                                msg.format
+                            );
+                            *warned_format = true;
+                        }
+                        *cb_img.write().unwrap() = Some(get_placeholder(width, height));
+                        return;
+                    }
+                };
+                match image::load_from_memory_with_format(&msg.data, format) {
+                    Ok(decoded) => {
+                        let thumbnail = imageops::thumbnail(&decoded.to_rgba8(), width, height);
+                        *cb_img.write().unwrap() = Some(thumbnail);
+                    }
+                    Err(e) => {
+                        log::warn!("Could not decode image ({}): {}", msg.format, e);
+                    }
+                }
+            },
+        )
+        .unwrap();
+
+        ThumbnailListener {
+            config,
+            img,
+            _subscriber: sub,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+    const JPEG_MAGIC: &[u8] = b"\xff\xd8\xff\xe0\0\x10JFIF\0";
+
+    #[test]
+    fn format_from_magic_bytes() {
+        assert_eq!(detect_format("", PNG_MAGIC), Some(ImageFormat::Png));
+        assert_eq!(detect_format("", JPEG_MAGIC), Some(ImageFormat::Jpeg));
+        assert_eq!(detect_format("", b"not an image"), None);
+    }
+
+    #[test]
+    fn format_from_field() {
+        // the format of image_transport wins over the data
+        assert_eq!(
+            detect_format("rgb8; jpeg compressed bgr8", PNG_MAGIC),
+            Some(ImageFormat::Jpeg)
+        );
+        assert_eq!(detect_format("PNG", b""), Some(ImageFormat::Png));
+        // a format naming no supported format falls back to the magic bytes
+        assert_eq!(detect_format("bgr8", PNG_MAGIC), Some(ImageFormat::Png));
+    }
+}
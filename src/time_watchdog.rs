@@ -0,0 +1,53 @@
+//! Detects the backward jumps of the ROS time, e.g. when a bag loops or a simulation is
+//! reset.
+//!
+//! After such a jump the data already transformed in the static frame would linger until
+//! it is received again. The jump is detected by comparing the ROS time at each tick with
+//! the previous one, so small non-monotonic stamps of a bag don't trigger it as long as
+//! they are below the threshold.
+
+use std::time::{Duration, Instant};
+
+/// For how long the notice is shown after a jump.
+const NOTICE_DURATION: Duration = Duration::from_secs(3);
+
+pub struct TimeWatchdog {
+    /// Minimum backward jump, in seconds. If not positive, the watchdog is disabled.
+    threshold: f64,
+    last_time: Option<f64>,
+    last_reset: Option<Instant>,
+}
+
+impl TimeWatchdog {
+    pub fn new(threshold: f64) -> TimeWatchdog {
+        TimeWatchdog {
+            threshold,
+            last_time: None,
+            last_reset: None,
+        }
+    }
+
+    /// Returns true if the ROS time jumped backward by more than the threshold since the
+    /// last call.
+    pub fn check(&mut self) -> bool {
+        if self.threshold.is_nan() || self.threshold <= 0.0 {
+            return false;
+        }
+        let now = rosrust::now().seconds();
+        let jumped = self
+            .last_time
+            .is_some_and(|last_time| last_time - now > self.threshold);
+        self.last_time = Some(now);
+        if jumped {
+            log::warn!("ROS time jumped backward, clearing the received data");
+            self.last_reset = Some(Instant::now());
+        }
+        jumped
+    }
+
+    /// Returns true if a jump happened in the last NOTICE_DURATION.
+    pub fn show_notice(&self) -> bool {
+        self.last_reset
+            .is_some_and(|reset| reset.elapsed() < NOTICE_DURATION)
+    }
+}
@@ -0,0 +1,95 @@
+//! Timer drawn over the modes, e.g. for timed experiments and live demonstrations.
+//!
+//! The timer shows the time elapsed while it runs, or the time left of a configured
+//! duration. It only counts the wall time between its starts and stops, independently of
+//! the ROS time.
+
+use crate::config::TimerConfig;
+use std::time::{Duration, Instant};
+
+/// Period of the flashing of the notice once a countdown reached zero.
+const FLASH_PERIOD: Duration = Duration::from_millis(500);
+
+pub struct Timer {
+    pub config: TimerConfig,
+    /// Time counted before the last start.
+    accumulated: Duration,
+    /// Instant of the last start, None while stopped.
+    started: Option<Instant>,
+}
+
+impl Timer {
+    pub fn new(config: TimerConfig) -> Timer {
+        Timer {
+            config,
+            accumulated: Duration::ZERO,
+            started: None,
+        }
+    }
+
+    /// Starts the timer, or stops it keeping the counted time.
+    pub fn toggle(&mut self) {
+        match self.started.take() {
+            Some(started) => self.accumulated += started.elapsed(),
+            None => self.started = Some(Instant::now()),
+        }
+    }
+
+    /// Stops the timer and forgets the counted time, which hides it.
+    pub fn reset(&mut self) {
+        self.accumulated = Duration::ZERO;
+        self.started = None;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    fn get_elapsed(&self) -> Duration {
+        self.accumulated
+            + self
+                .started
+                .map_or(Duration::ZERO, |started| started.elapsed())
+    }
+
+    /// Formats a time with the configured format: "clock" as [h:]mm:ss.d, "seconds" as a
+    /// number of seconds.
+    fn format(&self, time: Duration) -> String {
+        let seconds = time.as_secs_f64();
+        if self.config.format == "seconds" {
+            return format!("{:.1} s", seconds);
+        }
+        let tenths = (seconds * 10.0).floor() as u64;
+        let (hours, minutes) = (tenths / 36000, tenths / 600 % 60);
+        let seconds = (tenths % 600) as f64 / 10.0;
+        if hours > 0 {
+            format!("{}:{:02}:{:04.1}", hours, minutes, seconds)
+        } else {
+            format!("{:02}:{:04.1}", minutes, seconds)
+        }
+    }
+
+    /// Returns the text of the timer and true while its notice is highlighted, None if it
+    /// was never started since the last reset.
+    ///
+    /// With a duration, the time left is shown, and once it reaches zero the notice
+    /// flashes until the timer is reset.
+    pub fn get_text(&self) -> Option<(String, bool)> {
+        let elapsed = self.get_elapsed();
+        if !self.is_running() && elapsed.is_zero() {
+            return None;
+        }
+        if self.config.duration <= 0.0 {
+            return Some((self.format(elapsed), false));
+        }
+        let duration = Duration::from_secs_f64(self.config.duration);
+        match duration.checked_sub(elapsed) {
+            Some(left) if !left.is_zero() => Some((self.format(left), false)),
+            _ => {
+                let overtime = elapsed - duration;
+                let flash = (overtime.as_millis() / FLASH_PERIOD.as_millis()).is_multiple_of(2);
+                Some((format!("{} TIME UP", self.format(Duration::ZERO)), flash))
+            }
+        }
+    }
+}
@@ -1,5 +1,46 @@
 use nalgebra::geometry::{Isometry2, Isometry3, Point3, Quaternion, Translation3, UnitQuaternion};
 use nalgebra::Vector2;
+use std::sync::OnceLock;
+
+/// Time in seconds by which a stamp has to be ahead of the ROS time to be looked up as the
+/// latest transform instead, None to look up the stamps as they are.
+static FUTURE_STAMP_THRESHOLD: OnceLock<Option<f64>> = OnceLock::new();
+
+/// Sets how the stamps in the future are looked up by lookup_transform_with_tolerance, for
+/// all the listeners. Only the first call has an effect.
+pub fn set_future_stamp_threshold(threshold: Option<f64>) {
+    let _ = FUTURE_STAMP_THRESHOLD.set(threshold);
+}
+
+/// Returns the stamp to look up: the stamp of the message, or the latest transform if the
+/// stamp is ahead of the ROS time by more than the future stamp threshold, e.g. because of
+/// a clock skew between machines.
+fn clamp_future_stamp(stamp: rosrust::Time, to: &str) -> rosrust::Time {
+    match FUTURE_STAMP_THRESHOLD.get() {
+        Some(Some(threshold)) => clamp_stamp_at(stamp, rosrust::now(), *threshold, to),
+        _ => stamp,
+    }
+}
+
+/// Returns the stamp, or the latest transform if it is ahead of `now` by more than
+/// `threshold` seconds, see clamp_future_stamp.
+fn clamp_stamp_at(
+    stamp: rosrust::Time,
+    now: rosrust::Time,
+    threshold: f64,
+    to: &str,
+) -> rosrust::Time {
+    let ahead = stamp.seconds() - now.seconds();
+    if ahead <= threshold {
+        return stamp;
+    }
+    log::warn!(
+        "Message in {} stamped {:.3} s in the future, using the latest transform",
+        to,
+        ahead
+    );
+    rosrust::Time::new()
+}
 
 pub fn transform_relative_pt(
     tf: &rosrust_msg::geometry_msgs::Transform,
@@ -66,3 +107,70 @@ pub fn ros_transform_to_isometry(tf: &rosrust_msg::geometry_msgs::Transform) ->
     ));
     Isometry3::from_parts(tra, rot)
 }
+
+/// Returns true if the latest transform can be used for a message whose transform is not
+/// available at its stamp, i.e. if it is at most `tolerance` seconds away from the stamp.
+fn is_within_tolerance(latest: rosrust::Time, stamp: rosrust::Time, tolerance: f64) -> bool {
+    tolerance > 0.0 && (latest.seconds() - stamp.seconds()).abs() <= tolerance
+}
+
+/// Looks up the transform at the stamp of a message.
+///
+/// If the lookup fails and `tolerance` is positive, the latest transform is used instead,
+/// provided that it is at most `tolerance` seconds away from the stamp. This avoids
+/// dropping messages that arrive slightly before or after their transform. A stamp too far
+/// in the future is replaced by the latest transform first, see set_future_stamp_threshold.
+pub fn lookup_transform_with_tolerance(
+    tf_listener: &rustros_tf::TfListener,
+    from: &str,
+    to: &str,
+    stamp: rosrust::Time,
+    tolerance: f64,
+) -> Result<rosrust_msg::geometry_msgs::TransformStamped, rustros_tf::TfError> {
+    let stamp = clamp_future_stamp(stamp, to);
+    let error = match tf_listener.lookup_transform(from, to, stamp) {
+        Ok(transform) => return Ok(transform),
+        Err(e) => e,
+    };
+    if tolerance <= 0.0 {
+        return Err(error);
+    }
+    match tf_listener.lookup_transform(from, to, rosrust::Time::new()) {
+        Ok(latest) if is_within_tolerance(latest.header.stamp, stamp, tolerance) => Ok(latest),
+        _ => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latest_transform_within_tolerance() {
+        let stamp = rosrust::Time::from_nanos(10_000_000_000);
+        let before = rosrust::Time::from_nanos(9_950_000_000);
+        let after = rosrust::Time::from_nanos(10_200_000_000);
+        assert!(is_within_tolerance(before, stamp, 0.1));
+        assert!(!is_within_tolerance(after, stamp, 0.1));
+        assert!(is_within_tolerance(after, stamp, 0.5));
+        // without a tolerance, only the transform at the stamp is used
+        assert!(!is_within_tolerance(stamp, stamp, 0.0));
+    }
+
+    #[test]
+    fn future_stamp_clamped() {
+        let now = rosrust::Time::from_nanos(100_000_000_000);
+        let slightly_ahead = rosrust::Time::from_nanos(100_500_000_000);
+        let far_ahead = rosrust::Time::from_nanos(105_000_000_000);
+        assert_eq!(
+            clamp_stamp_at(slightly_ahead, now, 1.0, "map"),
+            slightly_ahead
+        );
+        assert_eq!(clamp_stamp_at(now, now, 1.0, "map"), now);
+        // a stamp past the threshold is looked up as the latest transform
+        assert_eq!(
+            clamp_stamp_at(far_ahead, now, 1.0, "map"),
+            rosrust::Time::new()
+        );
+    }
+}
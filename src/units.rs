@@ -0,0 +1,108 @@
+//! Units in which the lengths are shown to the user.
+//!
+//! Only the readouts are converted: the computations and the published messages are
+//! always in meters.
+
+use crate::config::UnitsConfig;
+
+const METERS_PER_FOOT: f64 = 0.3048;
+const INCHES_PER_FOOT: f64 = 12.0;
+
+pub const SUPPORTED_SYSTEMS: [&str; 2] = ["metric", "imperial"];
+
+#[derive(Clone, Debug)]
+pub struct Units {
+    imperial: bool,
+    precision: usize,
+}
+
+impl Units {
+    /// Reads the configuration, falls back to the metric system for an unknown system.
+    pub fn new(config: &UnitsConfig) -> Units {
+        if !SUPPORTED_SYSTEMS.contains(&config.system.as_str()) {
+            log::warn!(
+                "Unknown unit system {:?}, supported are {:?}. Using metric.",
+                config.system,
+                SUPPORTED_SYSTEMS
+            );
+        }
+        Units {
+            imperial: config.system == "imperial",
+            precision: config.precision,
+        }
+    }
+
+    /// Symbol of the main unit of length.
+    pub fn symbol(&self) -> &str {
+        if self.imperial {
+            "ft"
+        } else {
+            "m"
+        }
+    }
+
+    /// Converts a length in meters to the main unit.
+    pub fn in_unit(&self, meters: f64) -> f64 {
+        if self.imperial {
+            meters / METERS_PER_FOOT
+        } else {
+            meters
+        }
+    }
+
+    /// Converts a length in the main unit to meters.
+    pub fn to_meters(&self, length: f64) -> f64 {
+        if self.imperial {
+            length * METERS_PER_FOOT
+        } else {
+            length
+        }
+    }
+
+    /// Formats a length given in meters, with the configured precision. In the imperial
+    /// system, lengths below a foot are given in inches.
+    pub fn format_length(&self, meters: f64) -> String {
+        let length = self.in_unit(meters);
+        if self.imperial && length.abs() < 1.0 {
+            format!("{:.*} in", self.precision, length * INCHES_PER_FOOT)
+        } else {
+            format!("{:.*} {}", self.precision, length, self.symbol())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_units(system: &str) -> Units {
+        Units::new(&UnitsConfig {
+            system: system.to_string(),
+            precision: 2,
+        })
+    }
+
+    #[test]
+    fn metric_lengths() {
+        let units = get_units("metric");
+        assert_eq!(units.in_unit(1.5), 1.5);
+        assert_eq!(units.to_meters(units.in_unit(0.05)), 0.05);
+        // the lengths below a meter are not given in centimeters
+        assert_eq!(units.format_length(0.05), "0.05 m");
+        assert_eq!(units.format_length(12.345), "12.35 m");
+    }
+
+    #[test]
+    fn imperial_lengths() {
+        let units = get_units("imperial");
+        assert!((units.in_unit(0.3048) - 1.0).abs() < 1e-12);
+        assert!((units.to_meters(units.in_unit(2.5)) - 2.5).abs() < 1e-12);
+        assert_eq!(units.format_length(0.6096), "2.00 ft");
+        assert_eq!(units.format_length(0.1524), "6.00 in");
+    }
+
+    #[test]
+    fn unknown_system_is_metric() {
+        assert_eq!(get_units("cubits").symbol(), "m");
+    }
+}